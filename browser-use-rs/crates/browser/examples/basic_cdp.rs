@@ -28,7 +28,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Targets: {}", targets_result);
 
     // Subscribe to target events
-    let client_clone = Arc::clone(&client);
     client.subscribe(
         "Target.targetCreated",
         Arc::new(move |event| {
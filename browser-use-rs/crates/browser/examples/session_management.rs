@@ -1,6 +1,8 @@
 //! Session management example - creating tabs and navigating
 
+use browser::events::BrowserEvent;
 use browser::session::{BrowserSession, SessionConfig};
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -13,6 +15,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         cdp_url: "ws://localhost:9222".to_string(),
         headless: true,
         user_data_dir: None,
+        ..SessionConfig::default()
     };
 
     println!("Creating browser session: {}", config.id);
@@ -30,34 +33,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     session.start().await?;
     println!("✅ Session started");
 
-    // Create first tab
-    let tab1 = session
-        .new_tab(Some("https://www.rust-lang.org".to_string()))
-        .await?;
+    // Create first tab; navigate returns once the page has loaded
+    let tab1 = session.new_tab(None).await?;
+    session.navigate("https://www.rust-lang.org").await?;
     println!("📄 Created tab 1: {}", tab1);
 
-    // Wait for navigation
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-
     // Create second tab
-    let tab2 = session
-        .new_tab(Some("https://github.com".to_string()))
-        .await?;
+    let tab2 = session.new_tab(None).await?;
+    session.navigate("https://github.com").await?;
     println!("📄 Created tab 2: {}", tab2);
 
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-
     // Switch back to first tab
     session.switch_tab(tab1.clone()).await?;
     println!("🔄 Switched to tab 1");
 
-    // Navigate current tab
+    // Navigate current tab, waiting on the event bus for the final URL
+    let arrived = session.event_bus.wait_for(
+        |event| matches!(event, BrowserEvent::NavigationComplete { url, .. } if url.contains("crates.io")),
+        Duration::from_secs(30),
+    );
     session.navigate("https://crates.io").await?;
+    arrived.await?;
     println!("🧭 Navigated to crates.io");
 
-    // Wait a bit
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-
     // Check current session
     if let Some(current) = session.current_session().await {
         let info = current.get_target_info().await?;
@@ -4,22 +4,30 @@
 //! 1. Single WebSocket per browser connection (no per-session WS overhead)
 //! 2. Async message passing - no locks on send/receive path  
 //! 3. Request/response matching via ID, events broadcast to subscribers
-//! 4. Fail fast - no retries, no queuing. Let the caller decide.
+//! 4. Fail fast - no retries, no queuing. Let the caller decide
+//!    (reconnection is opt-in via `connect_with_reconnect`, retries via
+//!    `send_request_retry`).
 
 use dashmap::DashMap;
-use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
 use serde_json::Value;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot, RwLock};
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 
+use super::commands::CdpCommand;
+use super::discovery;
 use super::protocol::*;
+use super::retry::RetryPolicy;
 
 type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
 #[derive(Error, Debug)]
 pub enum CDPError {
@@ -29,8 +37,13 @@ pub enum CDPError {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
-    #[error("CDP protocol error: {code} - {message}")]
-    Protocol { code: i32, message: String },
+    #[error("CDP protocol error: {code} - {message}{}", data_summary(.data))]
+    Protocol {
+        code: i32,
+        message: String,
+        /// Extra detail some methods attach, e.g. why a navigation was blocked
+        data: Option<Value>,
+    },
 
     #[error("Request timeout")]
     Timeout,
@@ -40,14 +53,137 @@ pub enum CDPError {
 
     #[error("Invalid response for request {0}")]
     InvalidResponse(RequestId),
+
+    #[error("Base64 decode error: {0}")]
+    Decode(#[from] base64::DecodeError),
+
+    #[error("Element {0} has no visible area")]
+    ElementNotVisible(u32),
+
+    #[error("Element {0} has no layout bounds (not rendered, or not in the snapshot)")]
+    ElementHasNoBounds(u32),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("JavaScript exception: {0}")]
+    JavaScript(String),
+
+    #[error("DevTools endpoint discovery failed: {0}")]
+    Discovery(String),
+
+    #[error("DOM error: {0}")]
+    Dom(#[from] dom::DomError),
+}
+
+/// Longest `data` shown in a protocol error's message
+const DATA_SUMMARY_LEN: usize = 200;
+
+/// ` (data)` suffix for a protocol error's message, compact and capped
+fn data_summary(data: &Option<Value>) -> String {
+    let text = match data {
+        None | Some(Value::Null) => return String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(data) => data.to_string(),
+    };
+    match text.char_indices().nth(DATA_SUMMARY_LEN) {
+        Some((end, _)) => format!(" ({}...)", &text[..end]),
+        None => format!(" ({})", text),
+    }
+}
+
+impl CDPError {
+    /// Whether the remote end refused the TCP connection (nothing listening yet)
+    pub fn is_connection_refused(&self) -> bool {
+        match self {
+            CDPError::WebSocket(tokio_tungstenite::tungstenite::Error::Io(e)) => {
+                e.kind() == std::io::ErrorKind::ConnectionRefused
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether retrying the same request may succeed
+    ///
+    /// True for timeouts and for server errors (-32000) raised while a page
+    /// is navigating or still loading; see `TRANSIENT_PROTOCOL_ERRORS`.
+    /// Everything else is final: a closed or failed connection, malformed
+    /// requests (unknown method, invalid params), and other protocol errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            CDPError::Timeout => true,
+            CDPError::Protocol { code, message, .. } => {
+                *code == -32000
+                    && TRANSIENT_PROTOCOL_ERRORS
+                        .iter()
+                        .any(|fragment| message.contains(fragment))
+            }
+            _ => false,
+        }
+    }
 }
 
+/// Messages of protocol errors that come from racing a navigation or a
+/// page still being built
+const TRANSIENT_PROTOCOL_ERRORS: &[&str] = &[
+    "Execution context was destroyed",
+    "Cannot find context with specified id",
+    "Inspected target navigated or closed",
+    "Document needs to be loaded",
+    "Could not compute box model",
+];
+
 /// Result type for CDP operations
 pub type Result<T> = std::result::Result<T, CDPError>;
 
 /// Event subscriber callback
 pub type EventCallback = Arc<dyn Fn(CDPEvent) + Send + Sync>;
 
+/// Handle returned by `subscribe`, used to `unsubscribe` that one callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Catch-all subscriber callback - sees every event, by reference
+pub type AllEventsCallback = Arc<dyn Fn(&CDPEvent) + Send + Sync>;
+
+/// Hook run once when the connection is lost for good (see `CDPClient::on_close`)
+pub type CloseHook = Box<dyn FnOnce() + Send>;
+
+/// Default time to wait for a response before giving up on a request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Synthetic event dispatched to subscribers after an automatic reconnect
+pub const RECONNECTED_EVENT: &str = "CDPClient.reconnected";
+
+/// Backoff policy for `connect_with_reconnect`
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Attempts per disconnect before giving up for good
+    pub max_attempts: usize,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff_factor: f64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            backoff_factor: 2.0,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Delay before the given 0-based attempt
+    fn delay_for(&self, attempt: usize) -> Duration {
+        let factor = self.backoff_factor.max(1.0).powi(attempt.min(32) as i32);
+        self.initial_delay.mul_f64(factor).min(self.max_delay)
+    }
+}
+
 /// CDP Client - manages single WebSocket connection to browser
 pub struct CDPClient {
     /// Monotonic request ID counter
@@ -59,114 +195,593 @@ pub struct CDPClient {
 
     /// Event subscribers
     /// Key: method name (e.g., "Page.loadEventFired"), Value: callbacks
-    subscribers: Arc<DashMap<String, Vec<EventCallback>>>,
+    subscribers: Arc<DashMap<String, Vec<(SubscriptionId, EventCallback)>>>,
+
+    /// Monotonic subscription ID counter
+    next_subscription_id: AtomicU64,
+
+    /// Catch-all subscribers, fired for every event after method-specific ones
+    all_subscribers: Arc<std::sync::RwLock<Vec<AllEventsCallback>>>,
 
     /// WebSocket write half (wrapped for concurrent sending)
     ws_sink: Arc<RwLock<WsSink>>,
+
+    /// Timeout for `send_request`, in milliseconds
+    request_timeout_ms: AtomicU64,
+
+    /// Stops the receiver task; taken by `close`
+    shutdown_tx: std::sync::Mutex<Option<mpsc::Sender<()>>>,
+
+    /// Browser-level `*.enable` commands, replayed after a reconnect
+    enabled_domains: DashMap<String, Option<Value>>,
+
+    /// Whether the WebSocket is up; false while reconnecting
+    connected: AtomicBool,
+
+    /// Run when the receiver task ends on a lost connection; `None` once it has
+    close_hooks: std::sync::Mutex<Option<Vec<CloseHook>>>,
 }
+/// A request that was registered in `pending` and awaits its response
+struct PendingRequest {
+    id: RequestId,
+    rx: oneshot::Receiver<CDPResponse>,
+    /// Browser-level `X.enable` to replay after a reconnect, once it succeeds
+    replay: Option<(String, Option<Value>)>,
+}
+
 impl CDPClient {
     /// Connect to Chrome DevTools Protocol endpoint
     pub async fn connect(ws_url: &str) -> Result<Arc<Self>> {
+        Self::connect_inner(ws_url, None).await
+    }
+
+    /// Connect, and transparently re-establish the WebSocket if it drops
+    ///
+    /// On an unexpected disconnect, pending requests fail with
+    /// `CDPError::Closed`, then the client reconnects with exponential backoff,
+    /// replays browser-level `*.enable` commands and dispatches
+    /// `RECONNECTED_EVENT`. Target sessions don't survive a reconnect - their
+    /// owners should re-attach when they see that event.
+    pub async fn connect_with_reconnect(
+        ws_url: &str,
+        config: ReconnectConfig,
+    ) -> Result<Arc<Self>> {
+        Self::connect_inner(ws_url, Some(config)).await
+    }
+
+    async fn connect_inner(ws_url: &str, reconnect: Option<ReconnectConfig>) -> Result<Arc<Self>> {
         let (ws_stream, _) = connect_async(ws_url).await?;
-        let (sink, mut stream) = ws_stream.split();
+        let (sink, stream) = ws_stream.split();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
 
         let client = Arc::new(Self {
             next_id: AtomicU64::new(1),
             pending: Arc::new(DashMap::new()),
             subscribers: Arc::new(DashMap::new()),
+            next_subscription_id: AtomicU64::new(1),
+            all_subscribers: Arc::new(std::sync::RwLock::new(Vec::new())),
             ws_sink: Arc::new(RwLock::new(sink)),
+            request_timeout_ms: AtomicU64::new(DEFAULT_REQUEST_TIMEOUT.as_millis() as u64),
+            shutdown_tx: std::sync::Mutex::new(Some(shutdown_tx)),
+            enabled_domains: DashMap::new(),
+            connected: AtomicBool::new(true),
+            close_hooks: std::sync::Mutex::new(Some(Vec::new())),
         });
 
         // Spawn message receiver task
-        let client_clone = client.clone();
-        let (_shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+        let reconnect = reconnect.map(|config| (ws_url.to_string(), config));
+        tokio::spawn(client.clone().receive_loop(stream, shutdown_rx, reconnect));
 
-        tokio::spawn(async move {
-            loop {
-                tokio::select! {
-                    msg = stream.next() => {
-                        match msg {
-                            Some(Ok(Message::Text(text))) => {
-                                if let Err(e) = client_clone.handle_message(&text).await {
-                                    tracing::error!("Failed to handle message: {}", e);
-                                }
-                            }
-                            Some(Ok(Message::Close(_))) | None => {
-                                tracing::info!("WebSocket closed");
-                                break;
-                            }
-                            Some(Err(e)) => {
-                                tracing::error!("WebSocket error: {}", e);
-                                break;
-                            }
-                            _ => {}
+        Ok(client)
+    }
+
+    /// Receiver task: read until shutdown, reconnecting on drops if configured
+    async fn receive_loop(
+        self: Arc<Self>,
+        mut stream: WsStream,
+        mut shutdown_rx: mpsc::Receiver<()>,
+        reconnect: Option<(String, ReconnectConfig)>,
+    ) {
+        loop {
+            tokio::select! {
+                _ = self.read_until_closed(&mut stream) => {}
+                // A dropped sender yields None - only an explicit signal stops the loop
+                Some(()) = shutdown_rx.recv() => {
+                    tracing::info!("Shutdown signal received");
+                    break;
+                }
+            }
+
+            // Requests sent on the dead socket will never be answered
+            self.connected.store(false, Ordering::SeqCst);
+            self.pending.clear();
+
+            // `close` takes the sender - a deliberate close is not a drop
+            let closing = self
+                .shutdown_tx
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_none();
+            let Some((url, config)) = reconnect.as_ref().filter(|_| !closing) else {
+                break;
+            };
+            match self.reconnect(url, config, &mut shutdown_rx).await {
+                Some(new_stream) => {
+                    stream = new_stream;
+                    self.connected.store(true, Ordering::SeqCst);
+                }
+                None => break,
+            }
+        }
+
+        // Clear all pending requests
+        self.connected.store(false, Ordering::SeqCst);
+        self.pending.clear();
+
+        // `close` takes the sender - nobody needs telling about a deliberate close
+        let closing = self
+            .shutdown_tx
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none();
+        let hooks = {
+            let mut hooks = self.close_hooks.lock().unwrap_or_else(|e| e.into_inner());
+            if closing {
+                if let Some(hooks) = hooks.as_mut() {
+                    hooks.clear();
+                }
+                None
+            } else {
+                hooks.take()
+            }
+        };
+        for hook in hooks.into_iter().flatten() {
+            hook();
+        }
+    }
+
+    /// Whether the WebSocket is currently up
+    ///
+    /// False after `close`, after the browser went away, and while a
+    /// `connect_with_reconnect` client is between connections.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Run `hook` once the connection is lost for good
+    ///
+    /// That is when the socket closes or errors and no reconnect is
+    /// configured, or reconnecting gives up. Runs right away if that has
+    /// already happened; never runs after `close`.
+    pub fn on_close(&self, hook: CloseHook) {
+        let mut hooks = self.close_hooks.lock().unwrap_or_else(|e| e.into_inner());
+        match hooks.as_mut() {
+            Some(hooks) => hooks.push(hook),
+            None => {
+                drop(hooks);
+                hook();
+            }
+        }
+    }
+
+    /// Dispatch incoming messages until the socket closes or errors
+    async fn read_until_closed(&self, stream: &mut WsStream) {
+        while let Some(msg) = stream.next().await {
+            match msg {
+                Ok(Message::Text(text)) => {
+                    if let Err(e) = self.handle_message(&text).await {
+                        tracing::error!("Failed to handle message: {}", e);
+                    }
+                }
+                // Some CDP payloads (e.g. screencast frames) arrive as binary frames
+                Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                    Ok(text) => {
+                        if let Err(e) = self.handle_message(&text).await {
+                            tracing::error!("Failed to handle binary message: {}", e);
                         }
                     }
-                    _ = shutdown_rx.recv() => {
-                        tracing::info!("Shutdown signal received");
-                        break;
+                    Err(e) => tracing::warn!("Dropping non-UTF-8 binary message: {}", e),
+                },
+                // Answer explicitly so proxies don't drop idle connections
+                Ok(Message::Ping(payload)) => {
+                    let mut sink = self.ws_sink.write().await;
+                    if let Err(e) = sink.send(Message::Pong(payload)).await {
+                        tracing::warn!("Failed to answer ping: {}", e);
                     }
                 }
+                Ok(Message::Close(_)) => break,
+                Err(e) => {
+                    tracing::error!("WebSocket error: {}", e);
+                    return;
+                }
+                _ => {}
             }
+        }
+        tracing::info!("WebSocket closed");
+    }
 
-            // Clear all pending requests
-            client_clone.pending.clear();
-        });
+    /// Re-establish the WebSocket with backoff; None on shutdown or give-up
+    async fn reconnect(
+        self: &Arc<Self>,
+        url: &str,
+        config: &ReconnectConfig,
+        shutdown_rx: &mut mpsc::Receiver<()>,
+    ) -> Option<WsStream> {
+        for attempt in 0..config.max_attempts {
+            let delay = config.delay_for(attempt);
+            tracing::warn!(
+                "CDP connection lost, reconnecting in {:?} (attempt {}/{})",
+                delay,
+                attempt + 1,
+                config.max_attempts
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                Some(()) = shutdown_rx.recv() => return None,
+            }
 
-        // Store shutdown channel (need to make client mutable - fix this with Arc<Mutex<Option<_>>>)
-        // For now, rely on Drop
+            match connect_async(url).await {
+                Ok((ws_stream, _)) => {
+                    let (sink, stream) = ws_stream.split();
+                    *self.ws_sink.write().await = sink;
+                    tracing::info!("CDP reconnected after {} attempt(s)", attempt + 1);
 
-        Ok(client)
+                    // Replay needs the receiver loop running to see responses
+                    let client = self.clone();
+                    tokio::spawn(async move {
+                        client.replay_enabled_domains().await;
+                        client.dispatch_event(CDPEvent {
+                            method: RECONNECTED_EVENT.to_string(),
+                            params: Some(serde_json::json!({ "attempts": attempt + 1 })),
+                            session_id: None,
+                        });
+                    });
+                    return Some(stream);
+                }
+                Err(e) => tracing::debug!("Reconnect attempt failed: {}", e),
+            }
+        }
+
+        tracing::error!(
+            "Giving up on CDP reconnect after {} attempts",
+            config.max_attempts
+        );
+        None
+    }
+
+    /// Re-send recorded browser-level `*.enable` commands
+    async fn replay_enabled_domains(&self) {
+        let enabled: Vec<(String, Option<Value>)> = self
+            .enabled_domains
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+
+        for (method, params) in enabled {
+            if let Err(e) = self.send_request(method.as_str(), params, None).await {
+                tracing::warn!("Failed to replay {} after reconnect: {}", method, e);
+            }
+        }
+    }
+
+    /// Connect, retrying while the browser is still starting up
+    ///
+    /// Chrome opens its debugging port slightly after the process starts.
+    /// Only connection-refused is retried - protocol/TLS errors fail immediately.
+    pub async fn connect_with_retry(
+        ws_url: &str,
+        attempts: usize,
+        delay: Duration,
+    ) -> Result<Arc<Self>> {
+        let attempts = attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            match Self::connect(ws_url).await {
+                Ok(client) => return Ok(client),
+                Err(e) if attempt < attempts && e.is_connection_refused() => {
+                    tracing::debug!(
+                        "CDP connection refused (attempt {}/{}), retrying in {:?}",
+                        attempt,
+                        attempts,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Connect through Chrome's HTTP endpoint, e.g. `http://localhost:9222`
+    ///
+    /// Looks up the browser's WebSocket URL in `/json/version`, so callers
+    /// don't need the per-launch `/devtools/browser/<id>` path.
+    pub async fn connect_http(http_url: &str) -> Result<Arc<Self>> {
+        let ws_url = discovery::browser_ws_url(http_url).await?;
+        tracing::debug!("Discovered browser WebSocket URL: {}", ws_url);
+        Self::connect(&ws_url).await
+    }
+
+    /// Connect straight to one page through Chrome's HTTP endpoint
+    ///
+    /// Picks `target_id` from `/json/list`, or the first page when `None`.
+    /// The connection is scoped to that page: commands need no session id,
+    /// and the `Target` domain can't reach other tabs.
+    pub async fn connect_http_target(http_url: &str, target_id: Option<&str>) -> Result<Arc<Self>> {
+        let targets = discovery::list_targets(http_url).await?;
+        let target = targets
+            .iter()
+            .find(|target| match target_id {
+                Some(id) => target.id == id,
+                None => target.target_type == "page",
+            })
+            .ok_or_else(|| {
+                CDPError::Discovery(match target_id {
+                    Some(id) => format!("no target {} in /json/list", id),
+                    None => "no page in /json/list".to_string(),
+                })
+            })?;
+        let ws_url = target.web_socket_debugger_url.as_deref().ok_or_else(|| {
+            CDPError::Discovery(format!(
+                "target {} has no WebSocket URL (another client attached?)",
+                target.id
+            ))
+        })?;
+        Self::connect(ws_url).await
+    }
+
+    /// Timeout applied by `send_request`
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms.load(Ordering::Relaxed))
     }
 
-    /// Send CDP request and wait for response
+    /// Change the timeout applied by `send_request`
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        self.request_timeout_ms
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Send CDP request and wait for response (up to `request_timeout`)
     pub async fn send_request(
         &self,
         method: impl Into<String>,
         params: Option<Value>,
         session_id: Option<SessionId>,
     ) -> Result<Value> {
+        self.send_request_with_timeout(method, params, session_id, self.request_timeout())
+            .await
+    }
+
+    /// Send CDP request with an explicit response timeout
+    ///
+    /// For commands that legitimately take long, e.g. navigation on slow pages.
+    pub async fn send_request_with_timeout(
+        &self,
+        method: impl Into<String>,
+        params: Option<Value>,
+        session_id: Option<SessionId>,
+        timeout: Duration,
+    ) -> Result<Value> {
+        let (json, request) = self.prepare(method.into(), params, session_id)?;
+
+        // Serialize and send
+        let mut sink = self.ws_sink.write().await;
+        let sent = sink.send(Message::Text(json)).await;
+        drop(sink); // Release lock immediately
+        if let Err(e) = sent {
+            self.pending.remove(&request.id);
+            return Err(CDPError::WebSocket(e));
+        }
+
+        self.await_response(request, timeout).await
+    }
+
+    /// `send_request`, retried under `policy` while the error is transient
+    ///
+    /// Only `CDPError::is_transient` errors are retried; any other error,
+    /// or the last attempt's, is returned as is.
+    pub async fn send_request_retry(
+        &self,
+        method: impl Into<String>,
+        params: Option<Value>,
+        session_id: Option<SessionId>,
+        policy: &RetryPolicy,
+    ) -> Result<Value> {
+        let method = method.into();
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .send_request(method.clone(), params.clone(), session_id.clone())
+                .await;
+            match result {
+                Err(e) if e.is_transient() && attempt + 1 < max_attempts => {
+                    let delay = policy.delay_for(attempt);
+                    tracing::debug!(
+                        "{} failed ({}), retrying in {:?} (attempt {}/{})",
+                        method,
+                        e,
+                        delay,
+                        attempt + 1,
+                        max_attempts
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send several requests, then wait for all the responses
+    ///
+    /// Every request is written before any response is awaited, so a batch
+    /// costs about one round trip instead of one per request. Results are in
+    /// input order; each request fails or times out (`request_timeout`) on
+    /// its own.
+    pub async fn send_batch(
+        &self,
+        requests: Vec<(String, Option<Value>, Option<SessionId>)>,
+    ) -> Vec<Result<Value>> {
+        let mut slots: Vec<Result<PendingRequest>> = Vec::with_capacity(requests.len());
+        {
+            // One lock for the whole batch keeps its requests together
+            let mut sink = self.ws_sink.write().await;
+            for (method, params, session_id) in requests {
+                let slot = match self.prepare(method, params, session_id) {
+                    Ok((json, request)) => match sink.send(Message::Text(json)).await {
+                        Ok(()) => Ok(request),
+                        Err(e) => {
+                            self.pending.remove(&request.id);
+                            Err(CDPError::WebSocket(e))
+                        }
+                    },
+                    Err(e) => Err(CDPError::Json(e)),
+                };
+                slots.push(slot);
+            }
+        }
+
+        let timeout = self.request_timeout();
+        futures_util::future::join_all(
+            slots
+                .into_iter()
+                .map(|slot| async move { self.await_response(slot?, timeout).await }),
+        )
+        .await
+    }
+
+    /// Register a request in `pending` and serialize it
+    ///
+    /// The response slot exists before the request is sent, so a fast reply
+    /// can't arrive unclaimed.
+    fn prepare(
+        &self,
+        method: String,
+        params: Option<Value>,
+        session_id: Option<SessionId>,
+    ) -> serde_json::Result<(String, PendingRequest)> {
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let replay = (method.ends_with(".enable") && session_id.is_none())
+            .then(|| (method.clone(), params.clone()));
         let request = CDPRequest {
             id,
-            method: method.into(),
+            method,
             params,
             session_id,
         };
+        let json = serde_json::to_string(&request)?;
 
         let (tx, rx) = oneshot::channel();
         self.pending.insert(id, tx);
+        Ok((json, PendingRequest { id, rx, replay }))
+    }
 
-        // Serialize and send
-        let json = serde_json::to_string(&request)?;
-        let mut sink = self.ws_sink.write().await;
-        sink.send(Message::Text(json))
-            .await
-            .map_err(|e| CDPError::WebSocket(e))?;
-        drop(sink); // Release lock immediately
-
-        // Wait for response
-        let response = rx.await.map_err(|_| CDPError::Closed)?;
+    /// Wait for the response to a sent request
+    async fn await_response(&self, request: PendingRequest, timeout: Duration) -> Result<Value> {
+        let response = match tokio::time::timeout(timeout, request.rx).await {
+            Ok(response) => response.map_err(|_| CDPError::Closed)?,
+            Err(_) => {
+                self.pending.remove(&request.id);
+                return Err(CDPError::Timeout);
+            }
+        };
 
         if let Some(error) = response.error {
             return Err(CDPError::Protocol {
                 code: error.code,
                 message: error.message,
+                data: error.data,
             });
         }
 
+        if let Some((method, params)) = request.replay {
+            self.enabled_domains.insert(method, params);
+        }
+
         Ok(response.result.unwrap_or(Value::Null))
     }
 
+    /// Send a typed CDP command and wait for response
+    pub async fn send_command<C: CdpCommand>(
+        &self,
+        command: &C,
+        session_id: Option<SessionId>,
+    ) -> Result<Value> {
+        let params = serde_json::to_value(command)?;
+        self.send_request(C::METHOD, Some(params), session_id).await
+    }
+
     /// Subscribe to CDP events
-    pub fn subscribe(&self, method: impl Into<String>, callback: EventCallback) {
-        let method = method.into();
+    ///
+    /// The callback stays registered until `unsubscribe` is called with the
+    /// returned ID.
+    pub fn subscribe(&self, method: impl Into<String>, callback: EventCallback) -> SubscriptionId {
+        let id = SubscriptionId(self.next_subscription_id.fetch_add(1, Ordering::Relaxed));
         self.subscribers
-            .entry(method)
-            .or_insert_with(Vec::new)
+            .entry(method.into())
+            .or_default()
+            .push((id, callback));
+        id
+    }
+
+    /// Subscribe to CDP events from one session only
+    ///
+    /// `None` receives only browser-level events (no `sessionId`). Remove
+    /// with `unsubscribe`, like any other subscription.
+    pub fn subscribe_session(
+        &self,
+        method: impl Into<String>,
+        session_id: Option<SessionId>,
+        callback: EventCallback,
+    ) -> SubscriptionId {
+        self.subscribe(
+            method,
+            Arc::new(move |event| {
+                if event.session_id == session_id {
+                    callback(event);
+                }
+            }),
+        )
+    }
+
+    /// Remove one callback registered with `subscribe`
+    ///
+    /// Returns false if it was already removed.
+    pub fn unsubscribe(&self, method: &str, id: SubscriptionId) -> bool {
+        let removed = match self.subscribers.get_mut(method) {
+            Some(mut callbacks) => {
+                let before = callbacks.len();
+                callbacks.retain(|(sub_id, _)| *sub_id != id);
+                callbacks.len() != before
+            }
+            None => false,
+        };
+        self.subscribers
+            .remove_if(method, |_, callbacks| callbacks.is_empty());
+        removed
+    }
+
+    /// Subscribe to every CDP event regardless of method
+    ///
+    /// Extension point for logging, analytics, or custom watchdogs.
+    pub fn subscribe_all(&self, callback: AllEventsCallback) {
+        self.all_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
             .push(callback);
     }
 
+    /// Remove all catch-all subscribers
+    pub fn unsubscribe_all(&self) {
+        self.all_subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
     /// Handle incoming WebSocket message
     async fn handle_message(&self, text: &str) -> Result<()> {
         let msg: CDPMessage = serde_json::from_str(text)?;
@@ -179,20 +794,49 @@ impl CDPClient {
                     tracing::warn!("Received response for unknown request: {}", response.id);
                 }
             }
-            CDPMessage::Event(event) => {
-                if let Some(subscribers) = self.subscribers.get(&event.method) {
-                    for callback in subscribers.value() {
-                        callback(event.clone());
-                    }
-                }
-            }
+            CDPMessage::Event(event) => self.dispatch_event(event),
         }
 
         Ok(())
     }
 
+    /// Fire method-specific subscribers, then catch-all subscribers
+    fn dispatch_event(&self, event: CDPEvent) {
+        // Clone out of the map so callbacks may (un)subscribe without deadlocking
+        let callbacks: Vec<EventCallback> = self
+            .subscribers
+            .get(&event.method)
+            .map(|subs| subs.iter().map(|(_, cb)| cb.clone()).collect())
+            .unwrap_or_default();
+        for callback in callbacks {
+            callback(event.clone());
+        }
+
+        // Snapshot so callbacks may (un)subscribe without deadlocking
+        let all_subscribers = self
+            .all_subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        for callback in &all_subscribers {
+            callback(&event);
+        }
+    }
+
     /// Close connection gracefully
+    ///
+    /// Stops the receiver task (which holds its own `Arc` to the client)
+    /// even if the remote end never acknowledges the close frame.
     pub async fn close(self: Arc<Self>) -> Result<()> {
+        let shutdown_tx = self
+            .shutdown_tx
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(tx) = shutdown_tx {
+            let _ = tx.try_send(());
+        }
+
         let mut sink = self.ws_sink.write().await;
         sink.close().await?;
         Ok(())
@@ -202,6 +846,7 @@ impl CDPClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cdp::mock::{self, Reply};
 
     // Note: Real tests need a running Chrome instance
     // These are just compilation tests
@@ -220,4 +865,703 @@ mod tests {
 
         println!("Browser version: {:?}", result);
     }
+
+    #[test]
+    fn test_protocol_error_display() {
+        let error = |data: Option<Value>| CDPError::Protocol {
+            code: -32000,
+            message: "Cannot navigate".to_string(),
+            data,
+        };
+        assert_eq!(
+            error(None).to_string(),
+            "CDP protocol error: -32000 - Cannot navigate"
+        );
+        assert_eq!(
+            error(Some(Value::String("blocked by client".to_string()))).to_string(),
+            "CDP protocol error: -32000 - Cannot navigate (blocked by client)"
+        );
+        assert_eq!(
+            error(Some(serde_json::json!({ "nodeId": 7 }))).to_string(),
+            r#"CDP protocol error: -32000 - Cannot navigate ({"nodeId":7})"#
+        );
+        let long = error(Some(Value::String("x".repeat(500)))).to_string();
+        assert!(long.ends_with("...)"));
+        assert!(long.len() < 300);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_keeps_data() {
+        // Mock browser that fails every request with a data payload
+        let client = mock::connect(|request| {
+            Reply::Messages(vec![serde_json::json!({
+                "id": request["id"],
+                "error": { "code": -32000, "message": "No node", "data": "nodeId 42" },
+            })])
+        })
+        .await;
+        let err = client
+            .send_request("DOM.describeNode", None, None)
+            .await
+            .unwrap_err();
+        match err {
+            CDPError::Protocol { code, data, .. } => {
+                assert_eq!(code, -32000);
+                assert_eq!(data, Some(Value::String("nodeId 42".to_string())));
+            }
+            other => panic!("expected a protocol error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_request_retry() {
+        // Mock browser: "Flaky.call" fails twice mid-navigation, then
+        // succeeds; "Broken.call" always fails with a final error
+        let attempts = Arc::new(std::sync::Mutex::new(std::collections::HashMap::<
+            String,
+            usize,
+        >::new()));
+        let counted = attempts.clone();
+        let client = mock::connect(move |request| {
+            let method = request["method"].as_str().unwrap().to_string();
+            let attempt = {
+                let mut attempts = counted.lock().unwrap();
+                let count = attempts.entry(method.clone()).or_default();
+                *count += 1;
+                *count
+            };
+            let error = |code: i32, message: &str| {
+                Reply::Messages(vec![serde_json::json!({
+                    "id": request["id"],
+                    "error": { "code": code, "message": message },
+                })])
+            };
+            match method.as_str() {
+                "Flaky.call" if attempt < 3 => error(-32000, "Execution context was destroyed."),
+                "Broken.call" => error(-32601, "'Broken.call' wasn't found"),
+                _ => Reply::Result(serde_json::json!({ "ok": true })),
+            }
+        })
+        .await;
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            jitter: 0.0,
+        };
+
+        let result = client
+            .send_request_retry("Flaky.call", None, None, &policy)
+            .await
+            .unwrap();
+        assert_eq!(result["ok"], true);
+
+        let err = client
+            .send_request_retry("Broken.call", None, None, &policy)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CDPError::Protocol { code: -32601, .. }));
+
+        // The counter is past the failures, so a single attempt does
+        let result = client
+            .send_request_retry("Flaky.call", None, None, &RetryPolicy::none())
+            .await;
+        assert!(result.is_ok());
+
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(attempts["Flaky.call"], 4);
+        assert_eq!(attempts["Broken.call"], 1);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let protocol = |code: i32, message: &str| CDPError::Protocol {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+        assert!(CDPError::Timeout.is_transient());
+        assert!(protocol(-32000, "Cannot find context with specified id").is_transient());
+        assert!(!protocol(-32000, "No node with given id found").is_transient());
+        assert!(!protocol(-32602, "Execution context was destroyed").is_transient());
+        assert!(!CDPError::Closed.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_pipelines_and_keeps_order() {
+        use futures_util::{SinkExt, StreamExt};
+
+        // Mock browser that only answers once the whole batch is in, last
+        // request first; "Fail.me" gets an error
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut requests = Vec::new();
+            while requests.len() < 3 {
+                let Some(Ok(Message::Text(text))) = ws.next().await else {
+                    return;
+                };
+                requests.push(serde_json::from_str::<Value>(&text).unwrap());
+            }
+            for request in requests.iter().rev() {
+                let reply = match request["method"].as_str().unwrap() {
+                    "Fail.me" => serde_json::json!({
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": "'Fail.me' wasn't found" },
+                    }),
+                    method => serde_json::json!({
+                        "id": request["id"],
+                        "result": { "method": method, "session": request["sessionId"] },
+                    }),
+                };
+                ws.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+            std::future::pending::<()>().await;
+        });
+
+        let client = CDPClient::connect(&format!("ws://127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        client.set_request_timeout(Duration::from_secs(5));
+        let results = client
+            .send_batch(vec![
+                ("Page.enable".to_string(), None, Some("S1".to_string())),
+                ("Fail.me".to_string(), None, None),
+                ("DOM.enable".to_string(), None, Some("S2".to_string())),
+            ])
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &serde_json::json!({ "method": "Page.enable", "session": "S1" })
+        );
+        assert!(matches!(
+            results[1],
+            Err(CDPError::Protocol { code: -32601, .. })
+        ));
+        assert_eq!(results[2].as_ref().unwrap()["method"], "DOM.enable");
+        assert!(client.pending.is_empty());
+        assert!(client.send_batch(Vec::new()).await.is_empty());
+    }
+
+    /// Reserve a free local port by binding and immediately releasing it
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Connect to a mock browser that never answers
+    async fn connect_to_mock() -> Arc<CDPClient> {
+        mock::connect(|_| Reply::Ignore).await
+    }
+
+    #[tokio::test]
+    async fn test_send_request_times_out() {
+        let client = connect_to_mock().await;
+        assert_eq!(client.request_timeout(), DEFAULT_REQUEST_TIMEOUT);
+
+        let err = client
+            .send_request_with_timeout("Browser.getVersion", None, None, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CDPError::Timeout));
+        assert!(client.pending.is_empty());
+
+        client.set_request_timeout(Duration::from_millis(50));
+        let err = client
+            .send_request("Browser.getVersion", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CDPError::Timeout));
+        assert!(client.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_stops_receiver_tasks() {
+        // Mock browser that accepts connections and never sends a close frame
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    std::future::pending::<()>().await;
+                });
+            }
+        });
+
+        let url = format!("ws://127.0.0.1:{}", port);
+        let mut clients = Vec::new();
+        for _ in 0..20 {
+            let client = CDPClient::connect(&url).await.unwrap();
+            clients.push(Arc::downgrade(&client));
+            client.close().await.unwrap();
+        }
+
+        // The receiver task owns the last Arc - once it exits the client is freed
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while clients.iter().any(|weak| weak.upgrade().is_some()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("receiver tasks should exit after close");
+    }
+
+    #[tokio::test]
+    async fn test_on_close_runs_when_browser_goes_away() {
+        // Mock browser that closes the socket after its first message
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    ws.next().await;
+                    let _ = ws.close(None).await;
+                });
+            }
+        });
+        let url = format!("ws://127.0.0.1:{}", port);
+
+        let client = CDPClient::connect(&url).await.unwrap();
+        assert!(client.is_connected());
+        let (closed_tx, closed_rx) = oneshot::channel();
+        client.on_close(Box::new(move || {
+            let _ = closed_tx.send(());
+        }));
+
+        let _ = client.send_request("Browser.close", None, None).await;
+        tokio::time::timeout(Duration::from_secs(5), closed_rx)
+            .await
+            .expect("close hook")
+            .unwrap();
+        assert!(!client.is_connected());
+
+        // Registered after the fact: runs right away
+        let (late_tx, mut late_rx) = oneshot::channel();
+        client.on_close(Box::new(move || {
+            let _ = late_tx.send(());
+        }));
+        assert!(late_rx.try_recv().is_ok());
+
+        // A deliberate close doesn't run hooks
+        let client = CDPClient::connect(&url).await.unwrap();
+        let (closed_tx, mut closed_rx) = oneshot::channel();
+        client.on_close(Box::new(move || {
+            let _ = closed_tx.send(());
+        }));
+        client.clone().close().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.is_connected() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("receiver task should exit after close");
+        assert!(matches!(
+            closed_rx.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_http_discovers_ws_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Mock Chrome: HTTP discovery and WebSockets on one port, reporting
+        // its WebSocket URLs under a host it can't be reached at
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (path_tx, mut path_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let path_tx = path_tx.clone();
+                tokio::spawn(async move {
+                    let mut head = vec![0; 1024];
+                    let n = stream.peek(&mut head).await.unwrap();
+                    let head = String::from_utf8_lossy(&head[..n]).to_string();
+                    let ws_url =
+                        |path: &str| format!("ws://chrome.internal:9222/devtools/{}", path);
+                    let body = if head.starts_with("GET /json/version ") {
+                        serde_json::json!({
+                            "Browser": "Chrome/120",
+                            "webSocketDebuggerUrl": ws_url("browser/B1"),
+                        })
+                    } else if head.starts_with("GET /json/list ") {
+                        serde_json::json!([
+                            {"id": "W1", "type": "service_worker", "webSocketDebuggerUrl": ws_url("page/W1")},
+                            {"id": "P1", "type": "page", "title": "One", "webSocketDebuggerUrl": ws_url("page/P1")},
+                            {"id": "P2", "type": "page", "title": "Two"},
+                        ])
+                    } else {
+                        // tungstenite's callback signature, not ours to shrink
+                        #[allow(clippy::result_large_err)]
+                        let callback = |request: &tokio_tungstenite::tungstenite::handshake::server::Request,
+                                        response| {
+                            let _ = path_tx.send(request.uri().path().to_string());
+                            Ok(response)
+                        };
+                        let _ws = tokio_tungstenite::accept_hdr_async(stream, callback)
+                            .await
+                            .unwrap();
+                        std::future::pending::<()>().await;
+                        return;
+                    };
+                    // Consume the request, or closing resets the connection
+                    let _ = stream.read(&mut vec![0; 1024]).await;
+                    let body = body.to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        let http_url = format!("http://127.0.0.1:{}", port);
+
+        let targets = discovery::list_targets(&http_url).await.unwrap();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[1].title, "One");
+        assert_eq!(
+            targets[1].web_socket_debugger_url.as_deref(),
+            Some(format!("ws://127.0.0.1:{}/devtools/page/P1", port).as_str())
+        );
+
+        let _browser = CDPClient::connect_http(&http_url).await.unwrap();
+        assert_eq!(path_rx.recv().await.unwrap(), "/devtools/browser/B1");
+        // The first page, not the service worker
+        let _page = CDPClient::connect_http_target(&http_url, None)
+            .await
+            .unwrap();
+        assert_eq!(path_rx.recv().await.unwrap(), "/devtools/page/P1");
+
+        for target_id in ["P2", "P9"] {
+            let Err(err) = CDPClient::connect_http_target(&http_url, Some(target_id)).await else {
+                panic!("{} should not connect", target_id);
+            };
+            assert!(matches!(err, CDPError::Discovery(_)), "{:?}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_removes_only_that_callback() {
+        let client = connect_to_mock().await;
+        let hits = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let subscribe = |label: &'static str| {
+            let hits = hits.clone();
+            client.subscribe(
+                "Page.loadEventFired",
+                Arc::new(move |_| hits.lock().unwrap().push(label)),
+            )
+        };
+        let first = subscribe("first");
+        let _second = subscribe("second");
+
+        let fire = || client.handle_message(r#"{"method":"Page.loadEventFired","params":{}}"#);
+        fire().await.unwrap();
+        assert_eq!(*hits.lock().unwrap(), ["first", "second"]);
+
+        assert!(client.unsubscribe("Page.loadEventFired", first));
+        assert!(!client.unsubscribe("Page.loadEventFired", first));
+        fire().await.unwrap();
+        assert_eq!(*hits.lock().unwrap(), ["first", "second", "second"]);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_session_filters_by_session() {
+        let client = connect_to_mock().await;
+        let hits = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let subscribe = |label: &'static str, session: Option<&str>| {
+            let hits = hits.clone();
+            client.subscribe_session(
+                "Network.requestWillBeSent",
+                session.map(str::to_string),
+                Arc::new(move |_| hits.lock().unwrap().push(label)),
+            )
+        };
+        let tab_a = subscribe("a", Some("A"));
+        subscribe("b", Some("B"));
+        subscribe("browser", None);
+
+        for session in [r#","sessionId":"A""#, r#","sessionId":"B""#, ""] {
+            let message = format!(
+                r#"{{"method":"Network.requestWillBeSent","params":{{}}{}}}"#,
+                session
+            );
+            client.handle_message(&message).await.unwrap();
+        }
+        assert_eq!(*hits.lock().unwrap(), ["a", "b", "browser"]);
+
+        assert!(client.unsubscribe("Network.requestWillBeSent", tab_a));
+        client
+            .handle_message(r#"{"method":"Network.requestWillBeSent","sessionId":"A"}"#)
+            .await
+            .unwrap();
+        assert_eq!(hits.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_callback_can_unsubscribe_itself() {
+        let client = connect_to_mock().await;
+        let count = Arc::new(AtomicU64::new(0));
+
+        let id = Arc::new(std::sync::Mutex::new(None));
+        let (client_ref, id_ref, count_ref) = (Arc::downgrade(&client), id.clone(), count.clone());
+        *id.lock().unwrap() = Some(client.subscribe(
+            "Page.loadEventFired",
+            Arc::new(move |_| {
+                count_ref.fetch_add(1, Ordering::SeqCst);
+                if let (Some(client), Some(id)) = (client_ref.upgrade(), *id_ref.lock().unwrap()) {
+                    client.unsubscribe("Page.loadEventFired", id);
+                }
+            }),
+        ));
+
+        for _ in 0..3 {
+            client
+                .handle_message(r#"{"method":"Page.loadEventFired","params":{}}"#)
+                .await
+                .unwrap();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(client.subscribers.is_empty());
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let config = ReconnectConfig {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            backoff_factor: 2.0,
+        };
+        assert_eq!(config.delay_for(0), Duration::from_millis(100));
+        assert_eq!(config.delay_for(1), Duration::from_millis(200));
+        assert_eq!(config.delay_for(3), Duration::from_millis(800));
+        assert_eq!(config.delay_for(4), Duration::from_millis(1000));
+        assert_eq!(config.delay_for(1000), Duration::from_millis(1000));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_drop() {
+        // Mock browser: answers every request, except `Test.dropConnection`
+        // which kills the socket without replying. Reports (connection, method).
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (seen_tx, mut seen_rx) = mpsc::unbounded_channel::<(usize, String)>();
+        tokio::spawn(async move {
+            for conn in 1.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let seen_tx = seen_tx.clone();
+                tokio::spawn(async move {
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let request: Value = serde_json::from_str(&text).unwrap();
+                        let method = request["method"].as_str().unwrap().to_string();
+                        let _ = seen_tx.send((conn, method.clone()));
+                        if method == "Test.dropConnection" {
+                            return;
+                        }
+                        let reply = serde_json::json!({ "id": request["id"], "result": {} });
+                        ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    }
+                });
+            }
+        });
+
+        let config = ReconnectConfig {
+            initial_delay: Duration::from_millis(10),
+            ..ReconnectConfig::default()
+        };
+        let client = CDPClient::connect_with_reconnect(&format!("ws://127.0.0.1:{}", port), config)
+            .await
+            .unwrap();
+
+        let (reconnected_tx, mut reconnected_rx) = mpsc::unbounded_channel();
+        client.subscribe(
+            RECONNECTED_EVENT,
+            Arc::new(move |_| {
+                let _ = reconnected_tx.send(());
+            }),
+        );
+
+        client
+            .send_request("Network.enable", None, None)
+            .await
+            .unwrap();
+
+        // In flight at disconnect: fails instead of hanging
+        let err = client
+            .send_request("Test.dropConnection", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CDPError::Closed), "{:?}", err);
+
+        tokio::time::timeout(Duration::from_secs(5), reconnected_rx.recv())
+            .await
+            .expect("reconnect event")
+            .unwrap();
+
+        let mut seen = Vec::new();
+        while let Ok(entry) = seen_rx.try_recv() {
+            seen.push(entry);
+        }
+        assert!(
+            seen.contains(&(2, "Network.enable".to_string())),
+            "enable should be replayed on the new socket: {:?}",
+            seen
+        );
+
+        client
+            .send_request("Browser.getVersion", None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_binary_messages_and_ping() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (pong_tx, pong_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            // Wait for the client's go-ahead so its subscriber is in place
+            while !matches!(ws.next().await, Some(Ok(Message::Text(_)))) {}
+
+            let event = r#"{"method":"Page.screencastFrame","params":{"sessionId":1}}"#;
+            ws.send(Message::Binary(event.as_bytes().to_vec()))
+                .await
+                .unwrap();
+            ws.send(Message::Ping(b"keepalive".to_vec())).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let Message::Pong(payload) = msg {
+                    let _ = pong_tx.send(payload);
+                    break;
+                }
+            }
+            std::future::pending::<()>().await;
+        });
+
+        let client = CDPClient::connect(&format!("ws://127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        let (frame_tx, mut frame_rx) = mpsc::unbounded_channel();
+        client.subscribe(
+            "Page.screencastFrame",
+            Arc::new(move |event| {
+                let _ = frame_tx.send(event.params);
+            }),
+        );
+        let _ = client
+            .send_request_with_timeout("Test.start", None, None, Duration::from_millis(10))
+            .await;
+
+        let pong = tokio::time::timeout(Duration::from_secs(5), pong_rx)
+            .await
+            .expect("pong")
+            .unwrap();
+        assert_eq!(pong, b"keepalive");
+
+        let params = tokio::time::timeout(Duration::from_secs(5), frame_rx.recv())
+            .await
+            .expect("binary event dispatched")
+            .unwrap();
+        assert_eq!(params.unwrap()["sessionId"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_all_fires_for_any_method() {
+        let client = connect_to_mock().await;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        client.subscribe_all(Arc::new(move |event| {
+            seen_clone.lock().unwrap().push(event.method.clone());
+        }));
+
+        client
+            .handle_message(r#"{"method":"Custom.somethingHappened","params":{}}"#)
+            .await
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["Custom.somethingHappened"]);
+
+        client.unsubscribe_all();
+        client
+            .handle_message(r#"{"method":"Page.loadEventFired","params":{}}"#)
+            .await
+            .unwrap();
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_waits_for_listener() {
+        let port = free_port();
+
+        // Mock browser: nothing listening at first, then accepts a WebSocket
+        let server = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+                .await
+                .unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        });
+
+        let url = format!("ws://127.0.0.1:{}", port);
+        let client = CDPClient::connect_with_retry(&url, 20, Duration::from_millis(50)).await;
+        assert!(
+            client.is_ok(),
+            "Expected retry to succeed: {:?}",
+            client.err()
+        );
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_gives_up() {
+        let port = free_port();
+        let url = format!("ws://127.0.0.1:{}", port);
+
+        let err = CDPClient::connect_with_retry(&url, 3, Duration::from_millis(10))
+            .await
+            .err()
+            .expect("Nothing is listening");
+        assert!(err.is_connection_refused());
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_retry_fails_fast_on_protocol_error() {
+        // Plain TCP server that never speaks WebSocket - not retryable
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            use tokio::io::AsyncWriteExt;
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\n\r\n").await;
+        });
+
+        let url = format!("ws://127.0.0.1:{}", port);
+        let err = CDPClient::connect_with_retry(&url, 5, Duration::from_millis(10))
+            .await
+            .err()
+            .expect("Handshake must fail");
+        assert!(!err.is_connection_refused());
+    }
 }
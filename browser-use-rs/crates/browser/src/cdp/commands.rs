@@ -0,0 +1,1057 @@
+//! Typed CDP Command Parameters
+//!
+//! Hand-written `json!({...})` fails silently at Chrome when a field name is wrong.
+//! These structs serialize to the exact wire shape, so typos become compile errors.
+//!
+//! Only the commands this crate actually sends live here. Add more as needed.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::protocol::{Cookie, SessionId, TargetId};
+
+/// A CDP command with a fixed method name
+pub trait CdpCommand: Serialize {
+    const METHOD: &'static str;
+}
+
+/// Page.navigate
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Navigate {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition_type: Option<String>,
+}
+
+impl Navigate {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            referrer: None,
+            transition_type: None,
+        }
+    }
+}
+
+impl CdpCommand for Navigate {
+    const METHOD: &'static str = "Page.navigate";
+}
+
+/// Page.reload
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_cache: Option<bool>,
+}
+
+impl CdpCommand for Reload {
+    const METHOD: &'static str = "Page.reload";
+}
+
+/// Page.stopLoading
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StopLoading {}
+
+impl CdpCommand for StopLoading {
+    const METHOD: &'static str = "Page.stopLoading";
+}
+
+/// Page.handleJavaScriptDialog
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleJavaScriptDialog {
+    pub accept: bool,
+    /// Text entered into a `prompt()` before accepting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_text: Option<String>,
+}
+
+impl CdpCommand for HandleJavaScriptDialog {
+    const METHOD: &'static str = "Page.handleJavaScriptDialog";
+}
+
+/// Page.getNavigationHistory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GetNavigationHistory {}
+
+impl CdpCommand for GetNavigationHistory {
+    const METHOD: &'static str = "Page.getNavigationHistory";
+}
+
+/// Page.navigateToHistoryEntry
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigateToHistoryEntry {
+    pub entry_id: i64,
+}
+
+impl CdpCommand for NavigateToHistoryEntry {
+    const METHOD: &'static str = "Page.navigateToHistoryEntry";
+}
+
+/// Network.getCookies
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GetCookies {
+    /// Defaults to the URLs of the page and its frames
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urls: Option<Vec<String>>,
+}
+
+impl CdpCommand for GetCookies {
+    const METHOD: &'static str = "Network.getCookies";
+}
+
+/// Network.setCookies
+#[derive(Debug, Clone, Serialize)]
+pub struct SetCookies {
+    pub cookies: Vec<Cookie>,
+}
+
+impl CdpCommand for SetCookies {
+    const METHOD: &'static str = "Network.setCookies";
+}
+
+/// Network.clearBrowserCookies
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClearBrowserCookies {}
+
+impl CdpCommand for ClearBrowserCookies {
+    const METHOD: &'static str = "Network.clearBrowserCookies";
+}
+
+/// One `Fetch.enable` interception pattern
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestPattern {
+    /// Wildcards `*` and `?` allowed; omitted means every URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url_pattern: Option<String>,
+    /// e.g. "Document", "Image", "Script", "XHR"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_type: Option<String>,
+    /// "Request" (default) or "Response"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_stage: Option<String>,
+}
+
+/// Fetch.enable
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FetchEnable {
+    pub patterns: Vec<RequestPattern>,
+}
+
+impl CdpCommand for FetchEnable {
+    const METHOD: &'static str = "Fetch.enable";
+}
+
+/// Fetch.disable - releases any paused requests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FetchDisable {}
+
+impl CdpCommand for FetchDisable {
+    const METHOD: &'static str = "Fetch.disable";
+}
+
+/// Fetch.continueRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueRequest {
+    pub request_id: String,
+}
+
+impl CdpCommand for ContinueRequest {
+    const METHOD: &'static str = "Fetch.continueRequest";
+}
+
+/// Fetch.failRequest
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailRequest {
+    pub request_id: String,
+    /// Network.ErrorReason, e.g. "BlockedByClient"
+    pub error_reason: String,
+}
+
+impl CdpCommand for FailRequest {
+    const METHOD: &'static str = "Fetch.failRequest";
+}
+
+/// Target.createTarget
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTarget {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_window: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
+}
+
+impl CreateTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            new_window: None,
+            background: None,
+        }
+    }
+}
+
+impl CdpCommand for CreateTarget {
+    const METHOD: &'static str = "Target.createTarget";
+}
+
+/// Target.closeTarget
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseTarget {
+    pub target_id: TargetId,
+}
+
+impl CdpCommand for CloseTarget {
+    const METHOD: &'static str = "Target.closeTarget";
+}
+
+/// Target.getTargets
+#[derive(Debug, Clone, Serialize)]
+pub struct GetTargets {}
+
+impl CdpCommand for GetTargets {
+    const METHOD: &'static str = "Target.getTargets";
+}
+
+/// Target.setDiscoverTargets - report targets via `Target.targetCreated`
+/// and `Target.targetDestroyed`
+#[derive(Debug, Clone, Serialize)]
+pub struct SetDiscoverTargets {
+    pub discover: bool,
+}
+
+impl CdpCommand for SetDiscoverTargets {
+    const METHOD: &'static str = "Target.setDiscoverTargets";
+}
+
+/// Target.attachToTarget
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachToTarget {
+    pub target_id: TargetId,
+    pub flatten: bool,
+}
+
+impl CdpCommand for AttachToTarget {
+    const METHOD: &'static str = "Target.attachToTarget";
+}
+
+/// Target.detachFromTarget
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetachFromTarget {
+    pub session_id: SessionId,
+}
+
+impl CdpCommand for DetachFromTarget {
+    const METHOD: &'static str = "Target.detachFromTarget";
+}
+
+/// Target.getTargetInfo
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTargetInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<TargetId>,
+}
+
+impl CdpCommand for GetTargetInfo {
+    const METHOD: &'static str = "Target.getTargetInfo";
+}
+
+/// Mouse event type for Input.dispatchMouseEvent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MouseEventType {
+    MousePressed,
+    MouseReleased,
+    MouseMoved,
+    MouseWheel,
+}
+
+/// Mouse button for Input.dispatchMouseEvent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    None,
+    Left,
+    Middle,
+    Right,
+    Back,
+    Forward,
+}
+
+/// Input.dispatchMouseEvent
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchMouseEvent {
+    #[serde(rename = "type")]
+    pub event_type: MouseEventType,
+    pub x: f64,
+    pub y: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<MouseButton>,
+    /// Bitmask of pressed buttons (1 = left)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buttons: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub click_count: Option<u32>,
+    /// Bitmask: Alt=1, Ctrl=2, Meta=4, Shift=8
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifiers: Option<u32>,
+    /// Horizontal wheel scroll in CSS pixels (mouseWheel only)
+    #[serde(rename = "deltaX", skip_serializing_if = "Option::is_none")]
+    pub delta_x: Option<f64>,
+    /// Vertical wheel scroll in CSS pixels, positive is down (mouseWheel only)
+    #[serde(rename = "deltaY", skip_serializing_if = "Option::is_none")]
+    pub delta_y: Option<f64>,
+}
+
+impl DispatchMouseEvent {
+    pub fn new(event_type: MouseEventType, x: f64, y: f64) -> Self {
+        Self {
+            event_type,
+            x,
+            y,
+            button: None,
+            buttons: None,
+            click_count: None,
+            modifiers: None,
+            delta_x: None,
+            delta_y: None,
+        }
+    }
+
+    /// Wheel scroll by `(delta_x, delta_y)` with the pointer at `(x, y)`
+    pub fn wheel(x: f64, y: f64, delta_x: f64, delta_y: f64) -> Self {
+        Self {
+            delta_x: Some(delta_x),
+            delta_y: Some(delta_y),
+            ..Self::new(MouseEventType::MouseWheel, x, y)
+        }
+    }
+}
+
+impl CdpCommand for DispatchMouseEvent {
+    const METHOD: &'static str = "Input.dispatchMouseEvent";
+}
+
+/// Key event type for Input.dispatchKeyEvent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyEventType {
+    KeyDown,
+    KeyUp,
+    RawKeyDown,
+    Char,
+}
+
+/// Input.dispatchKeyEvent
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchKeyEvent {
+    #[serde(rename = "type")]
+    pub event_type: KeyEventType,
+    /// DOM `KeyboardEvent.key`, e.g. "Enter"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// DOM `KeyboardEvent.code`, e.g. "Enter"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Text the key produces, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows_virtual_key_code: Option<u32>,
+    /// Bitmask, see `Modifiers`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifiers: Option<u32>,
+    /// Editing commands to run, e.g. "selectAll"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+impl DispatchKeyEvent {
+    /// Event for a named key with no modifiers
+    pub fn key(
+        event_type: KeyEventType,
+        key: &str,
+        virtual_key_code: u32,
+        text: Option<&str>,
+    ) -> Self {
+        Self {
+            event_type,
+            key: Some(key.to_string()),
+            code: Some(key.to_string()),
+            text: text.map(str::to_string),
+            windows_virtual_key_code: Some(virtual_key_code),
+            modifiers: None,
+            commands: Vec::new(),
+        }
+    }
+
+    /// keyDown + keyUp for `key` with `modifiers` held
+    ///
+    /// Keys that produce text are sent as `keyDown` with the text (shifted
+    /// for Shift); with Ctrl, Alt or Meta held, or for keys without text,
+    /// `rawKeyDown` is sent so nothing gets typed.
+    pub fn press(key: Key, modifiers: Modifiers) -> [Self; 2] {
+        let (name, code, virtual_key_code) = key.definition();
+        let shifted = modifiers.contains(Modifiers::SHIFT);
+        let name = match key {
+            Key::Char(c) if shifted => c.to_uppercase().to_string(),
+            _ => name,
+        };
+        let text = match key {
+            Key::Enter => Some("\r".to_string()),
+            Key::Space | Key::Char(_) => Some(name.clone()),
+            _ => None,
+        }
+        .filter(|_| !modifiers.intersects(Modifiers::ALT | Modifiers::CTRL | Modifiers::META));
+
+        let event = |event_type, text| Self {
+            event_type,
+            key: Some(name.clone()),
+            code: Some(code.to_string()).filter(|code| !code.is_empty()),
+            text,
+            windows_virtual_key_code: Some(virtual_key_code).filter(|&vk| vk != 0),
+            modifiers: Some(modifiers.bits()).filter(|&bits| bits != 0),
+            commands: Vec::new(),
+        };
+        let down_type = if text.is_some() {
+            KeyEventType::KeyDown
+        } else {
+            KeyEventType::RawKeyDown
+        };
+        [event(down_type, text), event(KeyEventType::KeyUp, None)]
+    }
+}
+
+/// Keyboard modifier bitmask as CDP input events expect it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u32);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const ALT: Self = Self(1);
+    pub const CTRL: Self = Self(2);
+    pub const META: Self = Self(4);
+    pub const SHIFT: Self = Self(8);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// All of `other` is held
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Any of `other` is held
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Key for `CDPSession::press_key`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Insert,
+    Space,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// F1-F12
+    F(u8),
+    /// A printable character; letters and digits get their physical key
+    Char(char),
+}
+
+impl Key {
+    /// DOM `key`, DOM `code` ("" if unknown) and Windows virtual key code (0 if unknown)
+    fn definition(self) -> (String, String, u32) {
+        let named = |key: &str, code: &str, vk: u32| (key.to_string(), code.to_string(), vk);
+        match self {
+            Key::Enter => named("Enter", "Enter", 13),
+            Key::Tab => named("Tab", "Tab", 9),
+            Key::Escape => named("Escape", "Escape", 27),
+            Key::Backspace => named("Backspace", "Backspace", 8),
+            Key::Delete => named("Delete", "Delete", 46),
+            Key::Insert => named("Insert", "Insert", 45),
+            Key::Space => named(" ", "Space", 32),
+            Key::ArrowUp => named("ArrowUp", "ArrowUp", 38),
+            Key::ArrowDown => named("ArrowDown", "ArrowDown", 40),
+            Key::ArrowLeft => named("ArrowLeft", "ArrowLeft", 37),
+            Key::ArrowRight => named("ArrowRight", "ArrowRight", 39),
+            Key::Home => named("Home", "Home", 36),
+            Key::End => named("End", "End", 35),
+            Key::PageUp => named("PageUp", "PageUp", 33),
+            Key::PageDown => named("PageDown", "PageDown", 34),
+            Key::F(n @ 1..=12) => {
+                let name = format!("F{}", n);
+                (name.clone(), name, 111 + n as u32)
+            }
+            Key::F(n) => (format!("F{}", n), String::new(), 0),
+            Key::Char(c) if c.is_ascii_alphabetic() => {
+                let upper = c.to_ascii_uppercase();
+                (c.to_string(), format!("Key{}", upper), upper as u32)
+            }
+            Key::Char(c) if c.is_ascii_digit() => (c.to_string(), format!("Digit{}", c), c as u32),
+            Key::Char(c) => (c.to_string(), String::new(), 0),
+        }
+    }
+}
+
+impl CdpCommand for DispatchKeyEvent {
+    const METHOD: &'static str = "Input.dispatchKeyEvent";
+}
+
+/// Input.insertText - types text into the focused element as an IME commit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InsertText {
+    pub text: String,
+}
+
+impl CdpCommand for InsertText {
+    const METHOD: &'static str = "Input.insertText";
+}
+
+/// DOMSnapshot.captureSnapshot
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSnapshot {
+    pub computed_styles: Vec<String>,
+    pub include_paint_order: bool,
+    #[serde(rename = "includeDOMRects")]
+    pub include_dom_rects: bool,
+}
+
+impl Default for CaptureSnapshot {
+    fn default() -> Self {
+        Self {
+            // Styles come back positionally, in the order dom expects
+            computed_styles: dom::REQUIRED_COMPUTED_STYLES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            include_paint_order: true,
+            include_dom_rects: true,
+        }
+    }
+}
+
+impl CdpCommand for CaptureSnapshot {
+    const METHOD: &'static str = "DOMSnapshot.captureSnapshot";
+}
+
+/// DOM.getDocument
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDocument {
+    /// -1 for the entire subtree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pierce: Option<bool>,
+}
+
+impl CdpCommand for GetDocument {
+    const METHOD: &'static str = "DOM.getDocument";
+}
+
+/// Accessibility.getFullAXTree
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetFullAXTree {
+    /// Omit for the whole tree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<i32>,
+}
+
+impl CdpCommand for GetFullAXTree {
+    const METHOD: &'static str = "Accessibility.getFullAXTree";
+}
+
+/// DOM.getBoxModel
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBoxModel {
+    pub backend_node_id: u32,
+}
+
+impl CdpCommand for GetBoxModel {
+    const METHOD: &'static str = "DOM.getBoxModel";
+}
+
+/// DOM.scrollIntoViewIfNeeded
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrollIntoViewIfNeeded {
+    pub backend_node_id: u32,
+}
+
+impl CdpCommand for ScrollIntoViewIfNeeded {
+    const METHOD: &'static str = "DOM.scrollIntoViewIfNeeded";
+}
+
+/// Page.getLayoutMetrics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GetLayoutMetrics {}
+
+impl CdpCommand for GetLayoutMetrics {
+    const METHOD: &'static str = "Page.getLayoutMetrics";
+}
+
+/// Image format for Page.captureScreenshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ScreenshotFormat {
+    /// Whether `CaptureScreenshot::quality` applies
+    pub fn is_lossy(self) -> bool {
+        matches!(self, Self::Jpeg | Self::Webp)
+    }
+}
+
+/// Capture region for Page.captureScreenshot (CSS pixels, page coordinates)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Viewport {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+}
+
+/// Page.captureScreenshot
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureScreenshot {
+    pub format: ScreenshotFormat,
+    /// 0-100, jpeg/webp only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clip: Option<Viewport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture_beyond_viewport: Option<bool>,
+}
+
+impl CdpCommand for CaptureScreenshot {
+    const METHOD: &'static str = "Page.captureScreenshot";
+}
+
+/// Emulation.setDeviceMetricsOverride
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDeviceMetricsOverride {
+    pub width: u32,
+    pub height: u32,
+    pub device_scale_factor: f64,
+    pub mobile: bool,
+}
+
+impl CdpCommand for SetDeviceMetricsOverride {
+    const METHOD: &'static str = "Emulation.setDeviceMetricsOverride";
+}
+
+/// Emulation.clearDeviceMetricsOverride
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClearDeviceMetricsOverride {}
+
+impl CdpCommand for ClearDeviceMetricsOverride {
+    const METHOD: &'static str = "Emulation.clearDeviceMetricsOverride";
+}
+
+/// Common devices for `CDPSession::emulate_device`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceProfile {
+    /// iPhone 14: 390x844 at 3x
+    IPhone,
+    /// Pixel 7: 412x915 at 2.625x
+    Pixel,
+    /// 1920x1080 at 1x
+    Desktop,
+}
+
+impl DeviceProfile {
+    pub fn metrics(self) -> SetDeviceMetricsOverride {
+        let (width, height, device_scale_factor, mobile) = match self {
+            Self::IPhone => (390, 844, 3.0, true),
+            Self::Pixel => (412, 915, 2.625, true),
+            Self::Desktop => (1920, 1080, 1.0, false),
+        };
+        SetDeviceMetricsOverride {
+            width,
+            height,
+            device_scale_factor,
+            mobile,
+        }
+    }
+}
+
+/// Runtime.evaluate
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evaluate {
+    pub expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_by_value: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub await_promise: Option<bool>,
+}
+
+impl Evaluate {
+    pub fn new(expression: impl Into<String>) -> Self {
+        Self {
+            expression: expression.into(),
+            return_by_value: Some(true),
+            await_promise: None,
+        }
+    }
+}
+
+impl CdpCommand for Evaluate {
+    const METHOD: &'static str = "Runtime.evaluate";
+}
+
+/// DOM.resolveNode - JS object handle for a DOM node
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveNode {
+    pub backend_node_id: u32,
+}
+
+impl CdpCommand for ResolveNode {
+    const METHOD: &'static str = "DOM.resolveNode";
+}
+
+/// Argument of Runtime.callFunctionOn, passed by value
+#[derive(Debug, Clone, Serialize)]
+pub struct CallArgument {
+    pub value: Value,
+}
+
+/// Runtime.callFunctionOn
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFunctionOn {
+    pub function_declaration: String,
+    /// The function's `this`
+    pub object_id: String,
+    pub arguments: Vec<CallArgument>,
+    pub return_by_value: bool,
+    pub await_promise: bool,
+}
+
+impl CdpCommand for CallFunctionOn {
+    const METHOD: &'static str = "Runtime.callFunctionOn";
+}
+
+/// Runtime.releaseObject
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseObject {
+    pub object_id: String,
+}
+
+impl CdpCommand for ReleaseObject {
+    const METHOD: &'static str = "Runtime.releaseObject";
+}
+
+/// Browser.setDownloadBehavior
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDownloadBehavior {
+    /// deny, allow, allowAndName, default
+    pub behavior: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events_enabled: Option<bool>,
+}
+
+impl CdpCommand for SetDownloadBehavior {
+    const METHOD: &'static str = "Browser.setDownloadBehavior";
+}
+
+/// Browser.cancelDownload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelDownload {
+    pub guid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser_context_id: Option<String>,
+}
+
+impl CdpCommand for CancelDownload {
+    const METHOD: &'static str = "Browser.cancelDownload";
+}
+
+/// Permission names `Browser.grantPermissions` understands
+pub const PERMISSION_TYPES: &[&str] = &[
+    "ar",
+    "audioCapture",
+    "automaticFullscreen",
+    "backgroundFetch",
+    "backgroundSync",
+    "cameraPanTiltZoom",
+    "capturedSurfaceControl",
+    "clipboardReadWrite",
+    "clipboardSanitizedWrite",
+    "displayCapture",
+    "durableStorage",
+    "geolocation",
+    "handTracking",
+    "idleDetection",
+    "keyboardLock",
+    "localFonts",
+    "localNetworkAccess",
+    "midi",
+    "midiSysex",
+    "nfc",
+    "notifications",
+    "paymentHandler",
+    "periodicBackgroundSync",
+    "pointerLock",
+    "protectedMediaIdentifier",
+    "sensors",
+    "smartCard",
+    "speakerSelection",
+    "storageAccess",
+    "topLevelStorageAccess",
+    "videoCapture",
+    "vr",
+    "wakeLockScreen",
+    "wakeLockSystem",
+    "webAppInstallation",
+    "webPrinting",
+    "windowManagement",
+];
+
+/// Browser.grantPermissions
+#[derive(Debug, Clone, Serialize)]
+pub struct GrantPermissions {
+    pub permissions: Vec<String>,
+    /// Every origin when `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+impl CdpCommand for GrantPermissions {
+    const METHOD: &'static str = "Browser.grantPermissions";
+}
+
+/// Browser.resetPermissions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResetPermissions {}
+
+impl CdpCommand for ResetPermissions {
+    const METHOD: &'static str = "Browser.resetPermissions";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdp::protocol::CookieSameSite;
+    use serde_json::json;
+
+    #[test]
+    fn test_navigate_shape() {
+        let value = serde_json::to_value(Navigate::new("https://example.com")).unwrap();
+        assert_eq!(value, json!({ "url": "https://example.com" }));
+    }
+
+    #[test]
+    fn test_grant_permissions_shape() {
+        let global = GrantPermissions {
+            permissions: vec!["geolocation".to_string()],
+            origin: None,
+        };
+        assert_eq!(
+            serde_json::to_value(global).unwrap(),
+            json!({ "permissions": ["geolocation"] })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_mouse_event_shape() {
+        let event = DispatchMouseEvent {
+            button: Some(MouseButton::Left),
+            click_count: Some(1),
+            ..DispatchMouseEvent::new(MouseEventType::MousePressed, 10.0, 20.0)
+        };
+
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({
+                "type": "mousePressed",
+                "x": 10.0,
+                "y": 20.0,
+                "button": "left",
+                "clickCount": 1,
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(DispatchMouseEvent::wheel(640.0, 360.0, 0.0, 800.0)).unwrap(),
+            json!({
+                "type": "mouseWheel",
+                "x": 640.0,
+                "y": 360.0,
+                "deltaX": 0.0,
+                "deltaY": 800.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_cookie_round_trip() {
+        // Network.getCookies shape, with fields Cookie doesn't keep
+        let session: Cookie = serde_json::from_value(json!({
+            "name": "sid",
+            "value": "abc",
+            "domain": ".example.com",
+            "path": "/",
+            "expires": -1,
+            "size": 6,
+            "httpOnly": true,
+            "secure": true,
+            "session": true,
+            "sameSite": "Lax",
+            "priority": "Medium",
+        }))
+        .unwrap();
+        assert_eq!(session.expires, None);
+        assert_eq!(session.same_site, Some(CookieSameSite::Lax));
+
+        let persistent = Cookie {
+            name: "theme".to_string(),
+            value: "dark".to_string(),
+            domain: "example.com".to_string(),
+            path: "/".to_string(),
+            expires: Some(1_900_000_000.0),
+            http_only: false,
+            secure: false,
+            same_site: None,
+        };
+        assert_eq!(
+            serde_json::to_value(SetCookies {
+                cookies: vec![session, persistent]
+            })
+            .unwrap(),
+            json!({ "cookies": [
+                {
+                    "name": "sid",
+                    "value": "abc",
+                    "domain": ".example.com",
+                    "path": "/",
+                    "httpOnly": true,
+                    "secure": true,
+                    "sameSite": "Lax",
+                },
+                {
+                    "name": "theme",
+                    "value": "dark",
+                    "domain": "example.com",
+                    "path": "/",
+                    "expires": 1_900_000_000.0,
+                    "httpOnly": false,
+                    "secure": false,
+                },
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_key_press_shape() {
+        let [down, up] = DispatchKeyEvent::press(Key::Enter, Modifiers::NONE);
+        assert_eq!(
+            serde_json::to_value(&down).unwrap(),
+            serde_json::json!({
+                "type": "keyDown",
+                "key": "Enter",
+                "code": "Enter",
+                "text": "\r",
+                "windowsVirtualKeyCode": 13
+            })
+        );
+        assert_eq!(up.event_type, KeyEventType::KeyUp);
+        assert_eq!(up.text, None);
+
+        // Ctrl+A selects instead of typing
+        let [down, _] = DispatchKeyEvent::press(Key::Char('a'), Modifiers::CTRL);
+        assert_eq!(
+            serde_json::to_value(&down).unwrap(),
+            serde_json::json!({
+                "type": "rawKeyDown",
+                "key": "a",
+                "code": "KeyA",
+                "windowsVirtualKeyCode": 65,
+                "modifiers": 2
+            })
+        );
+
+        let [down, _] = DispatchKeyEvent::press(Key::Char('b'), Modifiers::SHIFT);
+        assert_eq!(down.key.as_deref(), Some("B"));
+        assert_eq!(down.text.as_deref(), Some("B"));
+        assert_eq!(down.modifiers, Some(8));
+
+        let [down, _] = DispatchKeyEvent::press(Key::F(5), Modifiers::ALT | Modifiers::META);
+        assert_eq!(down.windows_virtual_key_code, Some(116));
+        assert_eq!(down.modifiers, Some(5));
+        assert_eq!(
+            DispatchKeyEvent::press(Key::Char('7'), Modifiers::NONE)[0]
+                .code
+                .as_deref(),
+            Some("Digit7")
+        );
+        assert_eq!(
+            DispatchKeyEvent::press(Key::Char('é'), Modifiers::NONE)[0].windows_virtual_key_code,
+            None
+        );
+    }
+
+    #[test]
+    fn test_capture_snapshot_shape() {
+        let value = serde_json::to_value(CaptureSnapshot::default()).unwrap();
+        assert_eq!(value["includeDOMRects"], true);
+        assert_eq!(value["includePaintOrder"], true);
+        assert!(value["computedStyles"].is_array());
+    }
+}
@@ -0,0 +1,166 @@
+//! DevTools HTTP endpoint discovery
+//!
+//! Chrome started with `--remote-debugging-port=9222` serves the WebSocket
+//! URLs on plain HTTP: `/json/version` for the browser, `/json/list` for each
+//! target. The paths carry a per-launch id, so they have to be looked up.
+//!
+//! The endpoint speaks simple HTTP/1.1, so a single GET over a `TcpStream`
+//! does without pulling in an HTTP client.
+
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+use super::client::{CDPError, Result, DEFAULT_REQUEST_TIMEOUT};
+
+/// One entry of `/json/list`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevToolsTarget {
+    pub id: String,
+    /// "page", "iframe", "service_worker", ...
+    #[serde(rename = "type")]
+    pub target_type: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    /// Absent while another client is attached to the target
+    pub web_socket_debugger_url: Option<String>,
+}
+
+/// Browser-level WebSocket URL from `/json/version`
+///
+/// The host and port are taken from `http_url`: Chrome reports the address
+/// it bound to, which is wrong when reached through a proxy or container.
+pub async fn browser_ws_url(http_url: &str) -> Result<String> {
+    let base = parse_http_url(http_url).map_err(CDPError::Discovery)?;
+    let version = get_json(&base, "/json/version").await?;
+    let ws_url = version["webSocketDebuggerUrl"]
+        .as_str()
+        .ok_or_else(|| discovery_error("/json/version has no webSocketDebuggerUrl"))?;
+    rebase_ws_url(ws_url, &base).map_err(CDPError::Discovery)
+}
+
+/// Targets listed by `/json/list`, with WebSocket URLs rebased like
+/// `browser_ws_url`
+pub async fn list_targets(http_url: &str) -> Result<Vec<DevToolsTarget>> {
+    let base = parse_http_url(http_url).map_err(CDPError::Discovery)?;
+    let list = get_json(&base, "/json/list").await?;
+    let mut targets: Vec<DevToolsTarget> = serde_json::from_value(list)?;
+    for target in &mut targets {
+        if let Some(ws_url) = target.web_socket_debugger_url.as_mut() {
+            *ws_url = rebase_ws_url(ws_url, &base).map_err(CDPError::Discovery)?;
+        }
+    }
+    Ok(targets)
+}
+
+fn discovery_error(message: impl Into<String>) -> CDPError {
+    CDPError::Discovery(message.into())
+}
+
+fn parse_http_url(http_url: &str) -> std::result::Result<Url, String> {
+    let url = Url::parse(http_url).map_err(|e| format!("invalid URL {}: {}", http_url, e))?;
+    if url.scheme() != "http" {
+        return Err(format!("expected an http:// URL, got {}", http_url));
+    }
+    Ok(url)
+}
+
+/// `ws_url` with the host and port of `base`
+fn rebase_ws_url(ws_url: &str, base: &Url) -> std::result::Result<String, String> {
+    let mut url =
+        Url::parse(ws_url).map_err(|e| format!("invalid WebSocket URL {}: {}", ws_url, e))?;
+    url.set_host(base.host_str()).map_err(|e| e.to_string())?;
+    url.set_port(base.port())
+        .map_err(|_| format!("cannot set port on {}", ws_url))?;
+    Ok(url.to_string())
+}
+
+/// GET `path` on `base` and parse the body as JSON
+async fn get_json(base: &Url, path: &str) -> Result<Value> {
+    let host = base
+        .host_str()
+        .ok_or_else(|| discovery_error(format!("no host in {}", base)))?;
+    let port = base.port_or_known_default().unwrap_or(80);
+
+    let request = async {
+        let mut stream = TcpStream::connect((host, port)).await?;
+        // Chrome only answers requests whose Host is an IP or localhost
+        let head = format!(
+            "GET {} HTTP/1.1\r\nHost: {}:{}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+            path, host, port
+        );
+        stream.write_all(head.as_bytes()).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+    let response = tokio::time::timeout(DEFAULT_REQUEST_TIMEOUT, request)
+        .await
+        .map_err(|_| CDPError::Timeout)?
+        .map_err(|e| discovery_error(format!("GET {}: {}", path, e)))?;
+
+    let body =
+        response_body(&response).map_err(|e| discovery_error(format!("GET {}: {}", path, e)))?;
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Body of a complete HTTP/1.1 response, checking for status 200
+fn response_body(response: &[u8]) -> std::result::Result<&[u8], String> {
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or("truncated response")?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let body = &response[split + 4..];
+
+    let mut lines = head.lines();
+    let status = lines.next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("unexpected status: {}", status));
+    }
+
+    let content_length = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("content-length")
+            .then(|| value.trim().parse::<usize>().ok())
+            .flatten()
+    });
+    match content_length {
+        Some(length) if length <= body.len() => Ok(&body[..length]),
+        Some(_) => Err("truncated response".to_string()),
+        None => Ok(body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_body() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}trailing";
+        assert_eq!(response_body(response), Ok(&b"{}"[..]));
+        assert_eq!(
+            response_body(b"HTTP/1.1 200 OK\r\n\r\n[1]"),
+            Ok(&b"[1]"[..])
+        );
+        assert!(response_body(b"HTTP/1.1 404 Not Found\r\n\r\n").is_err());
+        assert!(response_body(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n{}").is_err());
+        assert!(response_body(b"HTTP/1.1 200 OK").is_err());
+    }
+
+    #[test]
+    fn test_rebase_ws_url() {
+        let base = Url::parse("http://chrome:9333").unwrap();
+        assert_eq!(
+            rebase_ws_url("ws://127.0.0.1:9222/devtools/browser/abc", &base).unwrap(),
+            "ws://chrome:9333/devtools/browser/abc"
+        );
+        assert!(parse_http_url("ws://localhost:9222").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Mock browser for tests - a local WebSocket server answering CDP requests
+//!
+//! Each request goes to a handler closure first. Unless it answers, the
+//! defaults apply: `Target.attachToTarget` attaches as `S-<targetId>`,
+//! `Target.getTargetInfo` describes an about:blank page, `Test.emit` replays
+//! the events in its `events` param before replying, and anything else gets
+//! an empty result.
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::CDPClient;
+
+/// How the mock answers one request
+pub(crate) enum Reply {
+    /// Answer as the defaults would
+    Default,
+    /// Answer with this `result`
+    Result(Value),
+    /// Send these messages, replies and events, in order
+    Messages(Vec<Value>),
+    /// Don't answer
+    Ignore,
+    /// Drop the connection
+    Close,
+}
+
+/// The reply message for `request`
+pub(crate) fn reply(request: &Value, result: Value) -> Value {
+    json!({ "id": request["id"], "result": result })
+}
+
+/// Serve one connection, answering through `handler`; returns its ws:// URL
+pub(crate) async fn mock_browser<F>(mut handler: F) -> String
+where
+    F: FnMut(&Value) -> Reply + Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let request: Value = serde_json::from_str(&text).unwrap();
+            let messages = match handler(&request) {
+                Reply::Default => default_reply(&request),
+                Reply::Result(result) => vec![reply(&request, result)],
+                Reply::Messages(messages) => messages,
+                Reply::Ignore => Vec::new(),
+                Reply::Close => break,
+            };
+            for message in messages {
+                if ws.send(Message::Text(message.to_string())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    format!("ws://127.0.0.1:{}", port)
+}
+
+/// Connect a client to a fresh `mock_browser`
+pub(crate) async fn connect<F>(handler: F) -> Arc<CDPClient>
+where
+    F: FnMut(&Value) -> Reply + Send + 'static,
+{
+    CDPClient::connect(&mock_browser(handler).await)
+        .await
+        .unwrap()
+}
+
+fn default_reply(request: &Value) -> Vec<Value> {
+    let params = &request["params"];
+    match request["method"].as_str().unwrap_or("") {
+        "Target.attachToTarget" => {
+            let session_id = format!("S-{}", params["targetId"].as_str().unwrap_or(""));
+            vec![reply(request, json!({ "sessionId": session_id }))]
+        }
+        "Target.getTargetInfo" => vec![reply(
+            request,
+            json!({
+                "targetInfo": {
+                    "targetId": params["targetId"],
+                    "type": "page",
+                    "title": "",
+                    "url": "about:blank",
+                    "attached": true,
+                }
+            }),
+        )],
+        "Test.emit" => {
+            let mut messages = params["events"].as_array().cloned().unwrap_or_default();
+            messages.push(reply(request, json!({})));
+            messages
+        }
+        _ => vec![reply(request, json!({}))],
+    }
+}
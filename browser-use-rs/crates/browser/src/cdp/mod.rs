@@ -4,9 +4,16 @@
 //! No locks in hot path - use message passing instead.
 
 pub mod client;
+pub mod commands;
+pub mod discovery;
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod protocol;
+pub mod retry;
 pub mod session;
 
-pub use client::CDPClient;
+pub use client::{CDPClient, CloseHook, ReconnectConfig, SubscriptionId};
+pub use commands::CdpCommand;
 pub use protocol::{CDPEvent, CDPRequest, CDPResponse};
+pub use retry::RetryPolicy;
 pub use session::CDPSession;
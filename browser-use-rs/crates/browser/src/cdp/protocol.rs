@@ -75,6 +75,78 @@ pub struct TargetInfo {
     pub attached: bool,
 }
 
+/// Cookie `SameSite` attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// Browser cookie, as returned by Network.getCookies and accepted by
+/// Network.setCookies
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    #[serde(default = "default_cookie_path")]
+    pub path: String,
+    /// Expiry in seconds since the epoch; `None` for session cookies
+    #[serde(
+        default,
+        deserialize_with = "deserialize_cookie_expiry",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires: Option<f64>,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<CookieSameSite>,
+}
+
+fn default_cookie_path() -> String {
+    "/".to_string()
+}
+
+/// Chrome reports session cookies with `expires: -1`
+fn deserialize_cookie_expiry<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let expires = Option::<f64>::deserialize(deserializer)?;
+    Ok(expires.filter(|&expires| expires >= 0.0))
+}
+
+/// One entry of Page.getNavigationHistory
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NavigationEntry {
+    pub id: i64,
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+/// Result of Page.getNavigationHistory
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NavigationHistory {
+    #[serde(rename = "currentIndex")]
+    pub current_index: usize,
+    pub entries: Vec<NavigationEntry>,
+}
+
+impl NavigationHistory {
+    /// Entry `offset` steps from the current one (-1 = back, 1 = forward)
+    pub fn relative(&self, offset: isize) -> Option<&NavigationEntry> {
+        self.current_index
+            .checked_add_signed(offset)
+            .and_then(|index| self.entries.get(index))
+    }
+}
+
 /// Result of Target.attachToTarget
 #[derive(Debug, Clone, Deserialize)]
 pub struct AttachToTargetResult {
@@ -0,0 +1,97 @@
+//! Bounded retries for flaky CDP calls
+//!
+//! `DOM.getDocument` or `Runtime.evaluate` issued while a page navigates can
+//! fail for reasons that are gone a moment later. `RetryPolicy` describes how
+//! long to keep trying; `CDPClient::send_request_retry` applies it, retrying
+//! only errors for which `CDPError::is_transient` holds.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Exponential backoff with jitter for `CDPClient::send_request_retry`
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, the first included (0 behaves like 1)
+    pub max_attempts: usize,
+    /// Delay after the first failure, doubling after each further one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay (0.0-1.0) randomly taken off, so callers
+    /// failing together don't retry in lockstep
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries: one attempt
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retrying after the given 0-based failed attempt
+    pub fn delay_for(&self, attempt: usize) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32 << attempt.min(31))
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+/// Uniform-ish value in [0, 1) from the std hasher's random keys
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_for() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: 0.0,
+        };
+        assert_eq!(policy.delay_for(0), Duration::from_millis(100));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(400));
+        assert_eq!(policy.delay_for(3), Duration::from_millis(500));
+        assert_eq!(policy.delay_for(usize::MAX), Duration::from_millis(500));
+
+        let jittered = RetryPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        for _ in 0..100 {
+            let delay = jittered.delay_for(1);
+            assert!(delay > Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+}
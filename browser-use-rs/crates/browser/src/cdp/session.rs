@@ -3,10 +3,40 @@
 //! Design: Lightweight wrapper around CDPClient with target-specific context.
 //! All sessions share the same WebSocket - no per-session connection overhead.
 
-use super::client::{CDPClient, Result};
-use super::protocol::{AttachToTargetResult, SessionId, TargetId, TargetInfo};
-use serde_json::{Value, json};
+use super::client::{CDPClient, CDPError, Result};
+use super::commands::{
+    AttachToTarget, CallArgument, CallFunctionOn, CaptureScreenshot, CaptureSnapshot, CdpCommand,
+    ClearBrowserCookies, ClearDeviceMetricsOverride, DetachFromTarget, DeviceProfile,
+    DispatchKeyEvent, DispatchMouseEvent, Evaluate, GetBoxModel, GetCookies, GetDocument,
+    GetFullAXTree, GetLayoutMetrics, GetNavigationHistory, GetTargetInfo, InsertText, Key,
+    KeyEventType, Modifiers, MouseButton, MouseEventType, Navigate, NavigateToHistoryEntry,
+    ReleaseObject, Reload, ResolveNode, ScreenshotFormat, ScrollIntoViewIfNeeded, SetCookies,
+    SetDeviceMetricsOverride, Viewport,
+};
+use super::protocol::{
+    AttachToTargetResult, Cookie, NavigationHistory, SessionId, TargetId, TargetInfo,
+};
+use base64::Engine;
+use dom::types::NodeId;
+use dom::{DomArena, DomState};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// DOM domain events that indicate the tree is still changing
+const DOM_MUTATION_EVENTS: &[&str] = &[
+    "DOM.documentUpdated",
+    "DOM.childNodeInserted",
+    "DOM.childNodeRemoved",
+    "DOM.childNodeCountUpdated",
+    "DOM.attributeModified",
+    "DOM.attributeRemoved",
+    "DOM.characterDataModified",
+    "DOM.setChildNodes",
+];
 
 /// CDP Session bound to a specific target
 #[derive(Clone)]
@@ -34,18 +64,17 @@ impl CDPSession {
     ) -> Result<Self> {
         // Attach to target
         let result = client
-            .send_request(
-                "Target.attachToTarget",
-                Some(json!({
-                    "targetId": target_id,
-                    "flatten": true,
-                })),
+            .send_command(
+                &AttachToTarget {
+                    target_id: target_id.clone(),
+                    flatten: true,
+                },
                 None,
             )
             .await?;
 
         let attach_result: AttachToTargetResult =
-            serde_json::from_value(result).map_err(|e| super::client::CDPError::Json(e))?;
+            serde_json::from_value(result).map_err(super::client::CDPError::Json)?;
 
         let session_id = attach_result.session_id;
 
@@ -61,22 +90,12 @@ impl CDPSession {
             ]
         });
 
-        // Enable all domains in parallel
-        let enable_futures: Vec<_> = domains
+        // Enable all domains in one batch (ignore individual failures)
+        let enables = domains
             .into_iter()
-            .map(|domain| {
-                let client = client.clone();
-                let session_id = session_id.clone();
-                async move {
-                    client
-                        .send_request(format!("{}.enable", domain), None, Some(session_id))
-                        .await
-                }
-            })
+            .map(|domain| (format!("{}.enable", domain), None, Some(session_id.clone())))
             .collect();
-
-        // Wait for all enables (ignore individual failures)
-        let results = futures_util::future::join_all(enable_futures).await;
+        let results = client.send_batch(enables).await;
         let failures: Vec<_> = results.iter().filter(|r| r.is_err()).collect();
         if !failures.is_empty() {
             tracing::warn!(
@@ -97,15 +116,16 @@ impl CDPSession {
 
         // Get target info
         let info_result = client
-            .send_request(
-                "Target.getTargetInfo",
-                Some(json!({ "targetId": &target_id })),
+            .send_command(
+                &GetTargetInfo {
+                    target_id: Some(target_id.clone()),
+                },
                 None,
             )
             .await?;
 
         let target_info: TargetInfo = serde_json::from_value(info_result["targetInfo"].clone())
-            .map_err(|e| super::client::CDPError::Json(e))?;
+            .map_err(super::client::CDPError::Json)?;
 
         Ok(Self {
             client,
@@ -116,6 +136,19 @@ impl CDPSession {
         })
     }
 
+    /// Detach from the target, ending this session
+    pub async fn detach(&self) -> Result<()> {
+        self.client
+            .send_command(
+                &DetachFromTarget {
+                    session_id: self.session_id.clone(),
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Send command within this session's context
     pub async fn send(&self, method: impl Into<String>, params: Option<Value>) -> Result<Value> {
         self.client
@@ -123,39 +156,1103 @@ impl CDPSession {
             .await
     }
 
+    /// Send a typed command within this session's context
+    pub async fn send_command<C: CdpCommand>(&self, command: &C) -> Result<Value> {
+        self.client
+            .send_command(command, Some(self.session_id.clone()))
+            .await
+    }
+
     /// Get current target info
     pub async fn get_target_info(&self) -> Result<TargetInfo> {
         let result = self
             .client
-            .send_request(
-                "Target.getTargetInfo",
-                Some(json!({ "targetId": &self.target_id })),
+            .send_command(
+                &GetTargetInfo {
+                    target_id: Some(self.target_id.clone()),
+                },
                 None,
             )
             .await?;
 
-        serde_json::from_value(result["targetInfo"].clone())
-            .map_err(|e| super::client::CDPError::Json(e))
+        serde_json::from_value(result["targetInfo"].clone()).map_err(super::client::CDPError::Json)
     }
 
     /// Navigate to URL
     pub async fn navigate(&self, url: impl Into<String>) -> Result<Value> {
-        self.send("Page.navigate", Some(json!({ "url": url.into() })))
-            .await
+        self.send_command(&Navigate::new(url)).await
+    }
+
+    /// Navigate to URL and wait for its `load` event
+    ///
+    /// Same-document navigations (fragment changes) return right away since
+    /// they never fire one. Fails with `CDPError::Timeout` if the page doesn't
+    /// finish loading within `timeout`.
+    pub async fn navigate_and_wait(
+        &self,
+        url: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<Value> {
+        self.send_and_wait(
+            &Navigate::new(url),
+            &["Page.loadEventFired"],
+            |result| result.get("loaderId").is_some(),
+            timeout,
+        )
+        .await
+    }
+
+    /// Reload the page and wait for its `load` event
+    pub async fn reload_and_wait(&self, timeout: Duration) -> Result<Value> {
+        self.send_and_wait(
+            &Reload::default(),
+            &["Page.loadEventFired"],
+            |_| true,
+            timeout,
+        )
+        .await
+    }
+
+    /// Back/forward history of this target
+    pub async fn navigation_history(&self) -> Result<NavigationHistory> {
+        let result = self.send_command(&GetNavigationHistory::default()).await?;
+        serde_json::from_value(result).map_err(CDPError::Json)
+    }
+
+    /// Go to a history entry and wait until it's shown
+    ///
+    /// Entries within the same document (fragments, `pushState`) finish on
+    /// `navigatedWithinDocument` rather than `load`.
+    pub async fn navigate_to_history_entry_and_wait(
+        &self,
+        entry_id: i64,
+        timeout: Duration,
+    ) -> Result<Value> {
+        self.send_and_wait(
+            &NavigateToHistoryEntry { entry_id },
+            &["Page.loadEventFired", "Page.navigatedWithinDocument"],
+            |_| true,
+            timeout,
+        )
+        .await
+    }
+
+    /// Send `command`, then wait for one of `done_events` on this session
+    /// if `expects_event` says the result will be followed by one
+    async fn send_and_wait<C: CdpCommand>(
+        &self,
+        command: &C,
+        done_events: &[&'static str],
+        expects_event: impl FnOnce(&Value) -> bool,
+        timeout: Duration,
+    ) -> Result<Value> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        // Subscribe before sending so a fast load can't slip past
+        let subscriptions: Vec<_> = done_events
+            .iter()
+            .map(|&method| {
+                let tx = tx.clone();
+                let session_id = self.session_id.clone();
+                let id = self.client.subscribe(
+                    method,
+                    Arc::new(move |event| {
+                        if event.session_id.as_deref() == Some(session_id.as_str()) {
+                            let _ = tx.send(());
+                        }
+                    }),
+                );
+                (method, id)
+            })
+            .collect();
+        drop(tx);
+
+        let result = async {
+            let result = tokio::time::timeout(timeout, self.send_command(command))
+                .await
+                .map_err(|_| CDPError::Timeout)??;
+            if expects_event(&result) {
+                tokio::time::timeout(timeout, rx.recv())
+                    .await
+                    .map_err(|_| CDPError::Timeout)?;
+            }
+            Ok(result)
+        }
+        .await;
+        for (method, id) in subscriptions {
+            self.client.unsubscribe(method, id);
+        }
+        result
+    }
+
+    /// Cookies visible to this page (its URL and its frames' URLs)
+    ///
+    /// The cookie methods work without `Network.enable`. The cookie jar is
+    /// shared by every tab in the browser context, so any page session will
+    /// do; sending through one keeps the page's URLs as the default scope.
+    pub async fn get_cookies(&self) -> Result<Vec<Cookie>> {
+        let result = self.send_command(&GetCookies::default()).await?;
+        serde_json::from_value(result["cookies"].clone()).map_err(CDPError::Json)
+    }
+
+    /// Add or overwrite cookies; each needs its `domain` set
+    pub async fn set_cookies(&self, cookies: &[Cookie]) -> Result<()> {
+        self.send_command(&SetCookies {
+            cookies: cookies.to_vec(),
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Delete every cookie in the browser context, not just this page's
+    pub async fn clear_cookies(&self) -> Result<()> {
+        self.send_command(&ClearBrowserCookies::default()).await?;
+        Ok(())
+    }
+
+    /// Emulate a viewport of `width`x`height` CSS pixels
+    ///
+    /// `mobile` turns on the meta viewport tag and overlay scrollbars. A
+    /// changed `device_scale_factor` also changes the snapshot's layout units:
+    /// pass it as `merge_snapshot`'s `device_pixel_ratio` for snapshots
+    /// captured afterwards.
+    pub async fn set_viewport(
+        &self,
+        width: u32,
+        height: u32,
+        device_scale_factor: f64,
+        mobile: bool,
+    ) -> Result<()> {
+        let metrics = SetDeviceMetricsOverride {
+            width,
+            height,
+            device_scale_factor,
+            mobile,
+        };
+        check_metrics(&metrics).map_err(CDPError::InvalidArgument)?;
+        self.send_command(&metrics).await?;
+        Ok(())
+    }
+
+    /// `set_viewport` with a preset device
+    pub async fn emulate_device(&self, profile: DeviceProfile) -> Result<()> {
+        let metrics = profile.metrics();
+        self.set_viewport(
+            metrics.width,
+            metrics.height,
+            metrics.device_scale_factor,
+            metrics.mobile,
+        )
+        .await
+    }
+
+    /// Back to the window's own viewport and scale factor
+    pub async fn clear_viewport(&self) -> Result<()> {
+        self.send_command(&ClearDeviceMetricsOverride::default())
+            .await?;
+        Ok(())
     }
 
     /// Evaluate JavaScript
     pub async fn evaluate(&self, expression: impl Into<String>) -> Result<Value> {
+        self.send_command(&Evaluate::new(expression)).await
+    }
+
+    /// Evaluate JavaScript and deserialize its result
+    ///
+    /// Promises are awaited. A thrown exception becomes
+    /// `CDPError::JavaScript` instead of an `exceptionDetails` blob in `Ok`.
+    pub async fn evaluate_typed<T: DeserializeOwned>(
+        &self,
+        expression: impl Into<String>,
+    ) -> Result<T> {
+        let command = Evaluate {
+            await_promise: Some(true),
+            ..Evaluate::new(expression)
+        };
+        let result = self.send_command(&command).await?;
+        let value = runtime_value(&result).map_err(CDPError::JavaScript)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Call a function declaration with JSON arguments
+    ///
+    /// `this` is the element for `backend_node_id`, or `globalThis` when
+    /// `None`. Arguments are bound by value, so they need no escaping into
+    /// the source text. Returns the function's result by value.
+    pub async fn call_function(
+        &self,
+        function_declaration: impl Into<String>,
+        args: &[Value],
+        backend_node_id: Option<u32>,
+    ) -> Result<Value> {
+        let object_id = match backend_node_id {
+            Some(backend_node_id) => {
+                let result = self.send_command(&ResolveNode { backend_node_id }).await?;
+                result["object"]["objectId"].as_str().map(str::to_string)
+            }
+            None => {
+                let command = Evaluate {
+                    return_by_value: Some(false),
+                    ..Evaluate::new("globalThis")
+                };
+                let result = self.send_command(&command).await?;
+                result["result"]["objectId"].as_str().map(str::to_string)
+            }
+        }
+        .ok_or_else(|| CDPError::InvalidArgument("target has no JavaScript object".into()))?;
+
+        let command = CallFunctionOn {
+            function_declaration: function_declaration.into(),
+            object_id: object_id.clone(),
+            arguments: args
+                .iter()
+                .map(|value| CallArgument {
+                    value: value.clone(),
+                })
+                .collect(),
+            return_by_value: true,
+            await_promise: true,
+        };
+        let result = self.send_command(&command).await;
+
+        // The handle pins the object in the page until released
+        if let Err(e) = self.send_command(&ReleaseObject { object_id }).await {
+            tracing::debug!("Failed to release remote object: {}", e);
+        }
+
+        runtime_value(&result?).map_err(CDPError::JavaScript)
+    }
+
+    /// Left-click at a viewport point
+    pub async fn click(&self, x: f64, y: f64) -> Result<()> {
+        for event in click_events(x, y) {
+            self.send_command(&event).await?;
+        }
+        Ok(())
+    }
+
+    /// Scroll a node into view and click its center
+    ///
+    /// `absolute_position` is in page coordinates, so the point clicked comes
+    /// from the node's box model once scrolled. Fails with
+    /// `CDPError::ElementHasNoBounds` if the snapshot has no (or an empty)
+    /// layout box for it.
+    pub async fn click_node(&self, arena: &DomArena, node_id: NodeId) -> Result<()> {
+        let node = arena.get(node_id)?;
+        node.absolute_position
+            .filter(|rect| rect.width > 0.0 && rect.height > 0.0)
+            .ok_or(CDPError::ElementHasNoBounds(node.backend_node_id))?;
+        self.scroll_into_view(node.backend_node_id).await?;
+        let (x, y) = self.node_center(node.backend_node_id).await?;
+        self.click(x, y).await
+    }
+
+    /// Type text into the focused element
+    ///
+    /// Plain text goes in via `Input.insertText`; newlines and tabs are sent
+    /// as Enter and Tab key presses so forms react to them.
+    pub async fn type_text(&self, text: &str) -> Result<()> {
+        for step in type_steps(text) {
+            match step {
+                TypeStep::Insert(command) => self.send_command(&command).await?,
+                TypeStep::Key(command) => self.send_command(&command).await?,
+            };
+        }
+        Ok(())
+    }
+
+    /// Press and release a key, with `modifiers` held, in the focused element
+    pub async fn press_key(&self, key: Key, modifiers: Modifiers) -> Result<()> {
+        for event in DispatchKeyEvent::press(key, modifiers) {
+            self.send_command(&event).await?;
+        }
+        Ok(())
+    }
+
+    /// Ctrl+A in the focused element
+    pub async fn select_all(&self) -> Result<()> {
+        self.editing_shortcut('a', "selectAll").await
+    }
+
+    /// Ctrl+C in the focused element
+    pub async fn copy(&self) -> Result<()> {
+        self.editing_shortcut('c', "copy").await
+    }
+
+    /// Ctrl+V in the focused element
+    pub async fn paste(&self) -> Result<()> {
+        self.editing_shortcut('v', "paste").await
+    }
+
+    /// Ctrl+`key`, naming the editing `command` on the keyDown
+    ///
+    /// Editing shortcuts are a platform keybinding (Cmd on macOS), which
+    /// synthetic events don't go through; the named command runs either way.
+    async fn editing_shortcut(&self, key: char, command: &str) -> Result<()> {
+        let [mut down, up] = DispatchKeyEvent::press(Key::Char(key), Modifiers::CTRL);
+        down.commands.push(command.to_string());
+        self.send_command(&down).await?;
+        self.send_command(&up).await?;
+        Ok(())
+    }
+
+    /// Drag with the left mouse button from one viewport point to another
+    ///
+    /// Dispatches mousePressed, `steps` interpolated mouseMoved, then mouseReleased.
+    pub async fn drag(&self, from: (f64, f64), to: (f64, f64), steps: usize) -> Result<()> {
+        for event in drag_events(from, to, steps) {
+            self.send_command(&event).await?;
+        }
+        Ok(())
+    }
+
+    /// Drag from the center of one node to the center of another
+    pub async fn drag_node_to_node(&self, from_backend: u32, to_backend: u32) -> Result<()> {
+        let from = self.node_center(from_backend).await?;
+        let to = self.node_center(to_backend).await?;
+        self.drag(from, to, 10).await
+    }
+
+    /// Scroll a node into view, if it isn't already
+    ///
+    /// Content below the fold on infinite-scroll pages loads once its
+    /// sentinel element is scrolled to.
+    pub async fn scroll_into_view(&self, backend_node_id: u32) -> Result<()> {
+        self.send_command(&ScrollIntoViewIfNeeded { backend_node_id })
+            .await?;
+        Ok(())
+    }
+
+    /// Scroll the page by a mouse wheel turn at the viewport center
+    ///
+    /// Positive `delta_y` scrolls down. Goes through the input pipeline, so
+    /// the page sees `wheel` and `scroll` events as from a user.
+    pub async fn scroll_by(&self, delta_x: f64, delta_y: f64) -> Result<()> {
+        let metrics = self.send_command(&GetLayoutMetrics::default()).await?;
+        let (x, y) = viewport_center(&metrics);
+        self.send_command(&DispatchMouseEvent::wheel(x, y, delta_x, delta_y))
+            .await?;
+        Ok(())
+    }
+
+    /// Scroll inside a scroll container (see `DomArena::find_scrollable`)
+    ///
+    /// The wheel turn goes to the container's center; once it can't scroll
+    /// further the browser passes it on to the page.
+    pub async fn scroll_node_by(
+        &self,
+        backend_node_id: u32,
+        delta_x: f64,
+        delta_y: f64,
+    ) -> Result<()> {
+        self.scroll_into_view(backend_node_id).await?;
+        let (x, y) = self.node_center(backend_node_id).await?;
+        self.send_command(&DispatchMouseEvent::wheel(x, y, delta_x, delta_y))
+            .await?;
+        Ok(())
+    }
+
+    /// Resolve the viewport center of a node's content box
+    pub async fn node_center(&self, backend_node_id: u32) -> Result<(f64, f64)> {
+        let result = self.send_command(&GetBoxModel { backend_node_id }).await?;
+
+        // Quad is 4 points clockwise: x1,y1,x2,y2,x3,y3,x4,y4
+        let quad: [f64; 8] = serde_json::from_value(result["model"]["content"].clone())?;
+        let x = (quad[0] + quad[2] + quad[4] + quad[6]) / 4.0;
+        let y = (quad[1] + quad[3] + quad[5] + quad[7]) / 4.0;
+        Ok((x, y))
+    }
+
+    /// Capture a screenshot of the viewport, or of the whole page with `full_page`
+    ///
+    /// `quality` (0-100) is only accepted for the lossy formats, JPEG and WebP.
+    pub async fn screenshot(
+        &self,
+        format: ScreenshotFormat,
+        quality: Option<u8>,
+        full_page: bool,
+    ) -> Result<Vec<u8>> {
+        check_quality(format, quality).map_err(CDPError::InvalidArgument)?;
+
+        let clip = if full_page {
+            let metrics = self.send_command(&GetLayoutMetrics::default()).await?;
+            Some(full_page_clip(&metrics))
+        } else {
+            None
+        };
+
         let result = self
-            .send(
-                "Runtime.evaluate",
-                Some(json!({
-                    "expression": expression.into(),
-                    "returnByValue": true,
-                })),
-            )
+            .send_command(&CaptureScreenshot {
+                format,
+                quality,
+                clip,
+                capture_beyond_viewport: full_page.then_some(true),
+            })
             .await?;
 
-        Ok(result)
+        let data = result["data"].as_str().unwrap_or("");
+        Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+    }
+
+    /// Capture a screenshot of a single element
+    ///
+    /// Scrolls the element into view and clips to its border box. Elements
+    /// larger than the viewport yield only the visible portion.
+    pub async fn screenshot_element(
+        &self,
+        backend_node_id: u32,
+        format: ScreenshotFormat,
+    ) -> Result<Vec<u8>> {
+        self.send_command(&ScrollIntoViewIfNeeded { backend_node_id })
+            .await?;
+
+        let box_model = self.send_command(&GetBoxModel { backend_node_id }).await?;
+        let quad: [f64; 8] = serde_json::from_value(box_model["model"]["border"].clone())?;
+
+        let metrics = self.send_command(&GetLayoutMetrics::default()).await?;
+        let visual = &metrics["cssVisualViewport"];
+        let viewport = Viewport {
+            x: visual["pageX"].as_f64().unwrap_or(0.0),
+            y: visual["pageY"].as_f64().unwrap_or(0.0),
+            width: visual["clientWidth"].as_f64().unwrap_or(0.0),
+            height: visual["clientHeight"].as_f64().unwrap_or(0.0),
+            scale: 1.0,
+        };
+
+        let clip =
+            element_clip(&quad, &viewport).ok_or(CDPError::ElementNotVisible(backend_node_id))?;
+
+        let result = self
+            .send_command(&CaptureScreenshot {
+                format,
+                clip: Some(clip),
+                ..CaptureScreenshot::default()
+            })
+            .await?;
+
+        let data = result["data"].as_str().unwrap_or("");
+        Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+    }
+
+    /// Fetch the document, layout snapshot and AX tree for `DomService::load`
+    ///
+    /// The three requests go out together; the first failure is returned.
+    pub async fn capture_dom_state(&self) -> Result<DomState> {
+        let document = GetDocument {
+            depth: Some(-1),
+            pierce: Some(true),
+        };
+        let snapshot = CaptureSnapshot::default();
+        let ax_tree = GetFullAXTree::default();
+        let (document, snapshot, ax_tree) = futures_util::future::try_join3(
+            self.send_command(&document),
+            self.send_command(&snapshot),
+            self.send_command(&ax_tree),
+        )
+        .await?;
+        Ok(DomState {
+            document,
+            snapshot,
+            ax_tree,
+        })
+    }
+
+    /// Wait until no DOM mutation events arrive for `quiet_ms`
+    ///
+    /// The DOM analog of network-idle: call after an action, before extracting
+    /// the tree. Fails with `CDPError::Timeout` if the page never settles.
+    pub async fn wait_for_dom_stable(&self, quiet_ms: u64, timeout: Duration) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let mut subscriptions = Vec::with_capacity(DOM_MUTATION_EVENTS.len());
+        for method in DOM_MUTATION_EVENTS {
+            let tx = tx.clone();
+            let session_id = self.session_id.clone();
+            let id = self.client.subscribe(
+                *method,
+                Arc::new(move |event| {
+                    if event.session_id.as_deref() == Some(session_id.as_str()) {
+                        let _ = tx.send(());
+                    }
+                }),
+            );
+            subscriptions.push((*method, id));
+        }
+        drop(tx);
+
+        let result = self.wait_for_quiet(&mut rx, quiet_ms, timeout).await;
+        for (method, id) in subscriptions {
+            self.client.unsubscribe(method, id);
+        }
+        result
+    }
+
+    /// Drive the quiet-period check off mutation signals on `rx`
+    async fn wait_for_quiet(
+        &self,
+        rx: &mut mpsc::UnboundedReceiver<()>,
+        quiet_ms: u64,
+        timeout: Duration,
+    ) -> Result<()> {
+        // Chrome only reports mutations for nodes the client has requested
+        self.send_command(&GetDocument {
+            depth: Some(-1),
+            pierce: None,
+        })
+        .await?;
+
+        let deadline = Instant::now() + timeout;
+        let mut quiet = QuietPeriod::new(Duration::from_millis(quiet_ms), Instant::now());
+
+        loop {
+            let now = Instant::now();
+            if quiet.is_quiet(now) {
+                return Ok(());
+            }
+            if now >= deadline {
+                return Err(CDPError::Timeout);
+            }
+
+            tokio::select! {
+                Some(()) = rx.recv() => quiet.record(Instant::now()),
+                _ = tokio::time::sleep_until(quiet.quiet_at().min(deadline)) => {}
+            }
+        }
+    }
+}
+
+/// Quiet-period state machine: settled once no activity for `quiet`
+#[derive(Debug, Clone, Copy)]
+struct QuietPeriod {
+    quiet: Duration,
+    last_activity: Instant,
+}
+
+impl QuietPeriod {
+    fn new(quiet: Duration, now: Instant) -> Self {
+        Self {
+            quiet,
+            last_activity: now,
+        }
+    }
+
+    /// Record a mutation - restarts the quiet window
+    fn record(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Instant at which the window closes if nothing else happens
+    fn quiet_at(&self) -> Instant {
+        self.last_activity + self.quiet
+    }
+
+    fn is_quiet(&self, now: Instant) -> bool {
+        now >= self.quiet_at()
+    }
+}
+
+/// Reject `quality` for PNG, or outside 0-100
+fn check_quality(format: ScreenshotFormat, quality: Option<u8>) -> std::result::Result<(), String> {
+    match quality {
+        Some(_) if !format.is_lossy() => Err(format!(
+            "quality only applies to JPEG and WebP, not {:?}",
+            format
+        )),
+        Some(quality) if quality > 100 => Err(format!("quality must be 0-100, got {}", quality)),
+        _ => Ok(()),
+    }
+}
+
+/// Reject empty viewports and non-positive scale factors
+///
+/// Chrome treats zero sizes and scale as "keep the current value"; reject
+/// them here so `clear_viewport` stays the only way back.
+fn check_metrics(metrics: &SetDeviceMetricsOverride) -> std::result::Result<(), String> {
+    if metrics.width == 0 || metrics.height == 0 {
+        return Err(format!(
+            "viewport must be non-empty, got {}x{}",
+            metrics.width, metrics.height
+        ));
+    }
+    if !(metrics.device_scale_factor.is_finite() && metrics.device_scale_factor > 0.0) {
+        return Err(format!(
+            "device scale factor must be positive, got {}",
+            metrics.device_scale_factor
+        ));
+    }
+    Ok(())
+}
+
+/// `result.value` of a `Runtime.evaluate`/`Runtime.callFunctionOn` reply,
+/// or the message of the exception it threw
+fn runtime_value(result: &Value) -> std::result::Result<Value, String> {
+    if let Some(details) = result.get("exceptionDetails") {
+        return Err(exception_message(details));
+    }
+    // `undefined` has no `value`
+    Ok(result["result"]
+        .get("value")
+        .cloned()
+        .unwrap_or(Value::Null))
+}
+
+/// One-line description of a thrown exception
+///
+/// `exception.description` holds "TypeError: x is undefined" plus the stack;
+/// only the first line is kept. Thrown non-errors fall back to `text`.
+fn exception_message(details: &Value) -> String {
+    let description = details["exception"]["description"]
+        .as_str()
+        .and_then(|d| d.lines().next());
+    let thrown = details["exception"]["value"].as_str();
+    match (description, thrown) {
+        (Some(description), _) => description.to_string(),
+        (None, Some(value)) => format!("Uncaught {}", value),
+        (None, None) => details["text"]
+            .as_str()
+            .unwrap_or("unknown exception")
+            .to_string(),
+    }
+}
+
+/// Center of the visual viewport from `Page.getLayoutMetrics`, in
+/// viewport coordinates
+fn viewport_center(metrics: &Value) -> (f64, f64) {
+    let visual = &metrics["cssVisualViewport"];
+    (
+        visual["clientWidth"].as_f64().unwrap_or(0.0) / 2.0,
+        visual["clientHeight"].as_f64().unwrap_or(0.0) / 2.0,
+    )
+}
+
+/// Clip covering the page's full content size, from Page.getLayoutMetrics
+///
+/// Uses the CSS-pixel `cssContentSize`, falling back to the deprecated
+/// `contentSize` on older browsers.
+fn full_page_clip(metrics: &Value) -> Viewport {
+    let content = match &metrics["cssContentSize"] {
+        Value::Null => &metrics["contentSize"],
+        content => content,
+    };
+    Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: content["width"].as_f64().unwrap_or(0.0),
+        height: content["height"].as_f64().unwrap_or(0.0),
+        scale: 1.0,
+    }
+}
+
+/// Compute the screenshot clip for an element's border quad
+///
+/// Quad points are viewport-relative; the clip is in page coordinates, so the
+/// viewport scroll offset is added back. Clamped to the visible viewport.
+fn element_clip(quad: &[f64; 8], viewport: &Viewport) -> Option<Viewport> {
+    let xs = [quad[0], quad[2], quad[4], quad[6]];
+    let ys = [quad[1], quad[3], quad[5], quad[7]];
+
+    let left = xs.iter().copied().fold(f64::INFINITY, f64::min).max(0.0);
+    let top = ys.iter().copied().fold(f64::INFINITY, f64::min).max(0.0);
+    let right = xs
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max)
+        .min(viewport.width);
+    let bottom = ys
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max)
+        .min(viewport.height);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some(Viewport {
+        x: viewport.x + left,
+        y: viewport.y + top,
+        width: right - left,
+        height: bottom - top,
+        scale: 1.0,
+    })
+}
+
+/// Pressed/released pair for a single left click
+fn click_events(x: f64, y: f64) -> [DispatchMouseEvent; 2] {
+    [
+        DispatchMouseEvent {
+            button: Some(MouseButton::Left),
+            buttons: Some(1),
+            click_count: Some(1),
+            ..DispatchMouseEvent::new(MouseEventType::MousePressed, x, y)
+        },
+        DispatchMouseEvent {
+            button: Some(MouseButton::Left),
+            buttons: Some(0),
+            click_count: Some(1),
+            ..DispatchMouseEvent::new(MouseEventType::MouseReleased, x, y)
+        },
+    ]
+}
+
+/// One command of `type_text`
+#[derive(Debug, PartialEq)]
+enum TypeStep {
+    Insert(InsertText),
+    Key(DispatchKeyEvent),
+}
+
+/// Split text into insertText runs and Enter/Tab key presses
+///
+/// `\r` is dropped so `\r\n` makes a single Enter.
+fn type_steps(text: &str) -> Vec<TypeStep> {
+    let mut steps = Vec::new();
+    let mut run = String::new();
+    for c in text.chars() {
+        let key = match c {
+            '\n' => ("Enter", 13, Some("\r")),
+            '\t' => ("Tab", 9, None),
+            '\r' => continue,
+            c => {
+                run.push(c);
+                continue;
+            }
+        };
+        if !run.is_empty() {
+            steps.push(TypeStep::Insert(InsertText {
+                text: std::mem::take(&mut run),
+            }));
+        }
+        let (name, code, key_text) = key;
+        steps.push(TypeStep::Key(DispatchKeyEvent::key(
+            KeyEventType::KeyDown,
+            name,
+            code,
+            key_text,
+        )));
+        steps.push(TypeStep::Key(DispatchKeyEvent::key(
+            KeyEventType::KeyUp,
+            name,
+            code,
+            None,
+        )));
+    }
+    if !run.is_empty() {
+        steps.push(TypeStep::Insert(InsertText { text: run }));
+    }
+    steps
+}
+
+/// Build the Input.dispatchMouseEvent sequence for a drag gesture
+fn drag_events(from: (f64, f64), to: (f64, f64), steps: usize) -> Vec<DispatchMouseEvent> {
+    let steps = steps.max(1);
+    let mut events = Vec::with_capacity(steps + 2);
+
+    events.push(DispatchMouseEvent {
+        button: Some(MouseButton::Left),
+        buttons: Some(1),
+        click_count: Some(1),
+        ..DispatchMouseEvent::new(MouseEventType::MousePressed, from.0, from.1)
+    });
+
+    for i in 1..=steps {
+        let t = i as f64 / steps as f64;
+        events.push(DispatchMouseEvent {
+            button: Some(MouseButton::Left),
+            buttons: Some(1),
+            ..DispatchMouseEvent::new(
+                MouseEventType::MouseMoved,
+                from.0 + (to.0 - from.0) * t,
+                from.1 + (to.1 - from.1) * t,
+            )
+        });
+    }
+
+    events.push(DispatchMouseEvent {
+        button: Some(MouseButton::Left),
+        buttons: Some(0),
+        click_count: Some(1),
+        ..DispatchMouseEvent::new(MouseEventType::MouseReleased, to.0, to.1)
+    });
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_value_and_exceptions() {
+        let ok = json!({"result": {"type": "number", "value": 3}});
+        assert_eq!(runtime_value(&ok), Ok(json!(3)));
+        let undefined = json!({"result": {"type": "undefined"}});
+        assert_eq!(runtime_value(&undefined), Ok(Value::Null));
+
+        let thrown = json!({
+            "result": {"type": "object", "subtype": "error"},
+            "exceptionDetails": {
+                "text": "Uncaught",
+                "exception": {
+                    "type": "object",
+                    "description": "TypeError: x is undefined\n    at <anonymous>:1:1"
+                }
+            }
+        });
+        assert_eq!(
+            runtime_value(&thrown),
+            Err("TypeError: x is undefined".to_string())
+        );
+
+        let thrown_string = json!({
+            "exceptionDetails": {"text": "Uncaught", "exception": {"type": "string", "value": "boom"}}
+        });
+        assert_eq!(
+            runtime_value(&thrown_string),
+            Err("Uncaught boom".to_string())
+        );
+        let bare = json!({"exceptionDetails": {"text": "Uncaught SyntaxError"}});
+        assert_eq!(
+            exception_message(&bare["exceptionDetails"]),
+            "Uncaught SyntaxError"
+        );
+    }
+
+    #[test]
+    fn test_call_function_on_shape() {
+        let command = CallFunctionOn {
+            function_declaration: "function (a) { return a + 1; }".into(),
+            object_id: "obj-1".into(),
+            arguments: vec![CallArgument { value: json!(1) }],
+            return_by_value: true,
+            await_promise: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&command).unwrap(),
+            json!({
+                "functionDeclaration": "function (a) { return a + 1; }",
+                "objectId": "obj-1",
+                "arguments": [{"value": 1}],
+                "returnByValue": true,
+                "awaitPromise": true
+            })
+        );
+    }
+
+    #[test]
+    fn test_drag_event_sequence() {
+        let events = drag_events((0.0, 0.0), (100.0, 50.0), 4);
+
+        // pressed + 4 moves + released
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[0].event_type, MouseEventType::MousePressed);
+        assert!(events[1..5]
+            .iter()
+            .all(|e| e.event_type == MouseEventType::MouseMoved));
+        assert_eq!(events[5].event_type, MouseEventType::MouseReleased);
+
+        assert_eq!(events[1].x, 25.0);
+        assert_eq!(events[2].y, 25.0);
+        assert_eq!(events[4].x, 100.0);
+        assert_eq!(events[5].y, 50.0);
+    }
+
+    #[test]
+    fn test_viewport_center() {
+        let metrics = json!({
+            "cssVisualViewport": {
+                "pageX": 0, "pageY": 2400, "clientWidth": 1280, "clientHeight": 720,
+            },
+        });
+        assert_eq!(viewport_center(&metrics), (640.0, 360.0));
+        assert_eq!(viewport_center(&json!({})), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_screenshot_quality_and_full_page_clip() {
+        assert!(check_quality(ScreenshotFormat::Png, None).is_ok());
+        assert!(check_quality(ScreenshotFormat::Jpeg, Some(80)).is_ok());
+        assert!(check_quality(ScreenshotFormat::Webp, Some(100)).is_ok());
+        assert!(check_quality(ScreenshotFormat::Png, Some(80)).is_err());
+        assert!(check_quality(ScreenshotFormat::Jpeg, Some(101)).is_err());
+
+        let metrics = json!({
+            "contentSize": { "x": 0, "y": 0, "width": 2560, "height": 8000 },
+            "cssContentSize": { "x": 0, "y": 0, "width": 1280, "height": 4000 },
+        });
+        let clip = full_page_clip(&metrics);
+        assert_eq!((clip.x, clip.y), (0.0, 0.0));
+        assert_eq!((clip.width, clip.height, clip.scale), (1280.0, 4000.0, 1.0));
+
+        let legacy = json!({ "contentSize": { "width": 800, "height": 3000 } });
+        let clip = full_page_clip(&legacy);
+        assert_eq!((clip.width, clip.height), (800.0, 3000.0));
+    }
+
+    #[test]
+    fn test_check_metrics() {
+        for profile in [
+            DeviceProfile::IPhone,
+            DeviceProfile::Pixel,
+            DeviceProfile::Desktop,
+        ] {
+            assert_eq!(check_metrics(&profile.metrics()), Ok(()));
+        }
+
+        let phone = DeviceProfile::IPhone.metrics();
+        assert!(check_metrics(&SetDeviceMetricsOverride { width: 0, ..phone }).is_err());
+        for device_scale_factor in [0.0, -1.0, f64::NAN] {
+            let metrics = SetDeviceMetricsOverride {
+                device_scale_factor,
+                ..phone
+            };
+            assert!(check_metrics(&metrics).is_err());
+        }
+    }
+
+    #[test]
+    fn test_click_event_pair() {
+        let [pressed, released] = click_events(12.0, 34.0);
+        assert_eq!(pressed.event_type, MouseEventType::MousePressed);
+        assert_eq!(released.event_type, MouseEventType::MouseReleased);
+        for event in [&pressed, &released] {
+            assert_eq!((event.x, event.y), (12.0, 34.0));
+            assert_eq!(event.button, Some(MouseButton::Left));
+            assert_eq!(event.click_count, Some(1));
+        }
+    }
+
+    #[test]
+    fn test_type_steps() {
+        let insert = |text: &str| {
+            TypeStep::Insert(InsertText {
+                text: text.to_string(),
+            })
+        };
+        assert_eq!(type_steps("héllo"), vec![insert("héllo")]);
+        assert!(type_steps("").is_empty());
+
+        let steps = type_steps("a\tb\r\n");
+        assert_eq!(steps.len(), 6);
+        assert_eq!(steps[0], insert("a"));
+        assert_eq!(
+            steps[1],
+            TypeStep::Key(DispatchKeyEvent::key(KeyEventType::KeyDown, "Tab", 9, None))
+        );
+        assert_eq!(steps[3], insert("b"));
+        assert_eq!(
+            steps[4],
+            TypeStep::Key(DispatchKeyEvent::key(
+                KeyEventType::KeyDown,
+                "Enter",
+                13,
+                Some("\r")
+            ))
+        );
+        assert!(matches!(
+            &steps[5],
+            TypeStep::Key(DispatchKeyEvent {
+                event_type: KeyEventType::KeyUp,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_element_clip_from_box_model() {
+        let viewport = Viewport {
+            x: 0.0,
+            y: 500.0,
+            width: 800.0,
+            height: 600.0,
+            scale: 1.0,
+        };
+
+        // 100x50 box at (10, 20) in the viewport, page scrolled 500px down
+        let quad = [10.0, 20.0, 110.0, 20.0, 110.0, 70.0, 10.0, 70.0];
+        let clip = element_clip(&quad, &viewport).unwrap();
+        assert_eq!((clip.x, clip.y), (10.0, 520.0));
+        assert_eq!((clip.width, clip.height), (100.0, 50.0));
+
+        // Taller than the viewport - only the visible part is captured
+        let quad = [0.0, -100.0, 800.0, -100.0, 800.0, 900.0, 0.0, 900.0];
+        let clip = element_clip(&quad, &viewport).unwrap();
+        assert_eq!((clip.y, clip.height), (500.0, 600.0));
+
+        // Entirely offscreen
+        let quad = [0.0, 700.0, 10.0, 700.0, 10.0, 710.0, 0.0, 710.0];
+        assert!(element_clip(&quad, &viewport).is_none());
+    }
+
+    #[test]
+    fn test_quiet_period_resets_on_mutation() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut quiet = QuietPeriod::new(ms(100), start);
+
+        assert!(!quiet.is_quiet(start + ms(50)));
+
+        // Mutation at 80ms pushes the window out to 180ms
+        quiet.record(start + ms(80));
+        assert!(!quiet.is_quiet(start + ms(150)));
+        assert_eq!(quiet.quiet_at(), start + ms(180));
+
+        quiet.record(start + ms(170));
+        assert!(!quiet.is_quiet(start + ms(200)));
+        assert!(quiet.is_quiet(start + ms(270)));
+    }
+
+    #[tokio::test]
+    async fn test_click_node_uses_viewport_coordinates() {
+        use crate::cdp::mock::{self, Reply};
+
+        // Page scrolled down 1000px: the button is at y=1100 on the page and
+        // y=100 in the viewport
+        let (tx, mut commands) = tokio::sync::mpsc::unbounded_channel();
+        let client = mock::connect(move |request| {
+            let _ = tx.send(request.clone());
+            match request["method"].as_str().unwrap() {
+                "DOM.getBoxModel" => Reply::Result(json!({
+                    "model": { "content": [10.0, 100.0, 110.0, 100.0, 110.0, 140.0, 10.0, 140.0] }
+                })),
+                _ => Reply::Default,
+            }
+        })
+        .await;
+        let session = CDPSession::attach(client, "T1".to_string(), Some(vec![]))
+            .await
+            .unwrap();
+
+        let mut arena = DomArena::new();
+        let mut node = dom::DomNode::new(
+            0,
+            42,
+            dom::NodeType::Element,
+            "BUTTON".to_string(),
+            "T1".to_string(),
+        );
+        node.absolute_position = Some(dom::DomRect::new(10.0, 1100.0, 100.0, 40.0));
+        let node_id = arena.add_node(node);
+        session.click_node(&arena, node_id).await.unwrap();
+
+        let mut methods = Vec::new();
+        while let Ok(command) = commands.try_recv() {
+            let method = command["method"].as_str().unwrap().to_string();
+            if method == "Input.dispatchMouseEvent" {
+                assert_eq!(
+                    (
+                        command["params"]["x"].as_f64(),
+                        command["params"]["y"].as_f64()
+                    ),
+                    (Some(60.0), Some(120.0))
+                );
+            }
+            methods.push(method);
+        }
+        let scrolled = methods
+            .iter()
+            .position(|m| m == "DOM.scrollIntoViewIfNeeded");
+        let clicked = methods.iter().position(|m| m == "Input.dispatchMouseEvent");
+        assert!(scrolled.is_some() && scrolled < clicked, "{:?}", methods);
     }
 }
@@ -3,42 +3,394 @@
 //! Design: Type-safe events with async handlers.
 //! No dynamic dispatch overhead - use enums, not trait objects.
 
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Events buffered per subscriber before the slowest one starts lagging
+const CHANNEL_CAPACITY: usize = 1024;
 
 /// Browser events that can be dispatched
+///
+/// Non-exhaustive: new variants aren't a breaking change.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum BrowserEvent {
     Started,
     Stopped,
-    NavigationStarted { url: String },
-    NavigationComplete { url: String },
-    TabCreated { target_id: String },
-    TabClosed { target_id: String },
-    TabSwitched { target_id: String },
-    FileDownloaded { path: String },
+    /// The browser connection was lost without `BrowserSession::stop`
+    Disconnected,
+    NavigationStarted {
+        url: String,
+    },
+    NavigationComplete {
+        target_id: String,
+        url: String,
+    },
+    TabCreated {
+        target_id: String,
+    },
+    TabClosed {
+        target_id: String,
+    },
+    TabSwitched {
+        target_id: String,
+    },
+    FileDownloaded {
+        path: String,
+    },
+    /// A crashed target was reloaded; `session_id` is the target's CDP session
+    TargetRecovered {
+        session_id: Option<String>,
+        attempt: u32,
+    },
+    /// SecurityWatchdog caught a navigation to a disallowed URL
+    NavigationBlocked {
+        target_id: String,
+        url: String,
+    },
+    /// A network request was sent
+    ///
+    /// `request_id` is only unique per tab; `target_id` is `None` for
+    /// browser-level requests.
+    RequestStarted {
+        target_id: Option<String>,
+        request_id: String,
+        url: String,
+        method: String,
+    },
+    /// A `RequestStarted` finished loading its response
+    RequestFinished {
+        target_id: Option<String>,
+        request_id: String,
+        status: u16,
+        elapsed_ms: u64,
+    },
+    /// A `RequestStarted` failed to load (blocked, aborted, DNS, timed out, ...)
+    RequestFailed {
+        target_id: Option<String>,
+        request_id: String,
+        error_text: String,
+        elapsed_ms: u64,
+    },
+    /// Application-defined event, e.g. `LoginNeeded`, published by user
+    /// code for its own watchdogs to react to
+    Custom {
+        name: String,
+        #[serde(default)]
+        data: serde_json::Value,
+    },
+}
+
+/// Fieldless mirror of `BrowserEvent`, for subscribing by variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum EventKind {
+    Started,
+    Stopped,
+    Disconnected,
+    NavigationStarted,
+    NavigationComplete,
+    TabCreated,
+    TabClosed,
+    TabSwitched,
+    FileDownloaded,
+    TargetRecovered,
+    NavigationBlocked,
+    RequestStarted,
+    RequestFinished,
+    RequestFailed,
+    Custom,
+}
+
+impl BrowserEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Started => EventKind::Started,
+            Self::Stopped => EventKind::Stopped,
+            Self::Disconnected => EventKind::Disconnected,
+            Self::NavigationStarted { .. } => EventKind::NavigationStarted,
+            Self::NavigationComplete { .. } => EventKind::NavigationComplete,
+            Self::TabCreated { .. } => EventKind::TabCreated,
+            Self::TabClosed { .. } => EventKind::TabClosed,
+            Self::TabSwitched { .. } => EventKind::TabSwitched,
+            Self::FileDownloaded { .. } => EventKind::FileDownloaded,
+            Self::TargetRecovered { .. } => EventKind::TargetRecovered,
+            Self::NavigationBlocked { .. } => EventKind::NavigationBlocked,
+            Self::RequestStarted { .. } => EventKind::RequestStarted,
+            Self::RequestFinished { .. } => EventKind::RequestFinished,
+            Self::RequestFailed { .. } => EventKind::RequestFailed,
+            Self::Custom { .. } => EventKind::Custom,
+        }
+    }
+
+    /// Browser and tab lifecycle events, which watchdogs keep state by
+    ///
+    /// A `LosslessSubscription` never drops these.
+    pub fn is_lifecycle(&self) -> bool {
+        matches!(
+            self,
+            Self::Started
+                | Self::Stopped
+                | Self::Disconnected
+                | Self::TabCreated { .. }
+                | Self::TabClosed { .. }
+        )
+    }
 }
 
 /// Simple event bus using tokio broadcast channel
+///
+/// Clones are handles to the same channel.
+///
+/// Each subscriber has its own buffer of `CHANNEL_CAPACITY` events. One that
+/// falls further behind loses the oldest ones: a raw receiver's next `recv`
+/// returns `RecvError::Lagged(skipped)` once, then continues with the oldest
+/// event still buffered. `EventSubscription` handles that for you;
+/// `subscribe_lossless` never drops lifecycle events.
+#[derive(Clone)]
 pub struct EventBus {
     tx: broadcast::Sender<BrowserEvent>,
+
+    /// Receivers from `subscribe_lossless`
+    lossless: Arc<Mutex<Vec<LosslessSender>>>,
+
+    /// Last `history_capacity` events, for `subscribe_with_history`
+    history: Arc<Mutex<VecDeque<BrowserEvent>>>,
+    history_capacity: usize,
 }
 
 impl EventBus {
+    /// Event bus without history
     pub fn new() -> Self {
-        let (tx, _) = broadcast::channel(1024);
-        Self { tx }
+        Self::with_history(0)
+    }
+
+    /// Event bus that remembers the last `capacity` events
+    pub fn with_history(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            tx,
+            lossless: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            history_capacity: capacity,
+        }
     }
 
     /// Publish an event
     pub fn publish(&self, event: BrowserEvent) {
-        let _ = self.tx.send(event); // Ignore error if no subscribers
+        self.publish_lossless(&event);
+        if self.history_capacity == 0 {
+            let _ = self.tx.send(event); // Ignore error if no subscribers
+            return;
+        }
+
+        // Send under the lock so subscribe_with_history sees each event
+        // exactly once, either in the replay or on the receiver
+        let mut history = self.lock_history();
+        if history.len() == self.history_capacity {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+        let _ = self.tx.send(event);
+    }
+
+    /// Subscribe, getting the remembered events (oldest first) to replay
+    ///
+    /// The replay and the receiver don't overlap or leave a gap. Without
+    /// history (`EventBus::new`) the replay is empty.
+    pub fn subscribe_with_history(&self) -> (Vec<BrowserEvent>, broadcast::Receiver<BrowserEvent>) {
+        let history = self.lock_history();
+        (history.iter().cloned().collect(), self.tx.subscribe())
+    }
+
+    /// The remembered events, oldest first
+    pub fn history(&self) -> Vec<BrowserEvent> {
+        self.lock_history().iter().cloned().collect()
+    }
+
+    fn publish_lossless(&self, event: &BrowserEvent) {
+        let mut senders = self.lossless.lock().unwrap_or_else(|e| e.into_inner());
+        senders.retain(|sender| sender.send(event));
+    }
+
+    fn lock_history(&self) -> std::sync::MutexGuard<'_, VecDeque<BrowserEvent>> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner())
     }
 
     /// Subscribe to events
+    ///
+    /// Only events published after this call are received. The receiver
+    /// must handle `RecvError::Lagged`; see the type docs.
     pub fn subscribe(&self) -> broadcast::Receiver<BrowserEvent> {
         self.tx.subscribe()
     }
+
+    /// Subscribe to every event, never losing lifecycle events
+    ///
+    /// For consumers that keep per-tab state, like the watchdog bridge.
+    /// Lifecycle events (`BrowserEvent::is_lifecycle`) are always queued;
+    /// others are dropped while `CHANNEL_CAPACITY` events are waiting, and
+    /// the next `recv` warns how many.
+    pub fn subscribe_lossless(&self) -> LosslessSubscription {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let counts = Arc::new(LosslessCounts::default());
+        self.lossless
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(LosslessSender {
+                tx,
+                counts: counts.clone(),
+            });
+        LosslessSubscription { rx, counts }
+    }
+
+    /// Subscribe to the events matching `predicate`
+    pub fn subscribe_filtered<F>(&self, predicate: F) -> EventSubscription
+    where
+        F: Fn(&BrowserEvent) -> bool + Send + Sync + 'static,
+    {
+        EventSubscription {
+            rx: self.tx.subscribe(),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Subscribe to one kind of event
+    pub fn subscribe_kind(&self, kind: EventKind) -> EventSubscription {
+        self.subscribe_filtered(move |event| event.kind() == kind)
+    }
+
+    /// Number of live subscribers
+    pub fn receiver_count(&self) -> usize {
+        let lossless = self.lossless.lock().unwrap_or_else(|e| e.into_inner());
+        let live = lossless
+            .iter()
+            .filter(|sender| !sender.tx.is_closed())
+            .count();
+        self.tx.receiver_count() + live
+    }
+
+    /// Wait for the first event matching `predicate`
+    ///
+    /// Subscribes right away, so events published between this call and
+    /// awaiting the future are not missed: call it, trigger the action,
+    /// then await. `timeout` counts from the first poll.
+    pub fn wait_for<F>(
+        &self,
+        predicate: F,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<BrowserEvent, WaitError>> + Send + 'static
+    where
+        F: Fn(&BrowserEvent) -> bool + Send + Sync + 'static,
+    {
+        let mut subscription = self.subscribe_filtered(predicate);
+        async move {
+            match tokio::time::timeout(timeout, subscription.recv()).await {
+                Ok(Some(event)) => Ok(event),
+                Ok(None) => Err(WaitError::Closed),
+                Err(_) => Err(WaitError::Timeout(timeout)),
+            }
+        }
+    }
+}
+
+/// Why `EventBus::wait_for` gave up
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WaitError {
+    #[error("No matching event within {0:?}")]
+    Timeout(Duration),
+
+    #[error("Event bus closed")]
+    Closed,
+}
+
+/// Filtered receiver from `EventBus::subscribe_filtered`
+///
+/// Skips past lag with a warning instead of surfacing it as an error.
+pub struct EventSubscription {
+    rx: broadcast::Receiver<BrowserEvent>,
+    predicate: Box<dyn Fn(&BrowserEvent) -> bool + Send + Sync>,
+}
+
+impl EventSubscription {
+    /// Next matching event; `None` once every `EventBus` handle is dropped
+    pub async fn recv(&mut self) -> Option<BrowserEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if (self.predicate)(&event) => return Some(event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Matching events as a `Stream`
+    pub fn into_stream(self) -> impl Stream<Item = BrowserEvent> {
+        futures_util::stream::unfold(self, |mut subscription| async move {
+            let event = subscription.recv().await?;
+            Some((event, subscription))
+        })
+    }
+}
+
+/// Queue lengths shared by a `LosslessSubscription` and its sender
+#[derive(Default)]
+struct LosslessCounts {
+    /// Events queued and not yet received
+    pending: AtomicUsize,
+    /// Events dropped since the last `recv`
+    skipped: AtomicUsize,
+}
+
+/// `EventBus` side of a `LosslessSubscription`
+struct LosslessSender {
+    tx: mpsc::UnboundedSender<BrowserEvent>,
+    counts: Arc<LosslessCounts>,
+}
+
+impl LosslessSender {
+    /// Queue `event` unless it may be dropped; false once the receiver is gone
+    fn send(&self, event: &BrowserEvent) -> bool {
+        if !event.is_lifecycle() && self.counts.pending.load(Ordering::Acquire) >= CHANNEL_CAPACITY
+        {
+            self.counts.skipped.fetch_add(1, Ordering::AcqRel);
+            return !self.tx.is_closed();
+        }
+        self.counts.pending.fetch_add(1, Ordering::AcqRel);
+        self.tx.send(event.clone()).is_ok()
+    }
+}
+
+/// Receiver from `EventBus::subscribe_lossless`
+pub struct LosslessSubscription {
+    rx: mpsc::UnboundedReceiver<BrowserEvent>,
+    counts: Arc<LosslessCounts>,
+}
+
+impl LosslessSubscription {
+    /// Next event; `None` once every `EventBus` handle is dropped
+    pub async fn recv(&mut self) -> Option<BrowserEvent> {
+        let event = self.rx.recv().await?;
+        self.counts.pending.fetch_sub(1, Ordering::AcqRel);
+        let skipped = self.counts.skipped.swap(0, Ordering::AcqRel);
+        if skipped > 0 {
+            tracing::warn!(
+                "Lossless subscriber fell {} events behind, skipped {} non-lifecycle events",
+                CHANNEL_CAPACITY,
+                skipped
+            );
+        }
+        Some(event)
+    }
 }
 
 impl Default for EventBus {
@@ -63,4 +415,136 @@ mod tests {
             _ => panic!("Expected Started event"),
         }
     }
+
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        use futures_util::StreamExt;
+
+        let bus = EventBus::new();
+        let mut tabs = bus.subscribe_kind(EventKind::TabCreated);
+        let downloads = bus
+            .subscribe_filtered(|event| {
+                matches!(event, BrowserEvent::FileDownloaded { path } if path.ends_with(".pdf"))
+            })
+            .into_stream();
+        assert_eq!(bus.receiver_count(), 2);
+
+        bus.publish(BrowserEvent::Started);
+        for path in ["a.zip", "b.pdf"] {
+            bus.publish(BrowserEvent::FileDownloaded {
+                path: path.to_string(),
+            });
+        }
+        bus.publish(BrowserEvent::TabCreated {
+            target_id: "T1".to_string(),
+        });
+
+        assert!(matches!(
+            tabs.recv().await,
+            Some(BrowserEvent::TabCreated { target_id }) if target_id == "T1"
+        ));
+        drop(bus);
+        assert!(tabs.recv().await.is_none());
+
+        let downloads: Vec<_> = downloads.collect().await;
+        assert_eq!(downloads.len(), 1);
+        assert_eq!(downloads[0].kind(), EventKind::FileDownloaded);
+    }
+
+    #[tokio::test]
+    async fn test_lossless_subscription_keeps_lifecycle_events() {
+        let bus = EventBus::new();
+        let mut lossless = bus.subscribe_lossless();
+        assert_eq!(bus.receiver_count(), 1);
+
+        bus.publish(BrowserEvent::TabCreated {
+            target_id: "T1".to_string(),
+        });
+        for _ in 0..CHANNEL_CAPACITY + 10 {
+            bus.publish(BrowserEvent::NavigationStarted {
+                url: "https://example.com".to_string(),
+            });
+        }
+        bus.publish(BrowserEvent::TabClosed {
+            target_id: "T1".to_string(),
+        });
+        drop(bus);
+
+        let mut kinds = Vec::new();
+        while let Some(event) = lossless.recv().await {
+            kinds.push(event.kind());
+        }
+        // The backlog capped navigations, but both tab events got through
+        assert_eq!(kinds.len(), CHANNEL_CAPACITY + 1);
+        assert_eq!(kinds.first(), Some(&EventKind::TabCreated));
+        assert_eq!(kinds.last(), Some(&EventKind::TabClosed));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_with_history() {
+        let bus = EventBus::with_history(2);
+        bus.publish(BrowserEvent::Started);
+        for id in ["T1", "T2"] {
+            bus.publish(BrowserEvent::TabCreated {
+                target_id: id.to_string(),
+            });
+        }
+
+        // Joined late: the oldest event fell out of the ring
+        let (replay, mut rx) = bus.subscribe_with_history();
+        let replayed: Vec<_> = replay.iter().map(BrowserEvent::kind).collect();
+        assert_eq!(replayed, vec![EventKind::TabCreated, EventKind::TabCreated]);
+        assert!(rx.try_recv().is_err());
+
+        bus.publish(BrowserEvent::Stopped);
+        assert!(matches!(rx.try_recv(), Ok(BrowserEvent::Stopped)));
+        assert_eq!(bus.history().len(), 2);
+
+        let (replay, _) = EventBus::new().subscribe_with_history();
+        assert!(replay.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for() {
+        let bus = EventBus::new();
+        let complete = bus.wait_for(
+            |event| {
+                matches!(event, BrowserEvent::NavigationComplete { url, .. } if url == "https://b.test/")
+            },
+            Duration::from_secs(5),
+        );
+
+        // Published before the future is first polled, still seen
+        for url in ["https://a.test/", "https://b.test/"] {
+            bus.publish(BrowserEvent::NavigationComplete {
+                target_id: "T1".to_string(),
+                url: url.to_string(),
+            });
+        }
+        assert!(matches!(
+            complete.await,
+            Ok(BrowserEvent::NavigationComplete { url, .. }) if url == "https://b.test/"
+        ));
+
+        let timeout = Duration::from_millis(20);
+        let stopped = bus.wait_for(|event| event.kind() == EventKind::Stopped, timeout);
+        bus.publish(BrowserEvent::Started);
+        assert_eq!(stopped.await.unwrap_err(), WaitError::Timeout(timeout));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_skips_lag() {
+        let bus = EventBus::new();
+        let mut started = bus.subscribe_kind(EventKind::Started);
+
+        // Overflow the buffer; the first Started is among the dropped events
+        bus.publish(BrowserEvent::Started);
+        for _ in 0..CHANNEL_CAPACITY {
+            bus.publish(BrowserEvent::Stopped);
+        }
+        bus.publish(BrowserEvent::Started);
+
+        assert!(matches!(started.recv().await, Some(BrowserEvent::Started)));
+        assert_eq!(started.rx.len(), 0);
+    }
 }
@@ -17,7 +17,11 @@ pub mod watchdog;
 pub mod watchdogs;
 
 pub use cdp::{CDPClient, CDPSession};
-pub use events::EventBus;
-pub use session::{BrowserSession, SessionConfig};
-pub use watchdog::{Watchdog, WatchdogManager};
+pub use events::{
+    BrowserEvent, EventBus, EventKind, EventSubscription, LosslessSubscription, WaitError,
+};
+pub use session::{BrowserSession, SessionConfig, TabInfo};
+pub use watchdog::{
+    Diagnostics, Watchdog, WatchdogBridge, WatchdogEvent, WatchdogEventKind, WatchdogManager,
+};
 pub use watchdogs::CrashWatchdog;
@@ -5,24 +5,61 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::cdp::protocol::TargetId;
+use crate::cdp::commands::{
+    CloseTarget, CreateTarget, GetTargets, GrantPermissions, ResetPermissions, PERMISSION_TYPES,
+};
+use crate::cdp::protocol::{TargetId, TargetInfo};
 use crate::cdp::{CDPClient, CDPSession};
 use crate::events::{BrowserEvent, EventBus};
-use crate::watchdog::WatchdogManager;
-use crate::watchdogs::{CrashWatchdog, DownloadsWatchdog, SecurityWatchdog};
+use crate::watchdog::{WatchdogBridge, WatchdogManager};
+use crate::watchdogs::{
+    CrashWatchdog, DialogWatchdog, DownloadsWatchdog, SecurityPolicy, SecurityWatchdog,
+};
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long `navigate` waits for the page's `load` event
+const DEFAULT_NAVIGATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Events kept on the session's bus for subscribers that join late
+const EVENT_HISTORY: usize = 256;
+
+/// Where downloads go when `SessionConfig::download_dir` isn't set
+const DEFAULT_DOWNLOAD_DIR: &str = "/tmp/browser-downloads";
 
 /// Session configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
     pub id: String,
+    /// Browser WebSocket URL, or Chrome's `http://host:port` endpoint to
+    /// look it up from
     pub cdp_url: String,
     pub headless: bool,
     pub user_data_dir: Option<String>,
+
+    /// Downloads directory, `/tmp/browser-downloads` when `None`
+    #[serde(default)]
+    pub download_dir: Option<PathBuf>,
+
+    /// URL policy for the security watchdog, allow-all when `None`
+    #[serde(default)]
+    pub security_policy: Option<SecurityPolicy>,
+
+    /// Register the crash, dialog, downloads and security watchdogs
+    ///
+    /// When off, the downloads and security watchdogs are still registered
+    /// if `download_dir` or `security_policy` is set.
+    #[serde(default = "default_true")]
+    pub enable_default_watchdogs: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for SessionConfig {
@@ -32,13 +69,25 @@ impl Default for SessionConfig {
             cdp_url: "ws://localhost:9222".to_string(),
             headless: true,
             user_data_dir: None,
+            download_dir: None,
+            security_policy: None,
+            enable_default_watchdogs: true,
         }
     }
 }
 
+/// An open tab, as reported by `BrowserSession::list_tabs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabInfo {
+    pub target_id: TargetId,
+    pub title: String,
+    pub url: String,
+}
+
 /// Browser Session - manages connection to Chrome and tabs
 pub struct BrowserSession {
     pub config: SessionConfig,
+    /// Keeps the last `EVENT_HISTORY` events for `subscribe_with_history`
     pub event_bus: EventBus,
 
     // CDP infrastructure
@@ -48,78 +97,148 @@ pub struct BrowserSession {
     // Current focus
     current_target: Arc<RwLock<Option<TargetId>>>,
 
+    // Open tabs, least recently focused first; the next focus on close
+    focus_order: Arc<RwLock<Vec<TargetId>>>,
+
     // Watchdog system - replaces Python's 11 separate fields
     watchdog_manager: Arc<RwLock<WatchdogManager>>,
+
+    // Forwards event_bus to the watchdogs between start and stop
+    watchdog_bridge: Arc<RwLock<Option<WatchdogBridge>>>,
+
+    // Whether grant_permissions was called, so stop() knows to reset
+    permissions_granted: Arc<AtomicBool>,
+
+    // Between start and stop, until the browser connection drops
+    connected: Arc<AtomicBool>,
 }
 
 impl BrowserSession {
     pub fn new(config: SessionConfig) -> Self {
-        // Initialize watchdog manager with default watchdogs
+        // Initialize watchdog manager from the config
         let mut watchdog_manager = WatchdogManager::new();
+        let defaults = config.enable_default_watchdogs;
 
         // Core watchdogs enabled by default
-        watchdog_manager.register(Box::new(CrashWatchdog::new()));
+        let event_bus = EventBus::with_history(EVENT_HISTORY);
+        if defaults {
+            watchdog_manager.register(Box::new(CrashWatchdog::with_event_sink(event_bus.clone())));
+
+            // Dialog watchdog - accept alerts/confirms/beforeunload so they can't hang a page
+            watchdog_manager.register(Box::new(DialogWatchdog::new()));
+        }
 
-        // Downloads watchdog - uses /tmp/browser-downloads by default
-        let downloads_dir = PathBuf::from("/tmp/browser-downloads");
-        watchdog_manager.register(Box::new(DownloadsWatchdog::new(downloads_dir)));
+        // Downloads watchdog - uses /tmp/browser-downloads unless configured
+        if defaults || config.download_dir.is_some() {
+            let downloads_dir = config
+                .download_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_DOWNLOAD_DIR));
+            watchdog_manager.register(Box::new(DownloadsWatchdog::with_event_sink(
+                downloads_dir,
+                event_bus.clone(),
+            )));
+        }
 
-        // Security watchdog - allow all by default (no restrictions)
-        watchdog_manager.register(Box::new(SecurityWatchdog::new()));
+        // Security watchdog - allow all unless configured (no restrictions)
+        if defaults || config.security_policy.is_some() {
+            watchdog_manager.register(Box::new(SecurityWatchdog::with_event_sink(
+                config.security_policy.clone().unwrap_or_default(),
+                event_bus.clone(),
+            )));
+        }
 
         Self {
             config,
-            event_bus: EventBus::new(),
+            event_bus,
             cdp_client: Arc::new(RwLock::new(None)),
             sessions: Arc::new(RwLock::new(HashMap::new())),
             current_target: Arc::new(RwLock::new(None)),
+            focus_order: Arc::new(RwLock::new(Vec::new())),
             watchdog_manager: Arc::new(RwLock::new(watchdog_manager)),
+            watchdog_bridge: Arc::new(RwLock::new(None)),
+            permissions_granted: Arc::new(AtomicBool::new(false)),
+            connected: Arc::new(AtomicBool::new(false)),
         }
     }
 
     /// Start the browser session
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
         // Connect to CDP
-        let client = CDPClient::connect(&self.config.cdp_url).await?;
+        let client = if self.config.cdp_url.starts_with("http://") {
+            CDPClient::connect_http(&self.config.cdp_url).await?
+        } else {
+            CDPClient::connect(&self.config.cdp_url).await?
+        };
         let client_arc = Arc::clone(&client);
         *self.cdp_client.write().await = Some(client);
+        self.connected.store(true, Ordering::SeqCst);
+
+        // Chrome went away: the bridge tells the watchdogs
+        let connected = self.connected.clone();
+        let event_bus = self.event_bus.clone();
+        client_arc.on_close(Box::new(move || {
+            connected.store(false, Ordering::SeqCst);
+            event_bus.publish(BrowserEvent::Disconnected);
+        }));
 
         // Attach watchdogs with CDP client
         self.watchdog_manager
-            .read()
+            .write()
             .await
             .attach_all(client_arc)
             .await?;
+        *self.watchdog_bridge.write().await = Some(WatchdogManager::bridge(
+            self.watchdog_manager.clone(),
+            &self.event_bus,
+        ));
 
-        // Publish event and dispatch to watchdogs
-        let event = Arc::new(BrowserEvent::Started);
-        self.event_bus.publish((*event).clone());
-        self.watchdog_manager.read().await.dispatch(event).await;
+        // Publish event; the bridge dispatches it to watchdogs
+        self.emit(BrowserEvent::Started).await;
 
         Ok(())
     }
 
     /// Stop the browser session
     pub async fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.connected.store(false, Ordering::SeqCst);
+
         // Detach watchdogs
-        self.watchdog_manager.read().await.detach_all().await?;
+        self.watchdog_manager.write().await.detach_all().await?;
+
+        // Don't leave grants behind in a browser we didn't launch
+        if self.permissions_granted.swap(false, Ordering::Relaxed) {
+            if let Err(e) = self.reset_permissions().await {
+                tracing::warn!("Failed to reset permissions: {}", e);
+            }
+        }
 
         // Close all sessions
         self.sessions.write().await.clear();
+        self.focus_order.write().await.clear();
 
         // Close CDP client
         if let Some(client) = self.cdp_client.write().await.take() {
             client.close().await?;
         }
 
-        // Publish event and dispatch to watchdogs
-        let event = Arc::new(BrowserEvent::Stopped);
-        self.event_bus.publish((*event).clone());
-        self.watchdog_manager.read().await.dispatch(event).await;
+        // Publish event; the bridge dispatches it to watchdogs
+        self.emit(BrowserEvent::Stopped).await;
+        if let Some(bridge) = self.watchdog_bridge.write().await.take() {
+            bridge.shutdown().await;
+        }
 
         Ok(())
     }
 
+    /// Whether the session is started and its browser connection is up
+    ///
+    /// Turns false, with a `BrowserEvent::Disconnected`, when the browser
+    /// closes the connection or dies.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
     /// Create new tab
     pub async fn new_tab(
         &self,
@@ -135,13 +254,7 @@ impl BrowserSession {
 
         let url = url.unwrap_or_else(|| "about:blank".to_string());
 
-        let result = client
-            .send_request(
-                "Target.createTarget",
-                Some(serde_json::json!({ "url": url })),
-                None,
-            )
-            .await?;
+        let result = client.send_command(&CreateTarget::new(url), None).await?;
 
         let target_id: TargetId = result["targetId"]
             .as_str()
@@ -155,14 +268,13 @@ impl BrowserSession {
             .write()
             .await
             .insert(target_id.clone(), session);
-        *self.current_target.write().await = Some(target_id.clone());
+        self.focus(&target_id).await;
 
-        // Publish event and dispatch to watchdogs
-        let event = Arc::new(BrowserEvent::TabCreated {
+        // Publish event; the bridge dispatches it to watchdogs
+        self.emit(BrowserEvent::TabCreated {
             target_id: target_id.clone(),
-        });
-        self.event_bus.publish((*event).clone());
-        self.watchdog_manager.read().await.dispatch(event).await;
+        })
+        .await;
 
         Ok(target_id)
     }
@@ -174,56 +286,830 @@ impl BrowserSession {
             return Err("Target not found".into());
         }
 
-        *self.current_target.write().await = Some(target_id.clone());
+        self.focus(&target_id).await;
 
-        // Publish event and dispatch to watchdogs
-        let event = Arc::new(BrowserEvent::TabSwitched {
+        // Publish event; the bridge dispatches it to watchdogs
+        self.emit(BrowserEvent::TabSwitched {
             target_id: target_id.clone(),
-        });
-        self.event_bus.publish((*event).clone());
-        self.watchdog_manager.read().await.dispatch(event).await;
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Make `target_id` the current tab and the last one focused
+    async fn focus(&self, target_id: &TargetId) {
+        let mut order = self.focus_order.write().await;
+        order.retain(|id| id != target_id);
+        order.push(target_id.clone());
+        *self.current_target.write().await = Some(target_id.clone());
+    }
+
+    /// Close tab
+    ///
+    /// If it was the current tab, focus moves to the open tab focused most
+    /// recently before it (if any).
+    pub async fn close_tab(&self, target_id: TargetId) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.sessions.read().await.contains_key(&target_id) {
+            return Err("Target not found".into());
+        }
+
+        let client = self
+            .cdp_client
+            .read()
+            .await
+            .as_ref()
+            .ok_or("Not connected")?
+            .clone();
+
+        client
+            .send_command(
+                &CloseTarget {
+                    target_id: target_id.clone(),
+                },
+                None,
+            )
+            .await?;
+
+        let switched_to = {
+            self.sessions.write().await.remove(&target_id);
+            let mut order = self.focus_order.write().await;
+            order.retain(|id| *id != target_id);
+            let mut current = self.current_target.write().await;
+            if current.as_ref() == Some(&target_id) {
+                *current = order.last().cloned();
+                current.clone()
+            } else {
+                None
+            }
+        };
+
+        // Publish events; the bridge dispatches them to watchdogs
+        self.emit(BrowserEvent::TabClosed { target_id }).await;
+
+        if let Some(target_id) = switched_to {
+            self.emit(BrowserEvent::TabSwitched { target_id }).await;
+        }
 
         Ok(())
     }
 
+    /// Open tabs with their title and url
+    ///
+    /// Asks the browser for fresh values; tabs it doesn't report (or all of
+    /// them, when the query fails) fall back to what was cached on attach.
+    pub async fn list_tabs(&self) -> Vec<TabInfo> {
+        let client = self.cdp_client.read().await.clone();
+        let live: Vec<TargetInfo> = match client {
+            Some(client) => client
+                .send_command(&GetTargets {}, None)
+                .await
+                .ok()
+                .and_then(|result| serde_json::from_value(result["targetInfos"].clone()).ok())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let sessions = self.sessions.read().await;
+        let mut tabs: Vec<TabInfo> = live
+            .into_iter()
+            .filter(|info| sessions.contains_key(&info.target_id))
+            .map(|info| TabInfo {
+                target_id: info.target_id,
+                title: info.title,
+                url: info.url,
+            })
+            .collect();
+        for (target_id, session) in sessions.iter() {
+            if !tabs.iter().any(|tab| &tab.target_id == target_id) {
+                tabs.push(TabInfo {
+                    target_id: target_id.clone(),
+                    title: session.title.clone(),
+                    url: session.url.clone(),
+                });
+            }
+        }
+        tabs
+    }
+
+    /// Publish an event and wait until the watchdogs have handled it
+    ///
+    /// Waiting keeps watchdogs in step with the session, e.g. request
+    /// interception is set up on `TabCreated` before the tab navigates.
+    async fn emit(&self, event: BrowserEvent) {
+        self.event_bus.publish(event);
+        if let Some(bridge) = self.watchdog_bridge.read().await.as_ref() {
+            bridge.flush().await;
+        }
+    }
+
+    /// Stop dispatching to a watchdog (e.g. `"SecurityWatchdog"`), detaching it if running
+    pub async fn disable_watchdog(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_watchdog_enabled(name, false).await
+    }
+
+    /// Turn a disabled watchdog back on, attaching it if running
+    ///
+    /// It only sees tabs opened from now on.
+    pub async fn enable_watchdog(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_watchdog_enabled(name, true).await
+    }
+
+    async fn set_watchdog_enabled(
+        &self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self
+            .watchdog_manager
+            .write()
+            .await
+            .set_enabled(name, enabled)
+            .await?
+        {
+            return Err("Watchdog not found".into());
+        }
+        Ok(())
+    }
+
+    /// Remove a watchdog for good, detaching it if running
+    pub async fn unregister_watchdog(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.watchdog_manager.write().await.unregister(name).await? {
+            return Err("Watchdog not found".into());
+        }
+        Ok(())
+    }
+
+    /// Names of registered watchdogs and whether each is enabled
+    pub async fn watchdogs(&self) -> Vec<(String, bool)> {
+        let manager = self.watchdog_manager.read().await;
+        manager
+            .names()
+            .into_iter()
+            .map(|name| (name.to_string(), manager.is_enabled(name) == Some(true)))
+            .collect()
+    }
+
+    /// Grant permissions (e.g. `["geolocation", "clipboardReadWrite"]`) so
+    /// pages don't stop at a permission prompt
+    ///
+    /// Applies to `origin` only, or to every origin when `None`. Names are
+    /// checked against `PERMISSION_TYPES`; the grants are revoked on `stop`.
+    pub async fn grant_permissions(
+        &self,
+        permissions: &[&str],
+        origin: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(unknown) = permissions
+            .iter()
+            .find(|name| !PERMISSION_TYPES.contains(name))
+        {
+            return Err(format!("Unknown permission: {}", unknown).into());
+        }
+
+        let client = self
+            .cdp_client
+            .read()
+            .await
+            .as_ref()
+            .ok_or("Not connected")?
+            .clone();
+
+        client
+            .send_command(
+                &GrantPermissions {
+                    permissions: permissions.iter().map(|name| name.to_string()).collect(),
+                    origin: origin.map(str::to_string),
+                },
+                None,
+            )
+            .await?;
+        self.permissions_granted.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Revoke everything granted with `grant_permissions`
+    pub async fn reset_permissions(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let client = self
+            .cdp_client
+            .read()
+            .await
+            .as_ref()
+            .ok_or("Not connected")?
+            .clone();
+
+        client.send_command(&ResetPermissions {}, None).await?;
+        self.permissions_granted.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Target id of the focused tab
+    pub async fn current_tab(&self) -> Option<TargetId> {
+        self.current_target.read().await.clone()
+    }
+
     /// Get current session
     pub async fn current_session(&self) -> Option<CDPSession> {
         let target_id = self.current_target.read().await.clone()?;
         self.sessions.read().await.get(&target_id).cloned()
     }
 
-    /// Navigate current tab
+    /// Navigate current tab and wait for the page to load
     pub async fn navigate(&self, url: impl Into<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.navigate_with_timeout(url, DEFAULT_NAVIGATION_TIMEOUT)
+            .await
+    }
+
+    /// Navigate current tab, failing with `CDPError::Timeout` if the page
+    /// hasn't loaded within `timeout`
+    ///
+    /// `NavigationComplete` carries the URL the tab ended up on, after redirects.
+    pub async fn navigate_with_timeout(
+        &self,
+        url: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let url = url.into();
         let session = self.current_session().await.ok_or("No active session")?;
+        self.run_navigation(&session, url.clone(), Navigation::Url(url), timeout)
+            .await
+    }
+
+    /// Reload current tab and wait for the page to load
+    pub async fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let session = self.current_session().await.ok_or("No active session")?;
+        let url = session.get_target_info().await?.url;
+        self.run_navigation(
+            &session,
+            url,
+            Navigation::Reload,
+            DEFAULT_NAVIGATION_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Go one entry back in the current tab's history
+    ///
+    /// Returns false, without navigating, if there's nothing to go back to.
+    pub async fn go_back(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.go_history(-1).await
+    }
 
+    /// Go one entry forward in the current tab's history
+    ///
+    /// Returns false, without navigating, if there's nothing to go forward to.
+    pub async fn go_forward(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        self.go_history(1).await
+    }
+
+    async fn go_history(&self, offset: isize) -> Result<bool, Box<dyn std::error::Error>> {
+        let session = self.current_session().await.ok_or("No active session")?;
+        let history = session.navigation_history().await?;
+        let Some(entry) = history.relative(offset) else {
+            return Ok(false);
+        };
+        self.run_navigation(
+            &session,
+            entry.url.clone(),
+            Navigation::HistoryEntry(entry.id),
+            DEFAULT_NAVIGATION_TIMEOUT,
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Publish `NavigationStarted`, perform `navigation` and wait for it,
+    /// then publish `NavigationComplete` with the URL the tab ended up on
+    async fn run_navigation(
+        &self,
+        session: &CDPSession,
+        url: String,
+        navigation: Navigation,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         // Publish navigation started event
-        let event_start = Arc::new(BrowserEvent::NavigationStarted { url: url.clone() });
-        self.event_bus.publish((*event_start).clone());
-        self.watchdog_manager
-            .read()
-            .await
-            .dispatch(event_start)
+        self.emit(BrowserEvent::NavigationStarted { url: url.clone() })
             .await;
 
-        session.navigate(&url).await?;
+        match navigation {
+            Navigation::Url(url) => session.navigate_and_wait(url, timeout).await?,
+            Navigation::Reload => session.reload_and_wait(timeout).await?,
+            Navigation::HistoryEntry(entry_id) => {
+                session
+                    .navigate_to_history_entry_and_wait(entry_id, timeout)
+                    .await?
+            }
+        };
+
+        let final_url = match session.get_target_info().await {
+            Ok(info) => info.url,
+            Err(_) => url,
+        };
+        if let Some(cached) = self.sessions.write().await.get_mut(&session.target_id) {
+            cached.url = final_url.clone();
+        }
 
         // Publish navigation complete event
-        let event_complete = Arc::new(BrowserEvent::NavigationComplete { url: url.clone() });
-        self.event_bus.publish((*event_complete).clone());
-        self.watchdog_manager
-            .read()
-            .await
-            .dispatch(event_complete)
-            .await;
+        self.emit(BrowserEvent::NavigationComplete {
+            target_id: session.target_id.clone(),
+            url: final_url,
+        })
+        .await;
 
         Ok(())
     }
 }
 
+/// What `run_navigation` asks the tab to do
+enum Navigation {
+    Url(String),
+    Reload,
+    HistoryEntry(i64),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cdp::mock::{self, Reply};
+    use crate::events::EventKind;
+
+    /// Mock browser: creates targets "T1", "T2", ... and answers anything
+    /// else with an empty result
+    ///
+    /// Navigations load right away, except `slow.test` never finishes and
+    /// `redirect.test` ends up on `final.test`. Each tab keeps a history
+    /// starting at about:blank.
+    async fn mock_browser() -> String {
+        let mut created = 0;
+        let mut open = Vec::new();
+        let mut histories: HashMap<String, (Vec<String>, usize)> = HashMap::new();
+        mock::mock_browser(move |request| {
+                let params = &request["params"];
+                let mut load_event = None;
+                let result = match request["method"].as_str().unwrap() {
+                    // The browser dies mid-request
+                    "Target.createTarget" if params["url"] == "https://close.test/" => {
+                        return Reply::Close
+                    }
+                    "Target.createTarget" => {
+                        created += 1;
+                        open.push(format!("T{}", created));
+                        histories.insert(
+                            format!("T{}", created),
+                            (vec!["about:blank".to_string()], 0),
+                        );
+                        serde_json::json!({ "targetId": format!("T{}", created) })
+                    }
+                    "Target.attachToTarget" => {
+                        serde_json::json!({ "sessionId": format!("S-{}", params["targetId"].as_str().unwrap()) })
+                    }
+                    "Target.getTargetInfo" => {
+                        let (entries, index) = &histories[params["targetId"].as_str().unwrap()];
+                        serde_json::json!({
+                            "targetInfo": {
+                                "targetId": params["targetId"],
+                                "type": "page",
+                                "title": "",
+                                "url": entries[*index],
+                                "attached": true,
+                            }
+                        })
+                    }
+                    "Page.navigate" => {
+                        let session_id = request["sessionId"].as_str().unwrap();
+                        let url = params["url"].as_str().unwrap();
+                        let (entries, index) = histories
+                            .get_mut(session_id.trim_start_matches("S-"))
+                            .unwrap();
+                        entries.truncate(*index + 1);
+                        entries.push(url.replace("redirect.test", "final.test"));
+                        *index += 1;
+                        if url.contains('#') {
+                            serde_json::json!({ "frameId": "F" })
+                        } else {
+                            if !url.contains("slow.test") {
+                                load_event = Some(serde_json::json!({
+                                    "method": "Page.loadEventFired",
+                                    "params": { "timestamp": 1.0 },
+                                    "sessionId": session_id,
+                                }));
+                            }
+                            serde_json::json!({ "frameId": "F", "loaderId": "L" })
+                        }
+                    }
+                    "Page.getNavigationHistory" => {
+                        let session_id = request["sessionId"].as_str().unwrap();
+                        let (entries, index) = &histories[session_id.trim_start_matches("S-")];
+                        let entries: Vec<_> = entries
+                            .iter()
+                            .enumerate()
+                            .map(|(id, url)| serde_json::json!({ "id": id, "url": url, "title": "" }))
+                            .collect();
+                        serde_json::json!({ "currentIndex": index, "entries": entries })
+                    }
+                    "Page.navigateToHistoryEntry" | "Page.reload" => {
+                        let session_id = request["sessionId"].as_str().unwrap();
+                        let (entries, index) = histories
+                            .get_mut(session_id.trim_start_matches("S-"))
+                            .unwrap();
+                        let from = entries[*index].split('#').next().unwrap().to_string();
+                        if let Some(entry_id) = params["entryId"].as_u64() {
+                            *index = entry_id as usize;
+                        }
+                        let within_document = params["entryId"].is_u64()
+                            && entries[*index].split('#').next() == Some(from.as_str());
+                        load_event = Some(serde_json::json!({
+                            "method": if within_document {
+                                "Page.navigatedWithinDocument"
+                            } else {
+                                "Page.loadEventFired"
+                            },
+                            "params": {},
+                            "sessionId": session_id,
+                        }));
+                        serde_json::json!({})
+                    }
+                    "Target.closeTarget" => {
+                        open.retain(|id| params["targetId"] != *id);
+                        serde_json::json!({ "success": true })
+                    }
+                    // Open tabs titled after their id, plus a worker nobody attached to
+                    "Target.getTargets" => {
+                        let mut infos: Vec<_> = open
+                            .iter()
+                            .map(|id| {
+                                serde_json::json!({
+                                    "targetId": id,
+                                    "type": "page",
+                                    "title": format!("Page {}", id),
+                                    "url": format!("https://{}.test/", id.to_lowercase()),
+                                    "attached": true,
+                                })
+                            })
+                            .collect();
+                        infos.push(serde_json::json!({
+                            "targetId": "W1",
+                            "type": "service_worker",
+                            "title": "",
+                            "url": "https://sw.test/",
+                            "attached": false,
+                        }));
+                        serde_json::json!({ "targetInfos": infos })
+                    }
+                    _ => serde_json::json!({}),
+                };
+                let mut messages = vec![mock::reply(request, result)];
+                messages.extend(load_event);
+                Reply::Messages(messages)
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_close_tab() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        session.start().await.unwrap();
+        let first = session.new_tab(None).await.unwrap();
+        let second = session.new_tab(None).await.unwrap();
+        let mut events = session.event_bus.subscribe();
+
+        // Closing a background tab keeps focus
+        session.close_tab(first.clone()).await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabClosed { target_id } if target_id == first
+        ));
+        assert!(events.try_recv().is_err());
+        assert_eq!(session.current_session().await.unwrap().target_id, second);
+        assert!(session.close_tab(first).await.is_err());
+
+        // Closing the focused tab moves focus to the one focused before it,
+        // not the newest
+        let third = session.new_tab(None).await.unwrap();
+        let fourth = session.new_tab(None).await.unwrap();
+        session.switch_tab(second.clone()).await.unwrap();
+        session.switch_tab(fourth.clone()).await.unwrap();
+        let mut events = session.event_bus.subscribe();
+        session.close_tab(fourth).await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabClosed { .. }
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabSwitched { target_id } if target_id == second
+        ));
+        assert_eq!(session.current_session().await.unwrap().target_id, second);
+        session.close_tab(second.clone()).await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabClosed { .. }
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabSwitched { target_id } if target_id == third
+        ));
+
+        // Closing the last tab leaves nothing focused
+        session.close_tab(third.clone()).await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::TabClosed { target_id } if target_id == third
+        ));
+        assert!(session.current_session().await.is_none());
+
+        session.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disconnected() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        assert!(!session.is_connected());
+        session.start().await.unwrap();
+        assert!(session.is_connected());
+
+        let disconnected = session.event_bus.wait_for(
+            |event| event.kind() == EventKind::Disconnected,
+            Duration::from_secs(5),
+        );
+        assert!(session
+            .new_tab(Some("https://close.test/".to_string()))
+            .await
+            .is_err());
+        disconnected.await.unwrap();
+        assert!(!session.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_navigate_waits_for_load() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        session.start().await.unwrap();
+        let target_id = session.new_tab(None).await.unwrap();
+        let mut events = session.event_bus.subscribe();
+
+        session.navigate("https://redirect.test/").await.unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::NavigationStarted { url } if url == "https://redirect.test/"
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            BrowserEvent::NavigationComplete { target_id: id, url }
+                if id == target_id && url == "https://final.test/"
+        ));
+
+        // Fragment changes fire no load event
+        session
+            .navigate_with_timeout("https://final.test/#top", Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let error = session
+            .navigate_with_timeout("https://slow.test/", Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<crate::cdp::client::CDPError>(),
+            Some(crate::cdp::client::CDPError::Timeout)
+        ));
+        while let Ok(event) = events.try_recv() {
+            assert!(
+                !matches!(&event, BrowserEvent::NavigationComplete { url, .. } if url.contains("slow")),
+                "no completion for a page that never loaded"
+            );
+        }
+
+        session.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_history_navigation() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        session.start().await.unwrap();
+        session.new_tab(None).await.unwrap();
+        for url in ["https://a.test/", "https://b.test/", "https://b.test/#x"] {
+            session.navigate(url).await.unwrap();
+        }
+        assert!(!session.go_forward().await.unwrap());
+
+        let mut events = session.event_bus.subscribe();
+        let mut completed = || match events.try_recv().unwrap() {
+            BrowserEvent::NavigationStarted { .. } => match events.try_recv().unwrap() {
+                BrowserEvent::NavigationComplete { url, .. } => url,
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        };
+
+        // Within the document, then across documents
+        assert!(session.go_back().await.unwrap());
+        assert_eq!(completed(), "https://b.test/");
+        assert!(session.go_back().await.unwrap());
+        assert_eq!(completed(), "https://a.test/");
+        assert!(session.go_back().await.unwrap());
+        assert_eq!(completed(), "about:blank");
+        assert!(!session.go_back().await.unwrap());
+
+        assert!(session.go_forward().await.unwrap());
+        assert_eq!(completed(), "https://a.test/");
+        session.reload().await.unwrap();
+        assert_eq!(completed(), "https://a.test/");
+        assert!(events.try_recv().is_err());
+
+        session.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watchdogs_from_config() {
+        let names = |session: BrowserSession| async move {
+            let watchdogs = session.watchdogs().await;
+            watchdogs
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(BrowserSession::new(SessionConfig::default())).await,
+            vec![
+                "CrashWatchdog",
+                "DialogWatchdog",
+                "DownloadsWatchdog",
+                "SecurityWatchdog"
+            ]
+        );
+
+        let bare = SessionConfig {
+            enable_default_watchdogs: false,
+            ..SessionConfig::default()
+        };
+        assert!(names(BrowserSession::new(bare.clone())).await.is_empty());
+
+        let policy_only = SessionConfig {
+            security_policy: Some(SecurityPolicy {
+                block_private_networks: true,
+                ..SecurityPolicy::default()
+            }),
+            ..bare
+        };
+        assert_eq!(
+            names(BrowserSession::new(policy_only)).await,
+            vec!["SecurityWatchdog"]
+        );
+
+        // Configs saved before these fields existed keep the defaults
+        let old: SessionConfig = serde_json::from_value(serde_json::json!({
+            "id": "s",
+            "cdp_url": "ws://localhost:9222",
+            "headless": true,
+            "user_data_dir": null,
+        }))
+        .unwrap();
+        assert!(old.enable_default_watchdogs);
+        assert!(old.download_dir.is_none() && old.security_policy.is_none());
+    }
+
+    /// Records the tabs it is told about
+    struct TabRecorder {
+        tabs: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::watchdog::Watchdog for TabRecorder {
+        fn name(&self) -> &str {
+            "TabRecorder"
+        }
+
+        async fn on_event(&self, event: &BrowserEvent) {
+            if let BrowserEvent::TabCreated { target_id } = event {
+                self.tabs.lock().unwrap().push(target_id.clone());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disable_watchdog() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        let tabs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        session
+            .watchdog_manager
+            .write()
+            .await
+            .register(Box::new(TabRecorder { tabs: tabs.clone() }));
+        session.start().await.unwrap();
+
+        session.disable_watchdog("SecurityWatchdog").await.unwrap();
+        session
+            .unregister_watchdog("DownloadsWatchdog")
+            .await
+            .unwrap();
+        assert!(session.disable_watchdog("DownloadsWatchdog").await.is_err());
+        assert_eq!(
+            session.watchdogs().await,
+            vec![
+                ("CrashWatchdog".to_string(), true),
+                ("DialogWatchdog".to_string(), true),
+                ("SecurityWatchdog".to_string(), false),
+                ("TabRecorder".to_string(), true),
+            ]
+        );
+
+        // A disabled watchdog sees no tabs; re-enabled, it sees new ones
+        session.disable_watchdog("TabRecorder").await.unwrap();
+        session.new_tab(None).await.unwrap();
+        assert!(tabs.lock().unwrap().is_empty());
+        session.enable_watchdog("TabRecorder").await.unwrap();
+        session.new_tab(None).await.unwrap();
+        assert_eq!(*tabs.lock().unwrap(), vec!["T2".to_string()]);
+        session.enable_watchdog("SecurityWatchdog").await.unwrap();
+
+        session.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grant_permissions() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        assert!(session
+            .grant_permissions(&["geolocation"], None)
+            .await
+            .is_err());
+        session.start().await.unwrap();
+
+        let err = session
+            .grant_permissions(&["geolocation", "clipboardReadWrte"], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Unknown permission: clipboardReadWrte");
+        assert!(!session.permissions_granted.load(Ordering::Relaxed));
+
+        session
+            .grant_permissions(
+                &["geolocation", "clipboardReadWrite"],
+                Some("https://example.com"),
+            )
+            .await
+            .unwrap();
+        assert!(session.permissions_granted.load(Ordering::Relaxed));
+
+        session.stop().await.unwrap();
+        assert!(!session.permissions_granted.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_list_tabs() {
+        let session = BrowserSession::new(SessionConfig {
+            cdp_url: mock_browser().await,
+            ..SessionConfig::default()
+        });
+        assert!(session.list_tabs().await.is_empty());
+        assert_eq!(session.current_tab().await, None);
+
+        session.start().await.unwrap();
+        let first = session.new_tab(None).await.unwrap();
+        let second = session.new_tab(None).await.unwrap();
+        assert_eq!(session.current_tab().await, Some(second.clone()));
+
+        let mut tabs = session.list_tabs().await;
+        tabs.sort_by(|a, b| a.target_id.cmp(&b.target_id));
+        assert_eq!(
+            tabs,
+            vec![
+                TabInfo {
+                    target_id: first.clone(),
+                    title: "Page T1".to_string(),
+                    url: "https://t1.test/".to_string(),
+                },
+                TabInfo {
+                    target_id: second.clone(),
+                    title: "Page T2".to_string(),
+                    url: "https://t2.test/".to_string(),
+                },
+            ]
+        );
+
+        let tab = tabs.iter().find(|tab| tab.url.contains("t1")).unwrap();
+        session.switch_tab(tab.target_id.clone()).await.unwrap();
+        assert_eq!(session.current_tab().await, Some(first));
+
+        session.stop().await.unwrap();
+    }
 
     #[tokio::test]
     #[ignore] // Needs running Chrome
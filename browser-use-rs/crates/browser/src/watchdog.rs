@@ -8,10 +8,14 @@
 //! - Zero-cost event dispatch
 
 use async_trait::async_trait;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+use tokio::task::JoinHandle;
 
-use crate::cdp::CDPClient;
-use crate::events::BrowserEvent;
+use crate::cdp::client::EventCallback;
+use crate::cdp::protocol::SessionId;
+use crate::cdp::{CDPClient, SubscriptionId};
+use crate::events::{BrowserEvent, EventBus};
 
 /// Watchdog trait - monitors browser state and reacts to events
 ///
@@ -60,46 +64,272 @@ pub trait Watchdog: Send + Sync {
     async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+
+    /// Optional: Where this watchdog reports what it did
+    ///
+    /// `WatchdogManager::register` connects it to the manager's
+    /// `diagnostics` channel.
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        None
+    }
+}
+
+/// Buffered diagnostics per `WatchdogManager::diagnostics` receiver
+const DIAGNOSTICS_CAPACITY: usize = 256;
+
+/// What a watchdog did, see `WatchdogEvent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogEventKind {
+    /// A navigation or request was blocked by policy
+    UrlBlocked,
+    /// A crashed target was reloaded, or given up on
+    CrashHandled,
+    /// A download (or auto-saved PDF) finished
+    DownloadCompleted,
+}
+
+/// Diagnostic report from a watchdog, e.g. "SecurityWatchdog blocked evil.com"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchdogEvent {
+    pub watchdog_name: String,
+    pub kind: WatchdogEventKind,
+    /// Free-form: the URL, session or file involved
+    pub detail: String,
+}
+
+/// A watchdog's handle for emitting `WatchdogEvent`s
+///
+/// Clones share the connection, so one cloned into a callback before
+/// `WatchdogManager::register` still reaches the manager. Emitting before
+/// that, or with nobody listening, does nothing.
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    channel: Arc<OnceLock<(String, broadcast::Sender<WatchdogEvent>)>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send events as `watchdog_name` to `tx`; only the first call counts
+    fn connect(&self, watchdog_name: &str, tx: broadcast::Sender<WatchdogEvent>) {
+        let _ = self.channel.set((watchdog_name.to_string(), tx));
+    }
+
+    pub fn emit(&self, kind: WatchdogEventKind, detail: impl Into<String>) {
+        if let Some((watchdog_name, tx)) = self.channel.get() {
+            let _ = tx.send(WatchdogEvent {
+                watchdog_name: watchdog_name.clone(),
+                kind,
+                detail: detail.into(),
+            });
+        }
+    }
+}
+
+/// CDP subscriptions owned by a watchdog, dropped together on detach
+///
+/// Without this, a detached watchdog's callbacks keep firing for the
+/// lifetime of the client.
+#[derive(Default)]
+pub struct CdpSubscriptions {
+    entries: std::sync::Mutex<Vec<(Arc<CDPClient>, String, SubscriptionId)>>,
+}
+
+impl CdpSubscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe and remember the handle
+    pub fn subscribe(&self, client: &Arc<CDPClient>, method: &str, callback: EventCallback) {
+        let id = client.subscribe(method, callback);
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((client.clone(), method.to_string(), id));
+    }
+
+    /// Subscribe to one session's events (see `CDPClient::subscribe_session`)
+    pub fn subscribe_session(
+        &self,
+        client: &Arc<CDPClient>,
+        method: &str,
+        session_id: Option<SessionId>,
+        callback: EventCallback,
+    ) {
+        let id = client.subscribe_session(method, session_id, callback);
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((client.clone(), method.to_string(), id));
+    }
+
+    /// Unsubscribe everything registered through this set
+    pub fn clear(&self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap_or_else(|e| e.into_inner()));
+        for (client, method, id) in entries {
+            client.unsubscribe(&method, id);
+        }
+    }
+
+    /// Number of live subscriptions
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Watchdog manager - dispatches events to all watchdogs
 pub struct WatchdogManager {
-    watchdogs: Vec<Box<dyn Watchdog>>,
+    watchdogs: Vec<Entry>,
+
+    /// Client the enabled watchdogs are attached to, between attach_all and detach_all
+    cdp_client: Option<Arc<CDPClient>>,
+
+    /// Where registered watchdogs' `Diagnostics` send
+    diagnostics: broadcast::Sender<WatchdogEvent>,
+}
+
+struct Entry {
+    watchdog: Box<dyn Watchdog>,
+    enabled: bool,
 }
 
 impl WatchdogManager {
     pub fn new() -> Self {
         Self {
             watchdogs: Vec::new(),
+            cdp_client: None,
+            diagnostics: broadcast::channel(DIAGNOSTICS_CAPACITY).0,
         }
     }
 
-    /// Add a watchdog
+    /// Add a watchdog, enabled
+    ///
+    /// Registering doesn't attach; call before `attach_all`.
     pub fn register(&mut self, watchdog: Box<dyn Watchdog>) {
         tracing::debug!("Registered watchdog: {}", watchdog.name());
-        self.watchdogs.push(watchdog);
+        if let Some(diagnostics) = watchdog.diagnostics() {
+            diagnostics.connect(watchdog.name(), self.diagnostics.clone());
+        }
+        self.watchdogs.push(Entry {
+            watchdog,
+            enabled: true,
+        });
+    }
+
+    /// Remove a watchdog by name, detaching it if it's attached
+    ///
+    /// Returns false if no watchdog has that name.
+    pub async fn unregister(&mut self, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(index) = self.position(name) else {
+            return Ok(false);
+        };
+        let entry = self.watchdogs.remove(index);
+        if entry.enabled && self.cdp_client.is_some() {
+            entry.watchdog.on_detach().await?;
+        }
+        tracing::debug!("Unregistered watchdog: {}", name);
+        Ok(true)
+    }
+
+    /// Turn a watchdog on or off by name
+    ///
+    /// Disabled watchdogs get no events and are skipped by `attach_all`.
+    /// While attached, disabling detaches and enabling re-attaches; a
+    /// re-enabled watchdog only sees tabs opened after that.
+    ///
+    /// Returns false if no watchdog has that name.
+    pub async fn set_enabled(
+        &mut self,
+        name: &str,
+        enabled: bool,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(index) = self.position(name) else {
+            return Ok(false);
+        };
+        let entry = &mut self.watchdogs[index];
+        if entry.enabled == enabled {
+            return Ok(true);
+        }
+        if let Some(client) = &self.cdp_client {
+            if enabled {
+                entry.watchdog.on_attach(client.clone()).await?;
+            } else {
+                entry.watchdog.on_detach().await?;
+            }
+        }
+        entry.enabled = enabled;
+        tracing::debug!(
+            "{} watchdog: {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            name
+        );
+        Ok(true)
+    }
+
+    /// Whether the named watchdog is enabled; `None` if it isn't registered
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.position(name)
+            .map(|index| self.watchdogs[index].enabled)
+    }
+
+    /// Receive what registered watchdogs report from now on: blocked URLs,
+    /// handled crashes, finished downloads
+    ///
+    /// A receiver that falls more than 256 events behind loses the oldest.
+    pub fn diagnostics(&self) -> broadcast::Receiver<WatchdogEvent> {
+        self.diagnostics.subscribe()
+    }
+
+    /// Names of all registered watchdogs, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.watchdogs
+            .iter()
+            .map(|entry| entry.watchdog.name())
+            .collect()
     }
 
-    /// Attach all watchdogs
+    fn position(&self, name: &str) -> Option<usize> {
+        self.watchdogs
+            .iter()
+            .position(|entry| entry.watchdog.name() == name)
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &dyn Watchdog> {
+        self.watchdogs
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| entry.watchdog.as_ref())
+    }
+
+    /// Attach all enabled watchdogs
     pub async fn attach_all(
-        &self,
+        &mut self,
         cdp_client: Arc<CDPClient>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        for watchdog in &self.watchdogs {
+        for watchdog in self.enabled() {
             watchdog.on_attach(cdp_client.clone()).await?;
         }
+        self.cdp_client = Some(cdp_client);
         Ok(())
     }
 
-    /// Detach all watchdogs
-    pub async fn detach_all(&self) -> Result<(), Box<dyn std::error::Error>> {
-        for watchdog in &self.watchdogs {
+    /// Detach all enabled watchdogs
+    pub async fn detach_all(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.cdp_client = None;
+        for watchdog in self.enabled() {
             watchdog.on_detach().await?;
         }
         Ok(())
     }
 
-    /// Dispatch event to all watchdogs in parallel
+    /// Dispatch event to all enabled watchdogs in parallel
     ///
     /// Python does this sequentially with asyncio.gather.
     /// Rust does it with join_all for true parallelism.
@@ -107,8 +337,7 @@ impl WatchdogManager {
         use futures_util::future::join_all;
 
         let tasks: Vec<_> = self
-            .watchdogs
-            .iter()
+            .enabled()
             .map(|w| {
                 let event = event.clone();
                 async move {
@@ -119,6 +348,68 @@ impl WatchdogManager {
 
         join_all(tasks).await;
     }
+
+    /// Dispatch every event published on `bus` from now on
+    ///
+    /// With the bridge running, publishing an event is enough to reach the
+    /// watchdogs, including events from user code such as
+    /// `BrowserEvent::Custom`. Events are dispatched one at a time, in order,
+    /// each under a read lock of `manager`. Lifecycle events are never lost,
+    /// however far dispatch falls behind; see `EventBus::subscribe_lossless`.
+    pub fn bridge(manager: Arc<RwLock<WatchdogManager>>, bus: &EventBus) -> WatchdogBridge {
+        let mut rx = bus.subscribe_lossless();
+        let (flush_tx, mut flush_rx) = mpsc::unbounded_channel::<oneshot::Sender<()>>();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                // Biased: everything already on the bus goes out before a
+                // flush is acknowledged or the bridge stops
+                tokio::select! {
+                    biased;
+                    event = rx.recv() => match event {
+                        Some(event) => manager.read().await.dispatch(Arc::new(event)).await,
+                        None => break,
+                    },
+                    flush = flush_rx.recv() => match flush {
+                        Some(done) => {
+                            let _ = done.send(());
+                        }
+                        None => break,
+                    },
+                }
+            }
+        });
+
+        WatchdogBridge { flush_tx, handle }
+    }
+}
+
+/// Running `WatchdogManager::bridge`
+///
+/// Dropping it stops the forwarding task.
+pub struct WatchdogBridge {
+    /// Flush requests; dropping it stops the task
+    flush_tx: mpsc::UnboundedSender<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl WatchdogBridge {
+    /// Wait until the events published before this call have been dispatched
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.flush_tx.send(done_tx).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    /// Dispatch the events already published, then stop
+    pub async fn shutdown(self) {
+        let Self { flush_tx, handle } = self;
+        drop(flush_tx);
+        if let Err(e) = handle.await {
+            tracing::warn!("Watchdog bridge task failed: {}", e);
+        }
+    }
 }
 
 impl Default for WatchdogManager {
@@ -170,4 +461,116 @@ mod tests {
 
         assert_eq!(counter.load(std::sync::atomic::Ordering::SeqCst), 2);
     }
+
+    #[tokio::test]
+    async fn test_bridge_forwards_bus_events() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut manager = WatchdogManager::new();
+        manager.register(Box::new(TestWatchdog {
+            name: "test".to_string(),
+            event_count: counter.clone(),
+        }));
+        let manager = Arc::new(RwLock::new(manager));
+
+        let bus = EventBus::new();
+        let bridge = WatchdogManager::bridge(manager.clone(), &bus);
+        bus.publish(BrowserEvent::Started);
+        bus.publish(BrowserEvent::Custom {
+            name: "LoginNeeded".to_string(),
+            data: serde_json::json!({ "url": "https://a.test/login" }),
+        });
+
+        bridge.flush().await;
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+
+        // Published events are delivered before the bridge stops
+        bus.publish(BrowserEvent::TabCreated {
+            target_id: "T1".to_string(),
+        });
+        bridge.shutdown().await;
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+        assert_eq!(bus.receiver_count(), 0);
+
+        bus.publish(BrowserEvent::Stopped);
+        tokio::task::yield_now().await;
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    /// Counts attaches and detaches
+    struct LifecycleWatchdog {
+        name: &'static str,
+        attached: Arc<std::sync::atomic::AtomicIsize>,
+    }
+
+    #[async_trait]
+    impl Watchdog for LifecycleWatchdog {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn on_event(&self, _event: &BrowserEvent) {}
+
+        async fn on_attach(
+            &self,
+            _cdp_client: Arc<CDPClient>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            self.attached
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
+            self.attached
+                .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enable_disable_and_unregister() {
+        use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+        // Mock browser that stays quiet
+        let client = crate::cdp::mock::connect(|_| crate::cdp::mock::Reply::Ignore).await;
+
+        let events = Arc::new(AtomicUsize::new(0));
+        let attached = Arc::new(AtomicIsize::new(0));
+        let mut manager = WatchdogManager::new();
+        manager.register(Box::new(TestWatchdog {
+            name: "events".to_string(),
+            event_count: events.clone(),
+        }));
+        manager.register(Box::new(LifecycleWatchdog {
+            name: "lifecycle",
+            attached: attached.clone(),
+        }));
+
+        // Disabled before start: skipped by attach_all and dispatch
+        assert!(manager.set_enabled("lifecycle", false).await.unwrap());
+        assert!(manager.set_enabled("events", false).await.unwrap());
+        manager.attach_all(client.clone()).await.unwrap();
+        manager.dispatch(Arc::new(BrowserEvent::Started)).await;
+        assert_eq!(attached.load(Ordering::SeqCst), 0);
+        assert_eq!(events.load(Ordering::SeqCst), 0);
+
+        // Toggled while running: attached and detached on the spot
+        assert!(manager.set_enabled("lifecycle", true).await.unwrap());
+        assert!(manager.set_enabled("lifecycle", true).await.unwrap());
+        assert_eq!(attached.load(Ordering::SeqCst), 1);
+        assert!(manager.set_enabled("events", true).await.unwrap());
+        manager.dispatch(Arc::new(BrowserEvent::Started)).await;
+        assert_eq!(events.load(Ordering::SeqCst), 1);
+
+        assert!(manager.unregister("lifecycle").await.unwrap());
+        assert_eq!(attached.load(Ordering::SeqCst), 0);
+        assert!(!manager.unregister("lifecycle").await.unwrap());
+        assert!(!manager.set_enabled("missing", true).await.unwrap());
+        assert_eq!(manager.names(), vec!["events"]);
+        assert_eq!(manager.is_enabled("events"), Some(true));
+        assert_eq!(manager.is_enabled("lifecycle"), None);
+
+        manager.detach_all().await.unwrap();
+    }
 }
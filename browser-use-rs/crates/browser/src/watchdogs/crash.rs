@@ -5,21 +5,114 @@
 
 use async_trait::async_trait;
 
+use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Notify, RwLock};
 
+use crate::cdp::client::{CDPError, Result as CdpResult};
+use crate::cdp::commands::Reload;
+use crate::cdp::protocol::{CDPEvent, SessionId, TargetId};
 use crate::cdp::{CDPClient, CDPSession};
-use crate::events::BrowserEvent;
-use crate::watchdog::Watchdog;
+use crate::events::{BrowserEvent, EventBus};
+use crate::watchdog::{CdpSubscriptions, Diagnostics, Watchdog, WatchdogEventKind};
 
 /// Tracks a single network request
 #[derive(Clone, Debug)]
-struct RequestTracker {
-    request_id: String,
-    start_time: Instant,
-    url: String,
-    method: String,
+pub struct RequestTracker {
+    pub request_id: String,
+    pub start_time: Instant,
+    pub url: String,
+    pub method: String,
+    /// Tab that issued the request, `None` for browser-level requests
+    pub target_id: Option<TargetId>,
+    /// CDP resource type from `Network.requestWillBeSent`, e.g. "Document", "Image"
+    pub resource_type: Option<String>,
+    /// Response MIME type, once `Network.responseReceived` arrives; the body
+    /// is tracked until `Network.loadingFinished`
+    pub mime_type: Option<String>,
+    /// Response status, once `Network.responseReceived` arrives
+    pub status: Option<u16>,
+}
+
+/// Which tracked requests count for `wait_for_idle` and the timeout check
+///
+/// Returns false for requests to ignore; see `ignore_background_requests`.
+pub type RequestFilter = Arc<dyn Fn(&RequestTracker) -> bool + Send + Sync>;
+
+/// Resource types that may stay open or load long after a page is usable
+const BACKGROUND_RESOURCE_TYPES: &[&str] = &[
+    "Image",
+    "Font",
+    "Media",
+    "Ping",
+    "EventSource",
+    "WebSocket",
+    "Manifest",
+    "CSPViolationReport",
+];
+
+/// Filter ignoring images, fonts, media, beacons and long-lived streams
+pub fn ignore_background_requests() -> RequestFilter {
+    Arc::new(|request: &RequestTracker| {
+        !request
+            .resource_type
+            .as_deref()
+            .is_some_and(|resource_type| BACKGROUND_RESOURCE_TYPES.contains(&resource_type))
+    })
+}
+
+/// A tab attached on `TabCreated`, dropped on `TabClosed`
+struct MonitoredTarget {
+    session: CDPSession,
+    subscriptions: CdpSubscriptions,
+}
+
+/// Crash and reload bookkeeping
+#[derive(Debug, Default)]
+struct CrashStats {
+    crashes: usize,
+    recoveries: usize,
+    /// Reload attempts and time of the last one, keyed by session id
+    reloads: HashMap<String, (u32, Instant)>,
+}
+
+impl CrashStats {
+    /// Record a crash and decide whether to reload
+    ///
+    /// Returns the delay before reloading and the attempt number,
+    /// or `None` once `max_attempts` reloads were spent on this target.
+    /// A target that stayed up for `recovery_window` since its last reload
+    /// recovered, and starts over with a full budget.
+    fn record_crash(
+        &mut self,
+        key: &str,
+        max_attempts: u32,
+        cooldown: Duration,
+        recovery_window: Duration,
+        now: Instant,
+    ) -> Option<(Duration, u32)> {
+        self.crashes += 1;
+
+        let (attempts, last) = match self.reloads.get(key).copied() {
+            Some((_, last)) if now.saturating_duration_since(last) >= recovery_window => (0, now),
+            Some(entry) => entry,
+            None => (0, now),
+        };
+        if attempts >= max_attempts {
+            return None;
+        }
+
+        let delay = if attempts == 0 {
+            Duration::ZERO
+        } else {
+            cooldown.saturating_sub(now.saturating_duration_since(last))
+        };
+        self.reloads
+            .insert(key.to_string(), (attempts + 1, now + delay));
+        Some((delay, attempts + 1))
+    }
 }
 
 /// Crash Watchdog - detects page crashes and hung requests
@@ -33,11 +126,41 @@ pub struct CrashWatchdog {
     /// Active network requests - using Arc<RwLock<Vec>> for simplicity
     active_requests: Arc<RwLock<Vec<RequestTracker>>>,
 
-    /// CDP sessions being monitored
-    sessions: Arc<RwLock<Vec<Arc<CDPSession>>>>,
+    /// Woken whenever a request starts or stops being tracked
+    requests_changed: Arc<Notify>,
+
+    /// Tabs being monitored, keyed by target id
+    targets: Arc<RwLock<HashMap<TargetId, MonitoredTarget>>>,
 
     /// Monitoring task handle
     monitor_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+
+    /// CDP event subscriptions, removed on detach
+    subscriptions: CdpSubscriptions,
+
+    /// Client captured in `on_attach`, used to reload crashed targets
+    cdp_client: Arc<RwLock<Option<Arc<CDPClient>>>>,
+
+    /// Where to publish `TargetRecovered` and the request lifecycle events
+    event_sink: Option<EventBus>,
+
+    /// Reloads attempted per crashed target before giving up
+    max_reload_attempts: u32,
+
+    /// Minimum time between reloads of the same target
+    reload_cooldown: Duration,
+
+    /// Uptime after a reload that resets the target's reload attempts
+    recovery_window: Duration,
+
+    /// Crash and recovery counters
+    stats: Arc<RwLock<CrashStats>>,
+
+    /// Requests left out of `wait_for_idle` and the timeout check
+    request_filter: Option<RequestFilter>,
+
+    /// Reports `CrashHandled` once each crash is reloaded or given up on
+    diagnostics: Diagnostics,
 }
 
 impl CrashWatchdog {
@@ -46,8 +169,18 @@ impl CrashWatchdog {
             network_timeout: Duration::from_secs(10),
             check_interval: Duration::from_secs(5),
             active_requests: Arc::new(RwLock::new(Vec::new())),
-            sessions: Arc::new(RwLock::new(Vec::new())),
+            requests_changed: Arc::new(Notify::new()),
+            targets: Arc::new(RwLock::new(HashMap::new())),
             monitor_task: Arc::new(RwLock::new(None)),
+            subscriptions: CdpSubscriptions::new(),
+            cdp_client: Arc::new(RwLock::new(None)),
+            event_sink: None,
+            max_reload_attempts: 3,
+            reload_cooldown: Duration::from_secs(5),
+            recovery_window: Duration::from_secs(60),
+            stats: Arc::new(RwLock::new(CrashStats::default())),
+            request_filter: None,
+            diagnostics: Diagnostics::new(),
         }
     }
 
@@ -55,17 +188,61 @@ impl CrashWatchdog {
         Self {
             network_timeout,
             check_interval,
-            active_requests: Arc::new(RwLock::new(Vec::new())),
-            sessions: Arc::new(RwLock::new(Vec::new())),
-            monitor_task: Arc::new(RwLock::new(None)),
+            ..Self::new()
         }
     }
 
+    /// Create a watchdog that publishes `TargetRecovered` and
+    /// `RequestStarted`/`RequestFinished`/`RequestFailed` to `event_bus`
+    pub fn with_event_sink(event_bus: EventBus) -> Self {
+        Self {
+            event_sink: Some(event_bus),
+            ..Self::new()
+        }
+    }
+
+    /// Limit reloads per crashed target and space them at least `reload_cooldown` apart
+    pub fn with_reload_limits(
+        mut self,
+        max_reload_attempts: u32,
+        reload_cooldown: Duration,
+    ) -> Self {
+        self.max_reload_attempts = max_reload_attempts;
+        self.reload_cooldown = reload_cooldown;
+        self
+    }
+
+    /// Give a crashed target a fresh reload budget once it has stayed up for
+    /// `recovery_window` after its last reload
+    pub fn with_recovery_window(mut self, recovery_window: Duration) -> Self {
+        self.recovery_window = recovery_window;
+        self
+    }
+
+    /// Only wait for and time out requests `filter` accepts
+    pub fn with_request_filter(mut self, filter: RequestFilter) -> Self {
+        self.request_filter = Some(filter);
+        self
+    }
+
+    /// Number of target crashes seen
+    pub async fn crash_count(&self) -> usize {
+        self.stats.read().await.crashes
+    }
+
+    /// Number of crashed targets successfully reloaded
+    pub async fn recovery_count(&self) -> usize {
+        self.stats.read().await.recoveries
+    }
+
     /// Start monitoring loop
     async fn start_monitoring(&self) {
         let active_requests = self.active_requests.clone();
+        let requests_changed = self.requests_changed.clone();
         let network_timeout = self.network_timeout;
         let check_interval = self.check_interval;
+        let request_filter = self.request_filter.clone();
+        let event_sink = self.event_sink.clone();
 
         let task = tokio::spawn(async move {
             let mut interval = tokio::time::interval(check_interval);
@@ -76,19 +253,35 @@ impl CrashWatchdog {
                 let now = Instant::now();
                 let mut requests = active_requests.write().await;
 
-                // Find and remove timed out requests
+                // Find and remove timed out requests; only those the filter
+                // accepts are reported
                 let mut i = 0;
                 while i < requests.len() {
                     let elapsed = now.duration_since(requests[i].start_time);
-                    if elapsed > network_timeout {
-                        let tracker = requests.remove(i);
-                        tracing::warn!(
-                            "[CrashWatchdog] Request timeout after {:?}: {}",
-                            elapsed,
-                            tracker.url
-                        );
-                    } else {
+                    if elapsed <= network_timeout {
                         i += 1;
+                        continue;
+                    }
+                    let tracker = requests.remove(i);
+                    requests_changed.notify_waiters();
+                    if !request_filter
+                        .as_ref()
+                        .is_none_or(|filter| filter(&tracker))
+                    {
+                        continue;
+                    }
+                    tracing::warn!(
+                        "[CrashWatchdog] Request timeout after {:?}: {}",
+                        elapsed,
+                        tracker.url
+                    );
+                    if let Some(event_bus) = &event_sink {
+                        event_bus.publish(BrowserEvent::RequestFailed {
+                            target_id: tracker.target_id,
+                            request_id: tracker.request_id,
+                            error_text: "net::ERR_TIMED_OUT".to_string(),
+                            elapsed_ms: elapsed.as_millis() as u64,
+                        });
                     }
                 }
             }
@@ -103,24 +296,33 @@ impl CrashWatchdog {
             task.abort();
         }
         self.active_requests.write().await.clear();
+        self.requests_changed.notify_waiters();
     }
 
     /// Track new network request
+    #[cfg(test)]
     async fn track_request(&self, request_id: String, url: String, method: String) {
         let tracker = RequestTracker {
             request_id: request_id.clone(),
             start_time: Instant::now(),
             url,
             method,
+            target_id: None,
+            resource_type: None,
+            mime_type: None,
+            status: None,
         };
         self.active_requests.write().await.push(tracker);
+        self.requests_changed.notify_waiters();
     }
 
     /// Remove request from tracking
+    #[cfg(test)]
     async fn untrack_request(&self, request_id: &str) {
         let mut requests = self.active_requests.write().await;
         if let Some(pos) = requests.iter().position(|r| r.request_id == request_id) {
             let tracker = requests.remove(pos);
+            self.requests_changed.notify_waiters();
             let elapsed = Instant::now().duration_since(tracker.start_time);
             tracing::debug!(
                 "[CrashWatchdog] Request completed in {:?}: {}",
@@ -130,10 +332,279 @@ impl CrashWatchdog {
         }
     }
 
+    /// Drop tracking for every request issued by `target_id`
+    async fn untrack_target(&self, target_id: &str) -> usize {
+        let mut requests = self.active_requests.write().await;
+        let before = requests.len();
+        requests.retain(|r| r.target_id.as_deref() != Some(target_id));
+        self.requests_changed.notify_waiters();
+        before - requests.len()
+    }
+
+    /// Attach to a new tab and track its network requests
+    async fn monitor_target(&self, target_id: &TargetId) {
+        let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+            tracing::debug!("[CrashWatchdog] Not attached, ignoring tab {}", target_id);
+            return;
+        };
+        if self.targets.read().await.contains_key(target_id) {
+            return;
+        }
+
+        let session = match CDPSession::attach(
+            cdp_client.clone(),
+            target_id.clone(),
+            Some(vec!["Page", "Network", "Inspector"]),
+        )
+        .await
+        {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("[CrashWatchdog] Failed to attach to {}: {}", target_id, e);
+                return;
+            }
+        };
+
+        let subscriptions = CdpSubscriptions::new();
+        Self::subscribe_network(
+            &subscriptions,
+            &cdp_client,
+            &self.active_requests,
+            &self.requests_changed,
+            &self.event_sink,
+            Some(session.session_id.clone()),
+            Some(target_id.clone()),
+        );
+        self.targets.write().await.insert(
+            target_id.clone(),
+            MonitoredTarget {
+                session,
+                subscriptions,
+            },
+        );
+    }
+
+    /// Track request lifecycle events from `session_id` (`None` = browser-level)
+    ///
+    /// Requests are tagged with `target_id` so they can be dropped with their tab.
+    /// Events go through one task, in the order the browser sent them, so a
+    /// response is never handled before its request.
+    fn subscribe_network(
+        subscriptions: &CdpSubscriptions,
+        cdp_client: &Arc<CDPClient>,
+        active_requests: &Arc<RwLock<Vec<RequestTracker>>>,
+        requests_changed: &Arc<Notify>,
+        event_sink: &Option<EventBus>,
+        session_id: Option<SessionId>,
+        target_id: Option<TargetId>,
+    ) {
+        let (tx, mut rx) = mpsc::unbounded_channel::<CDPEvent>();
+        for method in [
+            "Network.requestWillBeSent",
+            "Network.responseReceived",
+            "Network.loadingFinished",
+            "Network.loadingFailed",
+        ] {
+            let tx = tx.clone();
+            subscriptions.subscribe_session(
+                cdp_client,
+                method,
+                session_id.clone(),
+                Arc::new(move |event| {
+                    let _ = tx.send(event);
+                }),
+            );
+        }
+
+        // Ends once the subscriptions, and with them the senders, are dropped
+        let requests = active_requests.clone();
+        let changed = requests_changed.clone();
+        let sink = event_sink.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let Some(params) = event.params.as_ref() {
+                    Self::handle_network_event(
+                        &requests,
+                        &changed,
+                        sink.as_ref(),
+                        &target_id,
+                        &event.method,
+                        params,
+                    )
+                    .await;
+                }
+            }
+        });
+    }
+
+    /// Apply one Network event from `owner`'s session to the tracked requests
+    async fn handle_network_event(
+        requests: &RwLock<Vec<RequestTracker>>,
+        changed: &Notify,
+        sink: Option<&EventBus>,
+        owner: &Option<TargetId>,
+        method: &str,
+        params: &Value,
+    ) {
+        let request_id = params["requestId"].as_str().unwrap_or("");
+        if method == "Network.requestWillBeSent" {
+            let url = params["request"]["url"].as_str().unwrap_or("").to_string();
+            let method = params["request"]["method"]
+                .as_str()
+                .unwrap_or("GET")
+                .to_string();
+
+            let mut requests = requests.write().await;
+            // A redirect re-sends the same request id; it's still one request
+            if params.get("redirectResponse").is_some() {
+                if let Some(tracker) = requests
+                    .iter_mut()
+                    .find(|r| r.request_id == request_id && r.target_id == *owner)
+                {
+                    tracing::debug!(
+                        "[CrashWatchdog] Request {} redirected: {} -> {}",
+                        request_id,
+                        tracker.url,
+                        url
+                    );
+                    tracker.url = url;
+                    tracker.method = method;
+                    return;
+                }
+            }
+
+            requests.push(RequestTracker {
+                request_id: request_id.to_string(),
+                start_time: Instant::now(),
+                url: url.clone(),
+                method: method.clone(),
+                target_id: owner.clone(),
+                resource_type: params["type"].as_str().map(String::from),
+                mime_type: None,
+                status: None,
+            });
+            drop(requests);
+            changed.notify_waiters();
+            tracing::debug!("[CrashWatchdog] Tracking request {}: {}", request_id, url);
+            if let Some(event_bus) = sink {
+                event_bus.publish(BrowserEvent::RequestStarted {
+                    target_id: owner.clone(),
+                    request_id: request_id.to_string(),
+                    url,
+                    method,
+                });
+            }
+            return;
+        }
+
+        let mut requests_guard = requests.write().await;
+        let Some(pos) = requests_guard
+            .iter()
+            .position(|r| r.request_id == request_id && r.target_id == *owner)
+        else {
+            return;
+        };
+
+        // The body is still loading; note what's coming
+        if method == "Network.responseReceived" {
+            let tracker = &mut requests_guard[pos];
+            tracker.mime_type = params["response"]["mimeType"].as_str().map(String::from);
+            tracker.status = params["response"]["status"].as_u64().map(|s| s as u16);
+            return;
+        }
+
+        // Finished and failed loads both end tracking
+        let failed = method == "Network.loadingFailed";
+        let tracker = requests_guard.remove(pos);
+        drop(requests_guard);
+        changed.notify_waiters();
+        let elapsed = Instant::now().duration_since(tracker.start_time);
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let finished = if failed {
+            BrowserEvent::RequestFailed {
+                target_id: tracker.target_id.clone(),
+                request_id: tracker.request_id.clone(),
+                error_text: params["errorText"].as_str().unwrap_or_default().to_string(),
+                elapsed_ms,
+            }
+        } else {
+            BrowserEvent::RequestFinished {
+                target_id: tracker.target_id.clone(),
+                request_id: tracker.request_id.clone(),
+                status: tracker.status.unwrap_or_default(),
+                elapsed_ms,
+            }
+        };
+        if let Some(event_bus) = sink {
+            event_bus.publish(finished);
+        }
+        if failed {
+            tracing::warn!(
+                "[CrashWatchdog] Request failed after {:?}: {}",
+                elapsed,
+                tracker.url
+            );
+        } else {
+            tracing::debug!(
+                "[CrashWatchdog] Request completed in {:?}: {} ({} {})",
+                elapsed,
+                tracker.url,
+                tracker.resource_type.as_deref().unwrap_or("?"),
+                tracker.mime_type.as_deref().unwrap_or("?")
+            );
+        }
+    }
+
     /// Get active request count (for testing)
     pub async fn active_request_count(&self) -> usize {
         self.active_requests.read().await.len()
     }
+
+    /// Number of active requests the request filter accepts
+    async fn counted_request_count(&self) -> usize {
+        let requests = self.active_requests.read().await;
+        match &self.request_filter {
+            Some(filter) => requests.iter().filter(|r| filter(r)).count(),
+            None => requests.len(),
+        }
+    }
+
+    /// Wait until no request has been in flight for `idle_for` ("network idle")
+    ///
+    /// Requests the request filter rejects don't count. Rechecks whenever
+    /// a request starts or finishes. Doesn't need the monitor task, but
+    /// without it hung requests never expire, so a page that doesn't settle
+    /// fails with `CDPError::Timeout` after `timeout`.
+    pub async fn wait_for_idle(&self, idle_for: Duration, timeout: Duration) -> CdpResult<()> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut idle_since = None;
+
+        loop {
+            // Registered before counting, so a change in between still wakes us
+            let changed = self.requests_changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let now = tokio::time::Instant::now();
+            let wake_at = if self.counted_request_count().await == 0 {
+                let since = *idle_since.get_or_insert(now);
+                if now.duration_since(since) >= idle_for {
+                    return Ok(());
+                }
+                (since + idle_for).min(deadline)
+            } else {
+                idle_since = None;
+                deadline
+            };
+            if now >= deadline {
+                return Err(CDPError::Timeout);
+            }
+            tokio::select! {
+                _ = changed => {}
+                _ = tokio::time::sleep_until(wake_at) => {}
+            }
+        }
+    }
 }
 
 impl Default for CrashWatchdog {
@@ -160,15 +631,33 @@ impl Watchdog for CrashWatchdog {
                 self.stop_monitoring().await;
             }
 
+            BrowserEvent::Disconnected => {
+                tracing::debug!("[CrashWatchdog] Browser disconnected, ending monitoring");
+                self.stop_monitoring().await;
+            }
+
             BrowserEvent::TabCreated { target_id } => {
                 tracing::debug!("[CrashWatchdog] Tab created: {}", target_id);
-                // TODO: Attach to new target and register CDP event handlers
-                // This requires access to CDPClient, which we'll add in integration phase
+                self.monitor_target(target_id).await;
             }
 
             BrowserEvent::TabClosed { target_id } => {
                 tracing::debug!("[CrashWatchdog] Tab closed: {}", target_id);
-                // Clean up tracking for this target
+                if let Some(target) = self.targets.write().await.remove(target_id) {
+                    target.subscriptions.clear();
+                    tracing::debug!(
+                        "[CrashWatchdog] Stopped monitoring session {}",
+                        target.session.session_id
+                    );
+                }
+                let dropped = self.untrack_target(target_id).await;
+                if dropped > 0 {
+                    tracing::debug!(
+                        "[CrashWatchdog] Dropped {} pending requests of {}",
+                        dropped,
+                        target_id
+                    );
+                }
             }
 
             _ => {
@@ -183,92 +672,90 @@ impl Watchdog for CrashWatchdog {
     ) -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("[CrashWatchdog] Attaching to CDP");
 
-        // Subscribe to crash events
-        let _active_requests = self.active_requests.clone();
-        cdp_client.subscribe(
-            "Inspector.targetCrashed",
-            Arc::new(move |event| {
-                tracing::warn!("[CrashWatchdog] 💥 Target crashed: {:?}", event.params);
-                // TODO: Implement crash recovery (reload page, notify user)
-            }),
-        );
+        *self.cdp_client.write().await = Some(cdp_client.clone());
 
-        // Subscribe to network events for timeout tracking
-        let requests_clone = self.active_requests.clone();
-        cdp_client.subscribe(
-            "Network.requestWillBeSent",
+        // Subscribe to crash events and reload the crashed target
+        let client_slot = self.cdp_client.clone();
+        let stats = self.stats.clone();
+        let event_sink = self.event_sink.clone();
+        let max_reload_attempts = self.max_reload_attempts;
+        let reload_cooldown = self.reload_cooldown;
+        let recovery_window = self.recovery_window;
+        let diagnostics = self.diagnostics.clone();
+        self.subscriptions.subscribe(
+            &cdp_client,
+            "Inspector.targetCrashed",
             Arc::new(move |event| {
-                let requests = requests_clone.clone();
+                // Only page sessions can be reloaded
+                let Some(session_id) = event.session_id else {
+                    tracing::debug!("[CrashWatchdog] Ignoring crash event without a session");
+                    return;
+                };
+                tracing::warn!("[CrashWatchdog] 💥 Target crashed (session {})", session_id);
+                let client_slot = client_slot.clone();
+                let stats = stats.clone();
+                let event_sink = event_sink.clone();
+                let diagnostics = diagnostics.clone();
                 tokio::spawn(async move {
-                    if let Some(params) = event.params.as_ref() {
-                        let request_id = params["requestId"].as_str().unwrap_or("").to_string();
-                        let url = params["request"]["url"].as_str().unwrap_or("").to_string();
-                        let method = params["request"]["method"]
-                            .as_str()
-                            .unwrap_or("GET")
-                            .to_string();
-
-                        let tracker = RequestTracker {
-                            request_id: request_id.clone(),
-                            start_time: Instant::now(),
-                            url: url.clone(),
-                            method,
-                        };
-
-                        requests.write().await.push(tracker);
-                        tracing::debug!("[CrashWatchdog] Tracking request {}: {}", request_id, url);
-                    }
-                });
-            }),
-        );
+                    let key = session_id.as_str();
+                    let plan = stats.write().await.record_crash(
+                        key,
+                        max_reload_attempts,
+                        reload_cooldown,
+                        recovery_window,
+                        Instant::now(),
+                    );
+                    let Some((delay, attempt)) = plan else {
+                        tracing::error!(
+                            "[CrashWatchdog] Giving up on session {} after {} reloads",
+                            session_id,
+                            max_reload_attempts
+                        );
+                        diagnostics.emit(
+                            WatchdogEventKind::CrashHandled,
+                            format!(
+                                "gave up on session {} after {} reloads",
+                                key, max_reload_attempts
+                            ),
+                        );
+                        return;
+                    };
 
-        // Subscribe to response events
-        let requests_clone = self.active_requests.clone();
-        cdp_client.subscribe(
-            "Network.responseReceived",
-            Arc::new(move |event| {
-                let requests = requests_clone.clone();
-                tokio::spawn(async move {
-                    if let Some(params) = event.params.as_ref() {
-                        let request_id = params["requestId"].as_str().unwrap_or("");
-                        let mut requests_guard = requests.write().await;
-                        if let Some(pos) = requests_guard
-                            .iter()
-                            .position(|r| r.request_id == request_id)
-                        {
-                            let tracker = requests_guard.remove(pos);
-                            let elapsed = Instant::now().duration_since(tracker.start_time);
-                            tracing::debug!(
-                                "[CrashWatchdog] Request completed in {:?}: {}",
-                                elapsed,
-                                tracker.url
+                    tokio::time::sleep(delay).await;
+                    let Some(client) = client_slot.read().await.clone() else {
+                        return; // Detached while waiting
+                    };
+                    match client
+                        .send_command(&Reload::default(), Some(session_id.clone()))
+                        .await
+                    {
+                        Ok(_) => {
+                            stats.write().await.recoveries += 1;
+                            tracing::info!(
+                                "[CrashWatchdog] Reloaded session {} (attempt {})",
+                                session_id,
+                                attempt
+                            );
+                            diagnostics.emit(
+                                WatchdogEventKind::CrashHandled,
+                                format!("reloaded session {} (attempt {})", key, attempt),
                             );
+                            if let Some(event_bus) = event_sink {
+                                event_bus.publish(BrowserEvent::TargetRecovered {
+                                    session_id: Some(session_id),
+                                    attempt,
+                                });
+                            }
                         }
-                    }
-                });
-            }),
-        );
-
-        // Subscribe to failed request events
-        let requests_clone = self.active_requests.clone();
-        cdp_client.subscribe(
-            "Network.loadingFailed",
-            Arc::new(move |event| {
-                let requests = requests_clone.clone();
-                tokio::spawn(async move {
-                    if let Some(params) = event.params.as_ref() {
-                        let request_id = params["requestId"].as_str().unwrap_or("");
-                        let mut requests_guard = requests.write().await;
-                        if let Some(pos) = requests_guard
-                            .iter()
-                            .position(|r| r.request_id == request_id)
-                        {
-                            let tracker = requests_guard.remove(pos);
-                            let elapsed = Instant::now().duration_since(tracker.start_time);
+                        Err(e) => {
                             tracing::warn!(
-                                "[CrashWatchdog] Request failed after {:?}: {}",
-                                elapsed,
-                                tracker.url
+                                "[CrashWatchdog] Reload of session {} failed: {}",
+                                session_id,
+                                e
+                            );
+                            diagnostics.emit(
+                                WatchdogEventKind::CrashHandled,
+                                format!("reload of session {} failed: {}", key, e),
                             );
                         }
                     }
@@ -276,20 +763,41 @@ impl Watchdog for CrashWatchdog {
             }),
         );
 
+        // Browser-level network events; tabs are added on TabCreated
+        Self::subscribe_network(
+            &self.subscriptions,
+            &cdp_client,
+            &self.active_requests,
+            &self.requests_changed,
+            &self.event_sink,
+            None,
+            None,
+        );
+
         tracing::info!("[CrashWatchdog] Successfully attached to CDP events");
         Ok(())
     }
 
     async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.subscriptions.clear();
+        for (_, target) in self.targets.write().await.drain() {
+            target.subscriptions.clear();
+        }
+        self.cdp_client.write().await.take();
         self.stop_monitoring().await;
         tracing::info!("[CrashWatchdog] Detached");
         Ok(())
     }
+
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        Some(&self.diagnostics)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cdp::mock::{self, Reply};
 
     #[tokio::test]
     async fn test_crash_watchdog_lifecycle() {
@@ -321,6 +829,353 @@ mod tests {
         watchdog.on_detach().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_wait_for_idle() {
+        let watchdog = CrashWatchdog::new();
+
+        // Already idle: resolves after `idle_for`
+        watchdog
+            .wait_for_idle(Duration::from_millis(10), Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        // Idle only counts from when the last request finished
+        watchdog
+            .track_request("req1".into(), "https://example.com".into(), "GET".into())
+            .await;
+        let start = Instant::now();
+        let finish = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            watchdog.untrack_request("req1").await;
+        };
+        let (waited, ()) = tokio::join!(
+            watchdog.wait_for_idle(Duration::from_millis(30), Duration::from_secs(2)),
+            finish
+        );
+        waited.unwrap();
+        // Woken by the request finishing, not by the timeout
+        assert!(start.elapsed() >= Duration::from_millis(80));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Never settles, and no monitor task to expire the request
+        watchdog
+            .track_request(
+                "req2".into(),
+                "https://example.com/hang".into(),
+                "GET".into(),
+            )
+            .await;
+        let err = watchdog
+            .wait_for_idle(Duration::from_millis(10), Duration::from_millis(60))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CDPError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_request_filter() {
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog = CrashWatchdog {
+            event_sink: Some(event_bus),
+            ..CrashWatchdog::with_timeout(Duration::from_millis(100), Duration::from_millis(50))
+        }
+        .with_request_filter(ignore_background_requests());
+        watchdog.on_event(&BrowserEvent::Started).await;
+
+        let track = |request_id: &str, resource_type: &str| RequestTracker {
+            request_id: request_id.to_string(),
+            start_time: Instant::now(),
+            url: format!("https://example.com/{}", request_id),
+            method: "GET".to_string(),
+            target_id: None,
+            resource_type: Some(resource_type.to_string()),
+            mime_type: None,
+            status: None,
+        };
+        {
+            let mut requests = watchdog.active_requests.write().await;
+            requests.push(track("pixel", "Image"));
+            requests.push(track("socket", "WebSocket"));
+        }
+
+        // Background requests don't block idle
+        watchdog
+            .wait_for_idle(Duration::from_millis(10), Duration::from_millis(500))
+            .await
+            .unwrap();
+
+        watchdog
+            .active_requests
+            .write()
+            .await
+            .push(track("page", "Document"));
+        assert!(watchdog
+            .wait_for_idle(Duration::from_millis(10), Duration::from_millis(30))
+            .await
+            .is_err());
+
+        // All of them expire, but only the page is reported
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(watchdog.active_request_count().await, 0);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(BrowserEvent::RequestFailed { request_id, .. }) if request_id == "page"
+        ));
+        assert!(events.try_recv().is_err());
+
+        watchdog.stop_monitoring().await;
+    }
+
+    #[test]
+    fn test_reload_attempts_are_capped() {
+        let mut stats = CrashStats::default();
+        let cooldown = Duration::from_secs(5);
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+
+        assert_eq!(
+            stats.record_crash("s1", 2, cooldown, window, start),
+            Some((Duration::ZERO, 1))
+        );
+        // Second crash right after the first reload waits out the cooldown
+        assert_eq!(
+            stats.record_crash("s1", 2, cooldown, window, start + Duration::from_secs(1)),
+            Some((Duration::from_secs(4), 2))
+        );
+        assert_eq!(stats.record_crash("s1", 2, cooldown, window, start), None);
+        // Other targets have their own budget
+        assert_eq!(
+            stats.record_crash("s2", 2, cooldown, window, start),
+            Some((Duration::ZERO, 1))
+        );
+        // Up for a minute after the last reload: recovered, budget restored
+        let later = start + Duration::from_secs(5) + window;
+        assert_eq!(
+            stats.record_crash("s1", 2, cooldown, window, later),
+            Some((Duration::ZERO, 1))
+        );
+        assert_eq!(stats.crashes, 5);
+    }
+
+    #[tokio::test]
+    async fn test_crash_reloads_target_and_publishes_recovery() {
+        let (reload_tx, mut reload_rx) = tokio::sync::mpsc::unbounded_channel();
+        let url = mock::mock_browser(move |request| match request["method"].as_str() {
+            // Client is subscribed - a crash without a session, then crash
+            // the same target twice
+            Some("Test.start") => {
+                let crash = serde_json::json!({
+                    "method": "Inspector.targetCrashed",
+                    "params": {},
+                    "sessionId": "S1",
+                });
+                Reply::Messages(vec![
+                    mock::reply(request, serde_json::json!({})),
+                    serde_json::json!({ "method": "Inspector.targetCrashed", "params": {} }),
+                    crash.clone(),
+                    crash,
+                ])
+            }
+            Some("Page.reload") => {
+                let _ = reload_tx.send(request["sessionId"].clone());
+                Reply::Default
+            }
+            _ => Reply::Default,
+        })
+        .await;
+
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog = CrashWatchdog::with_event_sink(event_bus)
+            .with_reload_limits(1, Duration::from_millis(10));
+        let client = CDPClient::connect(&url).await.unwrap();
+        watchdog.on_attach(client.clone()).await.unwrap();
+        client.send_request("Test.start", None, None).await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            BrowserEvent::TargetRecovered { session_id: Some(ref id), attempt: 1 } if id == "S1"
+        ));
+        assert_eq!(reload_rx.recv().await.unwrap(), "S1");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while watchdog.crash_count().await < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        // The second crash exceeded max_reload_attempts
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(reload_rx.try_recv().is_err());
+        assert_eq!(watchdog.recovery_count().await, 1);
+
+        watchdog.on_detach().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_request_events_are_published() {
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog = CrashWatchdog::with_event_sink(event_bus);
+        let client = mock::connect(|_| Reply::Default).await;
+        watchdog.on_attach(client.clone()).await.unwrap();
+
+        let sent = |id: &str| {
+            serde_json::json!({
+                "method": "Network.requestWillBeSent",
+                "params": { "requestId": id, "request": { "url": format!("https://example.com/{}", id), "method": "POST" } },
+            })
+        };
+        let mut redirected = sent("1");
+        redirected["params"]["request"]["url"] = "https://example.com/1b".into();
+        redirected["params"]["redirectResponse"] = serde_json::json!({ "status": 302 });
+        // One batch, handled in order: each response follows its request
+        let batch = [
+            sent("1"),
+            sent("2"),
+            redirected,
+            serde_json::json!({
+                "method": "Network.responseReceived",
+                "params": { "requestId": "1", "response": { "status": 404, "mimeType": "text/html" } },
+            }),
+            serde_json::json!({
+                "method": "Network.loadingFinished",
+                "params": { "requestId": "1" },
+            }),
+            serde_json::json!({
+                "method": "Network.loadingFailed",
+                "params": { "requestId": "2", "errorText": "net::ERR_NAME_NOT_RESOLVED" },
+            }),
+        ];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": batch })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push(event);
+        }
+        let started: Vec<_> = received
+            .iter()
+            .filter_map(|event| match event {
+                BrowserEvent::RequestStarted {
+                    target_id: None,
+                    url,
+                    method,
+                    ..
+                } => Some((url.as_str(), method.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(started.len(), 2);
+        assert!(started.contains(&("https://example.com/1", "POST")));
+        assert!(received.iter().any(|event| matches!(
+            event,
+            BrowserEvent::RequestFinished { request_id, status: 404, .. } if request_id == "1"
+        )));
+        assert!(received.iter().any(|event| matches!(
+            event,
+            BrowserEvent::RequestFailed { request_id, error_text, .. }
+                if request_id == "2" && error_text == "net::ERR_NAME_NOT_RESOLVED"
+        )));
+        assert_eq!(watchdog.active_request_count().await, 0);
+        // The redirect didn't start a second request
+        assert!(events.try_recv().is_err());
+
+        watchdog.on_detach().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_tracked_per_tab() {
+        let watchdog = CrashWatchdog::new();
+        let client = mock::connect(|_| Reply::Default).await;
+        watchdog.on_attach(client.clone()).await.unwrap();
+        for target_id in ["T1", "T2"] {
+            watchdog
+                .on_event(&BrowserEvent::TabCreated {
+                    target_id: target_id.to_string(),
+                })
+                .await;
+        }
+
+        // Both tabs reuse request id "1"; the unknown session is ignored
+        let request = |session: &str| {
+            serde_json::json!({
+                "method": "Network.requestWillBeSent",
+                "params": { "requestId": "1", "request": { "url": "https://example.com", "method": "GET" } },
+                "sessionId": session,
+            })
+        };
+        let events = [request("S-T1"), request("S-T2"), request("S-other")];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": events })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let wait_for_count = |expected: usize| {
+            let watchdog = &watchdog;
+            async move {
+                tokio::time::timeout(Duration::from_secs(5), async {
+                    while watchdog.active_request_count().await != expected {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                })
+                .await
+                .unwrap()
+            }
+        };
+        wait_for_count(2).await;
+
+        watchdog
+            .on_event(&BrowserEvent::TabClosed {
+                target_id: "T1".to_string(),
+            })
+            .await;
+        wait_for_count(1).await;
+        let remaining = watchdog.active_requests.read().await.clone();
+        assert_eq!(remaining[0].target_id.as_deref(), Some("T2"));
+
+        // Closed tab's handlers are gone; the open tab's still fire
+        let finished = serde_json::json!({
+            "method": "Network.loadingFinished",
+            "params": { "requestId": "1" },
+            "sessionId": "S-T2",
+        });
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": [request("S-T1"), finished] })),
+                None,
+            )
+            .await
+            .unwrap();
+        wait_for_count(0).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(watchdog.active_request_count().await, 0);
+
+        watchdog.on_detach().await.unwrap();
+        assert!(watchdog.targets.read().await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_request_timeout() {
         let watchdog =
@@ -0,0 +1,283 @@
+//! Dialog Watchdog - Answers JavaScript dialogs so they can't hang a page
+//!
+//! `alert()`, `confirm()`, `prompt()` and `beforeunload` block the page
+//! until someone responds. Nobody is there to click, so we respond on
+//! `Page.javascriptDialogOpening` according to a policy.
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::cdp::commands::HandleJavaScriptDialog;
+use crate::cdp::CDPClient;
+use crate::events::BrowserEvent;
+use crate::watchdog::{CdpSubscriptions, Watchdog};
+
+/// A dialog that just opened
+#[derive(Clone, Debug)]
+pub struct JavaScriptDialog {
+    /// "alert", "confirm", "prompt" or "beforeunload"
+    pub dialog_type: String,
+    pub message: String,
+    /// Page that opened the dialog
+    pub url: String,
+    /// Prefilled text of a `prompt()`
+    pub default_prompt: Option<String>,
+}
+
+/// How to close a dialog
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DialogResponse {
+    /// OK (`true`) or Cancel (`false`); accepting `beforeunload` leaves the page
+    pub accept: bool,
+    /// Text to answer a `prompt()` with
+    pub prompt_text: Option<String>,
+}
+
+/// Which answer each dialog gets
+#[derive(Clone, Default)]
+pub enum DialogPolicy {
+    /// OK everything; prompts get their default text
+    #[default]
+    AcceptAll,
+    /// Cancel everything
+    DismissAll,
+    /// Decide per dialog
+    Custom(Arc<dyn Fn(&JavaScriptDialog) -> DialogResponse + Send + Sync>),
+}
+
+impl DialogPolicy {
+    fn respond(&self, dialog: &JavaScriptDialog) -> DialogResponse {
+        match self {
+            Self::AcceptAll => DialogResponse {
+                accept: true,
+                prompt_text: dialog.default_prompt.clone(),
+            },
+            Self::DismissAll => DialogResponse {
+                accept: false,
+                prompt_text: None,
+            },
+            Self::Custom(decide) => decide(dialog),
+        }
+    }
+}
+
+/// Dialog Watchdog - closes JavaScript dialogs per `DialogPolicy`
+///
+/// Relies on the `Page` domain being enabled on the tab's session, which
+/// `CDPSession::attach` does by default.
+pub struct DialogWatchdog {
+    policy: DialogPolicy,
+
+    /// `Page.javascriptDialogOpening` handler, removed on detach
+    subscriptions: CdpSubscriptions,
+
+    /// Dialogs answered successfully
+    handled: Arc<AtomicUsize>,
+}
+
+impl DialogWatchdog {
+    /// Accept every dialog
+    pub fn new() -> Self {
+        Self::with_policy(DialogPolicy::default())
+    }
+
+    pub fn with_policy(policy: DialogPolicy) -> Self {
+        Self {
+            policy,
+            subscriptions: CdpSubscriptions::new(),
+            handled: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Dialogs answered so far
+    pub fn handled_count(&self) -> usize {
+        self.handled.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for DialogWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Watchdog for DialogWatchdog {
+    fn name(&self) -> &str {
+        "DialogWatchdog"
+    }
+
+    async fn on_event(&self, _event: &BrowserEvent) {
+        // Dialogs arrive as CDP events, subscribed in on_attach
+    }
+
+    async fn on_attach(
+        &self,
+        cdp_client: Arc<CDPClient>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let policy = self.policy.clone();
+        let handled = self.handled.clone();
+        let client = cdp_client.clone();
+        self.subscriptions.subscribe(
+            &cdp_client,
+            "Page.javascriptDialogOpening",
+            Arc::new(move |event| {
+                let params = event.params.unwrap_or_default();
+                let dialog = JavaScriptDialog {
+                    dialog_type: params["type"].as_str().unwrap_or_default().to_string(),
+                    message: params["message"].as_str().unwrap_or_default().to_string(),
+                    url: params["url"].as_str().unwrap_or_default().to_string(),
+                    default_prompt: params["defaultPrompt"].as_str().map(str::to_string),
+                };
+                let response = policy.respond(&dialog);
+                tracing::info!(
+                    "[DialogWatchdog] {} {} dialog: {:?}",
+                    if response.accept {
+                        "Accepting"
+                    } else {
+                        "Dismissing"
+                    },
+                    dialog.dialog_type,
+                    dialog.message
+                );
+
+                let client = client.clone();
+                let handled = handled.clone();
+                let session_id = event.session_id;
+                tokio::spawn(async move {
+                    let command = HandleJavaScriptDialog {
+                        accept: response.accept,
+                        prompt_text: response.prompt_text,
+                    };
+                    match client.send_command(&command, session_id).await {
+                        Ok(_) => {
+                            handled.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            tracing::warn!("[DialogWatchdog] Failed to close dialog: {}", e);
+                        }
+                    }
+                });
+            }),
+        );
+        tracing::info!("[DialogWatchdog] Attached");
+        Ok(())
+    }
+
+    async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.subscriptions.clear();
+        tracing::info!("[DialogWatchdog] Detached");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdp::mock::{self, Reply};
+    use std::time::Duration;
+
+    fn dialog(dialog_type: &str, default_prompt: Option<&str>) -> JavaScriptDialog {
+        JavaScriptDialog {
+            dialog_type: dialog_type.to_string(),
+            message: "Sure?".to_string(),
+            url: "https://example.com".to_string(),
+            default_prompt: default_prompt.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let prompt = dialog("prompt", Some("42"));
+        assert_eq!(
+            DialogPolicy::AcceptAll.respond(&prompt),
+            DialogResponse {
+                accept: true,
+                prompt_text: Some("42".to_string()),
+            }
+        );
+        assert!(!DialogPolicy::DismissAll.respond(&prompt).accept);
+
+        // Leave pages, but refuse everything else
+        let custom = DialogPolicy::Custom(Arc::new(|dialog| DialogResponse {
+            accept: dialog.dialog_type == "beforeunload",
+            prompt_text: None,
+        }));
+        assert!(custom.respond(&dialog("beforeunload", None)).accept);
+        assert!(!custom.respond(&dialog("confirm", None)).accept);
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_are_answered() {
+        // Mock browser: reports each handleJavaScriptDialog on `tx`
+        let (tx, mut commands) = tokio::sync::mpsc::unbounded_channel();
+        let client = mock::connect(move |request| {
+            if request["method"] == "Page.handleJavaScriptDialog" {
+                let _ = tx.send(request.clone());
+            }
+            Reply::Default
+        })
+        .await;
+
+        let watchdog =
+            DialogWatchdog::with_policy(DialogPolicy::Custom(Arc::new(|dialog| DialogResponse {
+                accept: dialog.dialog_type != "confirm",
+                prompt_text: (dialog.dialog_type == "prompt").then(|| "bot".to_string()),
+            })));
+        watchdog.on_attach(client.clone()).await.unwrap();
+
+        let opening = |dialog_type: &str| {
+            serde_json::json!({
+                "method": "Page.javascriptDialogOpening",
+                "params": {
+                    "url": "https://example.com",
+                    "message": "?",
+                    "type": dialog_type,
+                    "hasBrowserHandler": false,
+                    "defaultPrompt": "",
+                },
+                "sessionId": format!("S-{}", dialog_type),
+            })
+        };
+        let events = [opening("confirm"), opening("prompt")];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": events })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut answers = Vec::new();
+        for _ in 0..2 {
+            let command = tokio::time::timeout(Duration::from_secs(5), commands.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            answers.push((command["sessionId"].clone(), command["params"].clone()));
+        }
+        answers.sort_by_key(|(session, _)| session.to_string());
+        assert_eq!(
+            answers,
+            vec![
+                ("S-confirm".into(), serde_json::json!({ "accept": false })),
+                (
+                    "S-prompt".into(),
+                    serde_json::json!({ "accept": true, "promptText": "bot" })
+                ),
+            ]
+        );
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while watchdog.handled_count() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        watchdog.on_detach().await.unwrap();
+        assert!(watchdog.subscriptions.is_empty());
+    }
+}
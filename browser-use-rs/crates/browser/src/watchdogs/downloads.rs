@@ -5,46 +5,313 @@
 //! - Track active downloads
 //! - Handle PDF auto-download
 //! - Emit download completion events
+//!
+//! Chrome shows PDFs in its viewer instead of downloading them. With
+//! `auto_download_pdfs`, each page target - open at attach, opened through
+//! the session, or opened by a page - gets a session watching for a main-frame
+//! document served as `application/pdf`; the page then fetches the file
+//! (with its cookies) and it is written to the download directory. A tab
+//! opened straight into a PDF may have loaded it before the session exists,
+//! so a new tab's `document.contentType` is checked once too.
 
 use async_trait::async_trait;
-use serde_json::json;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock};
+
+use crate::cdp::client::{CDPError, Result as CdpResult};
+use crate::cdp::commands::{CancelDownload, GetTargets, SetDiscoverTargets, SetDownloadBehavior};
+use crate::cdp::protocol::{TargetId, TargetInfo};
+use crate::cdp::{CDPClient, CDPSession};
+use crate::events::{BrowserEvent, EventBus};
+use crate::watchdog::{CdpSubscriptions, Diagnostics, Watchdog, WatchdogEventKind};
 
-use crate::cdp::CDPClient;
-use crate::events::BrowserEvent;
-use crate::watchdog::Watchdog;
+/// Fetches `{url}` in the page and resolves to its bytes, base64-encoded
+const FETCH_AS_BASE64_JS: &str = r#"(async () => {
+    const response = await fetch({url}, { credentials: "include" });
+    if (!response.ok) throw new Error("HTTP " + response.status);
+    const blob = await response.blob();
+    return await new Promise((resolve, reject) => {
+        const reader = new FileReader();
+        reader.onload = () => resolve(reader.result.split(",")[1] || "");
+        reader.onerror = () => reject(reader.error);
+        reader.readAsDataURL(blob);
+    });
+})()"#;
 
 /// Information about an active download
-#[derive(Clone, Debug)]
-struct DownloadInfo {
-    guid: String,
-    url: String,
-    suggested_filename: String,
-    total_bytes: i64,
-    received_bytes: i64,
-    state: DownloadState,
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadInfo {
+    pub guid: String,
+    pub url: String,
+    pub suggested_filename: String,
+    /// 0 while Chrome doesn't know the size yet
+    pub total_bytes: i64,
+    pub received_bytes: i64,
+    pub state: DownloadState,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-enum DownloadState {
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadState {
     InProgress,
     Completed,
     Canceled,
 }
 
+impl DownloadState {
+    /// Whether the download has finished, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Canceled)
+    }
+}
+
+/// Active downloads keyed by GUID
+type Downloads = Arc<RwLock<HashMap<String, DownloadInfo>>>;
+
+/// A tab watched for PDF documents, dropped on `TabClosed`
+struct PdfTarget {
+    subscriptions: CdpSubscriptions,
+}
+
+/// What saving a PDF from a tab needs, cloned into event callbacks
+#[derive(Clone)]
+struct PdfSaver {
+    session: CDPSession,
+    download_dir: PathBuf,
+    /// URLs already saved (or being saved), so reloads don't save twice
+    saved: Arc<RwLock<HashSet<String>>>,
+    event_sink: Option<EventBus>,
+    diagnostics: Diagnostics,
+}
+
+impl PdfSaver {
+    /// Fetch `url` in the tab, write it to the download directory and
+    /// publish `FileDownloaded`
+    async fn save(&self, url: String) {
+        if !self.saved.write().await.insert(url.clone()) {
+            return;
+        }
+        match self.fetch_and_write(&url).await {
+            Ok(path) => {
+                tracing::info!("[DownloadsWatchdog] Saved PDF {} -> {:?}", url, path);
+                self.diagnostics
+                    .emit(WatchdogEventKind::DownloadCompleted, path.to_string_lossy());
+                if let Some(event_bus) = &self.event_sink {
+                    event_bus.publish(BrowserEvent::FileDownloaded {
+                        path: path.to_string_lossy().to_string(),
+                    });
+                }
+            }
+            Err(e) => {
+                tracing::warn!("[DownloadsWatchdog] Failed to save PDF {}: {}", url, e);
+                // Let a later load try again
+                self.saved.write().await.remove(&url);
+            }
+        }
+    }
+
+    async fn fetch_and_write(
+        &self,
+        url: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let expression = FETCH_AS_BASE64_JS.replace("{url}", &serde_json::to_string(url)?);
+        let encoded: String = self.session.evaluate_typed(expression).await?;
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+
+        tokio::fs::create_dir_all(&self.download_dir).await?;
+        let path = unique_path(&self.download_dir, &pdf_filename(url));
+        tokio::fs::write(&path, bytes).await?;
+        Ok(path)
+    }
+
+    /// Save the tab's current document if it already is a PDF
+    async fn save_if_showing_pdf(&self) {
+        let state: Result<(String, String), _> = self
+            .session
+            .evaluate_typed("[document.contentType, location.href]")
+            .await;
+        if let Ok((content_type, url)) = state {
+            if content_type.eq_ignore_ascii_case("application/pdf") {
+                self.save(url).await;
+            }
+        }
+    }
+}
+
+/// Shared state for watching tabs for PDFs, cloned into target callbacks
+#[derive(Clone)]
+struct PdfWatch {
+    cdp_client: Arc<RwLock<Option<Arc<CDPClient>>>>,
+    targets: Arc<RwLock<HashMap<TargetId, PdfTarget>>>,
+    download_dir: PathBuf,
+    saved: Arc<RwLock<HashSet<String>>>,
+    event_sink: Option<EventBus>,
+    diagnostics: Diagnostics,
+}
+
+impl PdfWatch {
+    /// Attach to a tab and save any PDF its main frame loads
+    async fn watch(&self, target_id: &TargetId) {
+        let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+            return;
+        };
+        // Held across the attach so a tab reported twice is watched once
+        let mut targets = self.targets.write().await;
+        if targets.contains_key(target_id) {
+            return;
+        }
+
+        let session =
+            match CDPSession::attach(cdp_client.clone(), target_id.clone(), Some(vec!["Network"]))
+                .await
+            {
+                Ok(session) => session,
+                Err(e) => {
+                    tracing::warn!(
+                        "[DownloadsWatchdog] Failed to attach to {}: {}",
+                        target_id,
+                        e
+                    );
+                    return;
+                }
+            };
+
+        let saver = PdfSaver {
+            session: session.clone(),
+            download_dir: self.download_dir.clone(),
+            saved: self.saved.clone(),
+            event_sink: self.event_sink.clone(),
+            diagnostics: self.diagnostics.clone(),
+        };
+        let subscriptions = CdpSubscriptions::new();
+        let on_response = saver.clone();
+        subscriptions.subscribe_session(
+            &cdp_client,
+            "Network.responseReceived",
+            Some(session.session_id.clone()),
+            Arc::new(move |event| {
+                let Some(url) = event
+                    .params
+                    .as_ref()
+                    .and_then(|params| pdf_document_url(params, &on_response.session.target_id))
+                else {
+                    return;
+                };
+                let saver = on_response.clone();
+                tokio::spawn(async move { saver.save(url).await });
+            }),
+        );
+        targets.insert(target_id.clone(), PdfTarget { subscriptions });
+        drop(targets);
+
+        // A tab opened straight into a PDF may be done loading already
+        tokio::spawn(async move { saver.save_if_showing_pdf().await });
+    }
+
+    /// Stop watching a closed tab
+    async fn unwatch(&self, target_id: &str) {
+        if let Some(target) = self.targets.write().await.remove(target_id) {
+            target.subscriptions.clear();
+        }
+    }
+}
+
+/// URL of a main-frame PDF document, from `Network.responseReceived` params
+///
+/// The main frame's id is its target's id; PDFs in iframes are left alone.
+fn pdf_document_url(params: &Value, target_id: &str) -> Option<String> {
+    let response = &params["response"];
+    let is_pdf = response["mimeType"]
+        .as_str()
+        .is_some_and(|mime| mime.eq_ignore_ascii_case("application/pdf"));
+    (params["type"] == "Document" && params["frameId"] == target_id && is_pdf)
+        .then(|| response["url"].as_str().map(String::from))
+        .flatten()
+}
+
+/// File name for a PDF saved from `url`: its last path segment, made safe,
+/// ending in `.pdf`
+fn pdf_filename(url: &str) -> String {
+    let segment = url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()?
+                .rev()
+                .find(|segment| !segment.is_empty())
+                .map(String::from)
+        })
+        .unwrap_or_default();
+    let name: String = segment
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        "document.pdf".to_string()
+    } else if name.to_ascii_lowercase().ends_with(".pdf") {
+        name.to_string()
+    } else {
+        format!("{}.pdf", name)
+    }
+}
+
+/// `dir/name`, or `dir/stem (N).ext` if that file already exists
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((name, ""));
+    (1..)
+        .map(|n| dir.join(format!("{} ({}).{}", stem, n, ext)))
+        .find(|path| !path.exists())
+        .expect("some numbered name is free")
+}
+
 /// Downloads Watchdog - monitors browser downloads
 pub struct DownloadsWatchdog {
     /// Directory where downloads are saved
     download_dir: PathBuf,
 
     /// Active downloads tracked by GUID
-    active_downloads: Arc<RwLock<HashMap<String, DownloadInfo>>>,
+    active_downloads: Downloads,
+
+    /// Woken whenever a download completes or is canceled
+    download_finished: Arc<Notify>,
 
-    /// Whether PDF auto-download is enabled
+    /// Where to publish `FileDownloaded` when a download completes
+    event_sink: Option<EventBus>,
+
+    /// Save PDFs shown in Chrome's viewer to `download_dir`
     auto_download_pdfs: bool,
+
+    /// CDP event subscriptions, removed on detach
+    subscriptions: CdpSubscriptions,
+
+    /// Client captured in `on_attach`, used to attach to new tabs
+    cdp_client: Arc<RwLock<Option<Arc<CDPClient>>>>,
+
+    /// Tabs watched for PDFs, keyed by target id
+    pdf_targets: Arc<RwLock<HashMap<TargetId, PdfTarget>>>,
+
+    /// PDF URLs already saved
+    saved_pdfs: Arc<RwLock<HashSet<String>>>,
+
+    /// Reports `DownloadCompleted` for downloads and saved PDFs
+    diagnostics: Diagnostics,
 }
 
 impl DownloadsWatchdog {
@@ -53,16 +320,30 @@ impl DownloadsWatchdog {
         Self {
             download_dir,
             active_downloads: Arc::new(RwLock::new(HashMap::new())),
+            download_finished: Arc::new(Notify::new()),
+            event_sink: None,
             auto_download_pdfs: true,
+            subscriptions: CdpSubscriptions::new(),
+            cdp_client: Arc::new(RwLock::new(None)),
+            pdf_targets: Arc::new(RwLock::new(HashMap::new())),
+            saved_pdfs: Arc::new(RwLock::new(HashSet::new())),
+            diagnostics: Diagnostics::new(),
         }
     }
 
     /// Create with custom configuration
     pub fn with_config(download_dir: PathBuf, auto_download_pdfs: bool) -> Self {
         Self {
-            download_dir,
-            active_downloads: Arc::new(RwLock::new(HashMap::new())),
             auto_download_pdfs,
+            ..Self::new(download_dir)
+        }
+    }
+
+    /// Create a watchdog that publishes `FileDownloaded` to `event_bus`
+    pub fn with_event_sink(download_dir: PathBuf, event_bus: EventBus) -> Self {
+        Self {
+            event_sink: Some(event_bus),
+            ..Self::new(download_dir)
         }
     }
 
@@ -75,6 +356,270 @@ impl DownloadsWatchdog {
     pub async fn get_download(&self, guid: &str) -> Option<DownloadInfo> {
         self.active_downloads.read().await.get(guid).cloned()
     }
+
+    /// Tracked downloads, by GUID
+    ///
+    /// Finished downloads stay listed for a few seconds after they end.
+    pub async fn list_downloads(&self) -> Vec<DownloadInfo> {
+        let mut downloads: Vec<DownloadInfo> = self
+            .active_downloads
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        downloads.sort_by(|a, b| a.guid.cmp(&b.guid));
+        downloads
+    }
+
+    /// Ask Chrome to abort a download; its state becomes `Canceled` once
+    /// Chrome reports it
+    ///
+    /// Fails with `CDPError::Closed` when the watchdog isn't attached.
+    pub async fn cancel_download(&self, guid: &str) -> CdpResult<()> {
+        let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+            return Err(CDPError::Closed);
+        };
+        cdp_client
+            .send_command(
+                &CancelDownload {
+                    guid: guid.to_string(),
+                    browser_context_id: None,
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Wait until a download is `Completed` or `Canceled`
+    ///
+    /// The GUID may not be known yet when called, e.g. right after the
+    /// click that starts the download. Fails with `CDPError::Timeout` after
+    /// `timeout`, and also if the finished download was already dropped
+    /// from tracking.
+    pub async fn wait_for_download(
+        &self,
+        guid: &str,
+        timeout: Duration,
+    ) -> CdpResult<DownloadInfo> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            // Registered before the check, so a download finishing in
+            // between still wakes us
+            let finished = self.download_finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+
+            if let Some(info) = self.active_downloads.read().await.get(guid) {
+                if info.state.is_finished() {
+                    return Ok(info.clone());
+                }
+            }
+            if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                return Err(CDPError::Timeout);
+            }
+        }
+    }
+
+    /// What watching tabs for PDFs needs, for target discovery callbacks
+    fn pdf_watch(&self) -> PdfWatch {
+        PdfWatch {
+            cdp_client: self.cdp_client.clone(),
+            targets: self.pdf_targets.clone(),
+            download_dir: self.download_dir.clone(),
+            saved: self.saved_pdfs.clone(),
+            event_sink: self.event_sink.clone(),
+            diagnostics: self.diagnostics.clone(),
+        }
+    }
+
+    /// Watch open tabs and, through target discovery, every page opened
+    /// later - including those the page itself opens (`target=_blank`,
+    /// `window.open`), which never produce `TabCreated`
+    async fn watch_all_tabs(&self, cdp_client: &Arc<CDPClient>) {
+        let watch = self.pdf_watch();
+        self.subscriptions.subscribe(
+            cdp_client,
+            "Target.targetCreated",
+            Arc::new(move |event| {
+                let info = &event.params.as_ref().unwrap_or(&Value::Null)["targetInfo"];
+                if info["type"] != "page" {
+                    return;
+                }
+                let Some(target_id) = info["targetId"].as_str().map(String::from) else {
+                    return;
+                };
+                let watch = watch.clone();
+                tokio::spawn(async move { watch.watch(&target_id).await });
+            }),
+        );
+        let watch = self.pdf_watch();
+        self.subscriptions.subscribe(
+            cdp_client,
+            "Target.targetDestroyed",
+            Arc::new(move |event| {
+                let Some(target_id) = event
+                    .params
+                    .as_ref()
+                    .and_then(|params| params["targetId"].as_str().map(String::from))
+                else {
+                    return;
+                };
+                let watch = watch.clone();
+                tokio::spawn(async move { watch.unwatch(&target_id).await });
+            }),
+        );
+        if let Err(e) = cdp_client
+            .send_command(&SetDiscoverTargets { discover: true }, None)
+            .await
+        {
+            tracing::warn!("[DownloadsWatchdog] Failed to discover targets: {}", e);
+        }
+
+        let open: Vec<TargetInfo> = match cdp_client.send_command(&GetTargets {}, None).await {
+            Ok(result) => serde_json::from_value(result["targetInfos"].clone()).unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("[DownloadsWatchdog] Failed to list tabs: {}", e);
+                Vec::new()
+            }
+        };
+        let watch = self.pdf_watch();
+        for info in open.iter().filter(|info| info.target_type == "page") {
+            watch.watch(&info.target_id).await;
+        }
+    }
+
+    /// Stop watching every tab for PDFs
+    async fn unwatch_all(&self) {
+        for (_, target) in self.pdf_targets.write().await.drain() {
+            target.subscriptions.clear();
+        }
+    }
+
+    /// Start tracking a download from `Browser.downloadWillBegin` params
+    async fn handle_download_will_begin(downloads: &Downloads, params: &Value) {
+        let guid = params["guid"].as_str().unwrap_or("").to_string();
+        let url = params["url"].as_str().unwrap_or("").to_string();
+        let suggested_filename = params["suggestedFilename"]
+            .as_str()
+            .unwrap_or("download")
+            .to_string();
+
+        let info = DownloadInfo {
+            guid: guid.clone(),
+            url: url.clone(),
+            suggested_filename: suggested_filename.clone(),
+            total_bytes: 0,
+            received_bytes: 0,
+            state: DownloadState::InProgress,
+        };
+
+        downloads.write().await.insert(guid, info);
+        tracing::info!(
+            "[DownloadsWatchdog] Download started: {} -> {}",
+            url,
+            suggested_filename
+        );
+    }
+
+    /// Rename a finished download from the GUID Chrome saved it under
+    /// (`allowAndName`) to its suggested filename, made unique in
+    /// `download_dir`. Falls back to the GUID path if the rename fails.
+    fn move_to_suggested_name(download_dir: &Path, guid: &str, info: &DownloadInfo) -> PathBuf {
+        let guid_path = download_dir.join(guid);
+        let name = Path::new(&info.suggested_filename)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("download");
+        let final_path = unique_path(download_dir, name);
+        match std::fs::rename(&guid_path, &final_path) {
+            Ok(()) => final_path,
+            Err(e) => {
+                tracing::warn!(
+                    "[DownloadsWatchdog] Failed to rename {:?} to {:?}: {}",
+                    guid_path,
+                    final_path,
+                    e
+                );
+                guid_path
+            }
+        }
+    }
+
+    /// Update a tracked download from `Browser.downloadProgress` params
+    async fn handle_download_progress(
+        downloads: &Downloads,
+        finished: &Notify,
+        download_dir: &Path,
+        event_sink: Option<&EventBus>,
+        diagnostics: &Diagnostics,
+        params: &Value,
+    ) {
+        let guid = params["guid"].as_str().unwrap_or("");
+        let state = params["state"].as_str().unwrap_or("inProgress");
+        let total_bytes = params["totalBytes"].as_i64().unwrap_or(0);
+        let received_bytes = params["receivedBytes"].as_i64().unwrap_or(0);
+
+        let mut downloads_guard = downloads.write().await;
+        let Some(info) = downloads_guard.get_mut(guid) else {
+            return;
+        };
+        info.total_bytes = total_bytes;
+        info.received_bytes = received_bytes;
+
+        match state {
+            "completed" => {
+                info.state = DownloadState::Completed;
+                let final_path = Self::move_to_suggested_name(download_dir, guid, info);
+                tracing::info!(
+                    "[DownloadsWatchdog] Download completed: {} ({}/{} bytes) -> {:?}",
+                    info.url,
+                    received_bytes,
+                    total_bytes,
+                    final_path
+                );
+
+                diagnostics.emit(
+                    WatchdogEventKind::DownloadCompleted,
+                    final_path.to_string_lossy(),
+                );
+                if let Some(event_bus) = event_sink {
+                    event_bus.publish(BrowserEvent::FileDownloaded {
+                        path: final_path.to_string_lossy().to_string(),
+                    });
+                }
+            }
+            "canceled" => {
+                info.state = DownloadState::Canceled;
+                tracing::warn!("[DownloadsWatchdog] Download canceled: {}", info.url);
+            }
+            "inProgress" if total_bytes > 0 => {
+                let progress = (received_bytes as f64 / total_bytes as f64) * 100.0;
+                tracing::debug!(
+                    "[DownloadsWatchdog] Download progress: {} - {:.1}% ({}/{} bytes)",
+                    info.suggested_filename,
+                    progress,
+                    received_bytes,
+                    total_bytes
+                );
+            }
+            _ => {}
+        }
+
+        // Remove completed/canceled downloads from tracking after a delay
+        if info.state.is_finished() {
+            finished.notify_waiters();
+            let guid = guid.to_string();
+            let downloads_cleanup = downloads.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                downloads_cleanup.write().await.remove(&guid);
+            });
+        }
+    }
 }
 
 impl Default for DownloadsWatchdog {
@@ -109,6 +654,15 @@ impl Watchdog for DownloadsWatchdog {
             BrowserEvent::Stopped => {
                 tracing::info!("[DownloadsWatchdog] Browser stopped, clearing download state");
                 self.active_downloads.write().await.clear();
+                self.unwatch_all().await;
+            }
+
+            BrowserEvent::TabCreated { target_id } if self.auto_download_pdfs => {
+                self.pdf_watch().watch(target_id).await;
+            }
+
+            BrowserEvent::TabClosed { target_id } => {
+                self.pdf_watch().unwatch(target_id).await;
             }
 
             _ => {
@@ -122,19 +676,19 @@ impl Watchdog for DownloadsWatchdog {
         cdp_client: Arc<CDPClient>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("[DownloadsWatchdog] Attaching to CDP for download monitoring");
+        *self.cdp_client.write().await = Some(cdp_client.clone());
 
         // Set download behavior - allow downloads and set download path
         let download_path = self.download_dir.to_string_lossy().to_string();
 
         // Note: Browser.setDownloadBehavior requires browser-level session (sessionId = None)
         match cdp_client
-            .send_request(
-                "Browser.setDownloadBehavior",
-                Some(json!({
-                    "behavior": "allowAndName",
-                    "downloadPath": download_path,
-                    "eventsEnabled": true
-                })),
+            .send_command(
+                &SetDownloadBehavior {
+                    behavior: "allowAndName".to_string(),
+                    download_path: Some(download_path),
+                    events_enabled: Some(true),
+                },
                 None, // No session ID = browser-level command
             )
             .await
@@ -145,34 +699,14 @@ impl Watchdog for DownloadsWatchdog {
 
         // Subscribe to downloadWillBegin event
         let downloads = self.active_downloads.clone();
-        cdp_client.subscribe(
+        self.subscriptions.subscribe(
+            &cdp_client,
             "Browser.downloadWillBegin",
             Arc::new(move |event| {
                 let downloads = downloads.clone();
                 tokio::spawn(async move {
                     if let Some(params) = event.params.as_ref() {
-                        let guid = params["guid"].as_str().unwrap_or("").to_string();
-                        let url = params["url"].as_str().unwrap_or("").to_string();
-                        let suggested_filename = params["suggestedFilename"]
-                            .as_str()
-                            .unwrap_or("download")
-                            .to_string();
-
-                        let info = DownloadInfo {
-                            guid: guid.clone(),
-                            url: url.clone(),
-                            suggested_filename: suggested_filename.clone(),
-                            total_bytes: 0,
-                            received_bytes: 0,
-                            state: DownloadState::InProgress,
-                        };
-
-                        downloads.write().await.insert(guid.clone(), info);
-                        tracing::info!(
-                            "[DownloadsWatchdog] Download started: {} -> {}",
-                            url,
-                            suggested_filename
-                        );
+                        Self::handle_download_will_begin(&downloads, params).await;
                     }
                 });
             }),
@@ -180,88 +714,55 @@ impl Watchdog for DownloadsWatchdog {
 
         // Subscribe to downloadProgress event
         let downloads = self.active_downloads.clone();
+        let download_finished = self.download_finished.clone();
         let download_dir = self.download_dir.clone();
-        cdp_client.subscribe(
+        let event_sink = self.event_sink.clone();
+        let diagnostics = self.diagnostics.clone();
+        self.subscriptions.subscribe(
+            &cdp_client,
             "Browser.downloadProgress",
             Arc::new(move |event| {
                 let downloads = downloads.clone();
+                let download_finished = download_finished.clone();
                 let download_dir = download_dir.clone();
+                let event_sink = event_sink.clone();
+                let diagnostics = diagnostics.clone();
                 tokio::spawn(async move {
                     if let Some(params) = event.params.as_ref() {
-                        let guid = params["guid"].as_str().unwrap_or("");
-                        let state = params["state"].as_str().unwrap_or("inProgress");
-                        let total_bytes = params["totalBytes"].as_i64().unwrap_or(0);
-                        let received_bytes = params["receivedBytes"].as_i64().unwrap_or(0);
-
-                        let mut downloads_guard = downloads.write().await;
-                        if let Some(info) = downloads_guard.get_mut(guid) {
-                            info.total_bytes = total_bytes;
-                            info.received_bytes = received_bytes;
-
-                            match state {
-                                "completed" => {
-                                    info.state = DownloadState::Completed;
-                                    let final_path = download_dir.join(&info.suggested_filename);
-                                    tracing::info!(
-										"[DownloadsWatchdog] Download completed: {} ({}/{} bytes) -> {:?}",
-										info.url,
-										received_bytes,
-										total_bytes,
-										final_path
-									);
-
-                                    // TODO: Emit FileDownloadedEvent to event bus
-                                }
-                                "canceled" => {
-                                    info.state = DownloadState::Canceled;
-                                    tracing::warn!(
-                                        "[DownloadsWatchdog] Download canceled: {}",
-                                        info.url
-                                    );
-                                }
-                                "inProgress" => {
-                                    if total_bytes > 0 {
-                                        let progress =
-                                            (received_bytes as f64 / total_bytes as f64) * 100.0;
-                                        tracing::debug!(
-											"[DownloadsWatchdog] Download progress: {} - {:.1}% ({}/{} bytes)",
-											info.suggested_filename,
-											progress,
-											received_bytes,
-											total_bytes
-										);
-                                    }
-                                }
-                                _ => {}
-                            }
-
-                            // Remove completed/canceled downloads from tracking after a delay
-                            if matches!(
-                                info.state,
-                                DownloadState::Completed | DownloadState::Canceled
-                            ) {
-                                let guid = guid.to_string();
-                                let downloads_cleanup = downloads.clone();
-                                tokio::spawn(async move {
-                                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                                    downloads_cleanup.write().await.remove(&guid);
-                                });
-                            }
-                        }
+                        Self::handle_download_progress(
+                            &downloads,
+                            &download_finished,
+                            &download_dir,
+                            event_sink.as_ref(),
+                            &diagnostics,
+                            params,
+                        )
+                        .await;
                     }
                 });
             }),
         );
 
+        if self.auto_download_pdfs {
+            self.watch_all_tabs(&cdp_client).await;
+        }
+
         tracing::info!("[DownloadsWatchdog] Successfully attached to download events");
         Ok(())
     }
 
     async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("[DownloadsWatchdog] Detaching from CDP");
+        self.subscriptions.clear();
         self.active_downloads.write().await.clear();
+        self.unwatch_all().await;
+        *self.cdp_client.write().await = None;
         Ok(())
     }
+
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        Some(&self.diagnostics)
+    }
 }
 
 #[cfg(test)]
@@ -290,4 +791,269 @@ mod tests {
         watchdog.on_event(&BrowserEvent::Stopped).await;
         assert_eq!(watchdog.active_download_count().await, 0);
     }
+
+    #[test]
+    fn test_pdf_document_url() {
+        let response = |resource_type: &str, frame_id: &str, mime: &str| {
+            serde_json::json!({
+                "type": resource_type,
+                "frameId": frame_id,
+                "response": { "url": "https://example.com/a.pdf", "mimeType": mime },
+            })
+        };
+        assert_eq!(
+            pdf_document_url(&response("Document", "T1", "application/pdf"), "T1").as_deref(),
+            Some("https://example.com/a.pdf")
+        );
+        // Iframe, sub-resource, and HTML documents aren't routed
+        assert_eq!(
+            pdf_document_url(&response("Document", "F2", "application/pdf"), "T1"),
+            None
+        );
+        assert_eq!(
+            pdf_document_url(&response("Fetch", "T1", "application/pdf"), "T1"),
+            None
+        );
+        assert_eq!(
+            pdf_document_url(&response("Document", "T1", "text/html"), "T1"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pdf_filename_and_unique_path() {
+        assert_eq!(
+            pdf_filename("https://example.com/docs/report.PDF?x=1"),
+            "report.PDF"
+        );
+        assert_eq!(pdf_filename("https://example.com/download/42/"), "42.pdf");
+        assert_eq!(pdf_filename("https://example.com/a%20b..pdf"), "a_20b..pdf");
+        assert_eq!(pdf_filename("https://example.com/"), "document.pdf");
+        assert_eq!(pdf_filename("not a url"), "document.pdf");
+
+        let dir = std::env::temp_dir().join(format!("pdf-names-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a.pdf"));
+        std::fs::write(dir.join("a.pdf"), b"").unwrap();
+        std::fs::write(dir.join("a (1).pdf"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "a.pdf"), dir.join("a (2).pdf"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_and_wait_for_download() {
+        let watchdog = DownloadsWatchdog::new(PathBuf::from("/tmp/test-downloads"));
+        for guid in ["g2", "g1"] {
+            DownloadsWatchdog::handle_download_will_begin(
+                &watchdog.active_downloads,
+                &serde_json::json!({ "guid": guid, "url": "https://example.com/f.zip" }),
+            )
+            .await;
+        }
+
+        let downloads = watchdog.list_downloads().await;
+        assert_eq!(downloads.len(), 2);
+        assert_eq!(downloads[0].guid, "g1");
+        assert_eq!(
+            serde_json::to_value(&downloads[0]).unwrap()["state"],
+            "inProgress"
+        );
+
+        let result = watchdog
+            .wait_for_download("g1", Duration::from_millis(60))
+            .await;
+        assert!(matches!(result, Err(CDPError::Timeout)));
+
+        let downloads = watchdog.active_downloads.clone();
+        let finished = watchdog.download_finished.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            DownloadsWatchdog::handle_download_progress(
+                &downloads,
+                &finished,
+                Path::new("/tmp/test-downloads"),
+                None,
+                &Diagnostics::new(),
+                &serde_json::json!({
+                    "guid": "g1",
+                    "state": "canceled",
+                    "totalBytes": 100,
+                    "receivedBytes": 10,
+                }),
+            )
+            .await;
+        });
+        let info = watchdog
+            .wait_for_download("g1", Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(info.state, DownloadState::Canceled);
+        assert_eq!(info.received_bytes, 10);
+        assert_eq!(watchdog.get_download("g1").await, Some(info));
+
+        // Cancelling needs a browser
+        assert!(matches!(
+            watchdog.cancel_download("g2").await,
+            Err(CDPError::Closed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_completed_download_publishes_event() {
+        let event_bus = EventBus::new();
+        let mut rx = event_bus.subscribe();
+        let download_dir =
+            std::env::temp_dir().join(format!("downloads-done-{}", std::process::id()));
+        std::fs::create_dir_all(&download_dir).unwrap();
+        // Chrome saves under the GUID with `allowAndName`; a same-named file exists
+        std::fs::write(download_dir.join("g1"), b"%PDF").unwrap();
+        std::fs::write(download_dir.join("report.pdf"), b"older").unwrap();
+        let watchdog = DownloadsWatchdog::with_event_sink(download_dir.clone(), event_bus);
+
+        DownloadsWatchdog::handle_download_will_begin(
+            &watchdog.active_downloads,
+            &serde_json::json!({
+                "guid": "g1",
+                "url": "https://example.com/report.pdf",
+                "suggestedFilename": "report.pdf",
+            }),
+        )
+        .await;
+        DownloadsWatchdog::handle_download_progress(
+            &watchdog.active_downloads,
+            &watchdog.download_finished,
+            &watchdog.download_dir,
+            watchdog.event_sink.as_ref(),
+            &watchdog.diagnostics,
+            &serde_json::json!({
+                "guid": "g1",
+                "state": "completed",
+                "totalBytes": 100,
+                "receivedBytes": 100,
+            }),
+        )
+        .await;
+
+        let info = watchdog.get_download("g1").await.unwrap();
+        assert_eq!(info.state, DownloadState::Completed);
+
+        match rx.try_recv() {
+            Ok(BrowserEvent::FileDownloaded { path }) => {
+                assert_eq!(PathBuf::from(&path), download_dir.join("report (1).pdf"));
+                assert_eq!(std::fs::read(&path).unwrap(), b"%PDF");
+            }
+            other => panic!("Expected FileDownloaded, got {:?}", other),
+        }
+        assert!(!download_dir.join("g1").exists());
+        std::fs::remove_dir_all(&download_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completed_download_reaches_diagnostics() {
+        use crate::watchdog::{WatchdogEvent, WatchdogManager};
+
+        // No GUID file to rename, so the GUID path is reported
+        let download_dir = PathBuf::from("/tmp/test-downloads");
+        let watchdog = DownloadsWatchdog::new(download_dir.clone());
+        // Handles taken before registering still report
+        let diagnostics = watchdog.diagnostics.clone();
+        let downloads = watchdog.active_downloads.clone();
+        let mut manager = WatchdogManager::new();
+        let mut rx = manager.diagnostics();
+        manager.register(Box::new(watchdog));
+
+        DownloadsWatchdog::handle_download_will_begin(
+            &downloads,
+            &serde_json::json!({ "guid": "g1", "suggestedFilename": "a.zip" }),
+        )
+        .await;
+        DownloadsWatchdog::handle_download_progress(
+            &downloads,
+            &Notify::new(),
+            &download_dir,
+            None,
+            &diagnostics,
+            &serde_json::json!({ "guid": "g1", "state": "completed" }),
+        )
+        .await;
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            WatchdogEvent {
+                watchdog_name: "DownloadsWatchdog".to_string(),
+                kind: WatchdogEventKind::DownloadCompleted,
+                detail: download_dir.join("g1").to_string_lossy().to_string(),
+            }
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watches_open_and_page_opened_tabs() {
+        use crate::cdp::mock::{self, Reply};
+        use std::time::Duration;
+
+        let client = mock::connect(|request| match request["method"].as_str() {
+            Some("Target.getTargets") => Reply::Result(serde_json::json!({
+                "targetInfos": [
+                    { "targetId": "T1", "type": "page", "title": "", "url": "about:blank", "attached": true },
+                    { "targetId": "W1", "type": "service_worker", "title": "", "url": "", "attached": false },
+                ]
+            })),
+            _ => Reply::Default,
+        })
+        .await;
+        let watchdog = DownloadsWatchdog::with_config(PathBuf::from("/tmp/test-downloads"), true);
+        watchdog.on_attach(client.clone()).await.unwrap();
+        assert!(watchdog.pdf_targets.read().await.contains_key("T1"));
+
+        // A `window.open` tab is reported only through target discovery
+        let created = |target_id: &str, target_type: &str| {
+            serde_json::json!({
+                "method": "Target.targetCreated",
+                "params": { "targetInfo": {
+                    "targetId": target_id, "type": target_type, "title": "", "url": "", "attached": false,
+                }},
+            })
+        };
+        let events = [created("T2", "page"), created("W2", "worker")];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": events })),
+                None,
+            )
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !watchdog.pdf_targets.read().await.contains_key("T2") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let destroyed = serde_json::json!({
+            "method": "Target.targetDestroyed",
+            "params": { "targetId": "T1" },
+        });
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": [destroyed] })),
+                None,
+            )
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while watchdog.pdf_targets.read().await.contains_key("T1") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let mut watched: Vec<_> = watchdog.pdf_targets.read().await.keys().cloned().collect();
+        watched.sort();
+        assert_eq!(watched, vec!["T2".to_string()]);
+    }
 }
@@ -3,10 +3,14 @@
 //! Each watchdog is a separate module for clarity.
 
 pub mod crash;
+pub mod dialog;
 pub mod downloads;
+pub mod request;
 pub mod security;
 
 // Re-export for convenience
-pub use crash::CrashWatchdog;
-pub use downloads::DownloadsWatchdog;
-pub use security::{SecurityPolicy, SecurityWatchdog};
+pub use crash::{ignore_background_requests, CrashWatchdog, RequestFilter, RequestTracker};
+pub use dialog::{DialogPolicy, DialogResponse, DialogWatchdog, JavaScriptDialog};
+pub use downloads::{DownloadInfo, DownloadState, DownloadsWatchdog};
+pub use request::{InterceptedRequest, RequestAction, RequestWatchdog};
+pub use security::{BlockAction, SecurityPolicy, SecurityWatchdog};
@@ -0,0 +1,466 @@
+//! Request Watchdog - Blocks or lets through network requests
+//!
+//! Responsibilities:
+//! - Pause every request of each tab via the Fetch domain
+//! - Run user rules (URL globs, resource types, closures) over it
+//! - Fail blocked requests, continue the rest
+//! - Release paused requests (`Fetch.disable`) on detach
+
+use async_trait::async_trait;
+use dashmap::DashSet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::cdp::commands::{
+    ContinueRequest, FailRequest, FetchDisable, FetchEnable, RequestPattern,
+};
+use crate::cdp::protocol::{SessionId, TargetId};
+use crate::cdp::{CDPClient, CDPSession};
+use crate::events::BrowserEvent;
+use crate::watchdog::{CdpSubscriptions, Watchdog};
+use crate::watchdogs::SecurityWatchdog;
+
+/// What to do with a paused request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestAction {
+    /// Let it through unchanged
+    Continue,
+    /// Fail it with `BlockedByClient`
+    Block,
+}
+
+/// A paused request, as seen by the rules
+#[derive(Clone, Debug)]
+pub struct InterceptedRequest {
+    pub url: String,
+    pub method: String,
+    /// CDP resource type, e.g. "Document", "Image", "Script", "XHR"
+    pub resource_type: String,
+}
+
+/// A rule returns `None` to defer to the next one
+type RequestRule = Arc<dyn Fn(&InterceptedRequest) -> Option<RequestAction> + Send + Sync>;
+
+/// Request Watchdog - intercepts requests and applies blocking rules
+///
+/// Rules run in the order they were added; the first one with an opinion
+/// wins, and requests no rule cares about continue.
+pub struct RequestWatchdog {
+    rules: Vec<RequestRule>,
+
+    /// Client captured in `on_attach`, used to attach to new tabs
+    cdp_client: RwLock<Option<Arc<CDPClient>>>,
+
+    /// Tabs with interception enabled, keyed by target id
+    targets: RwLock<HashMap<TargetId, CDPSession>>,
+
+    /// Ids of the sessions in `targets`; other watchdogs' paused requests
+    /// arrive through the same event and are left to them
+    sessions: Arc<DashSet<SessionId>>,
+
+    /// `Fetch.requestPaused` handler, removed on detach
+    subscriptions: CdpSubscriptions,
+
+    blocked: Arc<AtomicUsize>,
+    continued: Arc<AtomicUsize>,
+}
+
+impl RequestWatchdog {
+    /// Create a watchdog with no rules (every request continues)
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            cdp_client: RwLock::new(None),
+            targets: RwLock::new(HashMap::new()),
+            sessions: Arc::new(DashSet::new()),
+            subscriptions: CdpSubscriptions::new(),
+            blocked: Arc::new(AtomicUsize::new(0)),
+            continued: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Block URLs matching a glob, using the security policy's syntax:
+    /// `*.doubleclick.net`, `ads.example.com/banner/*`
+    pub fn block_url(self, pattern: impl Into<String>) -> Self {
+        self.url_rule(pattern.into(), RequestAction::Block)
+    }
+
+    /// Let URLs matching a glob through, even if a later rule would block them
+    pub fn allow_url(self, pattern: impl Into<String>) -> Self {
+        self.url_rule(pattern.into(), RequestAction::Continue)
+    }
+
+    /// Block every request of a resource type, e.g. "Image" or "Font"
+    pub fn block_resource_type(self, resource_type: impl Into<String>) -> Self {
+        let resource_type = resource_type.into();
+        self.with_rule(move |request| {
+            request
+                .resource_type
+                .eq_ignore_ascii_case(&resource_type)
+                .then_some(RequestAction::Block)
+        })
+    }
+
+    /// Add a custom rule; return `None` to leave the decision to later rules
+    pub fn with_rule<F>(mut self, rule: F) -> Self
+    where
+        F: Fn(&InterceptedRequest) -> Option<RequestAction> + Send + Sync + 'static,
+    {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Requests failed so far
+    pub fn blocked_count(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    /// Requests let through so far
+    pub fn continued_count(&self) -> usize {
+        self.continued.load(Ordering::Relaxed)
+    }
+
+    fn url_rule(self, pattern: String, action: RequestAction) -> Self {
+        let pattern = SecurityWatchdog::normalize_pattern(&pattern);
+        self.with_rule(move |request| {
+            let url = url::Url::parse(&request.url).ok()?;
+            let host = url.host_str()?;
+            SecurityWatchdog::matches_pattern(host, url.path(), &pattern).then_some(action)
+        })
+    }
+
+    /// First verdict from `rules`, `Continue` if none applies
+    fn decide(rules: &[RequestRule], request: &InterceptedRequest) -> RequestAction {
+        rules
+            .iter()
+            .find_map(|rule| rule(request))
+            .unwrap_or(RequestAction::Continue)
+    }
+
+    /// Attach to a tab and start pausing its requests
+    async fn intercept_target(&self, target_id: &TargetId) {
+        let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+            tracing::debug!("[RequestWatchdog] Not attached, ignoring tab {}", target_id);
+            return;
+        };
+        if self.targets.read().await.contains_key(target_id) {
+            return;
+        }
+
+        let session = match CDPSession::attach(cdp_client, target_id.clone(), Some(vec![])).await {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("[RequestWatchdog] Failed to intercept {}: {}", target_id, e);
+                return;
+            }
+        };
+
+        // Track the session before enabling so no paused request is dropped
+        self.sessions.insert(session.session_id.clone());
+        let result = session
+            .send_command(&FetchEnable {
+                patterns: vec![RequestPattern {
+                    url_pattern: Some("*".to_string()),
+                    resource_type: None,
+                    request_stage: None,
+                }],
+            })
+            .await;
+
+        match result {
+            Ok(_) => {
+                self.targets
+                    .write()
+                    .await
+                    .insert(target_id.clone(), session);
+            }
+            Err(e) => {
+                self.sessions.remove(&session.session_id);
+                tracing::warn!("[RequestWatchdog] Failed to intercept {}: {}", target_id, e);
+            }
+        }
+    }
+}
+
+impl Default for RequestWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Watchdog for RequestWatchdog {
+    fn name(&self) -> &str {
+        "RequestWatchdog"
+    }
+
+    async fn on_event(&self, event: &BrowserEvent) {
+        match event {
+            BrowserEvent::TabCreated { target_id } => {
+                self.intercept_target(target_id).await;
+            }
+
+            BrowserEvent::TabClosed { target_id } => {
+                // The tab's paused requests went away with it
+                if let Some(session) = self.targets.write().await.remove(target_id) {
+                    self.sessions.remove(&session.session_id);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    async fn on_attach(
+        &self,
+        cdp_client: Arc<CDPClient>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        *self.cdp_client.write().await = Some(cdp_client.clone());
+
+        let rules: Arc<[RequestRule]> = self.rules.clone().into();
+        let blocked = self.blocked.clone();
+        let continued = self.continued.clone();
+        let client = cdp_client.clone();
+        let sessions = self.sessions.clone();
+        self.subscriptions.subscribe(
+            &cdp_client,
+            "Fetch.requestPaused",
+            Arc::new(move |event| {
+                if !event
+                    .session_id
+                    .as_ref()
+                    .is_some_and(|session_id| sessions.contains(session_id))
+                {
+                    return;
+                }
+                let Some(params) = event.params else {
+                    return;
+                };
+                let Some(request_id) = params["requestId"].as_str().map(str::to_string) else {
+                    return;
+                };
+                let request = InterceptedRequest {
+                    url: params["request"]["url"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    method: params["request"]["method"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    resource_type: params["resourceType"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                };
+                let action = Self::decide(&rules, &request);
+
+                let client = client.clone();
+                let session_id = event.session_id;
+                let counter = match action {
+                    RequestAction::Block => blocked.clone(),
+                    RequestAction::Continue => continued.clone(),
+                };
+                tokio::spawn(async move {
+                    let result = match action {
+                        RequestAction::Block => {
+                            tracing::debug!("[RequestWatchdog] Blocking {}", request.url);
+                            client
+                                .send_command(
+                                    &FailRequest {
+                                        request_id,
+                                        error_reason: "BlockedByClient".to_string(),
+                                    },
+                                    session_id,
+                                )
+                                .await
+                        }
+                        RequestAction::Continue => {
+                            client
+                                .send_command(&ContinueRequest { request_id }, session_id)
+                                .await
+                        }
+                    };
+                    match result {
+                        Ok(_) => {
+                            counter.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "[RequestWatchdog] Failed to resolve {}: {}",
+                                request.url,
+                                e
+                            );
+                        }
+                    }
+                });
+            }),
+        );
+
+        tracing::info!("[RequestWatchdog] Attached with {} rules", self.rules.len());
+        Ok(())
+    }
+
+    async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.subscriptions.clear();
+        // Disabling Fetch lets anything still paused continue
+        self.sessions.clear();
+        for (target_id, session) in self.targets.write().await.drain() {
+            if let Err(e) = session.send_command(&FetchDisable::default()).await {
+                tracing::debug!(
+                    "[RequestWatchdog] Fetch.disable failed for {}: {}",
+                    target_id,
+                    e
+                );
+            }
+        }
+        self.cdp_client.write().await.take();
+        tracing::info!("[RequestWatchdog] Detached");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdp::mock::{self, Reply};
+    use std::time::Duration;
+
+    fn request(url: &str, resource_type: &str) -> InterceptedRequest {
+        InterceptedRequest {
+            url: url.to_string(),
+            method: "GET".to_string(),
+            resource_type: resource_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rules_first_match_wins() {
+        let watchdog = RequestWatchdog::new()
+            .allow_url("cdn.example.com/keep/*")
+            .block_url("*.example.com")
+            .block_resource_type("font")
+            .with_rule(|request| (request.method == "POST").then_some(RequestAction::Block));
+        let decide =
+            |request: &InterceptedRequest| RequestWatchdog::decide(&watchdog.rules, request);
+
+        assert_eq!(
+            decide(&request("https://ads.example.com/x.js", "Script")),
+            RequestAction::Block
+        );
+        assert_eq!(
+            decide(&request("https://example.com/", "Document")),
+            RequestAction::Block
+        );
+        assert_eq!(
+            decide(&request("https://cdn.example.com/keep/a.png", "Image")),
+            RequestAction::Continue
+        );
+        assert_eq!(
+            decide(&request("https://other.org/f.woff2", "Font")),
+            RequestAction::Block
+        );
+        assert_eq!(
+            decide(&request("https://other.org/", "Document")),
+            RequestAction::Continue
+        );
+        assert_eq!(
+            decide(&InterceptedRequest {
+                method: "POST".to_string(),
+                ..request("https://other.org/api", "XHR")
+            }),
+            RequestAction::Block
+        );
+        assert_eq!(
+            decide(&request("not a url", "Other")),
+            RequestAction::Continue
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paused_requests_are_resolved() {
+        // Mock browser: reports every Fetch command on `tx`
+        let (tx, mut commands) = tokio::sync::mpsc::unbounded_channel();
+        let client = mock::connect(move |request| {
+            if request["method"].as_str().unwrap().starts_with("Fetch.") {
+                let _ = tx.send(request.clone());
+            }
+            Reply::Default
+        })
+        .await;
+
+        let watchdog = RequestWatchdog::new().block_url("*.tracker.test");
+        watchdog.on_attach(client.clone()).await.unwrap();
+        watchdog
+            .on_event(&BrowserEvent::TabCreated {
+                target_id: "T1".to_string(),
+            })
+            .await;
+
+        let enable = commands.recv().await.unwrap();
+        assert_eq!(enable["method"], "Fetch.enable");
+        assert_eq!(enable["sessionId"], "S-T1");
+
+        let paused = |id: &str, url: &str| {
+            serde_json::json!({
+                "method": "Fetch.requestPaused",
+                "params": {
+                    "requestId": id,
+                    "request": { "url": url, "method": "GET" },
+                    "resourceType": "Script",
+                },
+                "sessionId": "S-T1",
+            })
+        };
+        let events = [
+            paused("1", "https://cdn.tracker.test/t.js"),
+            paused("2", "https://example.com/app.js"),
+        ];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": events })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut resolved = Vec::new();
+        for _ in 0..2 {
+            let command = tokio::time::timeout(Duration::from_secs(5), commands.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(command["sessionId"], "S-T1");
+            resolved.push((
+                command["params"]["requestId"].as_str().unwrap().to_string(),
+                command["method"].as_str().unwrap().to_string(),
+            ));
+        }
+        resolved.sort();
+        assert_eq!(
+            resolved,
+            vec![
+                ("1".to_string(), "Fetch.failRequest".to_string()),
+                ("2".to_string(), "Fetch.continueRequest".to_string()),
+            ]
+        );
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while watchdog.blocked_count() + watchdog.continued_count() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(watchdog.blocked_count(), 1);
+        assert_eq!(watchdog.continued_count(), 1);
+
+        watchdog.on_detach().await.unwrap();
+        let disable = commands.recv().await.unwrap();
+        assert_eq!(disable["method"], "Fetch.disable");
+        assert_eq!(disable["sessionId"], "S-T1");
+        assert!(watchdog.subscriptions.is_empty());
+    }
+}
@@ -5,129 +5,725 @@
 //! - Block navigation to disallowed domains
 //! - Handle redirects to blocked domains
 //! - Support glob patterns for domain matching
+//!
+//! Each tab's document requests are paused with the Fetch domain and
+//! checked before they are sent, so a blocked page never loads. Requests
+//! made before the watchdog attached to a tab slip through; those are still
+//! caught when their navigation completes.
+//!
+//! Main-frame redirects are followed through `Network.requestWillBeSent`:
+//! every hop of the chain is checked, so an allowed URL can't 302 its way
+//! to a blocked one, and the whole chain is logged when a block happens.
 
 use async_trait::async_trait;
-use std::collections::HashSet;
-use std::net::IpAddr;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
-use crate::cdp::CDPClient;
-use crate::events::BrowserEvent;
-use crate::watchdog::Watchdog;
+use crate::cdp::commands::{
+    ContinueRequest, FailRequest, FetchDisable, FetchEnable, GetNavigationHistory, Navigate,
+    NavigateToHistoryEntry, RequestPattern, StopLoading,
+};
+use crate::cdp::protocol::TargetId;
+use crate::cdp::{CDPClient, CDPSession};
+use crate::events::{BrowserEvent, EventBus};
+use crate::watchdog::{CdpSubscriptions, Diagnostics, Watchdog, WatchdogEventKind};
+
+/// Cloud instance metadata endpoint, a classic SSRF target
+const METADATA_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(169, 254, 169, 254));
+
+/// What to do with a tab that reached a blocked URL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockAction {
+    /// Replace the page with `about:blank`
+    #[default]
+    AboutBlank,
+    /// Stop loading and go back one history entry (`about:blank` if there is none)
+    Back,
+    /// Leave the page alone, only publish `NavigationBlocked`
+    EmitEventOnly,
+}
 
 /// Security policy configuration
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SecurityPolicy {
     /// Allowed domains (whitelist). If empty, all domains allowed except prohibited ones.
+    ///
+    /// Domain entries may be Unicode (`例え.jp`) or punycode and any case;
+    /// the watchdog normalizes them to lowercase punycode.
     pub allowed_domains: Option<HashSet<String>>,
 
     /// Prohibited domains (blacklist)
     pub prohibited_domains: Option<HashSet<String>>,
 
-    /// Block IP addresses (localhost, 192.168.*, etc.)
+    /// Block every literal IP address host, public or not
     pub block_ip_addresses: bool,
+
+    /// Block loopback, private and link-local addresses, including
+    /// hostnames that resolve to them. Public IPs stay reachable.
+    pub block_private_networks: bool,
+
+    /// Allowed URL schemes, e.g. `https`. `None` allows any scheme.
+    /// Internal pages and `data:`/`blob:` URLs are always allowed.
+    pub allowed_schemes: Option<HashSet<String>>,
+
+    /// Ports that may never be reached, using the scheme's default when omitted
+    pub blocked_ports: Option<HashSet<u16>>,
+
+    /// How to enforce a block once a tab reached a disallowed URL
+    pub on_block: BlockAction,
+
+    /// Also check scripts, images, iframes and other sub-resources, failing
+    /// disallowed ones. Applies to tabs opened after it is set.
+    pub block_subresources: bool,
 }
 
-impl Default for SecurityPolicy {
-    fn default() -> Self {
-        Self {
-            allowed_domains: None,
-            prohibited_domains: None,
-            block_ip_addresses: false,
-        }
+impl SecurityPolicy {
+    /// Domain sets with every entry in lowercase punycode, like parsed hosts
+    fn normalized(mut self) -> Self {
+        let normalize = |domains: HashSet<String>| {
+            domains
+                .iter()
+                .map(|pattern| SecurityWatchdog::normalize_pattern(pattern))
+                .collect()
+        };
+        self.allowed_domains = self.allowed_domains.map(normalize);
+        self.prohibited_domains = self.prohibited_domains.map(normalize);
+        self
     }
 }
 
 /// Security Watchdog - enforces URL access policies
 pub struct SecurityWatchdog {
     policy: Arc<RwLock<SecurityPolicy>>,
+
+    /// Client captured in `on_attach`, used to navigate blocked tabs away
+    cdp_client: RwLock<Option<Arc<CDPClient>>>,
+
+    /// Where to publish `NavigationBlocked`
+    event_sink: Option<EventBus>,
+
+    /// Tabs whose requests are paused for checking, keyed by target id
+    targets: RwLock<HashMap<TargetId, GuardedTarget>>,
+
+    /// Reports `UrlBlocked` for every block
+    diagnostics: Diagnostics,
+}
+
+/// A tab with Fetch interception on, dropped on `TabClosed`
+struct GuardedTarget {
+    session: CDPSession,
+    subscriptions: CdpSubscriptions,
+}
+
+/// URLs a main-frame navigation went through, first to latest
+#[derive(Debug, Default)]
+struct RedirectChain {
+    hops: Vec<String>,
+    /// Set once the navigation was blocked, so it's reported only once
+    blocked: bool,
+}
+
+/// Outcome of the policy rules that need no DNS lookup
+#[derive(Debug, PartialEq, Eq)]
+enum UrlCheck {
+    Allowed,
+    Blocked,
+    /// Allowed unless `host` resolves to a private address
+    AllowedIfPublic {
+        host: String,
+        port: Option<u16>,
+    },
+}
+
+/// What checking a tab's requests needs, cloned into event callbacks
+#[derive(Clone)]
+struct RequestGuard {
+    session: CDPSession,
+    policy: Arc<RwLock<SecurityPolicy>>,
+    event_sink: Option<EventBus>,
+    diagnostics: Diagnostics,
+    /// Navigations in flight, keyed by network request id
+    chains: Arc<Mutex<HashMap<String, RedirectChain>>>,
+}
+
+impl RequestGuard {
+    /// Whether a request is a main-frame document, i.e. a navigation
+    ///
+    /// The main frame's id is its target's id.
+    fn is_navigation(&self, resource_type: &Value, frame_id: &Value) -> bool {
+        resource_type == "Document" && frame_id == self.session.target_id.as_str()
+    }
+
+    /// Track a navigation from `Network.requestWillBeSent` params, returning
+    /// its chain so far if this hop is a redirect
+    fn record_request(&self, params: &Value) -> Option<(String, Vec<String>)> {
+        if !self.is_navigation(&params["type"], &params["frameId"]) {
+            return None;
+        }
+        let request_id = params["requestId"].as_str()?;
+        let url = params["request"]["url"].as_str()?;
+        let mut chains = self.chains.lock().unwrap_or_else(|e| e.into_inner());
+        match params["redirectResponse"]["url"].as_str() {
+            Some(from) => {
+                let chain = chains
+                    .entry(request_id.to_string())
+                    .or_insert_with(|| RedirectChain {
+                        hops: vec![from.to_string()],
+                        blocked: false,
+                    });
+                chain.hops.push(url.to_string());
+                Some((request_id.to_string(), chain.hops.clone()))
+            }
+            None => {
+                chains.insert(
+                    request_id.to_string(),
+                    RedirectChain {
+                        hops: vec![url.to_string()],
+                        blocked: false,
+                    },
+                );
+                None
+            }
+        }
+    }
+
+    /// Forget a navigation once it finished or failed
+    fn forget_request(&self, params: &Value) {
+        if let Some(request_id) = params["requestId"].as_str() {
+            self.chains
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(request_id);
+        }
+    }
+
+    /// Check every hop of a redirect chain, blocking the navigation if any
+    /// is disallowed
+    async fn check_redirect(&self, request_id: String, hops: Vec<String>) {
+        let mut disallowed = None;
+        for hop in &hops {
+            if !SecurityWatchdog::check_url(&self.policy, hop).await {
+                disallowed = Some(hop.clone());
+                break;
+            }
+        }
+        let Some(url) = disallowed else {
+            return;
+        };
+        let action = self.policy.read().await.on_block;
+
+        if action == BlockAction::EmitEventOnly {
+            // The final URL may be allowed, so completion wouldn't report it
+            tracing::warn!(
+                "[SecurityWatchdog] ⛔️ Redirect through blocked URL {}: {}",
+                url,
+                hops.join(" -> ")
+            );
+            self.publish_blocked(&url);
+            return;
+        }
+        if let Err(e) = self.session.send_command(&StopLoading::default()).await {
+            tracing::warn!("[SecurityWatchdog] Failed to stop loading {}: {}", url, e);
+        }
+        self.block_navigation(&request_id, &url).await;
+    }
+
+    /// Report a blocked navigation and apply `on_block`, once per request
+    async fn block_navigation(&self, request_id: &str, url: &str) {
+        let hops = {
+            let mut chains = self.chains.lock().unwrap_or_else(|e| e.into_inner());
+            let chain = chains.entry(request_id.to_string()).or_default();
+            if chain.blocked {
+                return;
+            }
+            chain.blocked = true;
+            std::mem::take(&mut chain.hops)
+        };
+        if hops.len() > 1 {
+            tracing::warn!(
+                "[SecurityWatchdog] ⛔️ Blocked redirect chain at {}: {}",
+                url,
+                hops.join(" -> ")
+            );
+        } else {
+            tracing::warn!("[SecurityWatchdog] ⛔️ Blocked navigation to {}", url);
+        }
+
+        self.publish_blocked(url);
+        let action = self.policy.read().await.on_block;
+        // Leave the error page the failed navigation committed
+        if let Err(e) = SecurityWatchdog::leave_page(&self.session, action).await {
+            tracing::warn!(
+                "[SecurityWatchdog] Failed to leave blocked URL {}: {}",
+                url,
+                e
+            );
+        }
+    }
+
+    fn publish_blocked(&self, url: &str) {
+        self.diagnostics.emit(WatchdogEventKind::UrlBlocked, url);
+        if let Some(event_bus) = &self.event_sink {
+            event_bus.publish(BrowserEvent::NavigationBlocked {
+                target_id: self.session.target_id.clone(),
+                url: url.to_string(),
+            });
+        }
+    }
+
+    /// Fail or continue a request from `Fetch.requestPaused` params
+    async fn resolve(&self, params: Value) {
+        let Some(request_id) = params["requestId"].as_str().map(str::to_string) else {
+            return;
+        };
+        let url = params["request"]["url"].as_str().unwrap_or_default();
+        let navigation = self.is_navigation(&params["resourceType"], &params["frameId"]);
+
+        let (checked, action) = {
+            let policy = self.policy.read().await;
+            (navigation || policy.block_subresources, policy.on_block)
+        };
+        let blocked = checked && !SecurityWatchdog::check_url(&self.policy, url).await;
+        // Advisory mode lets the page load and reports it on completion
+        let fail = blocked && action != BlockAction::EmitEventOnly;
+
+        let result = if fail {
+            tracing::warn!("[SecurityWatchdog] ⛔️ Blocking request to {}", url);
+            if !navigation {
+                // Navigations are reported by `block_navigation`
+                self.diagnostics.emit(WatchdogEventKind::UrlBlocked, url);
+            }
+            self.session
+                .send_command(&FailRequest {
+                    request_id,
+                    error_reason: "BlockedByClient".to_string(),
+                })
+                .await
+        } else {
+            self.session
+                .send_command(&ContinueRequest { request_id })
+                .await
+        };
+        if let Err(e) = result {
+            tracing::warn!("[SecurityWatchdog] Failed to resolve {}: {}", url, e);
+            return;
+        }
+
+        if fail && navigation {
+            let network_id = params["networkId"].as_str().unwrap_or_default();
+            self.block_navigation(network_id, url).await;
+        }
+    }
 }
 
 impl SecurityWatchdog {
     /// Create new SecurityWatchdog with default policy (allow all)
     pub fn new() -> Self {
-        Self {
-            policy: Arc::new(RwLock::new(SecurityPolicy::default())),
-        }
+        Self::with_policy(SecurityPolicy::default())
     }
 
     /// Create with custom security policy
     pub fn with_policy(policy: SecurityPolicy) -> Self {
         Self {
-            policy: Arc::new(RwLock::new(policy)),
+            policy: Arc::new(RwLock::new(policy.normalized())),
+            cdp_client: RwLock::new(None),
+            event_sink: None,
+            targets: RwLock::new(HashMap::new()),
+            diagnostics: Diagnostics::new(),
+        }
+    }
+
+    /// Create a watchdog that publishes `NavigationBlocked` to `event_bus`
+    pub fn with_event_sink(policy: SecurityPolicy, event_bus: EventBus) -> Self {
+        Self {
+            event_sink: Some(event_bus),
+            ..Self::with_policy(policy)
         }
     }
 
     /// Update security policy at runtime
     pub async fn update_policy(&self, policy: SecurityPolicy) {
-        *self.policy.write().await = policy;
+        *self.policy.write().await = policy.normalized();
     }
 
     /// Check if a URL is allowed based on current policy
     pub async fn is_url_allowed(&self, url: &str) -> bool {
-        let policy = self.policy.read().await;
+        Self::check_url(&self.policy, url).await
+    }
 
-        // Always allow internal browser URLs
-        if matches!(
-            url,
-            "about:blank"
-                | "chrome://new-tab-page/"
-                | "chrome://new-tab-page"
-                | "chrome://newtab/"
-                | "chrome-extension://*"
-        ) {
-            return true;
+    /// Check `url` against `policy`
+    ///
+    /// The policy lock is released before any DNS lookup, so a slow
+    /// resolver never stalls `update_policy` or other tabs' checks.
+    async fn check_url(policy: &RwLock<SecurityPolicy>, url: &str) -> bool {
+        match Self::check_url_rules(&*policy.read().await, url) {
+            UrlCheck::Allowed => true,
+            UrlCheck::Blocked => false,
+            UrlCheck::AllowedIfPublic { host, port } => {
+                !Self::resolves_to_private_ip(&host, port).await
+            }
+        }
+    }
+
+    /// Check `url` against the rules of `policy` that need no DNS lookup
+    fn check_url_rules(policy: &SecurityPolicy, url: &str) -> UrlCheck {
+        // Always allow internal browser URLs - blocking them would loop
+        // with the about:blank redirect
+        if Self::is_internal_url(url) {
+            return UrlCheck::Allowed;
         }
 
         // Parse URL
         let parsed = match url::Url::parse(url) {
             Ok(p) => p,
-            Err(_) => return false,
+            Err(_) => return UrlCheck::Blocked,
         };
 
         // Allow data: and blob: URLs
         if matches!(parsed.scheme(), "data" | "blob") {
-            return true;
+            return UrlCheck::Allowed;
+        }
+
+        // Scheme and port rules apply even to whitelisted hosts
+        if let Some(ref schemes) = policy.allowed_schemes {
+            if !schemes.contains(parsed.scheme()) {
+                return UrlCheck::Blocked;
+            }
+        }
+        if let (Some(ref ports), Some(port)) =
+            (&policy.blocked_ports, parsed.port_or_known_default())
+        {
+            if ports.contains(&port) {
+                return UrlCheck::Blocked;
+            }
         }
 
         // Get hostname
+        // Special schemes already come lowercase and punycoded; a fully
+        // qualified `example.com.` is the same host as `example.com`
         let host = match parsed.host_str() {
-            Some(h) => h,
-            None => return false,
+            Some(h) => h.to_ascii_lowercase(),
+            None => return UrlCheck::Blocked,
+        };
+        let host = host.strip_suffix('.').unwrap_or(&host);
+        // `::ffff:a.b.c.d` reaches the same host as `a.b.c.d`
+        let ip = match parsed.host() {
+            Some(url::Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(url::Host::Ipv6(ip)) => Some(IpAddr::V6(ip).to_canonical()),
+            _ => None,
         };
 
+        // The metadata endpoint is blocked regardless of policy. Names that
+        // resolve to it are caught by `block_private_networks`.
+        if ip == Some(METADATA_IP) {
+            return UrlCheck::Blocked;
+        }
+
         // Check if IP address and should be blocked
-        if policy.block_ip_addresses && Self::is_ip_address(host) {
-            return false;
+        if policy.block_ip_addresses && ip.is_some() {
+            return UrlCheck::Blocked;
         }
 
-        // If no policies defined, allow all
-        if policy.allowed_domains.is_none() && policy.prohibited_domains.is_none() {
-            return true;
+        // Hostnames are resolved by the caller, once the policy is released
+        let resolve = match ip {
+            _ if !policy.block_private_networks => false,
+            Some(ip) if Self::is_private_ip(ip) => return UrlCheck::Blocked,
+            Some(_) => false,
+            None => true,
+        };
+
+        // Check allowed domains (whitelist takes precedence), then
+        // prohibited domains (blacklist); no policies allow all
+        let allowed = if let Some(ref allowed) = policy.allowed_domains {
+            Self::is_domain_in_set(host, parsed.path(), allowed)
+        } else if let Some(ref prohibited) = policy.prohibited_domains {
+            !Self::is_domain_in_set(host, parsed.path(), prohibited)
+        } else {
+            true
+        };
+
+        match (allowed, resolve) {
+            (false, _) => UrlCheck::Blocked,
+            (true, false) => UrlCheck::Allowed,
+            (true, true) => UrlCheck::AllowedIfPublic {
+                host: host.to_string(),
+                port: parsed.port_or_known_default(),
+            },
+        }
+    }
+
+    /// Browser-internal pages that are never subject to policy
+    fn is_internal_url(url: &str) -> bool {
+        matches!(
+            url,
+            "about:blank" | "chrome://new-tab-page/" | "chrome://new-tab-page" | "chrome://newtab/"
+        ) || url.starts_with("chrome-extension://")
+    }
+
+    /// Publish `NavigationBlocked` and apply `on_block` to the offending tab
+    async fn enforce_block(&self, target_id: &str, url: &str) {
+        self.diagnostics.emit(WatchdogEventKind::UrlBlocked, url);
+        if let Some(event_bus) = &self.event_sink {
+            event_bus.publish(BrowserEvent::NavigationBlocked {
+                target_id: target_id.to_string(),
+                url: url.to_string(),
+            });
+        }
+
+        let action = self.policy.read().await.on_block;
+        if action == BlockAction::EmitEventOnly {
+            return;
+        }
+        // Reuse the guarded tab's session; a tab without one gets a
+        // short-lived session, detached once it left the page
+        let guarded = self
+            .targets
+            .read()
+            .await
+            .get(target_id)
+            .map(|target| target.session.clone());
+        let (session, attached) = match guarded {
+            Some(session) => (session, false),
+            None => {
+                let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+                    tracing::warn!("[SecurityWatchdog] Not attached, cannot leave {}", url);
+                    return;
+                };
+                // Attach without enabling any domains - only Page commands are sent
+                match CDPSession::attach(cdp_client, target_id.to_string(), Some(vec![])).await {
+                    Ok(session) => (session, true),
+                    Err(e) => {
+                        tracing::warn!(
+                            "[SecurityWatchdog] Failed to attach to {}: {}",
+                            target_id,
+                            e
+                        );
+                        return;
+                    }
+                }
+            }
+        };
+
+        if let Err(e) = Self::leave_page(&session, action).await {
+            tracing::warn!(
+                "[SecurityWatchdog] Failed to leave blocked URL {}: {}",
+                url,
+                e
+            );
         }
+        if attached {
+            if let Err(e) = session.detach().await {
+                tracing::debug!(
+                    "[SecurityWatchdog] Failed to detach from {}: {}",
+                    target_id,
+                    e
+                );
+            }
+        }
+    }
 
-        // Check allowed domains (whitelist takes precedence)
-        if let Some(ref allowed) = policy.allowed_domains {
-            return Self::is_domain_in_set(host, allowed);
+    /// Attach to a tab and pause its requests for checking
+    async fn guard_target(&self, target_id: &TargetId) {
+        let Some(cdp_client) = self.cdp_client.read().await.clone() else {
+            tracing::debug!(
+                "[SecurityWatchdog] Not attached, ignoring tab {}",
+                target_id
+            );
+            return;
+        };
+        if self.targets.read().await.contains_key(target_id) {
+            return;
         }
 
-        // Check prohibited domains (blacklist)
-        if let Some(ref prohibited) = policy.prohibited_domains {
-            return !Self::is_domain_in_set(host, prohibited);
+        // Only documents, unless sub-resources are checked too
+        let resource_type =
+            (!self.policy.read().await.block_subresources).then(|| "Document".to_string());
+        let result = async {
+            let session =
+                CDPSession::attach(cdp_client.clone(), target_id.clone(), Some(vec!["Network"]))
+                    .await?;
+            // Subscribe before enabling so no paused request is missed
+            let guard = RequestGuard {
+                session: session.clone(),
+                policy: self.policy.clone(),
+                event_sink: self.event_sink.clone(),
+                diagnostics: self.diagnostics.clone(),
+                chains: Arc::new(Mutex::new(HashMap::new())),
+            };
+            let subscriptions = CdpSubscriptions::new();
+            let session_id = Some(session.session_id.clone());
+            let paused = guard.clone();
+            subscriptions.subscribe_session(
+                &cdp_client,
+                "Fetch.requestPaused",
+                session_id.clone(),
+                Arc::new(move |event| {
+                    let Some(params) = event.params else {
+                        return;
+                    };
+                    let guard = paused.clone();
+                    tokio::spawn(async move { guard.resolve(params).await });
+                }),
+            );
+            let redirects = guard.clone();
+            subscriptions.subscribe_session(
+                &cdp_client,
+                "Network.requestWillBeSent",
+                session_id.clone(),
+                Arc::new(move |event| {
+                    let Some((request_id, hops)) = event
+                        .params
+                        .as_ref()
+                        .and_then(|params| redirects.record_request(params))
+                    else {
+                        return;
+                    };
+                    let guard = redirects.clone();
+                    tokio::spawn(async move { guard.check_redirect(request_id, hops).await });
+                }),
+            );
+            for method in ["Network.loadingFinished", "Network.loadingFailed"] {
+                let finished = guard.clone();
+                subscriptions.subscribe_session(
+                    &cdp_client,
+                    method,
+                    session_id.clone(),
+                    Arc::new(move |event| {
+                        if let Some(params) = event.params.as_ref() {
+                            finished.forget_request(params);
+                        }
+                    }),
+                );
+            }
+            let target = GuardedTarget {
+                session,
+                subscriptions,
+            };
+            let enabled = target
+                .session
+                .send_command(&FetchEnable {
+                    patterns: vec![RequestPattern {
+                        url_pattern: Some("*".to_string()),
+                        resource_type,
+                        request_stage: None,
+                    }],
+                })
+                .await;
+            match enabled {
+                Ok(_) => Ok(target),
+                Err(e) => {
+                    target.subscriptions.clear();
+                    Err(e)
+                }
+            }
         }
+        .await;
 
-        true
+        match result {
+            Ok(target) => {
+                self.targets.write().await.insert(target_id.clone(), target);
+            }
+            Err(e) => {
+                tracing::warn!("[SecurityWatchdog] Failed to guard {}: {}", target_id, e);
+            }
+        }
     }
 
-    /// Check if hostname is an IP address
-    fn is_ip_address(host: &str) -> bool {
-        // Simple heuristic: if it parses as IP, it's an IP
-        host.parse::<IpAddr>().is_ok()
+    /// Navigate `session` away from the current page
+    async fn leave_page(
+        session: &CDPSession,
+        action: BlockAction,
+    ) -> crate::cdp::client::Result<()> {
+        if action == BlockAction::Back {
+            session.send_command(&StopLoading::default()).await?;
+            let history = session
+                .send_command(&GetNavigationHistory::default())
+                .await?;
+            let previous = history["currentIndex"]
+                .as_u64()
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| history["entries"].get(index as usize))
+                .and_then(|entry| entry["id"].as_i64());
+            if let Some(entry_id) = previous {
+                session
+                    .send_command(&NavigateToHistoryEntry { entry_id })
+                    .await?;
+                return Ok(());
+            }
+        }
+
+        session.send_command(&Navigate::new("about:blank")).await?;
+        Ok(())
     }
 
-    /// Check if domain matches any pattern in the set
-    fn is_domain_in_set(host: &str, domains: &HashSet<String>) -> bool {
+    /// Loopback, private, link-local, shared (CGNAT) or unspecified address
+    fn is_private_ip(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+                ip.is_loopback()
+                    || ip.is_private()
+                    || ip.is_link_local()
+                    || ip.is_unspecified()
+                    || ip.is_broadcast()
+                    || (a == 100 && (64..128).contains(&b))
+            }
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(mapped) => Self::is_private_ip(IpAddr::V4(mapped)),
+                None => {
+                    ip.is_loopback()
+                        || ip.is_unspecified()
+                        || ip.is_unique_local()
+                        || ip.is_unicast_link_local()
+                }
+            },
+        }
+    }
+
+    /// Resolve `host` and check every address it maps to
+    ///
+    /// Unresolvable hosts pass - the navigation itself will fail.
+    async fn resolves_to_private_ip(host: &str, port: Option<u16>) -> bool {
+        match tokio::net::lookup_host((host, port.unwrap_or(80))).await {
+            Ok(mut addrs) => addrs.any(|addr| {
+                let ip = addr.ip().to_canonical();
+                ip == METADATA_IP || Self::is_private_ip(ip)
+            }),
+            Err(_) => false,
+        }
+    }
+
+    /// Lowercase punycode form of a domain pattern's host part
+    ///
+    /// Works label by label so `*` wildcards survive; the path part is kept.
+    pub(crate) fn normalize_pattern(pattern: &str) -> String {
+        let (host_pattern, path_pattern) = match pattern.find('/') {
+            Some(slash) => pattern.split_at(slash),
+            None => (pattern, ""),
+        };
+        let host = host_pattern
+            .split('.')
+            .map(|label| {
+                if label.is_ascii() {
+                    return label.to_ascii_lowercase();
+                }
+                match url::Host::parse(label) {
+                    Ok(url::Host::Domain(label)) => label,
+                    _ => label.to_lowercase(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+        host + path_pattern
+    }
+
+    /// Check if host (and path) match any pattern in the set
+    fn is_domain_in_set(host: &str, path: &str, domains: &HashSet<String>) -> bool {
         // Try exact match first (fast path)
         if domains.contains(host) {
             return true;
@@ -139,48 +735,78 @@ impl SecurityWatchdog {
             return true;
         }
 
-        // Check for wildcard patterns
-        for pattern in domains {
-            if Self::matches_pattern(host, pattern) {
-                return true;
-            }
-        }
-
-        false
+        // Check for wildcard and path patterns
+        domains
+            .iter()
+            .filter(|pattern| pattern.contains('*') || pattern.contains('/'))
+            .any(|pattern| Self::matches_pattern(host, path, pattern))
     }
 
     /// Get domain variants (with and without www)
     fn get_domain_variants(host: &str) -> (&str, String) {
-        if host.starts_with("www.") {
-            (host, host[4..].to_string())
+        if let Some(stripped) = host.strip_prefix("www.") {
+            (host, stripped.to_string())
         } else {
             (host, format!("www.{}", host))
         }
     }
 
-    /// Check if hostname matches a pattern (supports wildcards)
-    fn matches_pattern(host: &str, pattern: &str) -> bool {
-        if !pattern.contains('*') {
-            return host == pattern;
-        }
+    /// Check if a URL's host and path match a pattern
+    ///
+    /// - `*` matches within one hostname label, `**` also matches across dots
+    /// - A leading `*.` matches the domain itself and any subdomain depth
+    /// - An optional `/path` part is matched against the URL path, where `*` matches anything
+    pub(crate) fn matches_pattern(host: &str, path: &str, pattern: &str) -> bool {
+        let (host_pattern, path_pattern) = match pattern.find('/') {
+            Some(slash) => (&pattern[..slash], Some(&pattern[slash..])),
+            None => (pattern, None),
+        };
 
-        // Handle *.example.com pattern
-        if pattern.starts_with("*.") {
-            let domain_part = &pattern[2..];
-            return host == domain_part || host.ends_with(&format!(".{}", domain_part));
-        }
+        let host = host.as_bytes();
+        let host_matches = match host_pattern.strip_prefix("*.") {
+            Some(domain_pattern) => {
+                let domain_pattern = domain_pattern.as_bytes();
+                Self::glob_match(domain_pattern, host, Some(b'.'))
+                    || (0..host.len()).any(|i| {
+                        host[i] == b'.'
+                            && Self::glob_match(domain_pattern, &host[i + 1..], Some(b'.'))
+                    })
+            }
+            None => Self::glob_match(host_pattern.as_bytes(), host, Some(b'.')),
+        };
+
+        host_matches
+            && path_pattern.is_none_or(|path_pattern| {
+                Self::glob_match(path_pattern.as_bytes(), path.as_bytes(), None)
+            })
+    }
 
-        // Handle other glob patterns (simple implementation)
-        // For production, use a proper glob library
-        if pattern.contains('*') {
-            let parts: Vec<&str> = pattern.split('*').collect();
-            if parts.len() == 2 {
-                let (prefix, suffix) = (parts[0], parts[1]);
-                return host.starts_with(prefix) && host.ends_with(suffix);
+    /// ASCII case-insensitive glob where `*` stops at `separator` and `**` does not
+    fn glob_match(pattern: &[u8], text: &[u8], separator: Option<u8>) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some((b'*', rest)) => {
+                let (crosses, rest) = match rest.split_first() {
+                    Some((b'*', rest)) => (true, rest),
+                    _ => (separator.is_none(), rest),
+                };
+                for skip in 0..=text.len() {
+                    if Self::glob_match(rest, &text[skip..], separator) {
+                        return true;
+                    }
+                    if !crosses && text.get(skip).copied() == separator {
+                        return false;
+                    }
+                }
+                false
             }
+            Some((c, rest)) => match text.split_first() {
+                Some((t, text)) if t.eq_ignore_ascii_case(c) => {
+                    Self::glob_match(rest, text, separator)
+                }
+                _ => false,
+            },
         }
-
-        false
     }
 }
 
@@ -201,23 +827,34 @@ impl Watchdog for SecurityWatchdog {
             BrowserEvent::Started => {
                 let policy = self.policy.read().await;
                 tracing::info!(
-                    "[SecurityWatchdog] Active - allowed_domains: {:?}, prohibited_domains: {:?}, block_ips: {}",
+                    "[SecurityWatchdog] Active - allowed_domains: {:?}, prohibited_domains: {:?}, block_ips: {}, block_private: {}",
                     policy.allowed_domains.as_ref().map(|d| d.len()),
                     policy.prohibited_domains.as_ref().map(|d| d.len()),
-                    policy.block_ip_addresses
+                    policy.block_ip_addresses,
+                    policy.block_private_networks
                 );
             }
 
-            BrowserEvent::NavigationComplete { url } => {
-                if !self.is_url_allowed(url).await {
-                    tracing::warn!(
-                        "[SecurityWatchdog] ⛔️ Navigation to blocked URL detected: {}",
-                        url
-                    );
-                    // TODO: Navigate to about:blank or emit error event
+            BrowserEvent::TabCreated { target_id } => {
+                self.guard_target(target_id).await;
+            }
+
+            BrowserEvent::TabClosed { target_id } => {
+                if let Some(target) = self.targets.write().await.remove(target_id) {
+                    target.subscriptions.clear();
                 }
             }
 
+            BrowserEvent::NavigationComplete { target_id, url }
+                if !self.is_url_allowed(url).await =>
+            {
+                tracing::warn!(
+                    "[SecurityWatchdog] ⛔️ Navigation to blocked URL detected: {}",
+                    url
+                );
+                self.enforce_block(target_id, url).await;
+            }
+
             _ => {
                 // Ignore other events
             }
@@ -226,21 +863,39 @@ impl Watchdog for SecurityWatchdog {
 
     async fn on_attach(
         &self,
-        _cdp_client: Arc<CDPClient>,
+        cdp_client: Arc<CDPClient>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        *self.cdp_client.write().await = Some(cdp_client);
         tracing::info!("[SecurityWatchdog] Attached");
         Ok(())
     }
 
     async fn on_detach(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Disabling Fetch lets anything still paused continue
+        for (target_id, target) in self.targets.write().await.drain() {
+            target.subscriptions.clear();
+            if let Err(e) = target.session.send_command(&FetchDisable::default()).await {
+                tracing::debug!(
+                    "[SecurityWatchdog] Fetch.disable failed for {}: {}",
+                    target_id,
+                    e
+                );
+            }
+        }
+        self.cdp_client.write().await.take();
         tracing::info!("[SecurityWatchdog] Detached");
         Ok(())
     }
+
+    fn diagnostics(&self) -> Option<&Diagnostics> {
+        Some(&self.diagnostics)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cdp::mock::{self, Reply};
 
     #[tokio::test]
     async fn test_security_watchdog_default_allows_all() {
@@ -261,6 +916,7 @@ mod tests {
             allowed_domains: Some(allowed),
             prohibited_domains: None,
             block_ip_addresses: false,
+            ..Default::default()
         };
         let watchdog = SecurityWatchdog::with_policy(policy);
 
@@ -280,6 +936,7 @@ mod tests {
             allowed_domains: None,
             prohibited_domains: Some(prohibited),
             block_ip_addresses: false,
+            ..Default::default()
         };
         let watchdog = SecurityWatchdog::with_policy(policy);
 
@@ -297,6 +954,7 @@ mod tests {
             allowed_domains: Some(allowed),
             prohibited_domains: None,
             block_ip_addresses: false,
+            ..Default::default()
         };
         let watchdog = SecurityWatchdog::with_policy(policy);
 
@@ -310,12 +968,228 @@ mod tests {
         assert!(!watchdog.is_url_allowed("https://other.com").await);
     }
 
+    #[tokio::test]
+    async fn test_security_watchdog_schemes_and_ports() {
+        let policy = SecurityPolicy {
+            allowed_domains: Some(HashSet::from(["example.com".to_string()])),
+            allowed_schemes: Some(HashSet::from(["https".to_string()])),
+            blocked_ports: Some(HashSet::from([8443])),
+            ..Default::default()
+        };
+        let watchdog = SecurityWatchdog::with_policy(policy);
+
+        assert!(watchdog.is_url_allowed("https://example.com").await);
+        assert!(!watchdog.is_url_allowed("http://example.com").await);
+        assert!(!watchdog.is_url_allowed("https://example.com:8443/x").await);
+        assert!(!watchdog.is_url_allowed("ftp://example.com").await);
+        assert!(!watchdog.is_url_allowed("https://other.com").await);
+
+        // Internal and inline URLs are unaffected
+        assert!(watchdog.is_url_allowed("about:blank").await);
+        assert!(watchdog.is_url_allowed("data:text/plain,hi").await);
+    }
+
+    #[tokio::test]
+    async fn test_security_watchdog_blocked_default_port() {
+        let policy = SecurityPolicy {
+            blocked_ports: Some(HashSet::from([80])),
+            ..Default::default()
+        };
+        let watchdog = SecurityWatchdog::with_policy(policy);
+
+        assert!(!watchdog.is_url_allowed("http://example.com").await);
+        assert!(!watchdog.is_url_allowed("https://example.com:80").await);
+        assert!(watchdog.is_url_allowed("https://example.com").await);
+        assert!(watchdog.is_url_allowed("http://example.com:8080").await);
+    }
+
+    #[test]
+    fn test_private_ip_classification() {
+        let private = |ip: &str| SecurityWatchdog::is_private_ip(ip.parse().unwrap());
+
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+        ] {
+            assert!(private(ip), "{ip} should be private");
+        }
+        for ip in ["8.8.8.8", "172.32.0.1", "100.128.0.1", "2606:4700::1111"] {
+            assert!(!private(ip), "{ip} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_security_watchdog_block_private_networks() {
+        let policy = SecurityPolicy {
+            block_private_networks: true,
+            ..Default::default()
+        };
+        let watchdog = SecurityWatchdog::with_policy(policy);
+
+        assert!(watchdog.is_url_allowed("http://8.8.8.8").await);
+        assert!(!watchdog.is_url_allowed("http://10.0.0.1").await);
+        assert!(!watchdog.is_url_allowed("http://[::1]:8080").await);
+        assert!(!watchdog.is_url_allowed("http://[::ffff:10.0.0.1]").await);
+
+        // Hostnames are left to DNS, checked without the policy lock
+        let policy = watchdog.policy.read().await;
+        assert_eq!(
+            SecurityWatchdog::check_url_rules(&policy, "http://localhost:3000"),
+            UrlCheck::AllowedIfPublic {
+                host: "localhost".to_string(),
+                port: Some(3000),
+            }
+        );
+        assert!(SecurityWatchdog::resolves_to_private_ip("127.0.0.1", None).await);
+        assert!(SecurityWatchdog::resolves_to_private_ip("::ffff:169.254.169.254", None).await);
+        assert!(!SecurityWatchdog::resolves_to_private_ip("8.8.8.8", None).await);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_endpoint_always_blocked() {
+        let watchdog = SecurityWatchdog::new();
+
+        assert!(
+            !watchdog
+                .is_url_allowed("http://169.254.169.254/latest/meta-data/")
+                .await
+        );
+        // Also spelled as an IPv4-mapped IPv6 address
+        assert!(
+            !watchdog
+                .is_url_allowed("http://[::ffff:169.254.169.254]/latest/meta-data/")
+                .await
+        );
+        assert!(watchdog.is_url_allowed("http://10.0.0.1").await);
+        // Only the address: a host that happens to be named "metadata" is
+        // left to the policy
+        assert!(watchdog.is_url_allowed("http://metadata/").await);
+    }
+
+    #[test]
+    fn test_multi_wildcard_patterns() {
+        let matches =
+            |host: &str, pattern: &str| SecurityWatchdog::matches_pattern(host, "/", pattern);
+
+        assert!(matches("a.corp.x.com", "*.corp.*.com"));
+        assert!(matches("a.b.corp.x.com", "*.corp.*.com"));
+        assert!(matches("corp.x.com", "*.corp.*.com"));
+        assert!(!matches("corp.x.y.com", "*.corp.*.com"));
+
+        assert!(matches("api-v2.example.com", "api-*.example.com"));
+        assert!(!matches("api-v2.evil.example.com", "api-*.example.com"));
+        assert!(matches("API-V2.Example.com", "api-*.example.com"));
+
+        // `*` stays within a label, `**` crosses dots
+        assert!(!matches("example.com.evil.net", "example.com*"));
+        assert!(!matches("a.b.example.com", "*example.com"));
+        assert!(matches("a.b.example.com", "**example.com"));
+        assert!(!matches("a.b.c", "a*b*c"));
+        assert!(matches("axbyc", "a*b*c"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let matches = SecurityWatchdog::matches_pattern;
+
+        assert!(matches(
+            "api-1.example.com",
+            "/v1/users",
+            "api-*.example.com/*"
+        ));
+        assert!(matches("api-1.example.com", "/", "api-*.example.com/*"));
+        assert!(!matches("www.example.com", "/v1", "api-*.example.com/*"));
+        assert!(matches("example.com", "/docs/a/b", "example.com/docs/*"));
+        assert!(!matches("example.com", "/admin", "example.com/docs/*"));
+    }
+
+    #[tokio::test]
+    async fn test_security_watchdog_glob_policy() {
+        let policy = SecurityPolicy {
+            allowed_domains: Some(HashSet::from([
+                "*.corp.*.com".to_string(),
+                "api-*.example.com/public/*".to_string(),
+            ])),
+            ..Default::default()
+        };
+        let watchdog = SecurityWatchdog::with_policy(policy);
+
+        assert!(watchdog.is_url_allowed("https://wiki.corp.acme.com").await);
+        assert!(!watchdog.is_url_allowed("https://corp.acme.evil.com").await);
+        assert!(
+            watchdog
+                .is_url_allowed("https://api-eu.example.com/public/x")
+                .await
+        );
+        assert!(
+            !watchdog
+                .is_url_allowed("https://api-eu.example.com/private/x")
+                .await
+        );
+    }
+
+    #[test]
+    fn test_normalize_pattern() {
+        let normalize = SecurityWatchdog::normalize_pattern;
+
+        assert_eq!(normalize("例え.jp"), "xn--r8jz45g.jp");
+        assert_eq!(normalize("*.Bücher.DE/Docs/*"), "*.xn--bcher-kva.de/Docs/*");
+        assert_eq!(normalize("API-*.Example.com"), "api-*.example.com");
+        assert_eq!(normalize("xn--r8jz45g.jp"), "xn--r8jz45g.jp");
+    }
+
+    #[tokio::test]
+    async fn test_security_watchdog_idn_and_case() {
+        let watchdog = SecurityWatchdog::with_policy(SecurityPolicy {
+            allowed_domains: Some(HashSet::from([
+                "例え.jp".to_string(),
+                "*.Bücher.de".to_string(),
+                "Example.COM".to_string(),
+            ])),
+            ..Default::default()
+        });
+
+        // Unicode and punycode spellings of the same host are equivalent
+        assert!(watchdog.is_url_allowed("https://例え.jp/").await);
+        assert!(watchdog.is_url_allowed("https://xn--r8jz45g.jp/").await);
+        assert!(watchdog.is_url_allowed("https://shop.BÜCHER.de/").await);
+        assert!(
+            watchdog
+                .is_url_allowed("https://www.xn--bcher-kva.de/")
+                .await
+        );
+        assert!(watchdog.is_url_allowed("https://EXAMPLE.com/").await);
+        assert!(watchdog.is_url_allowed("https://WWW.Example.Com/").await);
+        // A homograph is a different host
+        assert!(!watchdog.is_url_allowed("https://exаmple.com/").await);
+
+        watchdog
+            .update_policy(SecurityPolicy {
+                prohibited_domains: Some(HashSet::from(["例え.JP".to_string()])),
+                ..Default::default()
+            })
+            .await;
+        assert!(!watchdog.is_url_allowed("https://XN--R8JZ45G.jp/").await);
+        assert!(!watchdog.is_url_allowed("https://www.例え.jp/").await);
+        assert!(watchdog.is_url_allowed("https://example.com/").await);
+    }
+
     #[tokio::test]
     async fn test_security_watchdog_block_ips() {
         let policy = SecurityPolicy {
             allowed_domains: None,
             prohibited_domains: None,
             block_ip_addresses: true,
+            ..Default::default()
         };
         let watchdog = SecurityWatchdog::with_policy(policy);
 
@@ -333,6 +1207,7 @@ mod tests {
             allowed_domains: Some(allowed),
             prohibited_domains: None,
             block_ip_addresses: true,
+            ..Default::default()
         };
         let watchdog = SecurityWatchdog::with_policy(policy);
 
@@ -345,4 +1220,359 @@ mod tests {
                 .await
         );
     }
+
+    /// Mock browser that reports each Page and Fetch command on `tx`
+    async fn page_command_mock(
+        history_index: u64,
+    ) -> (
+        Arc<CDPClient>,
+        tokio::sync::mpsc::UnboundedReceiver<serde_json::Value>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let client = mock::connect(move |request| {
+            let method = request["method"].as_str().unwrap();
+            if method.starts_with("Page.") || method.starts_with("Fetch.") {
+                let _ = tx.send(request.clone());
+            }
+            match method {
+                "Page.getNavigationHistory" => Reply::Result(serde_json::json!({
+                    "currentIndex": history_index,
+                    "entries": [{ "id": 7 }, { "id": 8 }],
+                })),
+                _ => Reply::Default,
+            }
+        })
+        .await;
+        (client, rx)
+    }
+
+    fn blocking_policy(on_block: BlockAction) -> SecurityPolicy {
+        SecurityPolicy {
+            prohibited_domains: Some(HashSet::from(["blocked.org".to_string()])),
+            on_block,
+            ..Default::default()
+        }
+    }
+
+    fn navigation(url: &str) -> BrowserEvent {
+        BrowserEvent::NavigationComplete {
+            target_id: "T1".to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_navigation_goes_to_about_blank() {
+        let (client, mut commands) = page_command_mock(1).await;
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog =
+            SecurityWatchdog::with_event_sink(blocking_policy(BlockAction::AboutBlank), event_bus);
+        watchdog.on_attach(client).await.unwrap();
+
+        watchdog.on_event(&navigation("https://example.com")).await;
+        watchdog
+            .on_event(&navigation("https://blocked.org/x"))
+            .await;
+
+        let command = commands.recv().await.unwrap();
+        assert_eq!(command["method"], "Page.navigate");
+        assert_eq!(command["params"]["url"], "about:blank");
+        assert_eq!(command["sessionId"], "S-T1");
+        assert!(matches!(
+            events.try_recv(),
+            Ok(BrowserEvent::NavigationBlocked { target_id, url })
+                if target_id == "T1" && url == "https://blocked.org/x"
+        ));
+
+        // The redirect target itself is never blocked
+        watchdog.on_event(&navigation("about:blank")).await;
+        assert!(commands.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_blocked_navigation_goes_back() {
+        let (client, mut commands) = page_command_mock(1).await;
+        let watchdog = SecurityWatchdog::with_policy(blocking_policy(BlockAction::Back));
+        watchdog.on_attach(client).await.unwrap();
+
+        watchdog.on_event(&navigation("https://blocked.org")).await;
+
+        let methods: Vec<_> = (0..3).map(|_| commands.try_recv().unwrap()).collect();
+        assert_eq!(methods[0]["method"], "Page.stopLoading");
+        assert_eq!(methods[1]["method"], "Page.getNavigationHistory");
+        assert_eq!(methods[2]["method"], "Page.navigateToHistoryEntry");
+        assert_eq!(methods[2]["params"]["entryId"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_blocked_navigation_emit_event_only() {
+        let (client, mut commands) = page_command_mock(1).await;
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog = SecurityWatchdog::with_event_sink(
+            blocking_policy(BlockAction::EmitEventOnly),
+            event_bus,
+        );
+        watchdog.on_attach(client).await.unwrap();
+
+        watchdog.on_event(&navigation("https://blocked.org")).await;
+
+        assert!(matches!(
+            events.try_recv(),
+            Ok(BrowserEvent::NavigationBlocked { .. })
+        ));
+        assert!(commands.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_blocked_navigation_reuses_or_detaches_session() {
+        let (tx, mut targets) = tokio::sync::mpsc::unbounded_channel();
+        let client = mock::connect(move |request| {
+            let method = request["method"].as_str().unwrap();
+            if matches!(method, "Target.attachToTarget" | "Target.detachFromTarget") {
+                let _ = tx.send(method.to_string());
+            }
+            Reply::Default
+        })
+        .await;
+        let watchdog = SecurityWatchdog::with_policy(blocking_policy(BlockAction::AboutBlank));
+        watchdog.on_attach(client).await.unwrap();
+
+        // An unguarded tab gets a session only for leaving the page
+        watchdog.on_event(&navigation("https://blocked.org")).await;
+        assert_eq!(targets.try_recv().unwrap(), "Target.attachToTarget");
+        assert_eq!(targets.try_recv().unwrap(), "Target.detachFromTarget");
+
+        // A guarded tab's session is reused and stays attached
+        watchdog
+            .on_event(&BrowserEvent::TabCreated {
+                target_id: "T1".to_string(),
+            })
+            .await;
+        assert_eq!(targets.try_recv().unwrap(), "Target.attachToTarget");
+        watchdog.on_event(&navigation("https://blocked.org")).await;
+        assert!(targets.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_blocked_navigation_fails_before_loading() {
+        use std::time::Duration;
+
+        let (client, mut commands) = page_command_mock(1).await;
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog =
+            SecurityWatchdog::with_event_sink(blocking_policy(BlockAction::AboutBlank), event_bus);
+        watchdog.on_attach(client.clone()).await.unwrap();
+        watchdog
+            .on_event(&BrowserEvent::TabCreated {
+                target_id: "T1".to_string(),
+            })
+            .await;
+
+        let enable = commands.recv().await.unwrap();
+        assert_eq!(enable["method"], "Fetch.enable");
+        assert_eq!(enable["sessionId"], "S-T1");
+        assert_eq!(enable["params"]["patterns"][0]["resourceType"], "Document");
+
+        let paused = |id: &str, url: &str, frame_id: &str| {
+            serde_json::json!({
+                "method": "Fetch.requestPaused",
+                "params": {
+                    "requestId": id,
+                    "request": { "url": url, "method": "GET" },
+                    "resourceType": "Document",
+                    "frameId": frame_id,
+                },
+                "sessionId": "S-T1",
+            })
+        };
+        let paused_events = [
+            paused("1", "https://blocked.org/x", "T1"),
+            // Iframes are sub-resources, checked only with `block_subresources`
+            paused("2", "https://blocked.org/frame", "F2"),
+            paused("3", "https://example.com/", "T1"),
+        ];
+        client
+            .send_request(
+                "Test.emit",
+                Some(serde_json::json!({ "events": paused_events })),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let mut resolved = Vec::new();
+        for _ in 0..4 {
+            let command = tokio::time::timeout(Duration::from_secs(5), commands.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            resolved.push(format!(
+                "{} {}",
+                command["method"].as_str().unwrap(),
+                command["params"]["requestId"]
+                    .as_str()
+                    .or(command["params"]["url"].as_str())
+                    .unwrap()
+            ));
+        }
+        resolved.sort();
+        assert_eq!(
+            resolved,
+            vec![
+                "Fetch.continueRequest 2",
+                "Fetch.continueRequest 3",
+                "Fetch.failRequest 1",
+                "Page.navigate about:blank",
+            ]
+        );
+        assert!(matches!(
+            events.try_recv(),
+            Ok(BrowserEvent::NavigationBlocked { target_id, url })
+                if target_id == "T1" && url == "https://blocked.org/x"
+        ));
+
+        watchdog.on_detach().await.unwrap();
+        let disable = commands.recv().await.unwrap();
+        assert_eq!(disable["method"], "Fetch.disable");
+    }
+
+    #[tokio::test]
+    async fn test_block_subresources_checks_every_request() {
+        let (client, mut commands) = page_command_mock(1).await;
+        let watchdog = SecurityWatchdog::with_policy(SecurityPolicy {
+            block_subresources: true,
+            ..blocking_policy(BlockAction::AboutBlank)
+        });
+        watchdog.on_attach(client).await.unwrap();
+        watchdog
+            .on_event(&BrowserEvent::TabCreated {
+                target_id: "T1".to_string(),
+            })
+            .await;
+
+        let enable = commands.recv().await.unwrap();
+        assert_eq!(enable["method"], "Fetch.enable");
+        assert!(enable["params"]["patterns"][0]
+            .get("resourceType")
+            .is_none());
+
+        let session = watchdog.targets.read().await["T1"].session.clone();
+        let guard = RequestGuard {
+            session,
+            policy: watchdog.policy.clone(),
+            event_sink: None,
+            diagnostics: Diagnostics::new(),
+            chains: Arc::new(Mutex::new(HashMap::new())),
+        };
+        guard
+            .resolve(serde_json::json!({
+                "requestId": "9",
+                "request": { "url": "https://blocked.org/ad.js" },
+                "resourceType": "Script",
+                "frameId": "T1",
+            }))
+            .await;
+        let command = commands.recv().await.unwrap();
+        assert_eq!(command["method"], "Fetch.failRequest");
+        // Only navigations leave the page
+        assert!(commands.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redirect_chain_checks_every_hop() {
+        let (client, mut commands) = page_command_mock(1).await;
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+        let watchdog =
+            SecurityWatchdog::with_event_sink(blocking_policy(BlockAction::AboutBlank), event_bus);
+        watchdog.on_attach(client).await.unwrap();
+        watchdog
+            .on_event(&BrowserEvent::TabCreated {
+                target_id: "T1".to_string(),
+            })
+            .await;
+        assert_eq!(commands.recv().await.unwrap()["method"], "Fetch.enable");
+
+        let guard = RequestGuard {
+            session: watchdog.targets.read().await["T1"].session.clone(),
+            policy: watchdog.policy.clone(),
+            event_sink: watchdog.event_sink.clone(),
+            diagnostics: Diagnostics::new(),
+            chains: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let hop = |url: &str, from: Option<&str>| {
+            let mut params = serde_json::json!({
+                "requestId": "N1",
+                "type": "Document",
+                "frameId": "T1",
+                "request": { "url": url },
+            });
+            if let Some(from) = from {
+                params["redirectResponse"] = serde_json::json!({ "url": from, "status": 302 });
+            }
+            params
+        };
+
+        assert!(guard
+            .record_request(&hop("https://example.com/", None))
+            .is_none());
+        let (request_id, hops) = guard
+            .record_request(&hop(
+                "https://blocked.org/track",
+                Some("https://example.com/"),
+            ))
+            .unwrap();
+        guard
+            .record_request(&hop(
+                "https://example.org/landing",
+                Some("https://blocked.org/track"),
+            ))
+            .unwrap();
+        assert_eq!(request_id, "N1");
+        assert_eq!(hops, ["https://example.com/", "https://blocked.org/track"]);
+
+        // The final hop is allowed, but the chain went through a blocked host
+        guard
+            .check_redirect(
+                request_id.clone(),
+                vec![
+                    "https://example.com/".to_string(),
+                    "https://blocked.org/track".to_string(),
+                    "https://example.org/landing".to_string(),
+                ],
+            )
+            .await;
+        assert_eq!(commands.recv().await.unwrap()["method"], "Page.stopLoading");
+        let navigate = commands.recv().await.unwrap();
+        assert_eq!(navigate["method"], "Page.navigate");
+        assert_eq!(navigate["params"]["url"], "about:blank");
+        assert!(matches!(
+            events.try_recv(),
+            Ok(BrowserEvent::NavigationBlocked { url, .. }) if url == "https://blocked.org/track"
+        ));
+
+        // The same navigation failing in Fetch isn't reported twice
+        guard
+            .block_navigation(&request_id, "https://blocked.org/track")
+            .await;
+        assert!(events.try_recv().is_err());
+        assert!(commands.try_recv().is_err());
+
+        guard.forget_request(&serde_json::json!({ "requestId": "N1" }));
+        assert!(guard.chains.lock().unwrap().is_empty());
+
+        // Iframe redirects aren't navigations
+        assert!(guard
+            .record_request(&serde_json::json!({
+                "requestId": "N2",
+                "type": "Document",
+                "frameId": "F2",
+                "request": { "url": "https://blocked.org/" },
+                "redirectResponse": { "url": "https://example.com/" },
+            }))
+            .is_none());
+    }
 }
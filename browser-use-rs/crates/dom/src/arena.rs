@@ -19,9 +19,15 @@
 //!         ↑ 4-byte index, not 8-byte pointer
 //! ```
 
+use crate::diff::DomDiff;
 use crate::error::{DomError, Result};
-use crate::types::{DomNode, NodeId, NodeType};
-use ahash::AHashMap;
+use crate::intern::{StringInterner, Symbol};
+use crate::selector::Selector;
+use crate::types::{AXNode, DomNode, DomRect, NodeId, NodeType, SnapshotNode, STATIC_ATTRIBUTES};
+use ahash::{AHashMap, AHashSet};
+use smallvec::SmallVec;
+use std::collections::HashMap;
+use std::mem::size_of;
 
 /// Arena allocator for DOM nodes
 ///
@@ -29,16 +35,86 @@ use ahash::AHashMap;
 /// - Single Vec<DomNode> for sequential allocation
 /// - HashMap for backend_node_id → NodeId lookup (CDP uses backend IDs)
 /// - No Rc/Arc: use indices everywhere
-#[derive(Debug)]
+///
+/// Backend IDs are unique within one renderer, but trees merged from
+/// several targets (out-of-process iframes) can repeat them. The arena
+/// keeps every node under a shared ID; single lookups resolve to the one
+/// added first, i.e. the outer document, since parsing is in document
+/// order. Use `get_all_by_backend_id` to see the rest.
+///
+/// An arena from `with_interning` also records each node's tag name and
+/// `STATIC_ATTRIBUTES` keys as `Symbol`s when it is added; see `name_symbol`.
+#[derive(Debug, Clone)]
 pub struct DomArena {
     /// All nodes stored sequentially (cache-friendly)
     nodes: Vec<DomNode>,
 
-    /// Backend node ID → NodeId lookup (for CDP integration)
-    backend_id_map: AHashMap<u32, NodeId>,
+    /// Backend node ID → NodeIds lookup (for CDP integration), in the
+    /// order they were added
+    backend_id_map: AHashMap<u32, SmallVec<[NodeId; 1]>>,
+
+    /// CDP `nodeId` → NodeIds lookup (for DOM domain events), in the order
+    /// they were added
+    cdp_id_map: AHashMap<u32, SmallVec<[NodeId; 1]>>,
 
     /// Root node ID (if set)
     root_id: Option<NodeId>,
+
+    /// Removed node slots - indices stay valid, lookups skip them
+    tombstones: AHashSet<NodeId>,
+
+    /// Tombstoned slots available for reuse by `add_node`
+    free_list: Vec<NodeId>,
+
+    /// Tag names and common attribute keys, if interning
+    interner: Option<StringInterner>,
+
+    /// Per-slot symbols, parallel to `nodes` while interning
+    symbols: Vec<NodeSymbols>,
+
+    /// Nodes added since `checkpoint`, while one is open
+    checkpoint: Option<Vec<NodeId>>,
+}
+
+/// Approximate memory use of a `DomArena`, from `DomArena::memory_stats`
+///
+/// Heap sizes are estimated from capacities and the element sizes of the
+/// containers; allocator overhead and hash table padding aren't counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Live nodes
+    pub node_count: usize,
+    /// Allocated `nodes` slots, live or removed
+    pub node_capacity: usize,
+    /// The `nodes` Vec itself: `node_capacity` inline `DomNode`s
+    pub nodes_bytes: usize,
+    /// Names, values, IDs and spilled child lists of live nodes
+    pub string_bytes: usize,
+    /// Attribute maps of live nodes, keys and values included
+    pub attribute_bytes: usize,
+    /// Boxed AX and snapshot data of live nodes
+    pub enrichment_bytes: usize,
+    /// Slots the backend ID map can hold before growing
+    pub backend_id_map_capacity: usize,
+    /// The backend ID map's table
+    pub backend_id_map_bytes: usize,
+    /// The CDP node ID map's table
+    pub cdp_id_map_bytes: usize,
+    /// The interner and per-node symbols, 0 without interning
+    pub interned_bytes: usize,
+}
+
+impl ArenaStats {
+    /// Everything above, in bytes
+    pub fn total_bytes(&self) -> usize {
+        self.nodes_bytes
+            + self.string_bytes
+            + self.attribute_bytes
+            + self.enrichment_bytes
+            + self.backend_id_map_bytes
+            + self.cdp_id_map_bytes
+            + self.interned_bytes
+    }
 }
 
 impl DomArena {
@@ -47,7 +123,24 @@ impl DomArena {
         Self {
             nodes: Vec::with_capacity(1024), // Pre-allocate for typical page
             backend_id_map: AHashMap::with_capacity(1024),
+            cdp_id_map: AHashMap::with_capacity(1024),
             root_id: None,
+            tombstones: AHashSet::new(),
+            free_list: Vec::new(),
+            interner: None,
+            symbols: Vec::new(),
+            checkpoint: None,
+        }
+    }
+
+    /// Create an arena that interns tag names and `STATIC_ATTRIBUTES` keys
+    ///
+    /// Costs a table lookup per name and key in `add_node`. Nodes keep
+    /// their own strings; the symbols are extra, for comparing by integer.
+    pub fn with_interning() -> Self {
+        Self {
+            interner: Some(StringInterner::new()),
+            ..Self::new()
         }
     }
 
@@ -56,20 +149,104 @@ impl DomArena {
         Self {
             nodes: Vec::with_capacity(capacity),
             backend_id_map: AHashMap::with_capacity(capacity),
+            cdp_id_map: AHashMap::with_capacity(capacity),
             root_id: None,
+            tombstones: AHashSet::new(),
+            free_list: Vec::new(),
+            interner: None,
+            symbols: Vec::new(),
+            checkpoint: None,
+        }
+    }
+
+    /// An empty arena that interns like this one, starting from its strings
+    ///
+    /// For building a replacement tree while this one stays usable.
+    pub fn empty_like(&self) -> Self {
+        Self {
+            interner: self.interner.clone(),
+            ..Self::with_capacity(self.len().max(1024))
         }
     }
 
     /// Add a node to the arena, returns its ID
+    ///
+    /// Reuses a removed slot when one is free, so IDs of removed nodes
+    /// must not be held across an `add_node`.
     pub fn add_node(&mut self, node: DomNode) -> NodeId {
-        let node_id = self.nodes.len() as NodeId;
-        self.backend_id_map.insert(node.backend_node_id, node_id);
-        self.nodes.push(node);
+        let symbols = self
+            .interner
+            .as_mut()
+            .map(|interner| NodeSymbols::new(interner, &node));
+        let backend_id = node.backend_node_id;
+        let cdp_id = node.node_id;
+        let node_id = match self.free_list.pop() {
+            Some(node_id) => {
+                self.tombstones.remove(&node_id);
+                self.nodes[node_id as usize] = node;
+                if let Some(symbols) = symbols {
+                    self.symbols[node_id as usize] = symbols;
+                }
+                node_id
+            }
+            None => {
+                self.nodes.push(node);
+                self.symbols.extend(symbols);
+                (self.nodes.len() - 1) as NodeId
+            }
+        };
+        let ids = self.backend_id_map.entry(backend_id).or_default();
+        if let Some(&existing) = ids.first() {
+            tracing::warn!(
+                "backend node id {} is shared by nodes {} and {}; lookups return {}",
+                backend_id,
+                existing,
+                node_id,
+                existing
+            );
+        }
+        ids.push(node_id);
+        self.cdp_id_map.entry(cdp_id).or_default().push(node_id);
+        if let Some(added) = self.checkpoint.as_mut() {
+            added.push(node_id);
+        }
         node_id
     }
 
+    /// Start recording added nodes, so `rollback` can remove them again
+    ///
+    /// For parses that may fail halfway; a new checkpoint replaces an open one.
+    pub fn checkpoint(&mut self) {
+        self.checkpoint = Some(Vec::new());
+    }
+
+    /// Keep the nodes added since `checkpoint` and stop recording
+    pub fn commit(&mut self) {
+        self.checkpoint = None;
+    }
+
+    /// Remove every node added since `checkpoint`, returns how many
+    ///
+    /// Only nodes added since then may link to them, so nothing is unlinked.
+    pub fn rollback(&mut self) -> usize {
+        let added = self.checkpoint.take().unwrap_or_default();
+        let mut removed = 0;
+        for node_id in added {
+            if self.root_id == Some(node_id) {
+                self.root_id = None;
+            }
+            if self.tombstone(node_id).is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
     /// Get node by ID (immutable)
     pub fn get(&self, node_id: NodeId) -> Result<&DomNode> {
+        if self.tombstones.contains(&node_id) {
+            return Err(DomError::NodeNotFound(node_id));
+        }
         self.nodes
             .get(node_id as usize)
             .ok_or(DomError::NodeNotFound(node_id))
@@ -77,23 +254,172 @@ impl DomArena {
 
     /// Get node by ID (mutable)
     pub fn get_mut(&mut self, node_id: NodeId) -> Result<&mut DomNode> {
+        if self.tombstones.contains(&node_id) {
+            return Err(DomError::NodeNotFound(node_id));
+        }
         self.nodes
             .get_mut(node_id as usize)
             .ok_or(DomError::NodeNotFound(node_id))
     }
 
+    /// Mark a node slot as removed
+    ///
+    /// The slot stays in the Vec so every other NodeId remains valid.
+    /// Caller is responsible for unlinking it from its parent's `children_ids`.
+    pub fn tombstone(&mut self, node_id: NodeId) -> Result<()> {
+        let node = self.get(node_id)?;
+        let (backend_id, cdp_id) = (node.backend_node_id, node.node_id);
+        self.unindex(node_id, backend_id);
+        if let Some(ids) = self.cdp_id_map.get_mut(&cdp_id) {
+            ids.retain(|id| *id != node_id);
+            if ids.is_empty() {
+                self.cdp_id_map.remove(&cdp_id);
+            }
+        }
+        self.tombstones.insert(node_id);
+        self.free_list.push(node_id);
+        Ok(())
+    }
+
+    /// Drop `node_id` from the backend ID map entry for `backend_id`
+    fn unindex(&mut self, node_id: NodeId, backend_id: u32) {
+        if let Some(ids) = self.backend_id_map.get_mut(&backend_id) {
+            ids.retain(|id| *id != node_id);
+            if ids.is_empty() {
+                self.backend_id_map.remove(&backend_id);
+            }
+        }
+    }
+
+    /// Take a subtree out of the backend ID index, returning its node IDs
+    ///
+    /// The nodes stay in the tree; only lookups by backend ID skip them.
+    /// Lets a fresh parse of the same nodes be added before the old ones
+    /// are removed without the two sharing IDs. `reindex` undoes it.
+    pub fn unindex_subtree(&mut self, node_id: NodeId) -> Result<Vec<NodeId>> {
+        let ids = self.subtree_ids(node_id)?;
+        for &id in &ids {
+            let backend_id = self.get(id)?.backend_node_id;
+            self.unindex(id, backend_id);
+        }
+        Ok(ids)
+    }
+
+    /// Put nodes taken out by `unindex_subtree` back into the backend ID index
+    pub fn reindex(&mut self, node_ids: &[NodeId]) {
+        for &id in node_ids {
+            if let Ok(node) = self.get(id) {
+                let backend_id = node.backend_node_id;
+                self.backend_id_map.entry(backend_id).or_default().push(id);
+            }
+        }
+    }
+
+    /// A node and all its descendants, shadow roots and iframe documents included
+    fn subtree_ids(&self, node_id: NodeId) -> Result<Vec<NodeId>> {
+        self.get(node_id)?;
+        let mut ids = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            let Ok(node) = self.get(id) else {
+                continue;
+            };
+            ids.push(id);
+            stack.extend(node.children_ids.iter().copied());
+            stack.extend(node.shadow_root_ids.iter().flatten().copied());
+            stack.extend(node.content_document_id);
+        }
+        Ok(ids)
+    }
+
+    /// Remove a node and all its descendants, returns how many were removed
+    ///
+    /// Descendants include shadow roots and iframe documents. Unlinks the
+    /// node from its parent; slots go on the free list.
+    pub fn remove_subtree(&mut self, node_id: NodeId) -> Result<usize> {
+        let removed = self.subtree_ids(node_id)?;
+
+        if let Some(parent_id) = self.get(node_id)?.parent_id {
+            if let Ok(parent) = self.get_mut(parent_id) {
+                parent.children_ids.retain(|id| *id != node_id);
+                if let Some(shadow_root_ids) = parent.shadow_root_ids.as_mut() {
+                    shadow_root_ids.retain(|id| *id != node_id);
+                }
+                if parent.content_document_id == Some(node_id) {
+                    parent.content_document_id = None;
+                }
+            }
+        }
+        if self.root_id == Some(node_id) {
+            self.root_id = None;
+        }
+
+        for &id in &removed {
+            self.tombstone(id)?;
+        }
+        Ok(removed.len())
+    }
+
+    /// Move a node (with its subtree) to the end of `new_parent_id`'s children
+    ///
+    /// Fails with `CycleDetected` if `new_parent_id` is the node or one of
+    /// its descendants.
+    pub fn reparent(&mut self, node_id: NodeId, new_parent_id: NodeId) -> Result<()> {
+        let old_parent_id = self.get(node_id)?.parent_id;
+
+        let mut ancestor = Some(new_parent_id);
+        while let Some(id) = ancestor {
+            if id == node_id {
+                return Err(DomError::CycleDetected {
+                    node: node_id,
+                    new_parent: new_parent_id,
+                });
+            }
+            ancestor = self.get(id)?.parent_id;
+        }
+
+        if let Some(old_parent_id) = old_parent_id {
+            if let Ok(old_parent) = self.get_mut(old_parent_id) {
+                old_parent.children_ids.retain(|id| *id != node_id);
+            }
+        }
+        self.get_mut(new_parent_id)?.children_ids.push(node_id);
+        self.get_mut(node_id)?.parent_id = Some(new_parent_id);
+        Ok(())
+    }
+
+    /// Check if a node slot has been removed
+    pub fn is_tombstoned(&self, node_id: NodeId) -> bool {
+        self.tombstones.contains(&node_id)
+    }
+
     /// Get node by backend node ID (from CDP)
+    ///
+    /// If several nodes share the ID, returns the one added first.
     pub fn get_by_backend_id(&self, backend_id: u32) -> Result<&DomNode> {
         let node_id = self
-            .backend_id_map
-            .get(&backend_id)
+            .get_node_id_by_backend(backend_id)
             .ok_or(DomError::NodeNotFound(backend_id))?;
-        self.get(*node_id)
+        self.get(node_id)
     }
 
-    /// Get node ID by backend node ID
+    /// Get node ID by backend node ID, the first added if it's shared
     pub fn get_node_id_by_backend(&self, backend_id: u32) -> Option<NodeId> {
-        self.backend_id_map.get(&backend_id).copied()
+        self.get_all_by_backend_id(backend_id).first().copied()
+    }
+
+    /// Every live node with this backend node ID, in the order they were added
+    pub fn get_all_by_backend_id(&self, backend_id: u32) -> &[NodeId] {
+        self.backend_id_map
+            .get(&backend_id)
+            .map_or(&[], |ids| ids.as_slice())
+    }
+
+    /// Get node ID by CDP `nodeId`, the first added if it's shared
+    pub fn get_node_id_by_cdp_id(&self, cdp_id: u32) -> Option<NodeId> {
+        self.cdp_id_map
+            .get(&cdp_id)
+            .and_then(|ids| ids.first().copied())
     }
 
     /// Set root node
@@ -117,24 +443,121 @@ impl DomArena {
         self.get(root_id)
     }
 
-    /// Total number of nodes
+    /// Approximate memory use, without allocating
+    ///
+    /// Walks every live node, so it costs about as much as a `find`.
+    pub fn memory_stats(&self) -> ArenaStats {
+        let mut stats = ArenaStats {
+            node_count: self.len(),
+            node_capacity: self.nodes.capacity(),
+            nodes_bytes: self.nodes.capacity() * size_of::<DomNode>(),
+            backend_id_map_capacity: self.backend_id_map.capacity(),
+            backend_id_map_bytes: self.backend_id_map.capacity()
+                * (size_of::<(u32, SmallVec<[NodeId; 1]>)>() + 1),
+            cdp_id_map_bytes: self.cdp_id_map.capacity()
+                * (size_of::<(u32, SmallVec<[NodeId; 1]>)>() + 1),
+            ..ArenaStats::default()
+        };
+        for node in self.iter() {
+            stats.string_bytes += node.node_name.capacity()
+                + node.node_value.capacity()
+                + node.uuid.capacity()
+                + node.target_id.capacity()
+                + node.frame_id.as_ref().map_or(0, String::capacity)
+                + node.session_id.as_ref().map_or(0, String::capacity);
+            if node.children_ids.spilled() {
+                stats.string_bytes += node.children_ids.capacity() * size_of::<NodeId>();
+            }
+            stats.attribute_bytes += map_bytes(&node.attributes, String::capacity);
+            if let Some(ax) = &node.ax_node {
+                stats.enrichment_bytes += size_of::<AXNode>()
+                    + ax.ax_node_id.capacity()
+                    + [&ax.role, &ax.name, &ax.description]
+                        .into_iter()
+                        .flatten()
+                        .map(String::capacity)
+                        .sum::<usize>();
+            }
+            if let Some(snapshot) = &node.snapshot_node {
+                stats.enrichment_bytes += size_of::<SnapshotNode>()
+                    + snapshot.cursor_style.as_ref().map_or(0, String::capacity)
+                    + snapshot
+                        .computed_styles
+                        .as_ref()
+                        .map_or(0, |styles| map_bytes(styles, String::capacity));
+            }
+        }
+        if let Some(interner) = &self.interner {
+            stats.interned_bytes = interner.heap_bytes()
+                + self.symbols.capacity() * size_of::<NodeSymbols>()
+                + self
+                    .symbols
+                    .iter()
+                    .filter(|symbols| symbols.attribute_keys.spilled())
+                    .map(|symbols| symbols.attribute_keys.capacity() * size_of::<Symbol>())
+                    .sum::<usize>();
+        }
+        stats
+    }
+
+    /// The string table, if this arena interns
+    pub fn interner(&self) -> Option<&StringInterner> {
+        self.interner.as_ref()
+    }
+
+    /// The string behind an interned `symbol`
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.interner.as_ref()?.resolve(symbol)
+    }
+
+    /// Symbol of the tag name `node_id` was added with, if this arena interns
+    ///
+    /// Recorded by `add_node`; renaming through `get_mut` doesn't update it.
+    pub fn name_symbol(&self, node_id: NodeId) -> Option<Symbol> {
+        self.get(node_id).ok()?;
+        self.symbols
+            .get(node_id as usize)
+            .map(|symbols| symbols.name)
+    }
+
+    /// Symbols of the `STATIC_ATTRIBUTES` keys `node_id` was added with,
+    /// in `STATIC_ATTRIBUTES` order; empty if this arena doesn't intern
+    pub fn attribute_symbols(&self, node_id: NodeId) -> &[Symbol] {
+        match (self.get(node_id), self.symbols.get(node_id as usize)) {
+            (Ok(_), Some(symbols)) => &symbols.attribute_keys,
+            _ => &[],
+        }
+    }
+
+    /// Total number of live nodes
     pub fn len(&self) -> usize {
-        self.nodes.len()
+        self.nodes.len() - self.tombstones.len()
     }
 
     /// Check if arena is empty
     pub fn is_empty(&self) -> bool {
-        self.nodes.is_empty()
+        self.len() == 0
     }
 
-    /// Iterator over all nodes
+    /// Iterator over all live nodes
     pub fn iter(&self) -> impl Iterator<Item = &DomNode> {
-        self.nodes.iter()
+        self.live_nodes().map(|(_, node)| node)
     }
 
-    /// Iterator over all node IDs
+    /// Iterator over all live node IDs
     pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
-        (0..self.nodes.len()).map(|i| i as NodeId)
+        (0..self.nodes.len())
+            .map(|i| i as NodeId)
+            .filter(|id| !self.tombstones.contains(id))
+    }
+
+    /// Live nodes paired with their IDs
+    fn live_nodes(&self) -> impl Iterator<Item = (NodeId, &DomNode)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| (idx as NodeId, node))
+            .filter(|(id, _)| !self.tombstones.contains(id))
     }
 
     /// Get children of a node
@@ -142,19 +565,111 @@ impl DomArena {
         let node = self.get(node_id)?;
         node.children_ids
             .iter()
-            .map(|&child_id| self.get(child_id))
+            .map(|&child_id| {
+                self.get(child_id)
+                    .map_err(|e| e.context(|| format!("getting children of node {}", node_id)))
+            })
             .collect()
     }
 
+    /// Iterate child IDs without allocating
+    pub fn children_iter(&self, node_id: NodeId) -> Result<impl Iterator<Item = NodeId> + '_> {
+        let node = self.get(node_id)?;
+        Ok(node
+            .children_ids
+            .iter()
+            .copied()
+            .filter(|id| !self.tombstones.contains(id)))
+    }
+
+    /// Iterate descendants of `start_id` depth-first, in document order
+    ///
+    /// `start_id` itself is not yielded. Non-recursive like `traverse_df`;
+    /// the cursor stack stays inline for trees up to 32 levels deep.
+    pub fn descendants_iter(&self, start_id: NodeId) -> Result<impl Iterator<Item = NodeId> + '_> {
+        self.get(start_id)?;
+        let mut stack = SmallVec::new();
+        stack.push((start_id, 0));
+        Ok(Descendants { arena: self, stack })
+    }
+
     /// Get parent of a node
     pub fn parent(&self, node_id: NodeId) -> Result<Option<&DomNode>> {
         let node = self.get(node_id)?;
         match node.parent_id {
-            Some(parent_id) => Ok(Some(self.get(parent_id)?)),
+            Some(parent_id) => {
+                Ok(Some(self.get(parent_id).map_err(|e| {
+                    e.context(|| format!("getting parent of node {}", node_id))
+                })?))
+            }
             None => Ok(None),
         }
     }
 
+    /// Iterate ancestor IDs from the parent up to the root
+    ///
+    /// Stops at a dangling `parent_id` and never takes more steps than there
+    /// are nodes, so a corrupted parent cycle can't loop forever.
+    pub fn ancestor_ids(&self, node_id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut current = self.get(node_id).ok().and_then(|node| node.parent_id);
+        std::iter::from_fn(move || {
+            let id = current?;
+            current = self.get(id).ok()?.parent_id;
+            Some(id)
+        })
+        .take(self.nodes.len())
+    }
+
+    /// Iterate ancestors from the parent up to the root
+    pub fn ancestors(&self, node_id: NodeId) -> impl Iterator<Item = &DomNode> + '_ {
+        self.ancestor_ids(node_id)
+            .filter_map(move |id| self.get(id).ok())
+    }
+
+    /// First node matching predicate, starting at `node_id` and walking up
+    pub fn closest<F>(&self, node_id: NodeId, predicate: F) -> Option<NodeId>
+    where
+        F: Fn(&DomNode) -> bool,
+    {
+        std::iter::once(node_id)
+            .chain(self.ancestor_ids(node_id))
+            .find(|&id| self.get(id).is_ok_and(&predicate))
+    }
+
+    /// Ancestor path ending at `node_id`, e.g. `nav#menu > ul > li[role=tab] > a`
+    ///
+    /// Element ancestors only, lowercase, with `#id` and `[role=...]` when
+    /// present. At most `max_depth` elements are shown; a cut path starts with
+    /// `... > `. Unknown nodes give an empty string.
+    pub fn breadcrumb(&self, node_id: NodeId, max_depth: usize) -> String {
+        let Ok(node) = self.get(node_id) else {
+            return String::new();
+        };
+        let mut elements = std::iter::once(node)
+            .chain(self.ancestors(node_id))
+            .filter(|node| node.is_element());
+        let mut segments: Vec<String> = elements
+            .by_ref()
+            .take(max_depth)
+            .map(|node| {
+                let mut segment = node.node_name.to_ascii_lowercase();
+                if let Some(id) = node.attr("id").filter(|id| !id.is_empty()) {
+                    segment.push('#');
+                    segment.push_str(id);
+                }
+                if let Some(role) = node.attr("role").filter(|role| !role.is_empty()) {
+                    segment.push_str(&format!("[role={}]", role));
+                }
+                segment
+            })
+            .collect();
+        if !segments.is_empty() && elements.next().is_some() {
+            segments.push("...".to_string());
+        }
+        segments.reverse();
+        segments.join(" > ")
+    }
+
     /// Traverse tree depth-first (iterative, no recursion)
     ///
     /// This is the "good taste" version - no special cases for leaf nodes
@@ -162,15 +677,21 @@ impl DomArena {
     where
         F: FnMut(&DomNode) -> Result<()>,
     {
-        let mut stack = vec![start_id];
+        // (node, its parent in the walk) - the parent names a dangling child
+        let mut stack = vec![(start_id, None)];
 
-        while let Some(node_id) = stack.pop() {
-            let node = self.get(node_id)?;
+        while let Some((node_id, parent_id)) = stack.pop() {
+            let node = self.get(node_id).map_err(|e| match parent_id {
+                Some(parent_id) => {
+                    e.context(|| format!("traversing children of node {}", parent_id))
+                }
+                None => e,
+            })?;
             visit(node)?;
 
             // Push children in reverse order (so they're visited left-to-right)
             for &child_id in node.children_ids.iter().rev() {
-                stack.push(child_id);
+                stack.push((child_id, Some(node_id)));
             }
         }
 
@@ -183,14 +704,19 @@ impl DomArena {
         F: FnMut(&DomNode) -> Result<()>,
     {
         let mut queue = std::collections::VecDeque::new();
-        queue.push_back(start_id);
+        queue.push_back((start_id, None));
 
-        while let Some(node_id) = queue.pop_front() {
-            let node = self.get(node_id)?;
+        while let Some((node_id, parent_id)) = queue.pop_front() {
+            let node = self.get(node_id).map_err(|e| match parent_id {
+                Some(parent_id) => {
+                    e.context(|| format!("traversing children of node {}", parent_id))
+                }
+                None => e,
+            })?;
             visit(node)?;
 
             for &child_id in node.children_ids.iter() {
-                queue.push_back(child_id);
+                queue.push_back((child_id, Some(node_id)));
             }
         }
 
@@ -202,17 +728,16 @@ impl DomArena {
     where
         F: Fn(&DomNode) -> bool,
     {
-        self.nodes
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, node)| {
-                if predicate(node) {
-                    Some(idx as NodeId)
-                } else {
-                    None
-                }
-            })
-            .collect()
+        self.find_iter(predicate).collect()
+    }
+
+    /// Lazily yield nodes matching predicate (short-circuits with `take`/`find`)
+    pub fn find_iter<'a, F>(&'a self, predicate: F) -> impl Iterator<Item = NodeId> + 'a
+    where
+        F: Fn(&DomNode) -> bool + 'a,
+    {
+        self.live_nodes()
+            .filter_map(move |(id, node)| if predicate(node) { Some(id) } else { None })
     }
 
     /// Find first node matching predicate
@@ -220,13 +745,8 @@ impl DomArena {
     where
         F: Fn(&DomNode) -> bool,
     {
-        self.nodes.iter().enumerate().find_map(|(idx, node)| {
-            if predicate(node) {
-                Some(idx as NodeId)
-            } else {
-                None
-            }
-        })
+        self.live_nodes()
+            .find_map(|(id, node)| if predicate(node) { Some(id) } else { None })
     }
 
     /// Find all elements by tag name
@@ -242,6 +762,94 @@ impl DomArena {
         self.find_one(|node| node.node_type == NodeType::Element && node.attr("id") == Some(id))
     }
 
+    /// First element matching a CSS selector, in document order
+    ///
+    /// Unparseable selectors match nothing; use `Selector::parse` to see why.
+    pub fn query_selector(&self, selector: &str) -> Option<NodeId> {
+        let selector = Selector::parse(selector).ok()?;
+        self.document_order().find(|&id| selector.matches(self, id))
+    }
+
+    /// All elements matching a CSS selector, in document order
+    pub fn query_selector_all(&self, selector: &str) -> Vec<NodeId> {
+        match Selector::parse(selector) {
+            Ok(selector) => self
+                .document_order()
+                .filter(|&id| selector.matches(self, id))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Root and its descendants depth-first, or every live node if no root is set
+    fn document_order(&self) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        match self.root_id {
+            Some(root_id) => match self.descendants_iter(root_id) {
+                Ok(descendants) => Box::new(std::iter::once(root_id).chain(descendants)),
+                Err(_) => Box::new(std::iter::empty()),
+            },
+            None => Box::new(self.node_ids()),
+        }
+    }
+
+    /// Topmost element whose `absolute_position` contains `(x, y)`
+    ///
+    /// Topmost is the highest `paint_order`; ties (and elements without one)
+    /// go to the later node, usually the deeper one. Elements without a
+    /// layout box or with an empty one are ignored.
+    pub fn element_at_point(&self, x: f64, y: f64) -> Option<NodeId> {
+        self.laid_out_elements()
+            .filter(|(_, _, rect)| rect.contains_point(x, y))
+            .max_by_key(|(id, node, _)| {
+                let paint_order = node.snapshot_node.as_ref().and_then(|s| s.paint_order);
+                (paint_order, *id)
+            })
+            .map(|(id, _, _)| id)
+    }
+
+    /// Elements whose `absolute_position` intersects `rect`, in arena order
+    ///
+    /// Elements without a layout box or with an empty one are ignored.
+    pub fn elements_in_rect(&self, rect: DomRect) -> Vec<NodeId> {
+        self.laid_out_elements()
+            .filter(|(_, _, bounds)| bounds.intersects(&rect))
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    /// Live elements with a non-empty `absolute_position`
+    fn laid_out_elements(&self) -> impl Iterator<Item = (NodeId, &DomNode, DomRect)> {
+        self.live_nodes().filter_map(|(id, node)| {
+            let rect = node.absolute_position.filter(|rect| !rect.is_empty())?;
+            node.is_element().then_some((id, node, rect))
+        })
+    }
+
+    /// Nodes added, removed and changed since `previous`, an earlier capture
+    /// of the same page
+    ///
+    /// Matches nodes by `backend_node_id`, falling back to tree position
+    /// where an id was reused for a different node; see `diff` module docs.
+    pub fn diff(&self, previous: &DomArena) -> DomDiff {
+        crate::diff::diff(self, previous)
+    }
+
+    /// The whole tree as HTML, unfiltered; see `html` module docs
+    ///
+    /// Empty if no root is set.
+    pub fn to_html(&self) -> String {
+        self.root_id()
+            .and_then(|root_id| self.outer_html(root_id).ok())
+            .unwrap_or_default()
+    }
+
+    /// `node_id` and its subtree as HTML
+    pub fn outer_html(&self, node_id: NodeId) -> Result<String> {
+        let mut out = String::new();
+        crate::html::write_node(self, node_id, &mut out)?;
+        Ok(out)
+    }
+
     /// Find all visible elements
     pub fn find_visible(&self) -> Vec<NodeId> {
         self.find(|node| node.is_visible == Some(true))
@@ -252,11 +860,88 @@ impl DomArena {
         self.find(|node| node.is_clickable())
     }
 
+    /// Find all scroll containers (`isScrollable` from `DOM.getDocument`)
+    pub fn find_scrollable(&self) -> Vec<NodeId> {
+        self.find(|node| node.is_scrollable == Some(true))
+    }
+
     /// Clear arena (reuse allocation)
+    ///
+    /// Interned strings are kept for the next page, which likely uses the
+    /// same tags.
     pub fn clear(&mut self) {
         self.nodes.clear();
         self.backend_id_map.clear();
+        self.cdp_id_map.clear();
         self.root_id = None;
+        self.tombstones.clear();
+        self.free_list.clear();
+        self.symbols.clear();
+        self.checkpoint = None;
+    }
+}
+
+/// Interned tag name and `STATIC_ATTRIBUTES` keys of one node
+#[derive(Debug, Clone)]
+struct NodeSymbols {
+    name: Symbol,
+    attribute_keys: SmallVec<[Symbol; 4]>,
+}
+
+impl NodeSymbols {
+    fn new(interner: &mut StringInterner, node: &DomNode) -> Self {
+        Self {
+            name: interner.intern(&node.node_name),
+            attribute_keys: STATIC_ATTRIBUTES
+                .iter()
+                .filter(|key| node.attributes.contains_key(**key))
+                .map(|key| interner.intern(key))
+                .collect(),
+        }
+    }
+}
+
+/// Estimated heap use of a string map: its table plus every key and value
+fn map_bytes<K>(map: &HashMap<K, String>, key_bytes: impl Fn(&K) -> usize) -> usize {
+    map.capacity() * (size_of::<(K, String)>() + 1)
+        + map
+            .iter()
+            .map(|(key, value)| key_bytes(key) + value.capacity())
+            .sum::<usize>()
+}
+
+/// Depth-first cursor over a subtree: each frame is (node, next child index)
+struct Descendants<'a> {
+    arena: &'a DomArena,
+    stack: SmallVec<[(NodeId, usize); 32]>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        loop {
+            let (node_id, next_child) = self.stack.last_mut()?;
+            let child_id = self
+                .arena
+                .get(*node_id)
+                .ok()
+                .and_then(|node| node.children_ids.get(*next_child).copied());
+
+            match child_id {
+                Some(child_id) => {
+                    *next_child += 1;
+                    if self.arena.is_tombstoned(child_id) {
+                        continue;
+                    }
+                    self.stack.push((child_id, 0));
+                    return Some(child_id);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
     }
 }
 
@@ -308,6 +993,177 @@ mod tests {
         assert_eq!(found.node_name, "div");
     }
 
+    #[test]
+    fn test_cdp_id_lookup() {
+        let mut arena = DomArena::new();
+        let div = |cdp_id| {
+            DomNode::new(
+                cdp_id,
+                100 + cdp_id,
+                NodeType::Element,
+                "div".to_string(),
+                "target1".to_string(),
+            )
+        };
+
+        let id = arena.add_node(div(7));
+        assert_eq!(arena.get_node_id_by_cdp_id(7), Some(id));
+
+        // Removed nodes leave the index, and a reused slot is indexed anew
+        arena.remove_subtree(id).unwrap();
+        assert_eq!(arena.get_node_id_by_cdp_id(7), None);
+        let reused = arena.add_node(div(8));
+        assert_eq!(reused, id);
+        assert_eq!(arena.get_node_id_by_cdp_id(7), None);
+        assert_eq!(arena.get_node_id_by_cdp_id(8), Some(reused));
+
+        arena.clear();
+        assert_eq!(arena.get_node_id_by_cdp_id(8), None);
+    }
+
+    #[test]
+    fn test_memory_stats() {
+        let mut arena = DomArena::with_capacity(8);
+        let empty = arena.memory_stats();
+        assert_eq!(empty.node_count, 0);
+        assert_eq!(empty.nodes_bytes, 8 * size_of::<DomNode>());
+        assert_eq!(empty.attribute_bytes, 0);
+
+        let mut node = DomNode::new(
+            0,
+            100,
+            NodeType::Element,
+            "div".to_string(),
+            "target1".to_string(),
+        );
+        node.attributes
+            .insert("class".to_string(), "x".repeat(1000));
+        let id = arena.add_node(node);
+
+        let stats = arena.memory_stats();
+        assert_eq!(stats.node_count, 1);
+        assert!(stats.attribute_bytes >= 1005);
+        assert!(stats.string_bytes >= "div".len() + "target1".len());
+        assert!(stats.backend_id_map_capacity >= 1);
+        assert!(stats.total_bytes() > stats.nodes_bytes + stats.attribute_bytes);
+
+        // Removed nodes keep their slot but no longer count
+        arena.tombstone(id).unwrap();
+        let stats = arena.memory_stats();
+        assert_eq!(stats.node_count, 0);
+        assert_eq!(stats.attribute_bytes, 0);
+        assert_eq!(stats.nodes_bytes, empty.nodes_bytes);
+    }
+
+    #[test]
+    fn test_interning() {
+        let div = |backend_id: u32| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                "DIV".to_string(),
+                "target1".to_string(),
+            );
+            node.attributes
+                .insert("class".to_string(), "row".to_string());
+            node.attributes
+                .insert("data-x".to_string(), "1".to_string());
+            node
+        };
+
+        let mut plain = DomArena::new();
+        let mut interned = DomArena::with_interning();
+        for backend_id in 0..100 {
+            plain.add_node(div(backend_id));
+            interned.add_node(div(backend_id));
+        }
+
+        // Nodes keep their own strings either way
+        let (first, plain_first) = (interned.get(0).unwrap(), plain.get(0).unwrap());
+        assert_eq!(first.node_name, plain_first.node_name);
+        assert_eq!(first.attributes, plain_first.attributes);
+
+        let interner = interned.interner().unwrap();
+        assert_eq!(interner.len(), 2);
+        let symbol = interner.get("DIV").unwrap();
+        assert_eq!(interned.resolve(symbol), Some("DIV"));
+        assert!((0..100).all(|id| interned.name_symbol(id) == Some(symbol)));
+        // Only STATIC_ATTRIBUTES keys are interned
+        let class = interner.get("class").unwrap();
+        assert_eq!(interned.attribute_symbols(0), [class]);
+        assert_eq!(interner.get("data-x"), None);
+
+        assert!(plain.interner().is_none());
+        assert_eq!(plain.name_symbol(0), None);
+        assert!(plain.attribute_symbols(0).is_empty());
+
+        // A reused slot gets the new node's symbols
+        interned.tombstone(0).unwrap();
+        assert_eq!(interned.name_symbol(0), None);
+        let span = DomNode::new(
+            0,
+            100,
+            NodeType::Element,
+            "SPAN".to_string(),
+            "target1".to_string(),
+        );
+        let span_id = interned.add_node(span);
+        assert_eq!(span_id, 0);
+        let span_symbol = interned.interner().unwrap().get("SPAN");
+        assert_eq!(interned.name_symbol(span_id), span_symbol);
+        assert!(interned.attribute_symbols(span_id).is_empty());
+
+        assert!(interned.memory_stats().interned_bytes > 0);
+        assert_eq!(plain.memory_stats().interned_bytes, 0);
+    }
+
+    #[test]
+    fn test_find_scrollable() {
+        let mut arena = DomArena::new();
+        let mut ids = Vec::new();
+        for (backend_id, scrollable) in [(1, None), (2, Some(true)), (3, Some(false))] {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                "DIV".to_string(),
+                "target1".to_string(),
+            );
+            node.is_scrollable = scrollable;
+            ids.push(arena.add_node(node));
+        }
+        assert_eq!(arena.find_scrollable(), vec![ids[1]]);
+    }
+
+    #[test]
+    fn test_shared_backend_id() {
+        let mut arena = DomArena::new();
+        let node = |name: &str, target: &str| {
+            DomNode::new(
+                0,
+                100,
+                NodeType::Element,
+                name.to_string(),
+                target.to_string(),
+            )
+        };
+
+        // Main frame first, then an out-of-process iframe reusing the ID
+        let main = arena.add_node(node("div", "main"));
+        let frame = arena.add_node(node("span", "oopif"));
+        assert_eq!(arena.get_all_by_backend_id(100), &[main, frame]);
+        assert_eq!(arena.get_node_id_by_backend(100), Some(main));
+        assert_eq!(arena.get_by_backend_id(100).unwrap().node_name, "div");
+
+        // Removing one leaves the other reachable
+        arena.tombstone(main).unwrap();
+        assert_eq!(arena.get_node_id_by_backend(100), Some(frame));
+        arena.tombstone(frame).unwrap();
+        assert!(arena.get_all_by_backend_id(100).is_empty());
+        assert!(arena.get_by_backend_id(100).is_err());
+    }
+
     #[test]
     fn test_traverse_df() {
         let mut arena = DomArena::new();
@@ -354,5 +1210,302 @@ mod tests {
             .unwrap();
 
         assert_eq!(visited, vec!["div", "span", "span"]);
+
+        // A dangling child id names the node whose children were walked
+        arena.get_mut(root_id).unwrap().children_ids.push(99);
+        let err = arena.traverse_df(root_id, |_| Ok(())).unwrap_err();
+        assert_eq!(err.missing_node(), Some(99));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Node not found: 99 (while traversing children of node {})",
+                root_id
+            )
+        );
+        assert!(matches!(
+            arena.children(root_id),
+            Err(DomError::NodeNotFoundInContext { node_id: 99, .. })
+        ));
+    }
+
+    #[test]
+    fn test_lazy_iterators() {
+        let mut arena = DomArena::new();
+        let element = |backend_id: u32, tag: &str| {
+            DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                tag.to_string(),
+                "target1".to_string(),
+            )
+        };
+
+        // root -> [a -> [a1, a2], b]
+        let a1 = arena.add_node(element(101, "i"));
+        let a2 = arena.add_node(element(102, "b"));
+        let mut a_node = element(103, "p");
+        a_node.children_ids.extend([a1, a2]);
+        let a = arena.add_node(a_node);
+        let b = arena.add_node(element(104, "p"));
+        let mut root_node = element(100, "div");
+        root_node.children_ids.extend([a, b]);
+        let root = arena.add_node(root_node);
+
+        let descendants: Vec<NodeId> = arena.descendants_iter(root).unwrap().collect();
+        assert_eq!(descendants, vec![a, a1, a2, b]);
+        assert_eq!(arena.descendants_iter(b).unwrap().count(), 0);
+        assert!(arena.descendants_iter(999).is_err());
+
+        let children: Vec<NodeId> = arena.children_iter(root).unwrap().collect();
+        assert_eq!(children, vec![a, b]);
+
+        let first_p: Vec<NodeId> = arena.find_iter(|n| n.node_name == "p").take(1).collect();
+        assert_eq!(first_p, vec![a]);
+        assert_eq!(arena.find(|n| n.node_name == "p"), vec![a, b]);
+    }
+
+    #[test]
+    fn test_ancestors_and_closest() {
+        let mut arena = DomArena::new();
+        let mut parent = None;
+        let mut ids = Vec::new();
+        for depth in 0..50u32 {
+            let mut node = DomNode::new(
+                0,
+                depth,
+                NodeType::Element,
+                if depth % 10 == 0 { "section" } else { "div" }.to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            ids.push(id);
+            parent = Some(id);
+        }
+        let leaf = ids[49];
+
+        let ancestors: Vec<u32> = arena.ancestors(leaf).map(|n| n.backend_node_id).collect();
+        assert_eq!(ancestors, (0..49).rev().collect::<Vec<_>>());
+        assert_eq!(arena.ancestors(ids[0]).count(), 0);
+
+        assert_eq!(
+            arena.closest(leaf, |n| n.node_name == "section"),
+            Some(ids[40])
+        );
+        assert_eq!(
+            arena.closest(ids[40], |n| n.node_name == "section"),
+            Some(ids[40])
+        );
+        assert_eq!(arena.closest(leaf, |n| n.node_name == "span"), None);
+
+        // Detached: parent points past the end of the arena
+        let mut orphan = DomNode::new(
+            0,
+            999,
+            NodeType::Element,
+            "div".to_string(),
+            "target1".to_string(),
+        );
+        orphan.parent_id = Some(12345);
+        let orphan = arena.add_node(orphan);
+        assert_eq!(arena.ancestors(orphan).count(), 0);
+        assert_eq!(arena.closest(orphan, |n| n.node_name == "section"), None);
+
+        // Corrupted cycle terminates
+        arena.get_mut(ids[0]).unwrap().parent_id = Some(ids[1]);
+        assert!(arena.ancestors(ids[1]).count() <= arena.nodes.len());
+    }
+
+    #[test]
+    fn test_breadcrumb() {
+        let mut arena = DomArena::new();
+        let mut parent = None;
+        let mut ids = Vec::new();
+        for (backend_id, (tag, attrs)) in [
+            ("#document", &[][..]),
+            ("NAV", &[("id", "menu")][..]),
+            ("UL", &[][..]),
+            ("LI", &[("role", "tab"), ("id", "")][..]),
+            ("A", &[][..]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let node_type = if backend_id == 0 {
+                NodeType::Document
+            } else {
+                NodeType::Element
+            };
+            let mut node = DomNode::new(
+                0,
+                backend_id as u32,
+                node_type,
+                tag.to_string(),
+                "target1".to_string(),
+            );
+            for (name, value) in attrs {
+                node.attributes.insert(name.to_string(), value.to_string());
+            }
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            ids.push(id);
+            parent = Some(id);
+        }
+
+        assert_eq!(
+            arena.breadcrumb(ids[4], 10),
+            "nav#menu > ul > li[role=tab] > a"
+        );
+        assert_eq!(arena.breadcrumb(ids[4], 2), "... > li[role=tab] > a");
+        assert_eq!(arena.breadcrumb(ids[1], 1), "nav#menu");
+        assert_eq!(arena.breadcrumb(ids[0], 3), "");
+        assert_eq!(arena.breadcrumb(ids[4], 0), "");
+        assert_eq!(arena.breadcrumb(9999, 3), "");
+    }
+
+    #[test]
+    fn test_spatial_queries() {
+        let mut arena = DomArena::new();
+        let mut add = |rect: DomRect, paint_order: Option<i32>| {
+            let mut node = DomNode::new(
+                0,
+                arena.len() as u32,
+                NodeType::Element,
+                "div".to_string(),
+                "target1".to_string(),
+            );
+            node.absolute_position = Some(rect);
+            node.snapshot_node = Some(Box::new(SnapshotNode {
+                paint_order,
+                ..SnapshotNode::default()
+            }));
+            arena.add_node(node)
+        };
+        let page = add(DomRect::new(0.0, 0.0, 800.0, 600.0), Some(1));
+        let overlay = add(DomRect::new(100.0, 100.0, 200.0, 200.0), Some(9));
+        let card = add(DomRect::new(150.0, 150.0, 50.0, 50.0), Some(5));
+        let nested = add(DomRect::new(150.0, 150.0, 20.0, 20.0), Some(5));
+        let empty = add(DomRect::new(400.0, 400.0, 0.0, 30.0), Some(99));
+
+        assert_eq!(arena.element_at_point(10.0, 10.0), Some(page));
+        // The overlay paints over the card beneath it
+        assert_eq!(arena.element_at_point(160.0, 160.0), Some(overlay));
+        arena.get_mut(overlay).unwrap().absolute_position = None;
+        // Same paint order: the later node wins
+        assert_eq!(arena.element_at_point(160.0, 160.0), Some(nested));
+        assert_eq!(arena.element_at_point(190.0, 190.0), Some(card));
+        assert_eq!(arena.element_at_point(400.0, 410.0), Some(page));
+        assert_eq!(arena.element_at_point(900.0, 10.0), None);
+
+        assert_eq!(
+            arena.elements_in_rect(DomRect::new(140.0, 140.0, 15.0, 15.0)),
+            vec![page, card, nested]
+        );
+        assert!(!arena
+            .elements_in_rect(DomRect::new(390.0, 390.0, 20.0, 20.0))
+            .contains(&empty));
+    }
+
+    /// root -> [a -> [a1], b], with parent links
+    fn linked_tree(arena: &mut DomArena) -> [NodeId; 4] {
+        let mut add = |backend_id: u32, parent: Option<NodeId>| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                "div".to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            if let Some(parent) = parent {
+                arena.get_mut(parent).unwrap().children_ids.push(id);
+            }
+            id
+        };
+        let root = add(100, None);
+        let a = add(101, Some(root));
+        let a1 = add(102, Some(a));
+        let b = add(103, Some(root));
+        [root, a, a1, b]
+    }
+
+    #[test]
+    fn test_remove_subtree() {
+        let mut arena = DomArena::new();
+        let [root, a, a1, b] = linked_tree(&mut arena);
+
+        assert_eq!(arena.remove_subtree(a).unwrap(), 2);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(root).unwrap().children_ids.as_slice(), [b]);
+        assert!(arena.get(a1).is_err());
+        assert!(arena.get_node_id_by_backend(101).is_none());
+        assert!(arena.remove_subtree(a).is_err());
+
+        // Freed slots are reused, other IDs untouched
+        let reused = arena.add_node(DomNode::new(
+            0,
+            200,
+            NodeType::Element,
+            "span".to_string(),
+            "target1".to_string(),
+        ));
+        assert!(reused == a || reused == a1);
+        assert_eq!(arena.get(reused).unwrap().node_name, "span");
+        assert_eq!(arena.get_node_id_by_backend(200), Some(reused));
+        assert_eq!(arena.get(b).unwrap().backend_node_id, 103);
+        assert_eq!(arena.len(), 3);
+    }
+
+    #[test]
+    fn test_remove_subtree_takes_shadow_roots_and_frames() {
+        let mut arena = DomArena::new();
+        let [root, a, _, b] = linked_tree(&mut arena);
+        let mut add = |backend_id: u32, node_type: NodeType, parent: NodeId| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                node_type,
+                "#document".to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = Some(parent);
+            arena.add_node(node)
+        };
+        let shadow_root = add(200, NodeType::DocumentFragment, a);
+        let frame_document = add(201, NodeType::Document, b);
+        arena.get_mut(a).unwrap().shadow_root_ids = Some(smallvec::smallvec![shadow_root]);
+        arena.get_mut(b).unwrap().content_document_id = Some(frame_document);
+
+        assert_eq!(arena.remove_subtree(a).unwrap(), 3);
+        assert!(arena.is_tombstoned(shadow_root));
+
+        // Removing the document itself unlinks it from the iframe
+        assert_eq!(arena.remove_subtree(frame_document).unwrap(), 1);
+        assert_eq!(arena.get(b).unwrap().content_document_id, None);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(root).unwrap().children_ids.as_slice(), [b]);
+    }
+
+    #[test]
+    fn test_reparent() {
+        let mut arena = DomArena::new();
+        let [root, a, a1, b] = linked_tree(&mut arena);
+
+        arena.reparent(a1, b).unwrap();
+        assert!(arena.get(a).unwrap().children_ids.is_empty());
+        assert_eq!(arena.get(b).unwrap().children_ids.as_slice(), [a1]);
+        assert_eq!(arena.get(a1).unwrap().parent_id, Some(b));
+
+        assert!(matches!(
+            arena.reparent(root, a1),
+            Err(DomError::CycleDetected { .. })
+        ));
+        assert!(matches!(
+            arena.reparent(b, b),
+            Err(DomError::CycleDetected { .. })
+        ));
+        assert_eq!(arena.get(a1).unwrap().parent_id, Some(b));
     }
 }
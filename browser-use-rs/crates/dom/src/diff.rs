@@ -0,0 +1,313 @@
+//! DOM diffing - what changed between two captures of the same page
+//!
+//! Nodes are matched by `backend_node_id`, which Chrome keeps stable for a
+//! node's lifetime. Ids can be reused after the node is gone, so a match only
+//! counts if it also sits in the same place: same node type and name, under
+//! the node matched to its parent. Otherwise we fall back to position - the
+//! previous node with that name at the same index among its siblings.
+
+use crate::arena::DomArena;
+use crate::types::{DomNode, NodeId, NodeType};
+use ahash::{AHashMap, AHashSet};
+
+/// Result of `DomArena::diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomDiff {
+    /// Nodes of the current arena with no counterpart in the previous one
+    pub added: Vec<NodeId>,
+    /// Nodes of the previous arena with no counterpart in the current one
+    pub removed: Vec<NodeId>,
+    /// Nodes of the current arena whose value or attributes changed
+    pub changed: Vec<NodeId>,
+}
+
+impl DomDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether `node_id` (of the current arena) appeared since the previous capture
+    pub fn is_added(&self, node_id: NodeId) -> bool {
+        self.added.contains(&node_id)
+    }
+}
+
+/// Compare `current` against `previous`; see the module docs for matching
+pub(crate) fn diff(current: &DomArena, previous: &DomArena) -> DomDiff {
+    let previous_order = tree_order(previous);
+    let previous_parents: AHashMap<NodeId, Option<NodeId>> = previous_order
+        .iter()
+        .map(|&(id, parent)| (id, parent))
+        .collect();
+    // (parent, kind, index among the parent's children of that kind) -> node
+    let previous_by_position: AHashMap<(NodeId, Kind, usize), NodeId> =
+        kind_positions(previous, &previous_order)
+            .into_iter()
+            .filter_map(|(id, (kind, index))| {
+                let parent = previous_parents.get(&id).copied().flatten()?;
+                Some(((parent, kind, index), id))
+            })
+            .collect();
+    let current_order = tree_order(current);
+    let current_positions = kind_positions(current, &current_order);
+
+    let mut result = DomDiff::default();
+    // current node -> previous node
+    let mut matched: AHashMap<NodeId, NodeId> = AHashMap::new();
+    let mut claimed: AHashSet<NodeId> = AHashSet::new();
+
+    for (id, parent) in current_order {
+        let Ok(node) = current.get(id) else {
+            continue;
+        };
+        let previous_parent = parent.and_then(|parent| matched.get(&parent).copied());
+        // Under an added node everything is new
+        if parent.is_some() && previous_parent.is_none() {
+            result.added.push(id);
+            continue;
+        }
+
+        let by_backend_id = previous
+            .get_node_id_by_backend(node.backend_node_id)
+            .filter(|candidate| {
+                !claimed.contains(candidate)
+                    && previous_parents.get(candidate) == Some(&previous_parent)
+                    && previous
+                        .get(*candidate)
+                        .is_ok_and(|old| same_kind(old, node))
+            });
+        let counterpart = by_backend_id.or_else(|| {
+            let (kind, index) = current_positions.get(&id)?;
+            let candidate = *previous_by_position.get(&(previous_parent?, kind.clone(), *index))?;
+            (!claimed.contains(&candidate)).then_some(candidate)
+        });
+
+        match counterpart {
+            Some(old_id) => {
+                matched.insert(id, old_id);
+                claimed.insert(old_id);
+                if previous
+                    .get(old_id)
+                    .is_ok_and(|old| content_changed(old, node))
+                {
+                    result.changed.push(id);
+                }
+            }
+            None => result.added.push(id),
+        }
+    }
+
+    result.removed = previous_order
+        .into_iter()
+        .map(|(id, _)| id)
+        .filter(|id| !claimed.contains(id))
+        .collect();
+    result
+}
+
+/// Nodes reachable from the root(s) in document order, with the node they
+/// were reached from
+///
+/// Includes shadow roots and iframe documents. Without a root, every
+/// parentless node starts a tree.
+fn tree_order(arena: &DomArena) -> Vec<(NodeId, Option<NodeId>)> {
+    let roots: Vec<NodeId> = match arena.root_id() {
+        Some(root_id) => vec![root_id],
+        None => arena
+            .node_ids()
+            .filter(|&id| arena.get(id).is_ok_and(|node| node.parent_id.is_none()))
+            .collect(),
+    };
+
+    let mut order = Vec::with_capacity(arena.len());
+    let mut seen = AHashSet::new();
+    let mut stack: Vec<(NodeId, Option<NodeId>)> =
+        roots.into_iter().rev().map(|id| (id, None)).collect();
+    while let Some((id, parent)) = stack.pop() {
+        let Ok(node) = arena.get(id) else {
+            continue;
+        };
+        if !seen.insert(id) {
+            continue;
+        }
+        order.push((id, parent));
+        let children: Vec<NodeId> = structural_children(node).collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, Some(id))));
+    }
+    order
+}
+
+/// Shadow roots, then the iframe document, then light children
+fn structural_children(node: &DomNode) -> impl Iterator<Item = NodeId> + '_ {
+    node.shadow_root_ids
+        .iter()
+        .flatten()
+        .copied()
+        .chain(node.content_document_id)
+        .chain(node.children_ids.iter().copied())
+}
+
+fn same_kind(a: &DomNode, b: &DomNode) -> bool {
+    a.node_type == b.node_type && a.node_name.eq_ignore_ascii_case(&b.node_name)
+}
+
+fn content_changed(old: &DomNode, new: &DomNode) -> bool {
+    old.node_value != new.node_value || old.attributes != new.attributes
+}
+
+/// Node type and case-folded name, what `same_kind` compares
+type Kind = (NodeType, String);
+
+fn kind(node: &DomNode) -> Kind {
+    (node.node_type, node.node_name.to_ascii_uppercase())
+}
+
+/// Each node's kind and its index among its parent's children of that kind
+///
+/// Counted in one pass over each parent's children, so positional matching
+/// stays linear in wide sibling lists.
+fn kind_positions(
+    arena: &DomArena,
+    order: &[(NodeId, Option<NodeId>)],
+) -> AHashMap<NodeId, (Kind, usize)> {
+    let mut positions = AHashMap::with_capacity(order.len());
+    let mut counts: AHashMap<Kind, usize> = AHashMap::new();
+    for &(parent_id, _) in order {
+        let Ok(parent) = arena.get(parent_id) else {
+            continue;
+        };
+        counts.clear();
+        for child_id in structural_children(parent) {
+            let Ok(child) = arena.get(child_id) else {
+                continue;
+            };
+            let kind = kind(child);
+            let count = counts.entry(kind.clone()).or_default();
+            positions.insert(child_id, (kind, *count));
+            *count += 1;
+        }
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::NodeType;
+
+    /// Build `html -> body -> children`, each child `(backend_id, tag, text)`
+    fn page(children: &[(u32, &str, &str)]) -> (DomArena, Vec<NodeId>) {
+        let mut arena = DomArena::new();
+        let add = |arena: &mut DomArena, backend_id: u32, tag: &str, parent: Option<NodeId>| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                tag.to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            if let Some(parent) = parent {
+                arena.get_mut(parent).unwrap().children_ids.push(id);
+            }
+            id
+        };
+        let html = add(&mut arena, 1, "HTML", None);
+        let body = add(&mut arena, 2, "BODY", Some(html));
+        let ids = children
+            .iter()
+            .map(|&(backend_id, tag, text)| {
+                let id = add(&mut arena, backend_id, tag, Some(body));
+                arena
+                    .get_mut(id)
+                    .unwrap()
+                    .attributes
+                    .insert("aria-label".to_string(), text.to_string());
+                id
+            })
+            .collect();
+        arena.set_root(html).unwrap();
+        (arena, ids)
+    }
+
+    #[test]
+    fn test_diff_by_backend_id() {
+        let (before, old) = page(&[(10, "BUTTON", "Save"), (11, "A", "Help")]);
+        let (after, new) = page(&[
+            (12, "INPUT", "Name"),
+            (10, "BUTTON", "Saved"),
+            (13, "A", "Docs"),
+        ]);
+
+        let diff = after.diff(&before);
+        // Link 11 is gone; 13 takes its slot but by position, not by id
+        assert_eq!(diff.added, vec![new[0]]);
+        assert_eq!(diff.changed, vec![new[1], new[2]]);
+        assert!(diff.removed.is_empty());
+        assert!(diff.is_added(new[0]));
+        assert!(!diff.is_added(new[1]));
+
+        assert!(before.diff(&before).is_empty());
+        let (emptied, _) = page(&[]);
+        assert_eq!(emptied.diff(&before).removed, old);
+    }
+
+    #[test]
+    fn test_diff_rejects_reused_backend_ids() {
+        // After a navigation id 10 names a link, and the old button is gone
+        let (before, _) = page(&[(10, "BUTTON", "Save")]);
+        let (after, new) = page(&[(10, "A", "Home"), (11, "BUTTON", "Save")]);
+
+        let diff = after.diff(&before);
+        assert_eq!(diff.added, vec![new[0]]);
+        assert!(diff.changed.is_empty());
+        assert!(diff.removed.is_empty());
+
+        // Same id and tag, but under a different parent
+        let (mut moved, moved_ids) = page(&[(20, "DIV", "")]);
+        let mut button = DomNode::new(
+            0,
+            10,
+            NodeType::Element,
+            "BUTTON".to_string(),
+            "target1".to_string(),
+        );
+        button.parent_id = Some(moved_ids[0]);
+        button
+            .attributes
+            .insert("aria-label".to_string(), "Save".to_string());
+        let button = moved.add_node(button);
+        moved
+            .get_mut(moved_ids[0])
+            .unwrap()
+            .children_ids
+            .push(button);
+
+        let diff = moved.diff(&before);
+        assert_eq!(diff.added, vec![moved_ids[0], button]);
+        assert_eq!(diff.removed.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_matches_wide_sibling_lists_by_position() {
+        // Every backend id changed, so all 20k rows match by position
+        let rows = |first_id: u32| -> Vec<(u32, &str, &str)> {
+            (0..20_000)
+                .map(|i| (first_id + i, if i % 2 == 0 { "LI" } else { "li" }, ""))
+                .collect()
+        };
+        let (before, _) = page(&rows(10));
+        let (mut after, new) = page(&rows(100_000));
+        after
+            .get_mut(new[19_999])
+            .unwrap()
+            .attributes
+            .insert("aria-label".to_string(), "last".to_string());
+
+        let diff = after.diff(&before);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed, vec![new[19_999]]);
+    }
+}
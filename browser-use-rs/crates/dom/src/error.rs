@@ -11,6 +11,14 @@ pub enum DomError {
     #[error("Node not found: {0}")]
     NodeNotFound(u32),
 
+    /// `NodeNotFound` with what was being done when the lookup failed
+    #[error("Node not found: {node_id} (while {context})")]
+    NodeNotFoundInContext { node_id: u32, context: String },
+
+    /// No node with this CDP backend node ID
+    #[error("No node with backend node id {0}")]
+    BackendNodeNotFound(u32),
+
     #[error("Invalid node type: expected {expected}, got {actual}")]
     InvalidNodeType { expected: String, actual: String },
 
@@ -29,9 +37,45 @@ pub enum DomError {
     #[error("Timeout waiting for page: {0}")]
     Timeout(String),
 
+    #[error("Invalid selector: {0}")]
+    InvalidSelector(String),
+
+    #[error("Moving node {node} under {new_parent} would create a cycle")]
+    CycleDetected { node: u32, new_parent: u32 },
+
+    #[error("DOM tree deeper than {max} levels")]
+    MaxTreeDepthExceeded { max: usize },
+
     #[error("Maximum iframe depth exceeded: {current} > {max}")]
     MaxIframeDepthExceeded { current: usize, max: usize },
 
     #[error("Maximum iframe count exceeded: {current} > {max}")]
     MaxIframeCountExceeded { current: usize, max: usize },
 }
+
+impl DomError {
+    /// Say what was being done when a node lookup failed
+    ///
+    /// Turns `NodeNotFound` into `NodeNotFoundInContext`. Other errors, and
+    /// ones that already have context from deeper down, pass through, so the
+    /// innermost context wins.
+    pub fn context(self, context: impl FnOnce() -> String) -> Self {
+        match self {
+            Self::NodeNotFound(node_id) => Self::NodeNotFoundInContext {
+                node_id,
+                context: context(),
+            },
+            other => other,
+        }
+    }
+
+    /// The node a lookup failed for, with or without context
+    pub fn missing_node(&self) -> Option<u32> {
+        match self {
+            Self::NodeNotFound(node_id) | Self::NodeNotFoundInContext { node_id, .. } => {
+                Some(*node_id)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! HTML export - the parsed tree written back out as markup
+//!
+//! Unlike `DomSerializer`, nothing is filtered: every node is written, visible
+//! or not, with all its attributes. Attributes are sorted by name so output
+//! is stable across captures. Open and closed shadow roots become declarative
+//! `<template shadowrootmode>` elements; user-agent shadow roots and iframe
+//! documents aren't part of the page source and are left out (export a
+//! frame on its own with `DomArena::outer_html(content_document_id)`).
+
+use crate::arena::DomArena;
+use crate::error::Result;
+use crate::types::{NodeId, NodeType, ShadowRootType};
+use crate::utils::{escape_attribute, escape_text};
+
+/// Elements that never have content or an end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose text is written verbatim, not escaped
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "noscript", "xmp", "iframe", "noembed"];
+
+/// Write `node_id` and its subtree to `out`
+pub(crate) fn write_node(arena: &DomArena, node_id: NodeId, out: &mut String) -> Result<()> {
+    let node = arena.get(node_id)?;
+    match node.node_type {
+        NodeType::Document | NodeType::DocumentFragment => write_children(arena, node_id, out)?,
+        NodeType::DocumentType => {
+            out.push_str("<!DOCTYPE ");
+            out.push_str(&node.node_name);
+            out.push('>');
+        }
+        NodeType::Element => write_element(arena, node_id, out)?,
+        NodeType::Text => {
+            let raw = node
+                .parent_id
+                .and_then(|parent| arena.get(parent).ok())
+                .is_some_and(|parent| {
+                    RAW_TEXT_ELEMENTS.contains(&tag_name(&parent.node_name).as_str())
+                });
+            if raw {
+                out.push_str(&node.node_value);
+            } else {
+                out.push_str(&escape_text(&node.node_value));
+            }
+        }
+        NodeType::CdataSection => {
+            out.push_str("<![CDATA[");
+            out.push_str(&node.node_value);
+            out.push_str("]]>");
+        }
+        NodeType::Comment => {
+            out.push_str("<!--");
+            out.push_str(&node.node_value);
+            out.push_str("-->");
+        }
+        NodeType::ProcessingInstruction => {
+            out.push_str("<?");
+            out.push_str(&node.node_name);
+            if !node.node_value.is_empty() {
+                out.push(' ');
+                out.push_str(&node.node_value);
+            }
+            out.push('>');
+        }
+        // Attributes, entities and notations are never in a document tree
+        _ => {}
+    }
+    Ok(())
+}
+
+fn write_element(arena: &DomArena, node_id: NodeId, out: &mut String) -> Result<()> {
+    let node = arena.get(node_id)?;
+    let tag = tag_name(&node.node_name);
+    out.push('<');
+    out.push_str(&tag);
+
+    let mut attributes: Vec<_> = node.attributes.iter().collect();
+    attributes.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    for (name, value) in attributes {
+        out.push(' ');
+        out.push_str(name);
+        if !value.is_empty() {
+            out.push_str("=\"");
+            out.push_str(&escape_attribute(value));
+            out.push('"');
+        }
+    }
+
+    if VOID_ELEMENTS.contains(&tag.as_str()) {
+        out.push_str(" />");
+        return Ok(());
+    }
+    out.push('>');
+
+    for &shadow_root_id in node.shadow_root_ids.iter().flatten() {
+        let mode = match arena.get(shadow_root_id)?.shadow_root_type {
+            Some(ShadowRootType::Open) => "open",
+            Some(ShadowRootType::Closed) => "closed",
+            Some(ShadowRootType::UserAgent) | None => continue,
+        };
+        out.push_str("<template shadowrootmode=\"");
+        out.push_str(mode);
+        out.push_str("\">");
+        write_children(arena, shadow_root_id, out)?;
+        out.push_str("</template>");
+    }
+    write_children(arena, node_id, out)?;
+
+    out.push_str("</");
+    out.push_str(&tag);
+    out.push('>');
+    Ok(())
+}
+
+fn write_children(arena: &DomArena, node_id: NodeId, out: &mut String) -> Result<()> {
+    for child_id in arena.children_iter(node_id)? {
+        write_node(arena, child_id, out)?;
+    }
+    Ok(())
+}
+
+/// CDP reports HTML tags uppercase; SVG and MathML names keep their case
+fn tag_name(node_name: &str) -> String {
+    if node_name.bytes().any(|b| b.is_ascii_lowercase()) {
+        node_name.to_string()
+    } else {
+        node_name.to_ascii_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::DomService;
+
+    #[test]
+    fn test_to_html() {
+        let paragraph = serde_json::json!({
+            "nodeId": 9, "backendNodeId": 9, "nodeType": 1, "nodeName": "P",
+            "attributes": ["title", "say \"hi\" & <bye>", "class", "x", "hidden", ""],
+            "children": [
+                {"nodeId": 10, "backendNodeId": 10, "nodeType": 3, "nodeName": "#text", "nodeValue": "1 < 2 & 3"},
+                {"nodeId": 11, "backendNodeId": 11, "nodeType": 1, "nodeName": "BR"},
+                {"nodeId": 12, "backendNodeId": 12, "nodeType": 1, "nodeName": "IMG", "attributes": ["src", "a.png"]}
+            ]
+        });
+        let card = serde_json::json!({
+            "nodeId": 13, "backendNodeId": 13, "nodeType": 1, "nodeName": "X-CARD",
+            "shadowRoots": [{
+                "nodeId": 14, "backendNodeId": 14, "nodeType": 11, "nodeName": "#document-fragment",
+                "shadowRootType": "open",
+                "children": [{"nodeId": 15, "backendNodeId": 15, "nodeType": 1, "nodeName": "SLOT"}]
+            }],
+            "children": [{
+                "nodeId": 16, "backendNodeId": 16, "nodeType": 1, "nodeName": "svg",
+                "children": [{"nodeId": 17, "backendNodeId": 17, "nodeType": 1, "nodeName": "linearGradient"}]
+            }]
+        });
+        let input = serde_json::json!({
+            "nodeId": 18, "backendNodeId": 18, "nodeType": 1, "nodeName": "INPUT",
+            "shadowRoots": [{
+                "nodeId": 19, "backendNodeId": 19, "nodeType": 11, "nodeName": "#document-fragment",
+                "shadowRootType": "user-agent",
+                "children": [{"nodeId": 20, "backendNodeId": 20, "nodeType": 1, "nodeName": "DIV"}]
+            }]
+        });
+        let head = serde_json::json!({
+            "nodeId": 4, "backendNodeId": 4, "nodeType": 1, "nodeName": "HEAD",
+            "children": [{
+                "nodeId": 5, "backendNodeId": 5, "nodeType": 1, "nodeName": "STYLE",
+                "children": [{"nodeId": 6, "backendNodeId": 6, "nodeType": 3, "nodeName": "#text", "nodeValue": "a > b {}"}]
+            }]
+        });
+        let body = serde_json::json!({
+            "nodeId": 7, "backendNodeId": 7, "nodeType": 1, "nodeName": "BODY",
+            "children": [
+                {"nodeId": 8, "backendNodeId": 8, "nodeType": 8, "nodeName": "#comment", "nodeValue": " nav "},
+                paragraph,
+                card,
+                input
+            ]
+        });
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1, "backendNodeId": 1, "nodeType": 9, "nodeName": "#document",
+                "children": [
+                    {"nodeId": 2, "backendNodeId": 2, "nodeType": 10, "nodeName": "html"},
+                    {"nodeId": 3, "backendNodeId": 3, "nodeType": 1, "nodeName": "HTML", "children": [head, body]}
+                ]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        assert_eq!(
+            service.to_html(),
+            concat!(
+                "<!DOCTYPE html><html><head><style>a > b {}</style></head><body>",
+                "<!-- nav -->",
+                "<p class=\"x\" hidden title=\"say &quot;hi&quot; &amp; &lt;bye&gt;\">",
+                "1 &lt; 2 &amp; 3<br /><img src=\"a.png\" /></p>",
+                "<x-card><template shadowrootmode=\"open\"><slot></slot></template>",
+                "<svg><linearGradient></linearGradient></svg></x-card>",
+                "<input />",
+                "</body></html>",
+            )
+        );
+
+        let arena = service.arena();
+        let p = arena.find_by_tag("P")[0];
+        assert!(arena.outer_html(p).unwrap().starts_with("<p class=\"x\""));
+    }
+}
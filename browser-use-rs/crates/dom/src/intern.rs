@@ -0,0 +1,81 @@
+//! String interning for tag names and common attribute keys
+//!
+//! An interning arena (`DomArena::with_interning`) gives each distinct tag
+//! name and `STATIC_ATTRIBUTES` key one `Symbol`, so code that compares or
+//! groups nodes by them can work with integers instead of strings.
+
+use ahash::AHashMap;
+use std::sync::Arc;
+
+/// Index of a string in a `StringInterner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(pub u32);
+
+/// Deduplicating string table
+#[derive(Debug, Clone, Default)]
+pub struct StringInterner {
+    symbols: AHashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Symbol for `s`, adding it on first use
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(s) {
+            return symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let shared: Arc<str> = Arc::from(s);
+        self.strings.push(shared.clone());
+        self.symbols.insert(shared, symbol);
+        symbol
+    }
+
+    /// Symbol for `s`, if it has been interned
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// The string behind `symbol`
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings.get(symbol.0 as usize).map(|s| &**s)
+    }
+
+    /// Number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Approximate heap use: the string data plus both tables
+    pub fn heap_bytes(&self) -> usize {
+        self.strings.iter().map(|s| s.len()).sum::<usize>()
+            + self.strings.capacity() * std::mem::size_of::<Arc<str>>()
+            + self.symbols.capacity() * (std::mem::size_of::<(Arc<str>, Symbol)>() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_and_resolve() {
+        let mut interner = StringInterner::new();
+        let div = interner.intern("DIV");
+        assert_eq!(interner.intern("DIV"), div);
+        let span = interner.intern("SPAN");
+        assert_ne!(span, div);
+        assert_eq!(interner.resolve(div), Some("DIV"));
+        assert_eq!(interner.get("SPAN"), Some(span));
+        assert_eq!(interner.get("P"), None);
+        assert_eq!(interner.len(), 2);
+    }
+}
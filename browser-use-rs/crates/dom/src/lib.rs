@@ -18,15 +18,22 @@
 //! ```
 
 pub mod arena;
+pub mod diff;
 pub mod error;
+pub mod html;
+pub mod intern;
+pub mod selector;
 pub mod serializer;
 pub mod service;
 pub mod types;
 pub mod utils;
 
-pub use arena::DomArena;
+pub use arena::{ArenaStats, DomArena};
+pub use diff::DomDiff;
 pub use error::{DomError, Result};
-pub use service::DomService;
+pub use intern::{StringInterner, Symbol};
+pub use selector::Selector;
+pub use service::{DomService, DomState, ParseWarning};
 pub use types::*;
 
 #[cfg(test)]
@@ -0,0 +1,420 @@
+//! Minimal CSS selector matching against the arena
+//!
+//! Supports what automation scripts actually write:
+//! - `tag`, `*`, `#id`, `.class`, `[attr]`, `[attr=value]` (quoted or bare)
+//! - Compound selectors: `button.primary[type=submit]`
+//! - `:nth-of-type(n)` with a plain number
+//! - Descendant (`div span`) and child (`ul > li`) combinators
+//! - Selector lists: `input, textarea`
+//!
+//! Matching runs right-to-left over `parent_id`, so no per-query index is built.
+
+use crate::arena::DomArena;
+use crate::error::{DomError, Result};
+use crate::types::{DomNode, NodeId};
+
+/// Parsed selector list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<ComplexSelector>,
+}
+
+/// Compounds left-to-right; each carries the combinator linking it to the previous one
+#[derive(Debug, Clone, PartialEq)]
+struct ComplexSelector {
+    parts: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+    nth_of_type: Option<usize>,
+}
+
+impl Compound {
+    fn matches(&self, arena: &DomArena, node: &DomNode) -> bool {
+        if !node.is_element() {
+            return false;
+        }
+        if let Some(tag) = &self.tag {
+            if !node.node_name.eq_ignore_ascii_case(tag) {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if node.attr("id") != Some(id.as_str()) {
+                return false;
+            }
+        }
+        if !self.classes.is_empty() {
+            let class_attr = node.attr("class").unwrap_or("");
+            if !self
+                .classes
+                .iter()
+                .all(|class| class_attr.split_whitespace().any(|c| c == class))
+            {
+                return false;
+            }
+        }
+        if !self.attrs.iter().all(|(name, value)| match value {
+            Some(value) => node.attr(name) == Some(value.as_str()),
+            None => node.attributes.contains_key(name.as_str()),
+        }) {
+            return false;
+        }
+        self.nth_of_type
+            .is_none_or(|n| nth_of_type(arena, node) == n)
+    }
+}
+
+/// 1-based position of `node` among its parent's element children with the same tag
+pub(crate) fn nth_of_type(arena: &DomArena, node: &DomNode) -> usize {
+    let Some(parent) = node.parent_id.and_then(|id| arena.get(id).ok()) else {
+        return 1;
+    };
+    parent
+        .children_ids
+        .iter()
+        .filter_map(|&id| arena.get(id).ok())
+        .filter(|sibling| {
+            sibling.is_element() && sibling.node_name.eq_ignore_ascii_case(&node.node_name)
+        })
+        .position(|sibling| sibling.node_id == node.node_id)
+        .map_or(1, |p| p + 1)
+}
+
+/// Whether `value` can be written as a bare CSS identifier (`#value`, `.value`)
+pub(crate) fn is_css_ident(value: &str) -> bool {
+    let mut chars = value.chars();
+    let first = match chars.next() {
+        Some('-') => chars.next(),
+        first => first,
+    };
+    first.is_some_and(|c| is_ident_char(c) && !c.is_ascii_digit() && c != '-')
+        && chars.all(is_ident_char)
+}
+
+impl Selector {
+    /// Parse a selector string
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        };
+        let mut alternatives = vec![parser.complex()?];
+        loop {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => break,
+                Some(',') => {
+                    parser.pos += 1;
+                    parser.skip_whitespace();
+                    alternatives.push(parser.complex()?);
+                }
+                Some(c) => return Err(parser.error(&format!("unexpected '{}'", c))),
+            }
+        }
+        Ok(Self { alternatives })
+    }
+
+    /// Check if the node matches any selector in the list
+    pub fn matches(&self, arena: &DomArena, node_id: NodeId) -> bool {
+        self.alternatives
+            .iter()
+            .any(|complex| Self::match_from(arena, &complex.parts, node_id))
+    }
+
+    /// Match `parts` right-to-left, with the last part anchored at `node_id`
+    fn match_from(arena: &DomArena, parts: &[(Combinator, Compound)], node_id: NodeId) -> bool {
+        let Some(((combinator, compound), rest)) = parts.split_last() else {
+            return true;
+        };
+        let Ok(node) = arena.get(node_id) else {
+            return false;
+        };
+        if !compound.matches(arena, node) {
+            return false;
+        }
+        if rest.is_empty() {
+            return true;
+        }
+
+        match combinator {
+            Combinator::Child => node
+                .parent_id
+                .is_some_and(|parent_id| Self::match_from(arena, rest, parent_id)),
+            Combinator::Descendant => arena
+                .ancestor_ids(node_id)
+                .any(|ancestor_id| Self::match_from(arena, rest, ancestor_id)),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Returns true if any whitespace was consumed
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn error(&self, message: &str) -> DomError {
+        DomError::InvalidSelector(format!(
+            "{} at position {} in '{}'",
+            message,
+            self.pos,
+            self.chars.iter().collect::<String>()
+        ))
+    }
+
+    fn complex(&mut self) -> Result<ComplexSelector> {
+        let mut parts = vec![(Combinator::Descendant, self.compound()?)];
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                None | Some(',') => break,
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(_) if had_whitespace => Combinator::Descendant,
+                Some(c) => return Err(self.error(&format!("unexpected '{}'", c))),
+            };
+            parts.push((combinator, self.compound()?));
+        }
+        Ok(ComplexSelector { parts })
+    }
+
+    fn compound(&mut self) -> Result<Compound> {
+        let start = self.pos;
+        let mut compound = Compound::default();
+
+        if self.peek() == Some('*') {
+            self.pos += 1;
+        } else if self.peek().is_some_and(is_ident_char) {
+            compound.tag = Some(self.ident()?);
+        }
+
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    compound.id = Some(self.ident()?);
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    compound.classes.push(self.ident()?);
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    compound.attrs.push(self.attribute()?);
+                }
+                Some(':') => {
+                    self.pos += 1;
+                    compound.nth_of_type = Some(self.pseudo_class()?);
+                }
+                _ => break,
+            }
+        }
+
+        if self.pos == start {
+            return Err(self.error("expected selector"));
+        }
+        Ok(compound)
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_ident_char) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("expected identifier"));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Parse the rest of `:nth-of-type(n)` after the `:`
+    fn pseudo_class(&mut self) -> Result<usize> {
+        let name = self.ident()?;
+        if name != "nth-of-type" || self.peek() != Some('(') {
+            return Err(self.error(&format!("unsupported pseudo-class ':{}'", name)));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let n = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| self.error("expected a positive number"))?;
+        self.skip_whitespace();
+        if self.peek() != Some(')') {
+            return Err(self.error("expected ')'"));
+        }
+        self.pos += 1;
+        Ok(n)
+    }
+
+    /// Parse the rest of `[name]` or `[name=value]` after the `[`
+    fn attribute(&mut self) -> Result<(String, Option<String>)> {
+        self.skip_whitespace();
+        let name = self.ident()?;
+        self.skip_whitespace();
+
+        let value = match self.peek() {
+            Some(']') => None,
+            Some('=') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                let value = match self.peek() {
+                    Some(quote @ ('"' | '\'')) => {
+                        self.pos += 1;
+                        let start = self.pos;
+                        while self.peek().is_some_and(|c| c != quote) {
+                            self.pos += 1;
+                        }
+                        if self.peek().is_none() {
+                            return Err(self.error("unterminated string"));
+                        }
+                        let value: String = self.chars[start..self.pos].iter().collect();
+                        self.pos += 1;
+                        value
+                    }
+                    _ => self.ident()?,
+                };
+                self.skip_whitespace();
+                Some(value)
+            }
+            _ => return Err(self.error("expected ']' or '='")),
+        };
+
+        if self.peek() != Some(']') {
+            return Err(self.error("expected ']'"));
+        }
+        self.pos += 1;
+        Ok((name, value))
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::DomService;
+
+    fn sample_page() -> DomService {
+        let element =
+            |id: u32, tag: &str, attrs: serde_json::Value, children: serde_json::Value| {
+                serde_json::json!({
+                    "nodeId": id,
+                    "backendNodeId": id,
+                    "nodeType": 1,
+                    "nodeName": tag,
+                    "attributes": attrs,
+                    "children": children,
+                })
+            };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", serde_json::json!([]), serde_json::json!([
+                element(2, "FORM", serde_json::json!(["id", "login"]), serde_json::json!([
+                    element(3, "DIV", serde_json::json!(["class", "row"]), serde_json::json!([
+                        element(4, "INPUT", serde_json::json!(["name", "user", "type", "text"]), serde_json::json!([])),
+                    ])),
+                    element(5, "BUTTON", serde_json::json!(["class", "btn  primary", "type", "submit"]), serde_json::json!([])),
+                ])),
+                element(6, "BUTTON", serde_json::json!(["class", "btn"]), serde_json::json!([])),
+            ]))
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        service
+    }
+
+    fn backend_ids(arena: &DomArena, ids: Vec<NodeId>) -> Vec<u32> {
+        ids.into_iter()
+            .map(|id| arena.get(id).unwrap().backend_node_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_query_selector_all() {
+        let service = sample_page();
+        let arena = service.arena();
+        let query = |selector: &str| backend_ids(arena, arena.query_selector_all(selector));
+
+        assert_eq!(query("button"), vec![5, 6]);
+        assert_eq!(query("#login"), vec![2]);
+        assert_eq!(query(".btn.primary"), vec![5]);
+        assert_eq!(query("[type=submit]"), vec![5]);
+        assert_eq!(query("input[name=\"user\"]"), vec![4]);
+        assert_eq!(query("form button"), vec![5]);
+        assert_eq!(query("form input"), vec![4]);
+        assert_eq!(query("form > input"), Vec::<u32>::new());
+        assert_eq!(query("form > .row > input"), vec![4]);
+        assert_eq!(query("input, #login"), vec![2, 4]);
+        assert_eq!(query("*[name]"), vec![4]);
+        assert_eq!(query("button:nth-of-type(1)"), vec![5, 6]);
+        assert_eq!(query("body > button:nth-of-type(1)"), vec![6]);
+        assert_eq!(query("button:nth-of-type(2)"), Vec::<u32>::new());
+        assert_eq!(query("form > :nth-of-type(1)"), vec![3, 5]);
+
+        let first = arena.query_selector(".btn").unwrap();
+        assert_eq!(arena.get(first).unwrap().backend_node_id, 5);
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        for selector in [
+            "",
+            "div >",
+            "[name",
+            "[name=\"x]",
+            "a,,b",
+            "#",
+            "div!",
+            "a:hover",
+            "a:nth-of-type(0)",
+            "a:nth-of-type(2n)",
+        ] {
+            assert!(
+                matches!(Selector::parse(selector), Err(DomError::InvalidSelector(_))),
+                "{selector:?} should not parse"
+            );
+        }
+
+        let service = sample_page();
+        assert!(service.arena().query_selector_all("[name").is_empty());
+        assert!(service.arena().query_selector("div >").is_none());
+    }
+}
@@ -4,18 +4,94 @@
 //! - Filtering visible/interactive elements
 //! - Paint order optimization
 //! - Generating compact representation for LLMs
-//! - XPath generation for element identification
+//! - XPath and CSS selector generation for element identification
 
 use crate::arena::DomArena;
-use crate::error::Result;
+use crate::diff::DomDiff;
+use crate::error::{DomError, Result};
+use crate::selector;
 use crate::types::*;
+use crate::utils;
+use ahash::{AHashMap, AHashSet};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Interactive tags that take clicks for the interactive elements inside them
+const PROPAGATING_TAGS: &[&str] = &["a", "button"];
+
+/// Depth cap applied even without `max_depth`, so recursion can't overflow the stack
+const MAX_TREE_DEPTH: usize = 256;
+
+/// Emitted in place of nodes cut by `max_depth` / `max_nodes`
+const TRUNCATION_MARKER: &str = "<!-- truncated -->";
+
+/// Input types the browser renders as multi-part widgets (pickers, sliders)
+const COMPOUND_INPUT_TYPES: &[&str] = &[
+    "date",
+    "time",
+    "datetime-local",
+    "month",
+    "week",
+    "range",
+    "color",
+    "file",
+    "number",
+];
+
+/// Output format for `serialize`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Indented pseudo-HTML, one line per element
+    #[default]
+    Text,
+    /// JSON from `serialize_json`
+    Json,
+    /// Visible text blocks plus one `[N] tag "label"` line per interactive
+    /// element, no structural tags; see `compact_max_chars`
+    Compact,
+}
 
 /// Serializer configuration
 #[derive(Debug, Clone)]
 pub struct SerializerConfig {
+    /// Skip interactive elements covered by a later-painted interactive element
     pub paint_order_filtering: bool,
+    /// Attributes rendered on elements, in this order
     pub include_attributes: Vec<String>,
+    /// Rendered after `include_attributes`, e.g. a site's own test hook
+    pub extra_attributes: Vec<String>,
+    /// Never rendered, even if listed or matched by `include_all_data_attributes`
+    pub excluded_attributes: HashSet<String>,
+    /// Also render every `data-*` attribute, sorted by name
+    pub include_all_data_attributes: bool,
     pub max_text_length: usize,
+    /// Append viewport rect `@{x,y,w,h}` to interactive elements (spatial tasks)
+    pub include_bounds: bool,
+    /// Target chunk size in bytes for `serialize_chunked`
+    pub chunk_size: usize,
+    /// Render same-name radios/checkboxes as one grouped control
+    pub group_inputs: bool,
+    /// Prefix interactive elements with a `[N]` index the model can act on
+    pub index_interactive: bool,
+    /// What `serialize` and `serialize_chunked` produce
+    pub format: OutputFormat,
+    /// `Compact` only: stop once this many characters are written
+    pub compact_max_chars: Option<usize>,
+    /// Deepest element level rendered (root = 0); deeper subtrees become a marker
+    pub max_depth: Option<usize>,
+    /// Stop after this many elements and text nodes
+    pub max_nodes: Option<usize>,
+    /// Descend into shadow roots, rendered as `#shadow-root (mode)`
+    pub include_shadow_dom: bool,
+    /// Also render browser-internal shadow roots (input internals, media controls)
+    pub include_user_agent_shadow: bool,
+    /// Nested iframe documents to descend into, rendered as `#document`
+    pub max_iframe_depth: usize,
+    /// Let `generate_xpath` anchor on a unique `data-testid` as well as `id`
+    pub xpath_test_id_anchor: bool,
+    /// Don't treat elements in `aria-hidden="true"` or `inert` subtrees as
+    /// interactive; their content still renders
+    pub exclude_aria_hidden: bool,
 }
 
 impl Default for SerializerConfig {
@@ -26,7 +102,154 @@ impl Default for SerializerConfig {
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            extra_attributes: Vec::new(),
+            excluded_attributes: HashSet::new(),
+            include_all_data_attributes: false,
             max_text_length: 200,
+            include_bounds: false,
+            chunk_size: 16 * 1024,
+            group_inputs: true,
+            index_interactive: false,
+            format: OutputFormat::Text,
+            compact_max_chars: None,
+            max_depth: None,
+            max_nodes: None,
+            include_shadow_dom: true,
+            include_user_agent_shadow: false,
+            max_iframe_depth: 5,
+            xpath_test_id_anchor: false,
+            exclude_aria_hidden: false,
+        }
+    }
+}
+
+/// Per-walk state: output buffer that hands off full chunks at line
+/// boundaries, plus precomputed input groups
+struct WalkState<'a> {
+    buf: String,
+    chunk_size: usize,
+    sink: &'a mut dyn FnMut(String),
+
+    /// Radio/checkbox groups rendered as one control
+    groups: Vec<InputGroup>,
+    /// First option of each group → index into `groups`
+    group_heads: AHashMap<NodeId, usize>,
+    /// Remaining options, folded into their group's line
+    grouped_away: AHashSet<NodeId>,
+    /// Assigned `[N]` index → node, filled when `index_interactive` is on
+    selector_map: HashMap<usize, NodeId>,
+    /// Display decisions from `simplify`, by node
+    simplified: AHashMap<NodeId, SimplifiedNode>,
+    /// Elements and text nodes written so far, for `max_nodes`
+    emitted: usize,
+    /// Displayable nodes cut by the depth or node limits
+    omitted: usize,
+    /// Set once `max_nodes` is spent, so the marker is written only once
+    budget_exhausted: bool,
+    /// `Compact`: characters written so far
+    chars: usize,
+    /// `Compact`: text collected since the last block boundary
+    pending_text: String,
+}
+
+/// Inputs and output of one `simplify` pass
+struct SimplifyContext<'a> {
+    grouped_away: &'a AHashSet<NodeId>,
+    depth_limit: usize,
+    occluded: AHashSet<NodeId>,
+    /// Inside an `aria-hidden`/`inert` subtree, with `exclude_aria_hidden` on
+    in_hidden_subtree: bool,
+    out: Vec<SimplifiedNode>,
+}
+
+impl WalkState<'_> {
+    fn index_groups(&mut self, groups: Vec<InputGroup>) {
+        for (idx, group) in groups.iter().enumerate() {
+            let mut options = group.options.iter();
+            if let Some(head) = options.next() {
+                self.group_heads.insert(head.node_id, idx);
+            }
+            self.grouped_away.extend(options.map(|o| o.node_id));
+        }
+        self.groups = groups;
+    }
+
+    /// Render a group as a single control, if `node_id` heads one
+    fn group_line(&self, node_id: NodeId) -> Option<String> {
+        let group = &self.groups[*self.group_heads.get(&node_id)?];
+        let tag = match group.kind {
+            InputGroupKind::Radio => "radiogroup",
+            InputGroupKind::Checkbox => "checkboxgroup",
+        };
+        let options: Vec<String> = group
+            .options
+            .iter()
+            .map(|o| utils::escape_attribute(&o.value))
+            .collect();
+        let checked: Vec<String> = group
+            .checked()
+            .map(|o| utils::escape_attribute(&o.value))
+            .collect();
+
+        Some(format!(
+            "<{} name=\"{}\" options=\"{}\" checked=\"{}\" />",
+            tag,
+            utils::escape_attribute(&group.name),
+            options.join("|"),
+            checked.join("|")
+        ))
+    }
+
+    /// Assign the next index to `node_id`
+    fn assign_index(&mut self, node_id: NodeId) -> usize {
+        let index = self.selector_map.len();
+        self.selector_map.insert(index, node_id);
+        index
+    }
+
+    /// Write the `[N]` prefix and record the mapping
+    fn push_index(&mut self, node_id: NodeId) {
+        let index = self.assign_index(node_id);
+        self.buf.push_str(&format!("[{}]", index));
+    }
+
+    /// True once `max_nodes` is spent; records `node_id`'s subtree as omitted
+    ///
+    /// Returns `(exhausted, first)` where `first` marks the first cut node.
+    fn over_budget(
+        &mut self,
+        serializer: &DomSerializer,
+        arena: &DomArena,
+        node_id: NodeId,
+    ) -> (bool, bool) {
+        match serializer.config.max_nodes {
+            Some(max_nodes) if self.emitted >= max_nodes => {
+                let first = !std::mem::replace(&mut self.budget_exhausted, true);
+                self.omitted += serializer.displayable_subtree_size(arena, node_id);
+                (true, first)
+            }
+            _ => (false, false),
+        }
+    }
+
+    /// Called after each complete line - flush if the chunk is full
+    fn line_done(&mut self) {
+        if self.buf.len() >= self.chunk_size {
+            (self.sink)(std::mem::take(&mut self.buf));
+        }
+    }
+
+    /// Write a truncation marker line
+    fn push_marker(&mut self, indent: &str) {
+        self.buf.push_str(indent);
+        self.buf.push_str(TRUNCATION_MARKER);
+        self.buf.push('\n');
+        self.line_done();
+    }
+
+    fn finish(mut self) {
+        if !self.buf.is_empty() {
+            (self.sink)(std::mem::take(&mut self.buf));
         }
     }
 }
@@ -34,6 +257,8 @@ impl Default for SerializerConfig {
 /// DOM Tree Serializer
 pub struct DomSerializer {
     config: SerializerConfig,
+    /// Visibility of nodes whose `is_visible` isn't set, see `with_visibility`
+    visibility: AHashMap<NodeId, bool>,
 }
 
 impl DomSerializer {
@@ -42,78 +267,435 @@ impl DomSerializer {
     }
 
     pub fn with_config(config: SerializerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            visibility: AHashMap::new(),
+        }
+    }
+
+    /// Use `visibility` for nodes whose `is_visible` isn't set, so a tree
+    /// can be serialized without storing visibility in it first
+    pub(crate) fn with_visibility(mut self, visibility: AHashMap<NodeId, bool>) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Whether `node` is known to be hidden
+    fn is_hidden(&self, node_id: NodeId, node: &DomNode) -> bool {
+        node.is_visible
+            .or_else(|| self.visibility.get(&node_id).copied())
+            == Some(false)
     }
 
     /// Serialize DOM tree to string for LLM consumption
     pub fn serialize(&self, arena: &DomArena) -> Result<String> {
+        if self.config.format == OutputFormat::Json {
+            return self.serialize_json_string(arena);
+        }
+        Ok(self.serialize_with_omitted(arena)?.0)
+    }
+
+    /// `serialize`, plus the number of nodes cut by `max_depth` / `max_nodes`
+    pub fn serialize_with_omitted(&self, arena: &DomArena) -> Result<(String, usize)> {
+        if self.config.format == OutputFormat::Json {
+            let (value, omitted) = self.json_with_omitted(arena)?;
+            return Ok((Self::json_string(&value)?, omitted));
+        }
+        let mut output = String::with_capacity(4096);
+        let (_, omitted) = self.walk(arena, usize::MAX, &mut |chunk| output.push_str(&chunk))?;
+        Ok((output, omitted))
+    }
+
+    /// Serialize with `[N]` indices on interactive elements
+    ///
+    /// Returns the output plus the index → `NodeId` map used to resolve the
+    /// model's chosen index. Indices follow document order, so the same arena
+    /// always produces the same map.
+    pub fn serialize_indexed(&self, arena: &DomArena) -> Result<(String, HashMap<usize, NodeId>)> {
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            index_interactive: true,
+            ..self.config.clone()
+        })
+        .with_visibility(self.visibility.clone());
         let mut output = String::with_capacity(4096);
+        let (selector_map, _) =
+            serializer.walk(arena, usize::MAX, &mut |chunk| output.push_str(&chunk))?;
+        Ok((output, selector_map))
+    }
+
+    /// Serialize in chunks of roughly `config.chunk_size` bytes
+    ///
+    /// Chunks break on line boundaries and concatenate to exactly the
+    /// `serialize` output. Lets callers start feeding a model (or a channel)
+    /// before a huge page finishes serializing.
+    pub fn serialize_chunked<F>(&self, arena: &DomArena, mut on_chunk: F) -> Result<()>
+    where
+        F: FnMut(String),
+    {
+        // JSON isn't line-oriented - it goes out as one chunk
+        if self.config.format == OutputFormat::Json {
+            on_chunk(self.serialize_json_string(arena)?);
+            return Ok(());
+        }
+        self.walk(arena, self.config.chunk_size.max(1), &mut on_chunk)
+            .map(|_| ())
+    }
+
+    /// Serialize displayed nodes as a JSON array of top-level nodes
+    ///
+    /// Elements carry `tag`, `attributes` (filtered by `include_attributes`),
+    /// `index` when interactive, `bounds` when known, and `children`; text
+    /// nodes are `{"text": ...}`. Filtering and indices match `serialize_indexed`.
+    /// Nodes cut by `max_depth` / `max_nodes` become one `{"truncated": true}`.
+    pub fn serialize_json(&self, arena: &DomArena) -> Result<Value> {
+        Ok(self.json_with_omitted(arena)?.0)
+    }
+
+    /// `serialize_json`, plus the number of nodes cut by `max_depth` / `max_nodes`
+    fn json_with_omitted(&self, arena: &DomArena) -> Result<(Value, usize)> {
+        let mut sink = |_: String| {};
+        let mut state = self.walk_state(arena, usize::MAX, &mut sink);
+        let mut nodes = Vec::new();
+        if let Some(root_id) = arena.root_id() {
+            self.json_node(arena, root_id, 0, 0, &mut state, &mut nodes)?;
+        }
+        Ok((Value::Array(nodes), state.omitted))
+    }
+
+    fn serialize_json_string(&self, arena: &DomArena) -> Result<String> {
+        Self::json_string(&self.serialize_json(arena)?)
+    }
+
+    fn json_string(value: &Value) -> Result<String> {
+        serde_json::to_string(value).map_err(|e| DomError::SerializationError(e.to_string()))
+    }
+
+    /// Append the JSON for a node (or its children, for the root document) to `out`
+    fn json_node(
+        &self,
+        arena: &DomArena,
+        node_id: NodeId,
+        depth: usize,
+        frame_depth: usize,
+        state: &mut WalkState<'_>,
+        out: &mut Vec<Value>,
+    ) -> Result<()> {
+        let node = arena.get(node_id)?;
+        let Some(info) = state.simplified.get(&node_id) else {
+            return Ok(());
+        };
+        if !info.should_display {
+            if info.ignored_by_paint_order {
+                out.extend(self.json_children(arena, node, depth, frame_depth, state)?);
+            }
+            return Ok(());
+        }
+        let indexed = info.is_interactive && !info.excluded_by_parent;
+
+        if !Self::is_document_like(node) {
+            match state.over_budget(self, arena, node_id) {
+                (true, true) => {
+                    out.push(json!({ "truncated": true }));
+                    return Ok(());
+                }
+                (true, false) => return Ok(()),
+                _ => state.emitted += 1,
+            }
+        }
+
+        match node.node_type {
+            NodeType::Element => {
+                let mut object = Map::new();
+                if let Some(group) = state
+                    .group_heads
+                    .get(&node_id)
+                    .map(|&idx| &state.groups[idx])
+                {
+                    let tag = match group.kind {
+                        InputGroupKind::Radio => "radiogroup",
+                        InputGroupKind::Checkbox => "checkboxgroup",
+                    };
+                    object.insert("tag".into(), json!(tag));
+                    object.insert("name".into(), json!(group.name));
+                    object.insert(
+                        "options".into(),
+                        json!(group.options.iter().map(|o| &o.value).collect::<Vec<_>>()),
+                    );
+                    object.insert(
+                        "checked".into(),
+                        json!(group.checked().map(|o| &o.value).collect::<Vec<_>>()),
+                    );
+                } else {
+                    object.insert("tag".into(), json!(node.node_name));
+                    let attributes: Map<String, Value> = self
+                        .rendered_attributes(node)
+                        .into_iter()
+                        .map(|(name, value)| (name.to_string(), json!(self.clean_text(value))))
+                        .collect();
+                    if !attributes.is_empty() {
+                        object.insert("attributes".into(), Value::Object(attributes));
+                    }
+                    if node.is_disabled() {
+                        object.insert("disabled".into(), json!(true));
+                    }
+                    if node.is_readonly() {
+                        object.insert("readonly".into(), json!(true));
+                    }
+                }
+                if indexed {
+                    object.insert("index".into(), json!(state.assign_index(node_id)));
+                }
+                if let Some(rect) = Self::viewport_rect(node) {
+                    object.insert("bounds".into(), json!(rect));
+                }
+
+                if !state.group_heads.contains_key(&node_id) {
+                    let children =
+                        self.json_children(arena, node, depth + 1, frame_depth, state)?;
+                    if !children.is_empty() {
+                        object.insert("children".into(), Value::Array(children));
+                    }
+                }
+                out.push(Value::Object(object));
+            }
+            NodeType::Document | NodeType::DocumentFragment if node.parent_id.is_some() => {
+                let children = self.json_children(arena, node, depth + 1, frame_depth, state)?;
+                out.push(json!({
+                    "boundary": Self::boundary_label(node),
+                    "children": children,
+                }));
+            }
+            NodeType::Text => {
+                let text = self.clean_text(&node.node_value);
+                if !text.is_empty() {
+                    out.push(json!({ "text": text }));
+                }
+            }
+            NodeType::Document => {
+                out.extend(self.json_children(arena, node, depth, frame_depth, state)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// JSON for `node`'s rendered children at `depth`, or a marker past the depth limit
+    fn json_children(
+        &self,
+        arena: &DomArena,
+        node: &DomNode,
+        depth: usize,
+        frame_depth: usize,
+        state: &mut WalkState<'_>,
+    ) -> Result<Vec<Value>> {
+        let mut children = Vec::new();
+        if depth > self.depth_limit() {
+            let omitted = self.displayable_children_size(arena, node);
+            if omitted > 0 {
+                state.omitted += omitted;
+                children.push(json!({ "truncated": true }));
+            }
+            return Ok(children);
+        }
+        for (child_id, child_frame_depth) in self.render_children(arena, node, frame_depth) {
+            self.json_node(
+                arena,
+                child_id,
+                depth,
+                child_frame_depth,
+                state,
+                &mut children,
+            )
+            .map_err(|e| e.context(|| children_context(node)))?;
+        }
+        Ok(children)
+    }
+
+    /// Walk from the root, emitting output through `sink`
+    ///
+    /// Returns the interactive index map (empty unless `index_interactive`)
+    /// and the number of omitted nodes.
+    fn walk(
+        &self,
+        arena: &DomArena,
+        chunk_size: usize,
+        sink: &mut dyn FnMut(String),
+    ) -> Result<(HashMap<usize, NodeId>, usize)> {
+        let mut state = self.walk_state(arena, chunk_size, sink);
 
         if let Some(root_id) = arena.root_id() {
-            self.serialize_node(arena, root_id, 0, &mut output)?;
+            if self.config.format == OutputFormat::Compact {
+                self.compact_node(arena, root_id, 0, false, &mut state)?;
+                self.flush_compact_text(&mut state);
+            } else {
+                self.serialize_node(arena, root_id, 0, 0, &mut state)?;
+            }
         }
 
-        Ok(output)
+        let selector_map = std::mem::take(&mut state.selector_map);
+        let omitted = state.omitted;
+        state.finish();
+        Ok((selector_map, omitted))
+    }
+
+    /// Precompute groups and display decisions for a walk
+    fn walk_state<'a>(
+        &self,
+        arena: &DomArena,
+        chunk_size: usize,
+        sink: &'a mut dyn FnMut(String),
+    ) -> WalkState<'a> {
+        let mut state = WalkState {
+            buf: String::with_capacity(chunk_size.min(4096)),
+            chunk_size,
+            sink,
+            groups: Vec::new(),
+            group_heads: AHashMap::new(),
+            grouped_away: AHashSet::new(),
+            selector_map: HashMap::new(),
+            simplified: AHashMap::new(),
+            emitted: 0,
+            omitted: 0,
+            budget_exhausted: false,
+            chars: 0,
+            pending_text: String::new(),
+        };
+
+        if self.config.group_inputs {
+            state.index_groups(utils::find_input_groups(arena));
+        }
+        state.simplified = self
+            .simplify(arena, &state.grouped_away)
+            .into_iter()
+            .map(|node| (node.node_id, node))
+            .collect();
+        state
     }
 
     /// Serialize a single node recursively
+    ///
+    /// `frame_depth` counts the iframe documents entered so far.
     fn serialize_node(
         &self,
         arena: &DomArena,
         node_id: NodeId,
         depth: usize,
-        output: &mut String,
+        frame_depth: usize,
+        state: &mut WalkState<'_>,
     ) -> Result<()> {
         let node = arena.get(node_id)?;
 
-        // Skip invisible nodes (but allow None visibility to pass through)
-        if node.is_visible == Some(false) {
+        // Hidden, occluded and grouped-away nodes were decided by `simplify`
+        let Some(info) = state.simplified.get(&node_id) else {
+            return Ok(());
+        };
+        if !info.should_display {
+            // An occluded element's children render in its place
+            if info.ignored_by_paint_order {
+                self.serialize_children(arena, node, depth, frame_depth, state)?;
+            }
             return Ok(());
         }
+        let indexed =
+            self.config.index_interactive && info.is_interactive && !info.excluded_by_parent;
 
         // Add indentation
         let indent = "  ".repeat(depth);
 
+        if !Self::is_document_like(node) {
+            match state.over_budget(self, arena, node_id) {
+                (true, true) => {
+                    state.push_marker(&indent);
+                    return Ok(());
+                }
+                (true, false) => return Ok(()),
+                _ => state.emitted += 1,
+            }
+        }
+
         match node.node_type {
             NodeType::Element => {
-                // Format: <tag id="123" class="foo">
-                output.push_str(&indent);
-                output.push('<');
-                output.push_str(&node.node_name);
-
-                // Add relevant attributes
-                for attr_name in &self.config.include_attributes {
-                    if let Some(attr_value) = node.attr(attr_name) {
-                        output.push_str(&format!(" {}=\"{}\"", attr_name, attr_value));
+                // Radio/checkbox groups render once, at their first option
+                if let Some(line) = state.group_line(node_id) {
+                    state.buf.push_str(&indent);
+                    if indexed {
+                        state.push_index(node_id);
                     }
+                    state.buf.push_str(&line);
+                    state.buf.push('\n');
+                    state.line_done();
+                    return Ok(());
+                }
+
+                // Format: <tag id="123" class="foo">
+                state.buf.push_str(&indent);
+                if indexed {
+                    state.push_index(node_id);
                 }
+                state.buf.push('<');
+                state.buf.push_str(&node.node_name);
 
-                output.push_str(">\n");
+                // Add relevant attributes (disabled/readonly render as states below)
+                for (attr_name, attr_value) in self.rendered_attributes(node) {
+                    state.buf.push_str(&format!(
+                        " {}=\"{}\"",
+                        attr_name,
+                        utils::escape_attribute(&self.clean_text(attr_value))
+                    ));
+                }
+
+                // Agents shouldn't click disabled elements or type into readonly ones
+                if node.is_disabled() {
+                    state.buf.push_str(" disabled");
+                }
+                if node.is_readonly() {
+                    state.buf.push_str(" readonly");
+                }
 
-                // Serialize children
-                for &child_id in &node.children_ids {
-                    self.serialize_node(arena, child_id, depth + 1, output)?;
+                if self.config.include_bounds && node.is_clickable() {
+                    if let Some(rect) = Self::viewport_rect(node) {
+                        state.buf.push_str(&format!(
+                            " @{{{},{},{},{}}}",
+                            rect.x.round() as i64,
+                            rect.y.round() as i64,
+                            rect.width.round() as i64,
+                            rect.height.round() as i64
+                        ));
+                    }
                 }
 
+                state.buf.push_str(">\n");
+                state.line_done();
+
+                self.serialize_children(arena, node, depth + 1, frame_depth, state)?;
+
                 // Closing tag
-                output.push_str(&indent);
-                output.push_str("</");
-                output.push_str(&node.node_name);
-                output.push_str(">\n");
+                state.buf.push_str(&indent);
+                state.buf.push_str("</");
+                state.buf.push_str(&node.node_name);
+                state.buf.push_str(">\n");
+                state.line_done();
             }
             NodeType::Text => {
-                let text = node.node_value.trim();
+                let text = self.clean_text(&node.node_value);
                 if !text.is_empty() {
-                    output.push_str(&indent);
-                    output.push_str(text);
-                    output.push('\n');
+                    state.buf.push_str(&indent);
+                    state.buf.push_str(&utils::escape_text(&text));
+                    state.buf.push('\n');
+                    state.line_done();
                 }
             }
+            // Shadow roots and iframe documents: a boundary line, contents one level in
+            NodeType::Document | NodeType::DocumentFragment if node.parent_id.is_some() => {
+                state.buf.push_str(&indent);
+                state.buf.push_str(&Self::boundary_label(node));
+                state.buf.push('\n');
+                state.line_done();
+                self.serialize_children(arena, node, depth + 1, frame_depth, state)?;
+            }
             NodeType::Document => {
-                // For document nodes, just serialize children
-                for &child_id in &node.children_ids {
-                    self.serialize_node(arena, child_id, depth, output)?;
-                }
+                // For the root document, just serialize children
+                self.serialize_children(arena, node, depth, frame_depth, state)?;
             }
             _ => {
                 // Skip other node types for now
@@ -123,92 +705,766 @@ impl DomSerializer {
         Ok(())
     }
 
-    /// Generate XPath for a node
-    pub fn generate_xpath(&self, arena: &DomArena, node_id: NodeId) -> Result<String> {
-        let mut path_parts = Vec::new();
-        let mut current_id = Some(node_id);
-
-        while let Some(id) = current_id {
-            let node = arena.get(id)?;
-
-            if node.node_type == NodeType::Element {
-                // Get position among siblings with same tag name
-                let position = if let Some(parent_id) = node.parent_id {
-                    let parent = arena.get(parent_id)?;
-                    parent
-                        .children_ids
-                        .iter()
-                        .filter_map(|&child_id| arena.get(child_id).ok())
-                        .filter(|child| {
-                            child.node_type == NodeType::Element
-                                && child.node_name == node.node_name
-                        })
-                        .position(|child| child.node_id == node.node_id)
-                        .map(|p| p + 1) // XPath is 1-indexed
-                        .unwrap_or(1)
-                } else {
-                    1
-                };
+    /// `Compact` output for a node: interactive elements get an indexed line,
+    /// text gathers into one line per block
+    ///
+    /// `labelled` is set under an interactive element, whose label already
+    /// carries the text. `max_depth` and `max_nodes` don't apply.
+    fn compact_node(
+        &self,
+        arena: &DomArena,
+        node_id: NodeId,
+        frame_depth: usize,
+        labelled: bool,
+        state: &mut WalkState<'_>,
+    ) -> Result<()> {
+        if state.budget_exhausted {
+            return Ok(());
+        }
+        let node = arena.get(node_id)?;
+        let Some(info) = state.simplified.get(&node_id) else {
+            return Ok(());
+        };
+        if !info.should_display {
+            if info.ignored_by_paint_order {
+                for (child_id, child_frame_depth) in self.render_children(arena, node, frame_depth)
+                {
+                    self.compact_node(arena, child_id, child_frame_depth, labelled, state)
+                        .map_err(|e| e.context(|| children_context(node)))?;
+                }
+            }
+            return Ok(());
+        }
 
-                path_parts.push(format!("{}[{}]", node.node_name.to_lowercase(), position));
+        match node.node_type {
+            NodeType::Element => {
+                let indexed = info.is_interactive && !info.excluded_by_parent;
+                let block = indexed || utils::is_block_tag(&node.node_name);
+                if block {
+                    self.flush_compact_text(state);
+                }
+                if indexed {
+                    let line = match state.group_line(node_id) {
+                        Some(line) => line,
+                        None => self.compact_label(arena, node_id, node)?,
+                    };
+                    self.push_compact_line(state, Some(node_id), &line);
+                    if state.group_heads.contains_key(&node_id) {
+                        return Ok(());
+                    }
+                }
+                for (child_id, child_frame_depth) in self.render_children(arena, node, frame_depth)
+                {
+                    self.compact_node(
+                        arena,
+                        child_id,
+                        child_frame_depth,
+                        labelled || indexed,
+                        state,
+                    )
+                    .map_err(|e| e.context(|| children_context(node)))?;
+                }
+                if block {
+                    self.flush_compact_text(state);
+                }
             }
+            NodeType::Text if !labelled => {
+                let text = self.clean_text(&node.node_value);
+                if !text.is_empty() {
+                    if !state.pending_text.is_empty() {
+                        state.pending_text.push(' ');
+                    }
+                    state.pending_text.push_str(&text);
+                }
+            }
+            NodeType::Document | NodeType::DocumentFragment => {
+                self.flush_compact_text(state);
+                for (child_id, child_frame_depth) in self.render_children(arena, node, frame_depth)
+                {
+                    self.compact_node(arena, child_id, child_frame_depth, labelled, state)
+                        .map_err(|e| e.context(|| children_context(node)))?;
+                }
+                self.flush_compact_text(state);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-            current_id = node.parent_id;
+    /// `button "Submit"`: lowercase tag, then the element's text or, failing
+    /// that, the first of its naming attributes
+    fn compact_label(&self, arena: &DomArena, node_id: NodeId, node: &DomNode) -> Result<String> {
+        let options = utils::TextOptions {
+            newline_between_blocks: false,
+            max_len: Some(self.config.max_text_length),
+            ..utils::TextOptions::default()
+        };
+        let mut label = utils::get_text_content_opts(arena, node_id, &options)?;
+        if label.is_empty() {
+            label = ["aria-label", "placeholder", "title", "alt", "value", "name"]
+                .iter()
+                .find_map(|name| node.attr(name).filter(|v| !v.trim().is_empty()))
+                .map(|v| self.clean_text(v))
+                .unwrap_or_default();
         }
 
-        path_parts.reverse();
-        Ok(format!("/{}", path_parts.join("/")))
+        let mut line = node.node_name.to_ascii_lowercase();
+        if !label.is_empty() {
+            line.push_str(&format!(" \"{}\"", utils::escape_attribute(&label)));
+        }
+        if node.is_disabled() {
+            line.push_str(" disabled");
+        }
+        Ok(line)
     }
 
-    /// Filter elements by paint order (optimization)
-    pub fn filter_by_paint_order(&self, arena: &DomArena) -> Result<Vec<NodeId>> {
-        // TODO: Implement paint order filtering
-        // For now, return all visible elements
-        Ok(arena.find_visible())
+    /// Write the collected text as one line
+    fn flush_compact_text(&self, state: &mut WalkState<'_>) {
+        if !state.pending_text.is_empty() {
+            let text = utils::escape_text(&std::mem::take(&mut state.pending_text));
+            self.push_compact_line(state, None, &text);
+        }
     }
-}
 
-impl Default for DomSerializer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    /// Write a line, `[N]`-prefixed for `node_id`, unless it would overrun
+    /// `compact_max_chars` - then write the marker and stop the walk
+    fn push_compact_line(&self, state: &mut WalkState<'_>, node_id: Option<NodeId>, line: &str) {
+        if state.budget_exhausted {
+            return;
+        }
+        let prefix = match node_id {
+            Some(_) => format!("[{}] ", state.selector_map.len()),
+            None => String::new(),
+        };
+        let len = prefix.chars().count() + line.chars().count() + 1;
+        if self
+            .config
+            .compact_max_chars
+            .is_some_and(|max_chars| state.chars + len > max_chars)
+        {
+            state.budget_exhausted = true;
+            state.push_marker("");
+            return;
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::service::DomService;
+        if let Some(node_id) = node_id {
+            state.assign_index(node_id);
+        }
+        state.chars += len;
+        state.buf.push_str(&prefix);
+        state.buf.push_str(line);
+        state.buf.push('\n');
+        state.line_done();
+    }
 
-    #[test]
-    fn test_serialize_simple_dom() {
-        let cdp_json = serde_json::json!({
-            "root": {
-                "nodeId": 1,
-                "backendNodeId": 1,
-                "nodeType": 9,
-                "nodeName": "#document",
-                "nodeValue": "",
-                "children": [{
-                    "nodeId": 2,
-                    "backendNodeId": 2,
-                    "nodeType": 1,
-                    "nodeName": "HTML",
-                    "nodeValue": "",
-                    "attributes": [],
-                    "children": [{
-                        "nodeId": 3,
-                        "backendNodeId": 3,
-                        "nodeType": 3,
-                        "nodeName": "#text",
-                        "nodeValue": "Hello",
-                        "attributes": []
-                    }]
-                }]
+    /// Serialize `node`'s rendered children at `depth`, or a marker past the depth limit
+    fn serialize_children(
+        &self,
+        arena: &DomArena,
+        node: &DomNode,
+        depth: usize,
+        frame_depth: usize,
+        state: &mut WalkState<'_>,
+    ) -> Result<()> {
+        if depth > self.depth_limit() {
+            let omitted = self.displayable_children_size(arena, node);
+            if omitted > 0 {
+                state.omitted += omitted;
+                state.push_marker(&"  ".repeat(depth));
             }
-        });
+            return Ok(());
+        }
+        for (child_id, child_frame_depth) in self.render_children(arena, node, frame_depth) {
+            self.serialize_node(arena, child_id, depth, child_frame_depth, state)
+                .map_err(|e| e.context(|| children_context(node)))?;
+        }
+        Ok(())
+    }
 
-        let mut service = DomService::new();
-        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+    /// Children in render order - shadow roots, iframe document, then light
+    /// DOM - each with the iframe depth it's at
+    fn render_children(
+        &self,
+        arena: &DomArena,
+        node: &DomNode,
+        frame_depth: usize,
+    ) -> Vec<(NodeId, usize)> {
+        let mut children = Vec::with_capacity(node.children_ids.len());
+        if self.config.include_shadow_dom {
+            let shown = |&&id: &&NodeId| {
+                self.config.include_user_agent_shadow
+                    || arena.get(id).map_or(true, |root| {
+                        root.shadow_root_type != Some(ShadowRootType::UserAgent)
+                    })
+            };
+            children.extend(
+                node.shadow_root_ids
+                    .iter()
+                    .flatten()
+                    .filter(shown)
+                    .map(|&id| (id, frame_depth)),
+            );
+        }
+        if frame_depth < self.config.max_iframe_depth {
+            children.extend(node.content_document_id.map(|id| (id, frame_depth + 1)));
+        }
+        children.extend(node.children_ids.iter().map(|&id| (id, frame_depth)));
+        children
+    }
+
+    /// Documents and shadow roots - containers that don't count as output nodes
+    fn is_document_like(node: &DomNode) -> bool {
+        matches!(
+            node.node_type,
+            NodeType::Document | NodeType::DocumentFragment
+        )
+    }
+
+    /// `#shadow-root (open)` / `#document` line for a nested boundary
+    fn boundary_label(node: &DomNode) -> String {
+        match (node.node_type, node.shadow_root_type) {
+            (NodeType::DocumentFragment, Some(ShadowRootType::UserAgent)) => {
+                "#shadow-root (user-agent)".to_string()
+            }
+            (NodeType::DocumentFragment, Some(ShadowRootType::Closed)) => {
+                "#shadow-root (closed)".to_string()
+            }
+            (NodeType::DocumentFragment, _) => "#shadow-root (open)".to_string(),
+            _ => "#document".to_string(),
+        }
+    }
+
+    /// Per-node display decisions for the tree under the root, in document order
+    ///
+    /// The structured form of what `serialize` prints, for callers rendering
+    /// their own representation. `is_new` is always false - the serializer
+    /// keeps no state between calls; set it with `mark_new`.
+    pub fn build_simplified(&self, arena: &DomArena) -> Vec<SimplifiedNode> {
+        let mut grouped_away = AHashSet::new();
+        if self.config.group_inputs {
+            for group in utils::find_input_groups(arena) {
+                grouped_away.extend(group.options.iter().skip(1).map(|o| o.node_id));
+            }
+        }
+        self.simplify(arena, &grouped_away)
+    }
+
+    /// `build_simplified` with precomputed input groups
+    fn simplify(&self, arena: &DomArena, grouped_away: &AHashSet<NodeId>) -> Vec<SimplifiedNode> {
+        let mut ctx = SimplifyContext {
+            grouped_away,
+            occluded: if self.config.paint_order_filtering {
+                self.occluded_by_paint_order(arena)
+            } else {
+                AHashSet::new()
+            },
+            in_hidden_subtree: false,
+            out: Vec::with_capacity(arena.len()),
+            depth_limit: self.depth_limit(),
+        };
+        if let Some(root_id) = arena.root_id() {
+            self.simplify_node(arena, (root_id, 0), 0, true, None, &mut ctx);
+        }
+        ctx.out
+    }
+
+    /// Classify one node, then its children
+    ///
+    /// `clickable_rect` is the rect of the nearest `a`/`button` ancestor; an
+    /// interactive node inside it is `excluded_by_parent`.
+    fn simplify_node(
+        &self,
+        arena: &DomArena,
+        (node_id, frame_depth): (NodeId, usize),
+        depth: usize,
+        parent_displayed: bool,
+        clickable_rect: Option<DomRect>,
+        ctx: &mut SimplifyContext<'_>,
+    ) {
+        let Ok(node) = arena.get(node_id) else {
+            return;
+        };
+
+        // Occlusion hides the element itself, not what it contains
+        let ignored_by_paint_order = ctx.occluded.contains(&node_id);
+        let displayed = parent_displayed
+            && !self.is_hidden(node_id, node)
+            && match node.node_type {
+                NodeType::Element => !ctx.grouped_away.contains(&node_id),
+                NodeType::Text => !node.node_value.trim().is_empty(),
+                NodeType::Document | NodeType::DocumentFragment => true,
+                _ => false,
+            };
+        let should_display = displayed && !ignored_by_paint_order;
+        let entered_hidden = self.config.exclude_aria_hidden
+            && !ctx.in_hidden_subtree
+            && utils::is_aria_hidden_or_inert(node);
+        if entered_hidden {
+            ctx.in_hidden_subtree = true;
+        }
+        let is_interactive = node.is_element()
+            && !ignored_by_paint_order
+            && !ctx.in_hidden_subtree
+            && node.is_interactive();
+        let rect = Self::viewport_rect(node);
+        let excluded_by_parent = is_interactive
+            && matches!((clickable_rect, rect), (Some(parent), Some(rect)) if parent.contains(&rect));
+
+        ctx.out.push(SimplifiedNode {
+            node_id,
+            should_display,
+            is_interactive,
+            is_new: false,
+            ignored_by_paint_order,
+            excluded_by_parent,
+            is_shadow_host: node
+                .shadow_root_ids
+                .as_ref()
+                .is_some_and(|ids| !ids.is_empty()),
+            is_compound_component: Self::is_compound_component(node),
+        });
+
+        let propagates = is_interactive
+            && PROPAGATING_TAGS
+                .iter()
+                .any(|tag| node.node_name.eq_ignore_ascii_case(tag));
+        let clickable_rect = if propagates { rect } else { clickable_rect };
+        let child_depth = match node.node_type {
+            NodeType::Document if node.parent_id.is_none() => depth,
+            _ => depth + 1,
+        };
+        if child_depth <= ctx.depth_limit {
+            for child in self.render_children(arena, node, frame_depth) {
+                self.simplify_node(arena, child, child_depth, displayed, clickable_rect, ctx);
+            }
+        }
+        if entered_hidden {
+            ctx.in_hidden_subtree = false;
+        }
+    }
+
+    /// Effective `max_depth`, never above `MAX_TREE_DEPTH`
+    fn depth_limit(&self) -> usize {
+        self.config
+            .max_depth
+            .unwrap_or(MAX_TREE_DEPTH)
+            .min(MAX_TREE_DEPTH)
+    }
+
+    /// Elements and non-blank text under `node`'s children that aren't hidden
+    fn displayable_children_size(&self, arena: &DomArena, node: &DomNode) -> usize {
+        node.children_ids
+            .iter()
+            .map(|&child_id| self.displayable_subtree_size(arena, child_id))
+            .sum()
+    }
+
+    /// Elements and non-blank text in the subtree at `node_id` that aren't hidden
+    ///
+    /// Iterative, so it's safe on trees too deep to recurse.
+    fn displayable_subtree_size(&self, arena: &DomArena, node_id: NodeId) -> usize {
+        let displayable = |id: NodeId| {
+            arena.get(id).is_ok_and(|node| {
+                !self.is_hidden(id, node)
+                    && match node.node_type {
+                        NodeType::Element => true,
+                        NodeType::Text => !node.node_value.trim().is_empty(),
+                        _ => false,
+                    }
+            })
+        };
+        let descendants = arena
+            .descendants_iter(node_id)
+            .map(|iter| iter.filter(|&id| displayable(id)).count())
+            .unwrap_or(0);
+        usize::from(displayable(node_id)) + descendants
+    }
+
+    /// Native widgets with internal parts: selects, media, pickers, sliders
+    fn is_compound_component(node: &DomNode) -> bool {
+        match node.tag_name().map(str::to_ascii_lowercase).as_deref() {
+            Some("select" | "details" | "audio" | "video") => true,
+            Some("input") => node.attr("type").is_some_and(|input_type| {
+                COMPOUND_INPUT_TYPES
+                    .iter()
+                    .any(|t| input_type.eq_ignore_ascii_case(t))
+            }),
+            _ => false,
+        }
+    }
+
+    /// Attributes to render for `node`: `include_attributes`, then
+    /// `extra_attributes`, then (optionally) remaining `data-*` attributes,
+    /// minus `excluded_attributes`
+    ///
+    /// `disabled`/`readonly` are left out; they render as states.
+    fn rendered_attributes<'a>(&'a self, node: &'a DomNode) -> Vec<(&'a str, &'a str)> {
+        let mut seen: AHashSet<&str> = AHashSet::new();
+        let mut rendered: Vec<(&str, &str)> = self
+            .config
+            .include_attributes
+            .iter()
+            .chain(&self.config.extra_attributes)
+            .filter_map(|name| Some((name.as_str(), node.attr(name)?)))
+            .filter(|(name, _)| seen.insert(name))
+            .collect();
+
+        if self.config.include_all_data_attributes {
+            let mut data: Vec<(&str, &str)> = node
+                .attributes
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .filter(|(name, _)| name.starts_with("data-") && !seen.contains(name))
+                .collect();
+            data.sort_unstable_by_key(|(name, _)| *name);
+            rendered.extend(data);
+        }
+
+        rendered.retain(|(name, _)| {
+            !matches!(*name, "disabled" | "readonly")
+                && !self.config.excluded_attributes.contains(*name)
+        });
+        rendered
+    }
+
+    /// Single-line text capped at `max_text_length` characters
+    fn clean_text(&self, text: &str) -> String {
+        utils::cap_text_length(
+            &utils::normalize_whitespace(text),
+            self.config.max_text_length,
+        )
+    }
+
+    /// Viewport rect for geometry annotation (client rect, else absolute position)
+    fn viewport_rect(node: &DomNode) -> Option<DomRect> {
+        node.snapshot_node
+            .as_ref()
+            .and_then(|s| s.client_rects)
+            .or(node.absolute_position)
+    }
+
+    /// Generate XPath for a node
+    ///
+    /// Starts from the nearest element (the node itself or an ancestor) with
+    /// a unique `id` - `//*[@id='main']/div[2]` - falling back to a full
+    /// positional path from the root when there is none.
+    pub fn generate_xpath(&self, arena: &DomArena, node_id: NodeId) -> Result<String> {
+        let mut path_parts = Vec::new();
+
+        for node in std::iter::once(arena.get(node_id)?).chain(arena.ancestors(node_id)) {
+            if node.node_type == NodeType::Element {
+                if let Some(anchor) = self.xpath_anchor(arena, node) {
+                    path_parts.push(anchor);
+                    path_parts.reverse();
+                    return Ok(path_parts.join("/"));
+                }
+
+                // Get position among siblings with same tag name
+                let position = if let Some(parent_id) = node.parent_id {
+                    let parent = arena.get(parent_id)?;
+                    parent
+                        .children_ids
+                        .iter()
+                        .filter_map(|&child_id| arena.get(child_id).ok())
+                        .filter(|child| {
+                            child.node_type == NodeType::Element
+                                && child.node_name == node.node_name
+                        })
+                        .position(|child| child.node_id == node.node_id)
+                        .map(|p| p + 1) // XPath is 1-indexed
+                        .unwrap_or(1)
+                } else {
+                    1
+                };
+
+                path_parts.push(format!("{}[{}]", node.node_name.to_lowercase(), position));
+            }
+        }
+
+        path_parts.reverse();
+        Ok(format!("/{}", path_parts.join("/")))
+    }
+
+    /// Shortest CSS selector that matches only `node_id`
+    ///
+    /// `#id` when the id is unique, otherwise a `tag.class` chain joined by
+    /// `>`, with `:nth-of-type(n)` where siblings share a tag, walking up until
+    /// the chain is unique or reaches an element with a unique id.
+    pub fn generate_css_selector(&self, arena: &DomArena, node_id: NodeId) -> Result<String> {
+        let node = arena.get(node_id)?;
+        if !node.is_element() {
+            return Err(DomError::InvalidNodeType {
+                expected: "element".to_string(),
+                actual: format!("{:?}", node.node_type),
+            });
+        }
+
+        let unique = |steps: &[String]| {
+            let selector: Vec<&str> = steps.iter().rev().map(String::as_str).collect();
+            let selector = selector.join(" > ");
+            (arena.query_selector_all(&selector) == [node_id]).then_some(selector)
+        };
+
+        let mut steps = Vec::new();
+        let mut current = node;
+        loop {
+            if let Some(id) = current.attr("id").filter(|id| selector::is_css_ident(id)) {
+                let id_step = format!("#{}", id);
+                if arena.query_selector_all(&id_step).len() == 1 {
+                    steps.push(id_step);
+                    if let Some(selector) = unique(&steps) {
+                        return Ok(selector);
+                    }
+                    steps.pop();
+                }
+            }
+
+            let mut step = current.node_name.to_lowercase();
+            for class in current
+                .attr("class")
+                .unwrap_or("")
+                .split_whitespace()
+                .filter(|class| selector::is_css_ident(class))
+            {
+                step.push('.');
+                step.push_str(class);
+            }
+            steps.push(step);
+            if let Some(selector) = unique(&steps) {
+                return Ok(selector);
+            }
+
+            let parent = current.parent_id.and_then(|id| arena.get(id).ok());
+            let has_same_tag_siblings = parent.is_some_and(|parent| {
+                parent
+                    .children_ids
+                    .iter()
+                    .filter_map(|&id| arena.get(id).ok())
+                    .filter(|n| n.is_element() && n.node_name == current.node_name)
+                    .nth(1)
+                    .is_some()
+            });
+            if has_same_tag_siblings {
+                if let Some(step) = steps.last_mut() {
+                    let position = selector::nth_of_type(arena, current);
+                    step.push_str(&format!(":nth-of-type({})", position));
+                }
+                if let Some(selector) = unique(&steps) {
+                    return Ok(selector);
+                }
+            }
+
+            // `>` can't cross a shadow root or document boundary
+            match parent {
+                Some(parent) if parent.is_element() => current = parent,
+                _ => break,
+            }
+        }
+
+        // Ambiguous even from the top: e.g. identical shadow trees
+        let selector: Vec<&str> = steps.iter().rev().map(String::as_str).collect();
+        Ok(selector.join(" > "))
+    }
+
+    /// `//*[@id='...']` (or `@data-testid`) step if `node` carries a unique one
+    fn xpath_anchor(&self, arena: &DomArena, node: &DomNode) -> Option<String> {
+        let mut attributes = vec!["id"];
+        if self.config.xpath_test_id_anchor {
+            attributes.push("data-testid");
+        }
+        attributes.into_iter().find_map(|name| {
+            let value = node.attr(name).filter(|v| !v.is_empty())?;
+            let mut matches = arena
+                .find_iter(|n| n.node_type == NodeType::Element && n.attr(name) == Some(value));
+            let unique = matches.next().is_some() && matches.next().is_none();
+            unique.then(|| format!("//*[@{}={}]", name, xpath_literal(value)))
+        })
+    }
+
+    /// Visible elements, minus interactive ones occluded by paint order
+    pub fn filter_by_paint_order(&self, arena: &DomArena) -> Result<Vec<NodeId>> {
+        let mut visible = arena.find_visible();
+        if self.config.paint_order_filtering {
+            let occluded = self.occluded_by_paint_order(arena);
+            visible.retain(|id| !occluded.contains(id));
+        }
+        Ok(visible)
+    }
+
+    /// Set `ignored_by_paint_order` on each node
+    pub fn mark_paint_order(&self, arena: &DomArena, nodes: &mut [SimplifiedNode]) {
+        let occluded = self.occluded_by_paint_order(arena);
+        for node in nodes {
+            node.ignored_by_paint_order = occluded.contains(&node.node_id);
+        }
+    }
+
+    /// Set `is_new` on interactive nodes that `diff` reports as added
+    ///
+    /// `diff` must come from the arena the nodes were built from, against
+    /// the previous capture: `arena.diff(&previous)`.
+    pub fn mark_new(&self, nodes: &mut [SimplifiedNode], diff: &DomDiff) {
+        let added: AHashSet<NodeId> = diff.added.iter().copied().collect();
+        for node in nodes {
+            node.is_new = node.is_interactive && added.contains(&node.node_id);
+        }
+    }
+
+    /// Interactive elements fully covered by later-painted interactive elements
+    ///
+    /// Needs `paint_order` and a rect from the snapshot; elements without them
+    /// are never occluded. Coverage may be split across several elements, but
+    /// a partial overlap (a floating chat button, a sticky header's edge)
+    /// doesn't count. An element doesn't occlude its own ancestors or
+    /// descendants - a button's label paints over the button.
+    pub fn occluded_by_paint_order(&self, arena: &DomArena) -> AHashSet<NodeId> {
+        let mut candidates: Vec<(NodeId, i32, DomRect)> = arena
+            .node_ids()
+            .filter_map(|id| {
+                let node = arena.get(id).ok()?;
+                if self.is_hidden(id, node) || !node.is_interactive() {
+                    return None;
+                }
+                if self.config.exclude_aria_hidden
+                    && arena.closest(id, utils::is_aria_hidden_or_inert).is_some()
+                {
+                    return None;
+                }
+                let paint_order = node.snapshot_node.as_ref()?.paint_order?;
+                Some((id, paint_order, Self::viewport_rect(node)?))
+            })
+            .collect();
+        // Topmost first
+        candidates.sort_by_key(|&(_, paint_order, _)| std::cmp::Reverse(paint_order));
+
+        // Each candidate's candidate ancestors, walked once up front
+        let ids: AHashSet<NodeId> = candidates.iter().map(|&(id, _, _)| id).collect();
+        let ancestors: AHashMap<NodeId, AHashSet<NodeId>> = candidates
+            .iter()
+            .map(|&(id, _, _)| {
+                let above = arena.ancestor_ids(id).filter(|a| ids.contains(a));
+                (id, above.collect())
+            })
+            .collect();
+        let is_related =
+            |a: NodeId, b: NodeId| ancestors[&a].contains(&b) || ancestors[&b].contains(&a);
+
+        let mut occluded = AHashSet::new();
+        for (i, (id, paint_order, rect)) in candidates.iter().enumerate() {
+            // Parts of `rect` not yet painted over
+            let mut uncovered = vec![*rect];
+            for (above_id, above_order, above_rect) in &candidates[..i] {
+                if above_order <= paint_order
+                    || !above_rect.intersects(rect)
+                    || is_related(*id, *above_id)
+                {
+                    continue;
+                }
+                uncovered = uncovered
+                    .iter()
+                    .flat_map(|piece| Self::subtract_rect(piece, above_rect))
+                    .collect();
+                if uncovered.is_empty() {
+                    occluded.insert(*id);
+                    break;
+                }
+            }
+        }
+        occluded
+    }
+
+    /// The parts of `rect` outside `cover`, as up to four rectangles
+    fn subtract_rect(rect: &DomRect, cover: &DomRect) -> Vec<DomRect> {
+        if !rect.intersects(cover) {
+            return vec![*rect];
+        }
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        let inner_top = cover.y.max(top);
+        let inner_bottom = (cover.y + cover.height).min(bottom);
+        let pieces = [
+            DomRect::new(left, top, rect.width, inner_top - top),
+            DomRect::new(left, inner_bottom, rect.width, bottom - inner_bottom),
+            DomRect::new(left, inner_top, cover.x - left, inner_bottom - inner_top),
+            DomRect::new(
+                cover.x + cover.width,
+                inner_top,
+                right - (cover.x + cover.width),
+                inner_bottom - inner_top,
+            ),
+        ];
+        pieces
+            .into_iter()
+            .filter(|piece| !piece.is_empty())
+            .collect()
+    }
+}
+
+impl Default for DomSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quote `value` as an XPath string literal, using `concat()` when it
+/// contains both quote characters
+fn xpath_literal(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else if !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        let parts: Vec<String> = value
+            .split('\'')
+            .map(|part| format!("'{}'", part))
+            .collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
+/// Error context for a failure under `node`, e.g. "serializing children of <DIV> (node 3)"
+fn children_context(node: &DomNode) -> String {
+    format!(
+        "serializing children of <{}> (node {})",
+        node.node_name, node.node_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::DomService;
+
+    #[test]
+    fn test_serialize_simple_dom() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "nodeValue": "",
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 2,
+                    "nodeType": 1,
+                    "nodeName": "HTML",
+                    "nodeValue": "",
+                    "attributes": [],
+                    "children": [{
+                        "nodeId": 3,
+                        "backendNodeId": 3,
+                        "nodeType": 3,
+                        "nodeName": "#text",
+                        "nodeValue": "Hello",
+                        "attributes": []
+                    }]
+                }]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
 
         // Set visibility for all nodes (required for serialization)
         for node_id in service.arena().node_ids().collect::<Vec<_>>() {
@@ -225,5 +1481,1100 @@ mod tests {
             "Output should contain HTML tag. Got: {}",
             output
         );
+
+        // A dangling child id says where serialization hit it
+        let html_id = service.arena().get_node_id_by_backend(2).unwrap();
+        service
+            .arena_mut()
+            .get_mut(html_id)
+            .unwrap()
+            .children_ids
+            .push(99);
+        let err = serializer.serialize(service.arena()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Node not found: 99 (while serializing children of <HTML> (node 2))"
+        );
+    }
+
+    #[test]
+    fn test_serialize_indexed() {
+        let element = |id: u32, tag: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": tag,
+                "attributes": [],
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "BODY",
+                "attributes": [],
+                "children": [
+                    element(2, "BUTTON"),
+                    element(3, "SPAN"),
+                    element(4, "INPUT"),
+                    // Interactive by role alone, without snapshot data
+                    {
+                        "nodeId": 5,
+                        "backendNodeId": 5,
+                        "nodeType": 1,
+                        "nodeName": "DIV",
+                        "attributes": ["role", "tab", "tabindex", "0"],
+                    },
+                ]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        for node_id in service.arena().node_ids().collect::<Vec<_>>() {
+            service.arena_mut().get_mut(node_id).unwrap().is_visible = Some(true);
+        }
+        let arena = service.arena();
+
+        let plain = DomSerializer::new().serialize(arena).unwrap();
+        assert!(!plain.contains('['), "No indices by default: {}", plain);
+
+        let serializer = DomSerializer::new();
+        let (output, selector_map) = serializer.serialize_indexed(arena).unwrap();
+        assert!(output.contains("[0]<BUTTON>"), "Got: {}", output);
+        assert!(output.contains("  <SPAN>"), "Got: {}", output);
+        assert!(output.contains("[1]<INPUT>"), "Got: {}", output);
+        assert!(output.contains("[2]<DIV"), "Got: {}", output);
+        assert_eq!(selector_map.len(), 3);
+        assert_eq!(selector_map[&0], arena.get_node_id_by_backend(2).unwrap());
+        assert_eq!(selector_map[&1], arena.get_node_id_by_backend(4).unwrap());
+
+        // Deterministic across calls
+        assert_eq!(serializer.serialize_indexed(arena).unwrap().0, output);
+    }
+
+    #[test]
+    fn test_serialize_compact() {
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let element = |id: u32, tag: &str, attributes: &[&str], children: Vec<Value>| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": tag,
+                "attributes": attributes,
+                "children": children,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", &[], vec![
+                element(2, "DIV", &[], vec![
+                    element(3, "P", &[], vec![
+                        text(4, "Welcome"),
+                        element(5, "SPAN", &[], vec![text(6, "back")]),
+                    ]),
+                    element(7, "BUTTON", &[], vec![
+                        element(8, "SPAN", &[], vec![text(9, "Submit")]),
+                    ]),
+                    element(10, "INPUT", &["placeholder", "Search \"all\""], vec![]),
+                    element(11, "P", &[], vec![text(12, "Footer")]),
+                ]),
+            ])
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        for node_id in service.arena().node_ids().collect::<Vec<_>>() {
+            service.arena_mut().get_mut(node_id).unwrap().is_visible = Some(true);
+        }
+        let arena = service.arena();
+
+        let config = SerializerConfig {
+            format: OutputFormat::Compact,
+            ..SerializerConfig::default()
+        };
+        let serializer = DomSerializer::with_config(config.clone());
+        let (output, selector_map) = serializer.serialize_indexed(arena).unwrap();
+        assert_eq!(
+            output,
+            "Welcome back\n[0] button \"Submit\"\n[1] input \"Search &quot;all&quot;\"\nFooter\n"
+        );
+        assert_eq!(selector_map[&0], arena.get_node_id_by_backend(7).unwrap());
+        assert_eq!(serializer.serialize(arena).unwrap(), output);
+
+        // The budget stops at a whole line
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            compact_max_chars: Some(40),
+            ..config
+        });
+        let (output, selector_map) = serializer.serialize_indexed(arena).unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "Welcome back\n[0] button \"Submit\"\n{}\n",
+                TRUNCATION_MARKER
+            )
+        );
+        assert_eq!(selector_map.len(), 1);
+    }
+
+    #[test]
+    fn test_serialize_include_bounds() {
+        let mut arena = DomArena::new();
+        let mut button = DomNode::new(
+            0,
+            1,
+            NodeType::Element,
+            "BUTTON".to_string(),
+            "target1".to_string(),
+        );
+        button.is_visible = Some(true);
+        button.snapshot_node = Some(Box::new(SnapshotNode {
+            is_clickable: Some(true),
+            client_rects: Some(DomRect::new(10.4, 20.6, 100.0, 30.2)),
+            ..SnapshotNode::default()
+        }));
+        let id = arena.add_node(button);
+        arena.set_root(id).unwrap();
+
+        let output = DomSerializer::new().serialize(&arena).unwrap();
+        assert!(
+            !output.contains('@'),
+            "Bounds are off by default: {}",
+            output
+        );
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            include_bounds: true,
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(
+            output.contains("<BUTTON @{10,21,100,30}>"),
+            "Expected geometry annotation. Got: {}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_serialize_chunked_matches_serialize() {
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "UL",
+                "children": (2..40).map(|i| serde_json::json!({
+                    "nodeId": i * 100,
+                    "backendNodeId": i * 100,
+                    "nodeType": 1,
+                    "nodeName": "LI",
+                    "attributes": ["id", format!("item-{}", i)],
+                    "children": [text(i * 100 + 1, &format!("Item number {}", i))]
+                })).collect::<Vec<_>>()
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            chunk_size: 128,
+            ..SerializerConfig::default()
+        });
+        let full = serializer.serialize(service.arena()).unwrap();
+
+        let mut chunks = Vec::new();
+        serializer
+            .serialize_chunked(service.arena(), |chunk| chunks.push(chunk))
+            .unwrap();
+
+        assert!(chunks.len() > 1, "Expected multiple chunks");
+        assert!(chunks.iter().all(|c| c.ends_with('\n')));
+        assert_eq!(chunks.concat(), full);
+    }
+
+    #[test]
+    fn test_serialize_radio_group() {
+        let radio = |id: u32, value: &str, checked: bool| {
+            let mut attrs = vec!["type", "radio", "name", "size", "value", value];
+            if checked {
+                attrs.extend(["checked", ""]);
+            }
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "INPUT",
+                "attributes": attrs,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "FORM",
+                "children": [radio(2, "s", false), radio(3, "m", true), radio(4, "l", false)]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let output = DomSerializer::new().serialize(service.arena()).unwrap();
+        assert!(
+            output.contains(r#"<radiogroup name="size" options="s|m|l" checked="m" />"#),
+            "Expected grouped control. Got: {}",
+            output
+        );
+        assert!(
+            !output.contains("<INPUT"),
+            "Options should be folded: {}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_paint_order_filtering() {
+        let mut arena = DomArena::new();
+        let mut element = |tag: &str, backend_id: u32, paint_order: i32, rect: DomRect| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                tag.to_string(),
+                "target1".to_string(),
+            );
+            node.is_visible = Some(true);
+            node.attributes
+                .insert("id".to_string(), format!("e{}", backend_id));
+            node.snapshot_node = Some(Box::new(SnapshotNode {
+                is_clickable: Some(true),
+                paint_order: Some(paint_order),
+                client_rects: Some(rect),
+                ..SnapshotNode::default()
+            }));
+            arena.add_node(node)
+        };
+
+        let body = element("BODY", 1, 0, DomRect::new(0.0, 0.0, 800.0, 600.0));
+        let covered = element("BUTTON", 2, 1, DomRect::new(10.0, 10.0, 100.0, 30.0));
+        let modal = element("BUTTON", 3, 5, DomRect::new(0.0, 0.0, 400.0, 300.0));
+        let clear = element("BUTTON", 4, 2, DomRect::new(500.0, 500.0, 50.0, 30.0));
+        // Nested inside the modal button and painted over it
+        let icon = element("BUTTON", 5, 6, DomRect::new(5.0, 5.0, 10.0, 10.0));
+        // A chat bubble over a corner of `clear` - partial overlap isn't occlusion
+        let bubble = element("BUTTON", 6, 7, DomRect::new(540.0, 520.0, 60.0, 60.0));
+        // Covered only by the two halves together
+        let split = element("BUTTON", 7, 3, DomRect::new(200.0, 400.0, 50.0, 30.0));
+        let left = element("BUTTON", 8, 8, DomRect::new(190.0, 390.0, 35.0, 50.0));
+        let right = element("BUTTON", 9, 9, DomRect::new(225.0, 395.0, 40.0, 40.0));
+        for child in [covered, modal, clear, bubble, split, left, right] {
+            arena.get_mut(child).unwrap().parent_id = Some(body);
+            arena.get_mut(body).unwrap().children_ids.push(child);
+        }
+        arena.get_mut(icon).unwrap().parent_id = Some(modal);
+        arena.get_mut(modal).unwrap().children_ids.push(icon);
+        // The covered button's own text isn't occluded with it
+        let mut label = DomNode::new(
+            0,
+            10,
+            NodeType::Text,
+            "#text".to_string(),
+            "target1".to_string(),
+        );
+        label.node_value = "Covered label".to_string();
+        label.parent_id = Some(covered);
+        let label = arena.add_node(label);
+        arena.get_mut(covered).unwrap().children_ids.push(label);
+        arena.set_root(body).unwrap();
+
+        let serializer = DomSerializer::new();
+        let occluded = serializer.occluded_by_paint_order(&arena);
+        assert_eq!(
+            occluded.len(),
+            2,
+            "Only fully covered buttons: {:?}",
+            occluded
+        );
+        assert!(occluded.contains(&covered) && occluded.contains(&split));
+        assert!(!serializer
+            .filter_by_paint_order(&arena)
+            .unwrap()
+            .contains(&covered));
+
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(!output.contains("e2"), "Got: {}", output);
+        assert!(output.contains("e3") && output.contains("e4") && output.contains("e5"));
+        assert!(output.contains("Covered label"), "Got: {}", output);
+        let simplified = serializer.build_simplified(&arena);
+        let shown = |id| {
+            simplified
+                .iter()
+                .any(|n| n.node_id == id && n.should_display)
+        };
+        assert!(!shown(covered) && shown(label));
+
+        let unfiltered = DomSerializer::with_config(SerializerConfig {
+            paint_order_filtering: false,
+            ..SerializerConfig::default()
+        });
+        assert!(unfiltered.serialize(&arena).unwrap().contains("e2"));
+
+        let mut simplified: Vec<SimplifiedNode> = [covered, clear]
+            .into_iter()
+            .map(|node_id| SimplifiedNode {
+                node_id,
+                should_display: true,
+                is_interactive: true,
+                is_new: false,
+                ignored_by_paint_order: false,
+                excluded_by_parent: false,
+                is_shadow_host: false,
+                is_compound_component: false,
+            })
+            .collect();
+        serializer.mark_paint_order(&arena, &mut simplified);
+        assert!(simplified[0].ignored_by_paint_order);
+        assert!(!simplified[1].ignored_by_paint_order);
+    }
+
+    #[test]
+    fn test_mark_new() {
+        let add = |arena: &mut DomArena, backend_id: u32, tag: &str, parent: Option<NodeId>| {
+            let mut node = DomNode::new(
+                0,
+                backend_id,
+                NodeType::Element,
+                tag.to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            if let Some(parent) = parent {
+                arena.get_mut(parent).unwrap().children_ids.push(id);
+            }
+            id
+        };
+        let mut before = DomArena::new();
+        let body = add(&mut before, 1, "BODY", None);
+        let old_button = add(&mut before, 2, "BUTTON", Some(body));
+        before.set_root(body).unwrap();
+
+        // After an action: a dialog with a new button appeared
+        let mut after = before.clone();
+        let dialog = add(&mut after, 3, "DIV", Some(body));
+        let new_button = add(&mut after, 4, "BUTTON", Some(dialog));
+
+        let serializer = DomSerializer::new();
+        let mut simplified = serializer.build_simplified(&after);
+        assert!(simplified.iter().all(|n| !n.is_new));
+        serializer.mark_new(&mut simplified, &after.diff(&before));
+        let new: Vec<NodeId> = simplified
+            .iter()
+            .filter(|n| n.is_new)
+            .map(|n| n.node_id)
+            .collect();
+        assert_eq!(new, vec![new_button], "The div isn't interactive");
+        assert!(!new.contains(&old_button));
+    }
+
+    #[test]
+    fn test_build_simplified() {
+        let mut arena = DomArena::new();
+        let mut add = |tag: &str, parent: Option<NodeId>, rect: Option<DomRect>| {
+            let mut node = DomNode::new(
+                0,
+                arena.len() as u32 + 1,
+                NodeType::Element,
+                tag.to_string(),
+                "target1".to_string(),
+            );
+            node.parent_id = parent;
+            node.absolute_position = rect;
+            let id = arena.add_node(node);
+            if let Some(parent) = parent {
+                arena.get_mut(parent).unwrap().children_ids.push(id);
+            }
+            id
+        };
+
+        let body = add("BODY", None, None);
+        let link = add("A", Some(body), Some(DomRect::new(0.0, 0.0, 200.0, 50.0)));
+        let nested = add(
+            "BUTTON",
+            Some(link),
+            Some(DomRect::new(10.0, 10.0, 50.0, 20.0)),
+        );
+        let hidden = add("DIV", Some(body), None);
+        let hidden_child = add("INPUT", Some(hidden), None);
+        let select = add("SELECT", Some(body), None);
+        let date = add("INPUT", Some(body), None);
+        let host = add("DIV", Some(body), None);
+        arena.set_root(body).unwrap();
+        arena
+            .get_mut(link)
+            .unwrap()
+            .attributes
+            .insert("href".to_string(), "/x".to_string());
+        arena.get_mut(hidden).unwrap().is_visible = Some(false);
+        arena
+            .get_mut(date)
+            .unwrap()
+            .attributes
+            .insert("type".to_string(), "date".to_string());
+        let mut fragment = DomNode::new(
+            0,
+            arena.len() as u32 + 1,
+            NodeType::DocumentFragment,
+            "#document-fragment".to_string(),
+            "target1".to_string(),
+        );
+        fragment.parent_id = Some(host);
+        let shadow_root = arena.add_node(fragment);
+        arena.get_mut(host).unwrap().shadow_root_ids = Some(smallvec::smallvec![shadow_root]);
+
+        let serializer = DomSerializer::new();
+        let simplified = serializer.build_simplified(&arena);
+        let ids: Vec<NodeId> = simplified.iter().map(|n| n.node_id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                body,
+                link,
+                nested,
+                hidden,
+                hidden_child,
+                select,
+                date,
+                host,
+                shadow_root
+            ]
+        );
+        let get = |id: NodeId| simplified.iter().find(|n| n.node_id == id).unwrap();
+
+        assert!(get(link).is_interactive && !get(link).excluded_by_parent);
+        assert!(get(nested).is_interactive && get(nested).excluded_by_parent);
+        assert!(!get(hidden).should_display);
+        assert!(
+            !get(hidden_child).should_display,
+            "Hidden parent hides the subtree"
+        );
+        assert!(get(select).is_compound_component && get(date).is_compound_component);
+        assert!(!get(link).is_compound_component);
+        assert!(get(host).is_shadow_host && !get(body).is_shadow_host);
+
+        // The text output follows the same decisions
+        let (output, selector_map) = serializer.serialize_indexed(&arena).unwrap();
+        assert_eq!(output.matches("<INPUT").count(), 1, "Got: {}", output);
+        assert!(!selector_map.values().any(|&id| id == nested));
+        assert!(selector_map.values().any(|&id| id == link));
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 2,
+                    "nodeType": 1,
+                    "nodeName": "FORM",
+                    "attributes": ["id", "login", "data-secret", "x"],
+                    "children": [
+                        {
+                            "nodeId": 3,
+                            "backendNodeId": 3,
+                            "nodeType": 3,
+                            "nodeName": "#text",
+                            "nodeValue": "  Sign in ",
+                        },
+                        {
+                            "nodeId": 4,
+                            "backendNodeId": 4,
+                            "nodeType": 1,
+                            "nodeName": "BUTTON",
+                            "attributes": ["type", "submit", "disabled", ""],
+                        },
+                        {
+                            "nodeId": 5,
+                            "backendNodeId": 5,
+                            "nodeType": 1,
+                            "nodeName": "INPUT",
+                            "attributes": ["name", "user"],
+                        }
+                    ]
+                }]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let input = service.arena().get_node_id_by_backend(5).unwrap();
+        service
+            .arena_mut()
+            .get_mut(input)
+            .unwrap()
+            .absolute_position = Some(DomRect::new(1.0, 2.0, 3.0, 4.0));
+
+        let serializer = DomSerializer::new();
+        let value = serializer.serialize_json(service.arena()).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([{
+                "tag": "FORM",
+                "attributes": { "id": "login" },
+                "children": [
+                    { "text": "Sign in" },
+                    { "tag": "BUTTON", "attributes": { "type": "submit" }, "disabled": true },
+                    {
+                        "tag": "INPUT",
+                        "attributes": { "name": "user" },
+                        "index": 0,
+                        "bounds": { "x": 1.0, "y": 2.0, "width": 3.0, "height": 4.0 },
+                    },
+                ],
+            }])
+        );
+
+        // Same indices as the text form
+        let (_, selector_map) = serializer.serialize_indexed(service.arena()).unwrap();
+        assert_eq!(selector_map[&0], input);
+
+        let json_serializer = DomSerializer::with_config(SerializerConfig {
+            format: OutputFormat::Json,
+            ..SerializerConfig::default()
+        });
+        let output = json_serializer.serialize(service.arena()).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&output).unwrap(), value);
+    }
+
+    #[test]
+    fn test_escaping_and_text_cap() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "DIV",
+                "attributes": ["title", "a \"quoted\" <b>\nvalue & more", "aria-label", "x".repeat(50)],
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 2,
+                    "nodeType": 3,
+                    "nodeName": "#text",
+                    "nodeValue": "line one\n</DIV><BUTTON>fake</BUTTON>\u{0}",
+                }]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            max_text_length: 40,
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(service.arena()).unwrap();
+
+        assert!(
+            output.contains(r#"title="a &quot;quoted&quot; &lt;b&gt; value &amp; more""#),
+            "Got: {}",
+            output
+        );
+        assert!(
+            output.contains(&format!("aria-label=\"{}...\"", "x".repeat(40))),
+            "Long attributes are capped: {}",
+            output
+        );
+        assert!(
+            output.contains("  line one &lt;/DIV&gt;&lt;BUTTON&gt;fake&lt;/BUTTON&gt;\n"),
+            "Got: {}",
+            output
+        );
+        assert!(!output.contains("<BUTTON"));
+        assert_eq!(output.lines().count(), 3, "One line per node: {}", output);
+
+        let value = serializer.serialize_json(service.arena()).unwrap();
+        assert_eq!(
+            value[0]["children"][0]["text"],
+            "line one </DIV><BUTTON>fake</BUTTON>"
+        );
+    }
+
+    /// `<DIV id="d0"><DIV id="d1">...` nested `depth` deep, with text at the bottom
+    fn nested_divs(depth: u32) -> DomArena {
+        let mut arena = DomArena::new();
+        let mut parent = None;
+        for i in 0..depth {
+            let mut node = DomNode::new(
+                0,
+                i + 1,
+                NodeType::Element,
+                "DIV".to_string(),
+                "target1".to_string(),
+            );
+            node.attributes.insert("id".to_string(), format!("d{}", i));
+            node.parent_id = parent;
+            let id = arena.add_node(node);
+            match parent {
+                Some(parent) => arena.get_mut(parent).unwrap().children_ids.push(id),
+                None => arena.set_root(id).unwrap(),
+            }
+            parent = Some(id);
+        }
+        let mut text = DomNode::new(
+            0,
+            depth + 1,
+            NodeType::Text,
+            "#text".to_string(),
+            "target1".to_string(),
+        );
+        text.node_value = "bottom".to_string();
+        text.parent_id = parent;
+        let text_id = arena.add_node(text);
+        arena
+            .get_mut(parent.unwrap())
+            .unwrap()
+            .children_ids
+            .push(text_id);
+        arena
+    }
+
+    #[test]
+    fn test_max_depth_truncates() {
+        let arena = nested_divs(5);
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            max_depth: Some(1),
+            ..SerializerConfig::default()
+        });
+
+        let (output, omitted) = serializer.serialize_with_omitted(&arena).unwrap();
+        assert_eq!(
+            output,
+            "<DIV id=\"d0\">\n  <DIV id=\"d1\">\n    <!-- truncated -->\n  </DIV>\n</DIV>\n"
+        );
+        assert_eq!(omitted, 4, "d2, d3, d4 and the text");
+
+        let value = serializer.serialize_json(&arena).unwrap();
+        assert_eq!(
+            value[0]["children"][0]["children"],
+            serde_json::json!([{ "truncated": true }])
+        );
+        let json = DomSerializer::with_config(SerializerConfig {
+            format: OutputFormat::Json,
+            ..serializer.config.clone()
+        });
+        assert_eq!(json.serialize_with_omitted(&arena).unwrap().1, 4);
+
+        let (full, omitted) = DomSerializer::new().serialize_with_omitted(&arena).unwrap();
+        assert!(full.contains("bottom") && !full.contains("truncated"));
+        assert_eq!(omitted, 0);
+    }
+
+    #[test]
+    fn test_max_nodes_truncates() {
+        let element = |id: u32| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "LI",
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "UL",
+                "children": (2..12).map(element).collect::<Vec<_>>()
+            }
+        });
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            max_nodes: Some(4),
+            ..SerializerConfig::default()
+        });
+        let (output, omitted) = serializer.serialize_with_omitted(service.arena()).unwrap();
+        assert_eq!(output.matches("<LI>").count(), 3, "Got: {}", output);
+        assert_eq!(output.matches("<!-- truncated -->").count(), 1);
+        assert_eq!(omitted, 7);
+
+        let json = DomSerializer::with_config(SerializerConfig {
+            format: OutputFormat::Json,
+            max_nodes: Some(4),
+            ..SerializerConfig::default()
+        });
+        let (output, omitted) = json.serialize_with_omitted(service.arena()).unwrap();
+        assert_eq!(
+            output.matches("\"truncated\":true").count(),
+            1,
+            "Got: {}",
+            output
+        );
+        assert_eq!(omitted, 7);
+    }
+
+    #[test]
+    fn test_pathologically_deep_tree() {
+        // Deep enough to overflow the stack if every level recursed
+        let arena = nested_divs(100_000);
+        let (output, omitted) = DomSerializer::new().serialize_with_omitted(&arena).unwrap();
+        assert!(output.contains("<!-- truncated -->"));
+        assert_eq!(omitted, 100_000 - MAX_TREE_DEPTH);
+    }
+
+    /// A host with open and user-agent shadow roots next to an iframe
+    /// whose document nests a second iframe
+    fn shadow_and_frames() -> DomArena {
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let shadow_root = |id: u32, mode: &str, child: serde_json::Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 11,
+                "nodeName": "#document-fragment",
+                "shadowRootType": mode,
+                "children": [child]
+            })
+        };
+        let iframe = |id: u32, inner: serde_json::Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "IFRAME",
+                "contentDocument": {
+                    "nodeId": id + 1,
+                    "backendNodeId": id + 1,
+                    "nodeType": 9,
+                    "nodeName": "#document",
+                    "children": [{
+                        "nodeId": id + 2,
+                        "backendNodeId": id + 2,
+                        "nodeType": 1,
+                        "nodeName": "P",
+                        "children": [inner]
+                    }]
+                }
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "BODY",
+                "children": [
+                    {
+                        "nodeId": 2,
+                        "backendNodeId": 2,
+                        "nodeType": 1,
+                        "nodeName": "MY-WIDGET",
+                        "shadowRoots": [
+                            shadow_root(3, "open", text(4, "in shadow")),
+                            shadow_root(5, "user-agent", text(6, "ua internals")),
+                        ],
+                        "children": [text(7, "light")]
+                    },
+                    iframe(10, iframe(20, text(30, "inner frame"))),
+                ]
+            }
+        });
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        service.arena().clone()
+    }
+
+    #[test]
+    fn test_shadow_dom_and_iframes() {
+        let arena = shadow_and_frames();
+        let output = DomSerializer::new().serialize(&arena).unwrap();
+        assert!(
+            output.contains(
+                "  <MY-WIDGET>\n    #shadow-root (open)\n      in shadow\n    light\n  </MY-WIDGET>\n"
+            ),
+            "Got: {}",
+            output
+        );
+        assert!(!output.contains("ua internals"));
+        assert!(
+            output.contains(
+                "  <IFRAME>\n    #document\n      <P>\n        <IFRAME>\n          #document\n"
+            ),
+            "Got: {}",
+            output
+        );
+        assert!(output.contains("inner frame"));
+
+        let value = DomSerializer::new().serialize_json(&arena).unwrap();
+        assert_eq!(
+            value[0]["children"][0]["children"][0],
+            serde_json::json!({
+                "boundary": "#shadow-root (open)",
+                "children": [{ "text": "in shadow" }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_shadow_dom_and_iframe_limits() {
+        let arena = shadow_and_frames();
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            include_user_agent_shadow: true,
+            max_iframe_depth: 1,
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(output.contains("#shadow-root (user-agent)\n      ua internals"));
+        assert_eq!(output.matches("#document").count(), 1, "Got: {}", output);
+        assert!(!output.contains("inner frame"));
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            include_shadow_dom: false,
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(!output.contains("#shadow-root") && !output.contains("in shadow"));
+        assert!(output.contains("light") && output.contains("inner frame"));
+    }
+
+    #[test]
+    fn test_generate_xpath() {
+        let element =
+            |id: u32, tag: &str, attributes: &[&str], children: Vec<serde_json::Value>| {
+                serde_json::json!({
+                    "nodeId": id,
+                    "backendNodeId": id,
+                    "nodeType": 1,
+                    "nodeName": tag,
+                    "attributes": attributes,
+                    "children": children,
+                })
+            };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "HTML", &[], vec![element(2, "BODY", &[], vec![
+                element(3, "DIV", &["id", "dup"], vec![]),
+                element(4, "DIV", &["id", "dup"], vec![
+                    element(5, "SPAN", &[], vec![]),
+                ]),
+                element(6, "DIV", &["id", "main"], vec![
+                    element(7, "LI", &[], vec![]),
+                    element(8, "LI", &["data-testid", "it's \"quoted\""], vec![
+                        element(9, "A", &[], vec![]),
+                    ]),
+                ]),
+            ])])
+        });
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let arena = service.arena();
+        let node = |backend_id: u32| arena.get_node_id_by_backend(backend_id).unwrap();
+
+        let serializer = DomSerializer::new();
+        let xpath = |id| serializer.generate_xpath(arena, node(id)).unwrap();
+        assert_eq!(xpath(6), "//*[@id='main']");
+        assert_eq!(xpath(9), "//*[@id='main']/li[2]/a[1]");
+        // Duplicate ids are no anchor
+        assert_eq!(xpath(5), "/html[1]/body[1]/div[2]/span[1]");
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            xpath_test_id_anchor: true,
+            ..SerializerConfig::default()
+        });
+        assert_eq!(
+            serializer.generate_xpath(arena, node(9)).unwrap(),
+            "//*[@data-testid=concat('it', \"'\", 's \"quoted\"')]/a[1]"
+        );
+    }
+
+    #[test]
+    fn test_generate_css_selector() {
+        let element =
+            |id: u32, tag: &str, attributes: &[&str], children: Vec<serde_json::Value>| {
+                serde_json::json!({
+                    "nodeId": id,
+                    "backendNodeId": id,
+                    "nodeType": 1,
+                    "nodeName": tag,
+                    "attributes": attributes,
+                    "children": children,
+                })
+            };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", &[], vec![
+                element(2, "DIV", &["id", "nav", "class", "menu"], vec![
+                    element(3, "A", &[], vec![]),
+                    element(4, "A", &["class", "active"], vec![]),
+                    element(5, "A", &[], vec![]),
+                ]),
+                element(6, "DIV", &["id", "1st", "class", "menu"], vec![
+                    element(7, "A", &[], vec![]),
+                ]),
+                element(8, "BUTTON", &["class", "btn primary"], vec![]),
+                element(9, "BUTTON", &["class", "btn"], vec![]),
+            ])
+        });
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let arena = service.arena();
+        let node = |backend_id: u32| arena.get_node_id_by_backend(backend_id).unwrap();
+        let serializer = DomSerializer::new();
+        let css = |id| serializer.generate_css_selector(arena, node(id)).unwrap();
+
+        assert_eq!(css(2), "#nav");
+        assert_eq!(css(4), "a.active");
+        assert_eq!(css(5), "a:nth-of-type(3)");
+        assert_eq!(css(3), "#nav > a:nth-of-type(1)");
+        assert_eq!(css(8), "button.btn.primary");
+        assert_eq!(css(9), "button.btn:nth-of-type(2)");
+        // `1st` isn't a valid identifier, so fall back to position
+        assert_eq!(css(6), "div.menu:nth-of-type(2)");
+        assert_eq!(css(7), "div.menu:nth-of-type(2) > a");
+
+        for id in 1..=9 {
+            assert_eq!(arena.query_selector_all(&css(id)), vec![node(id)]);
+        }
+        assert!(matches!(
+            serializer.generate_css_selector(arena, NodeId::MAX),
+            Err(DomError::NodeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_disabled_state() {
+        let mut arena = DomArena::new();
+
+        let mut button = DomNode::new(
+            0,
+            1,
+            NodeType::Element,
+            "BUTTON".to_string(),
+            "target1".to_string(),
+        );
+        button
+            .attributes
+            .insert("disabled".to_string(), String::new());
+        button.snapshot_node = Some(Box::new(SnapshotNode {
+            is_clickable: Some(true),
+            ..SnapshotNode::default()
+        }));
+        let button_id = arena.add_node(button);
+
+        let mut div = DomNode::new(
+            1,
+            2,
+            NodeType::Element,
+            "DIV".to_string(),
+            "target1".to_string(),
+        );
+        div.attributes
+            .insert("aria-disabled".to_string(), "true".to_string());
+        div.children_ids.push(button_id);
+        let div_id = arena.add_node(div);
+        arena.get_mut(button_id).unwrap().parent_id = Some(div_id);
+        arena.set_root(div_id).unwrap();
+
+        let button = arena.get(button_id).unwrap();
+        assert!(button.is_disabled());
+        assert!(!button.is_clickable(), "Disabled buttons aren't clickable");
+        assert!(arena.get(div_id).unwrap().is_disabled());
+
+        let output = DomSerializer::new().serialize(&arena).unwrap();
+        assert!(output.contains("<DIV disabled>"), "Got: {}", output);
+        assert!(output.contains("<BUTTON disabled>"), "Got: {}", output);
+    }
+
+    #[test]
+    fn test_attribute_layering() {
+        let mut arena = DomArena::new();
+        let mut div = DomNode::new(
+            0,
+            1,
+            NodeType::Element,
+            "DIV".to_string(),
+            "target1".to_string(),
+        );
+        for (name, value) in [
+            ("id", "main"),
+            ("data-qa", "hook"),
+            ("data-b", "2"),
+            ("data-a", "1"),
+            ("data-state", "open"),
+            ("onclick", "go()"),
+        ] {
+            div.attributes.insert(name.to_string(), value.to_string());
+        }
+        let div_id = arena.add_node(div);
+        arena.set_root(div_id).unwrap();
+
+        let output = DomSerializer::new().serialize(&arena).unwrap();
+        assert!(
+            output.contains(r#"<DIV id="main" data-state="open">"#),
+            "Got: {}",
+            output
+        );
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            extra_attributes: vec!["data-qa".to_string(), "id".to_string()],
+            excluded_attributes: HashSet::from(["data-state".to_string()]),
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(
+            output.contains(r#"<DIV id="main" data-qa="hook">"#),
+            "Got: {}",
+            output
+        );
+
+        let serializer = DomSerializer::with_config(SerializerConfig {
+            extra_attributes: vec!["data-qa".to_string()],
+            include_all_data_attributes: true,
+            ..SerializerConfig::default()
+        });
+        let output = serializer.serialize(&arena).unwrap();
+        assert!(
+            output.contains(
+                r#"<DIV id="main" data-state="open" data-qa="hook" data-a="1" data-b="2">"#
+            ),
+            "Got: {}",
+            output
+        );
     }
 }
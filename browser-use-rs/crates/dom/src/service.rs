@@ -11,11 +11,78 @@
 
 use crate::arena::DomArena;
 use crate::error::{DomError, Result};
+use crate::serializer::{DomSerializer, SerializerConfig};
 use crate::types::*;
 use crate::utils;
+use ahash::AHashMap;
 use serde_json::Value;
 use std::collections::HashMap;
 
+/// Deepest node `parse_node` accepts, counting shadow roots and iframe
+/// documents as levels
+///
+/// Parsing recurses once per level, so this bounds stack use on hostile
+/// pages. Chrome's HTML parser stops nesting at the same depth, so only
+/// script-built trees run into it.
+pub const MAX_PARSE_DEPTH: usize = 512;
+
+/// Ids synthesized for nodes missing `nodeId`/`backendNodeId` count down
+/// from here, far above any id Chrome hands out
+pub const SYNTHETIC_ID_START: u32 = u32::MAX;
+
+/// Something the parser had to fill in for a node CDP sent incomplete
+///
+/// Some node kinds (pseudo-elements, certain shadow entries) omit fields in
+/// some Chrome versions; outside `strict_parsing` the node is kept with a
+/// guess and the guess is reported here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// No `nodeId`; the node got the synthetic id `assigned`
+    MissingNodeId { assigned: u32 },
+    /// No `backendNodeId`; the node got the synthetic id `assigned`
+    MissingBackendNodeId { node_id: u32, assigned: u32 },
+    /// `nodeType` missing (`value: None`) or unknown; `assumed` was derived
+    /// from `nodeName`
+    InvalidNodeType {
+        node_id: u32,
+        value: Option<u64>,
+        assumed: NodeType,
+    },
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingNodeId { assigned } => {
+                write!(f, "node without nodeId, assigned {}", assigned)
+            }
+            Self::MissingBackendNodeId { node_id, assigned } => write!(
+                f,
+                "node {} without backendNodeId, assigned {}",
+                node_id, assigned
+            ),
+            Self::InvalidNodeType {
+                node_id,
+                value: Some(value),
+                assumed,
+            } => write!(
+                f,
+                "node {} has unknown nodeType {}, assumed {:?}",
+                node_id, value, assumed
+            ),
+            Self::InvalidNodeType {
+                node_id,
+                value: None,
+                assumed,
+            } => write!(
+                f,
+                "node {} without nodeType, assumed {:?}",
+                node_id, assumed
+            ),
+        }
+    }
+}
+
 /// Configuration for DOM service
 #[derive(Debug, Clone)]
 pub struct DomServiceConfig {
@@ -23,6 +90,23 @@ pub struct DomServiceConfig {
     pub paint_order_filtering: bool,
     pub max_iframes: usize,
     pub max_iframe_depth: usize,
+    /// Fail parsing with `MaxIframeDepthExceeded`/`MaxIframeCountExceeded`
+    /// instead of leaving iframes past the limits empty
+    pub strict_iframe_limits: bool,
+    /// Keep elements in `aria-hidden="true"` and `inert` subtrees out of the
+    /// interactive elements `serialize_for_llm` offers; their text still renders
+    ///
+    /// Off by default: such subtrees can still be on screen and clickable.
+    pub exclude_aria_hidden: bool,
+    /// Parse into an interning arena (`DomArena::with_interning`)
+    pub intern_strings: bool,
+    /// Run `coalesce_text_nodes` after parsing a full tree
+    pub coalesce_text_nodes: bool,
+    /// Give parsed nodes random UUIDs instead of ones stable across captures
+    pub random_node_uuids: bool,
+    /// Fail parsing on nodes missing `nodeId`, `backendNodeId` or a valid
+    /// `nodeType` instead of filling them in (see `ParseWarning`)
+    pub strict_parsing: bool,
 }
 
 impl Default for DomServiceConfig {
@@ -32,14 +116,54 @@ impl Default for DomServiceConfig {
             paint_order_filtering: true,
             max_iframes: 100,
             max_iframe_depth: 5,
+            strict_iframe_limits: false,
+            exclude_aria_hidden: false,
+            intern_strings: false,
+            coalesce_text_nodes: false,
+            random_node_uuids: false,
+            strict_parsing: false,
         }
     }
 }
 
+/// Position of a node under its parent, kept across `apply_dom_update`
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Root,
+    /// Neither the root nor linked to a parent
+    Detached,
+    Child(NodeId, usize),
+    ShadowRoot(NodeId, usize),
+    ContentDocument(NodeId),
+}
+
+/// Frame a node is parsed in
+#[derive(Debug, Clone)]
+struct FrameContext {
+    target_id: TargetId,
+    /// 0 for the top document, +1 per iframe
+    depth: usize,
+    /// `scheme://host[:port]` of the document, if it has one
+    origin: Option<String>,
+}
+
+/// Raw CDP responses that make up one page capture, for `DomService::load`
+#[derive(Debug, Clone, Default)]
+pub struct DomState {
+    /// `DOM.getDocument` with `depth: -1`
+    pub document: Value,
+    /// `DOMSnapshot.captureSnapshot` using `REQUIRED_COMPUTED_STYLES`
+    pub snapshot: Value,
+    /// `Accessibility.getFullAXTree`
+    pub ax_tree: Value,
+}
+
 /// Main DOM service
 pub struct DomService {
     config: DomServiceConfig,
     arena: DomArena,
+    /// Ids synthesized since the arena was last replaced
+    synthetic_ids: u32,
 }
 
 impl DomService {
@@ -50,9 +174,14 @@ impl DomService {
 
     /// Create DOM service with custom config
     pub fn with_config(config: DomServiceConfig) -> Self {
+        let arena = match config.intern_strings {
+            true => DomArena::with_interning(),
+            false => DomArena::new(),
+        };
         Self {
             config,
-            arena: DomArena::new(),
+            arena,
+            synthetic_ids: 0,
         }
     }
 
@@ -83,48 +212,353 @@ impl DomService {
     ///   }
     /// }
     /// ```
+    ///
+    /// Incomplete nodes are filled in and logged; use
+    /// `parse_cdp_dom_tree_with_warnings` to get the list.
     pub fn parse_cdp_dom_tree(&mut self, cdp_response: &Value) -> Result<NodeId> {
+        let (root_id, warnings) = self.parse_cdp_dom_tree_with_warnings(cdp_response)?;
+        log_warnings(&warnings);
+        Ok(root_id)
+    }
+
+    /// `parse_cdp_dom_tree`, also returning what had to be filled in for
+    /// incomplete nodes (always empty with `strict_parsing`)
+    pub fn parse_cdp_dom_tree_with_warnings(
+        &mut self,
+        cdp_response: &Value,
+    ) -> Result<(NodeId, Vec<ParseWarning>)> {
         let root = cdp_response
             .get("root")
             .ok_or_else(|| DomError::CdpError("Missing 'root' in CDP response".to_string()))?;
 
-        self.arena.clear();
-        let root_id = self.parse_node(root, None, &TargetId::from("default"))?;
+        self.parse_fresh(|service| {
+            let frame = FrameContext {
+                target_id: TargetId::from("default"),
+                depth: 0,
+                origin: root["documentURL"].as_str().and_then(utils::url_origin),
+            };
+            let mut warnings = Vec::new();
+            let root_id = service.parse_node(root, None, 0, &frame, &mut 0, &mut warnings)?;
+            service.finish_parse(root_id)?;
+            Ok((root_id, warnings))
+        })
+    }
+
+    /// Run a full parse into an empty arena, swapped in only if it succeeds
+    ///
+    /// A parse that fails partway leaves the previous tree as it was.
+    fn parse_fresh<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let fresh = self.arena.empty_like();
+        let previous = std::mem::replace(&mut self.arena, fresh);
+        let previous_synthetic_ids = std::mem::replace(&mut self.synthetic_ids, 0);
+        let parsed = parse(self);
+        if parsed.is_err() {
+            self.arena = previous;
+            self.synthetic_ids = previous_synthetic_ids;
+        }
+        parsed
+    }
+
+    /// Root a freshly parsed tree and run the post-parse passes
+    fn finish_parse(&mut self, root_id: NodeId) -> Result<()> {
         self.arena.set_root(root_id)?;
+        self.assign_stable_uuids(root_id)?;
+        if self.config.coalesce_text_nodes {
+            self.coalesce_text_nodes()?;
+        }
+        Ok(())
+    }
 
+    /// Build the tree from a full capture
+    ///
+    /// Parses the document, then merges the AX tree and the snapshot, then
+    /// calculates visibility - the order the later steps depend on.
+    pub fn load(&mut self, state: &DomState, device_pixel_ratio: f64) -> Result<NodeId> {
+        let root_id = self.parse_cdp_dom_tree(&state.document)?;
+        self.merge_ax_tree(&state.ax_tree)?;
+        self.merge_snapshot(&state.snapshot, device_pixel_ratio)?;
+        self.calculate_visibility()?;
         Ok(root_id)
     }
 
+    /// Parse the flat node list from `DOM.getFlattenedDocument`
+    ///
+    /// Each entry names its parent by CDP `parentId` instead of nesting
+    /// children, which keeps the response small for deep pages and needs no
+    /// recursion. Nodes are added in a first pass and linked in a second:
+    /// documents under an element become its `content_document_id`, shadow
+    /// roots go to `shadow_root_ids`, everything else to `children_ids` in
+    /// list order. The root is the first node without a known parent.
+    ///
+    /// `cross_origin_iframes` and the iframe limits apply as they do for
+    /// `parse_cdp_dom_tree`; skipped iframe documents are dropped once linked.
+    pub fn parse_cdp_flattened(&mut self, cdp_response: &Value) -> Result<NodeId> {
+        let nodes = cdp_response["nodes"]
+            .as_array()
+            .ok_or_else(|| DomError::CdpError("Missing 'nodes' in CDP response".to_string()))?;
+
+        self.parse_fresh(|service| {
+            let frame = FrameContext {
+                target_id: TargetId::from("default"),
+                depth: 0,
+                origin: None,
+            };
+
+            let mut warnings = Vec::new();
+            let mut by_cdp_id: HashMap<u32, NodeId> = HashMap::with_capacity(nodes.len());
+            let mut sources: HashMap<NodeId, &Value> = HashMap::with_capacity(nodes.len());
+            let mut linked = Vec::with_capacity(nodes.len());
+            for cdp_node in nodes {
+                let node = service.build_node(cdp_node, &frame, &mut warnings)?;
+                let cdp_id = node.node_id;
+                let node_id = service.arena.add_node(node);
+                by_cdp_id.insert(cdp_id, node_id);
+                sources.insert(node_id, cdp_node);
+                linked.push((node_id, cdp_node["parentId"].as_u64()));
+            }
+
+            let mut root_id = None;
+            for (node_id, parent_cdp_id) in linked {
+                let parent_id = parent_cdp_id.and_then(|id| by_cdp_id.get(&(id as u32)).copied());
+                let Some(parent_id) = parent_id else {
+                    root_id.get_or_insert(node_id);
+                    continue;
+                };
+
+                let node = service.arena.get_mut(node_id)?;
+                node.parent_id = Some(parent_id);
+                let is_document = node.node_type == NodeType::Document;
+                let is_shadow_root = node.shadow_root_type.is_some();
+
+                let parent = service.arena.get_mut(parent_id)?;
+                if is_document && parent.is_element() {
+                    parent.content_document_id = Some(node_id);
+                } else if is_shadow_root {
+                    parent
+                        .shadow_root_ids
+                        .get_or_insert_with(Default::default)
+                        .push(node_id);
+                } else {
+                    parent.children_ids.push(node_id);
+                }
+            }
+
+            let root_id = root_id
+                .ok_or_else(|| DomError::CdpError("No root in flattened document".to_string()))?;
+            service.limit_flattened_iframes(root_id, &sources)?;
+            service.finish_parse(root_id)?;
+            log_warnings(&warnings);
+            Ok(root_id)
+        })
+    }
+
+    /// Run the linked tree under `root_id` through `enter_frame`, removing
+    /// the iframe documents it skips
+    ///
+    /// Visits nodes in the order `parse_node` parses them, so `max_iframes`
+    /// keeps the same documents. `sources` maps nodes to their CDP entries,
+    /// for document URLs.
+    fn limit_flattened_iframes(
+        &mut self,
+        root_id: NodeId,
+        sources: &HashMap<NodeId, &Value>,
+    ) -> Result<()> {
+        /// A node to descend into, or an iframe document to check once the
+        /// iframe's children are done
+        enum Visit {
+            Node(NodeId, usize),
+            Frame(NodeId, usize),
+        }
+        let document_url = |node_id: NodeId| {
+            sources
+                .get(&node_id)
+                .and_then(|cdp_node| cdp_node["documentURL"].as_str())
+        };
+
+        let mut frames = vec![FrameContext {
+            target_id: TargetId::from("default"),
+            depth: 0,
+            origin: document_url(root_id).and_then(utils::url_origin),
+        }];
+        let mut iframes = 0;
+        let mut stack = vec![Visit::Node(root_id, 0)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Node(node_id, frame) => {
+                    let node = self.arena.get(node_id)?;
+                    let shadow_root_ids = node.shadow_root_ids.iter().flatten().rev();
+                    stack.extend(shadow_root_ids.map(|&id| Visit::Node(id, frame)));
+                    stack.extend(node.content_document_id.map(|id| Visit::Frame(id, frame)));
+                    stack.extend(
+                        node.children_ids
+                            .iter()
+                            .rev()
+                            .map(|&id| Visit::Node(id, frame)),
+                    );
+                }
+                Visit::Frame(doc_id, frame) => {
+                    let content_doc = sources.get(&doc_id).copied().unwrap_or(&Value::Null);
+                    match self.enter_frame(content_doc, &frames[frame], &mut iframes)? {
+                        Some(inner) => {
+                            frames.push(inner);
+                            stack.push(Visit::Node(doc_id, frames.len() - 1));
+                        }
+                        None => {
+                            self.arena.remove_subtree(doc_id)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Recursively parse a CDP node
+    ///
+    /// `depth` is the number of ancestors, checked against `MAX_PARSE_DEPTH`.
+    /// `iframes` counts the iframe documents parsed so far, for `max_iframes`.
     fn parse_node(
         &mut self,
         cdp_node: &Value,
         parent_id: Option<NodeId>,
-        target_id: &TargetId,
+        depth: usize,
+        frame: &FrameContext,
+        iframes: &mut usize,
+        warnings: &mut Vec<ParseWarning>,
     ) -> Result<NodeId> {
-        let node_id = cdp_node["nodeId"]
-            .as_u64()
-            .ok_or_else(|| DomError::CdpError("Missing nodeId".to_string()))?
-            as u32;
-
-        let backend_node_id = cdp_node["backendNodeId"]
-            .as_u64()
-            .ok_or_else(|| DomError::CdpError("Missing backendNodeId".to_string()))?
-            as u32;
-
-        let node_type_val = cdp_node["nodeType"]
-            .as_u64()
-            .ok_or_else(|| DomError::CdpError("Missing nodeType".to_string()))?
-            as u8;
-
-        let node_type =
-            NodeType::from_u8(node_type_val).ok_or_else(|| DomError::InvalidNodeType {
-                expected: "valid NodeType".to_string(),
-                actual: format!("{}", node_type_val),
-            })?;
+        if depth >= MAX_PARSE_DEPTH {
+            return Err(DomError::MaxTreeDepthExceeded {
+                max: MAX_PARSE_DEPTH,
+            });
+        }
+
+        let mut node = self.build_node(cdp_node, frame, warnings)?;
+        node.parent_id = parent_id;
+
+        // Add node to arena
+        let current_node_id = self.arena.add_node(node);
+
+        // Parse children
+        if let Some(children) = cdp_node["children"].as_array() {
+            let mut child_ids = smallvec::SmallVec::new();
+
+            for child in children {
+                let child_id = self.parse_node(
+                    child,
+                    Some(current_node_id),
+                    depth + 1,
+                    frame,
+                    iframes,
+                    warnings,
+                )?;
+                child_ids.push(child_id);
+            }
+
+            // Update parent's children list
+            if let Ok(node) = self.arena.get_mut(current_node_id) {
+                node.children_ids = child_ids;
+            }
+        }
+
+        // Parse content document (iframe)
+        if let Some(content_doc) = cdp_node.get("contentDocument") {
+            if let Some(inner) = self.enter_frame(content_doc, frame, iframes)? {
+                let doc_id = self.parse_node(
+                    content_doc,
+                    Some(current_node_id),
+                    depth + 1,
+                    &inner,
+                    iframes,
+                    warnings,
+                )?;
+                if let Ok(node) = self.arena.get_mut(current_node_id) {
+                    node.content_document_id = Some(doc_id);
+                }
+            }
+        }
+
+        // Parse shadow roots
+        if let Some(shadow_roots) = cdp_node["shadowRoots"].as_array() {
+            let mut shadow_ids = smallvec::SmallVec::new();
+
+            for shadow in shadow_roots {
+                let shadow_id = self.parse_node(
+                    shadow,
+                    Some(current_node_id),
+                    depth + 1,
+                    frame,
+                    iframes,
+                    warnings,
+                )?;
+                shadow_ids.push(shadow_id);
+            }
+
+            if let Ok(node) = self.arena.get_mut(current_node_id) {
+                node.shadow_root_ids = Some(shadow_ids);
+            }
+        }
 
+        Ok(current_node_id)
+    }
+
+    /// Build a node from its CDP fields, without children or parent
+    ///
+    /// Missing ids and node types are filled in and reported to `warnings`,
+    /// or are errors with `strict_parsing`.
+    fn build_node(
+        &mut self,
+        cdp_node: &Value,
+        frame: &FrameContext,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<DomNode> {
+        let strict = self.config.strict_parsing;
         let node_name = cdp_node["nodeName"].as_str().unwrap_or("").to_string();
 
+        let node_id = match cdp_node["nodeId"].as_u64() {
+            Some(node_id) => node_id as u32,
+            None if strict => return Err(DomError::CdpError("Missing nodeId".to_string())),
+            None => {
+                let assigned = self.next_synthetic_id();
+                warnings.push(ParseWarning::MissingNodeId { assigned });
+                assigned
+            }
+        };
+
+        let (backend_node_id, synthetic) = match cdp_node["backendNodeId"].as_u64() {
+            Some(backend_node_id) => (backend_node_id as u32, false),
+            None if strict => return Err(DomError::CdpError("Missing backendNodeId".to_string())),
+            None => {
+                let assigned = self.next_synthetic_id();
+                warnings.push(ParseWarning::MissingBackendNodeId { node_id, assigned });
+                (assigned, true)
+            }
+        };
+
+        let node_type_val = cdp_node["nodeType"].as_u64();
+        let node_type = match node_type_val {
+            Some(value) => u8::try_from(value).ok().and_then(NodeType::from_u8),
+            None if strict => return Err(DomError::CdpError("Missing nodeType".to_string())),
+            None => None,
+        };
+        let node_type = match node_type {
+            Some(node_type) => node_type,
+            None if strict => {
+                return Err(DomError::InvalidNodeType {
+                    expected: "valid NodeType".to_string(),
+                    actual: format!("{}", node_type_val.unwrap_or_default()),
+                })
+            }
+            None => {
+                let assumed = node_type_from_name(&node_name);
+                warnings.push(ParseWarning::InvalidNodeType {
+                    node_id,
+                    value: node_type_val,
+                    assumed,
+                });
+                assumed
+            }
+        };
+
         let node_value = cdp_node["nodeValue"].as_str().unwrap_or("").to_string();
 
         // Parse attributes
@@ -139,18 +573,29 @@ impl DomService {
             }
         }
 
-        // Create node
-        let mut node = DomNode::new(
-            node_id,
-            backend_node_id,
-            node_type,
-            node_name,
-            target_id.clone(),
-        );
+        // Create node. Stable UUIDs need the node's frame, so they are filled
+        // in once the tree is linked; synthetic ids are unique across frames
+        // already.
+        let target_id = frame.target_id.clone();
+        let mut node = if self.config.random_node_uuids {
+            DomNode::new(node_id, backend_node_id, node_type, node_name, target_id)
+        } else {
+            let uuid = match synthetic {
+                true => DomNode::stable_uuid(&target_id, None, backend_node_id, true),
+                false => String::new(),
+            };
+            DomNode::with_uuid(
+                node_id,
+                backend_node_id,
+                node_type,
+                node_name,
+                target_id,
+                uuid,
+            )
+        };
 
         node.node_value = node_value;
         node.attributes = attributes;
-        node.parent_id = parent_id;
         node.frame_id = cdp_node["frameId"].as_str().map(String::from);
         node.is_scrollable = cdp_node.get("isScrollable").and_then(|v| v.as_bool());
 
@@ -164,47 +609,395 @@ impl DomService {
             };
         }
 
-        // Add node to arena
-        let current_node_id = self.arena.add_node(node);
+        Ok(node)
+    }
 
-        // Parse children
-        if let Some(children) = cdp_node["children"].as_array() {
-            let mut child_ids = smallvec::SmallVec::new();
+    /// Give the subtree at `root_id` UUIDs stable across captures of the page
+    ///
+    /// Does nothing with `random_node_uuids`, and skips nodes `build_node`
+    /// already gave one. A node's frame is that of the nearest iframe whose
+    /// document holds it, taken from the iframe element's `frameId`.
+    fn assign_stable_uuids(&mut self, root_id: NodeId) -> Result<()> {
+        if self.config.random_node_uuids {
+            return Ok(());
+        }
 
-            for child in children {
-                let child_id = self.parse_node(child, Some(current_node_id), target_id)?;
-                child_ids.push(child_id);
+        let mut frames: Vec<Option<String>> = vec![self.frame_id_of(root_id)?];
+        let mut stack = vec![(root_id, 0)];
+        let mut uuids = Vec::new();
+        while let Some((node_id, frame)) = stack.pop() {
+            let node = self.arena.get(node_id)?;
+            if node.uuid.is_empty() {
+                uuids.push((
+                    node_id,
+                    DomNode::stable_uuid(
+                        &node.target_id,
+                        frames[frame].as_deref(),
+                        node.backend_node_id,
+                        false,
+                    ),
+                ));
+            }
+            stack.extend(node.children_ids.iter().map(|&id| (id, frame)));
+            stack.extend(node.shadow_root_ids.iter().flatten().map(|&id| (id, frame)));
+            if let Some(doc_id) = node.content_document_id {
+                frames.push(node.frame_id.clone().or_else(|| frames[frame].clone()));
+                stack.push((doc_id, frames.len() - 1));
             }
+        }
+        for (node_id, uuid) in uuids {
+            self.arena.get_mut(node_id)?.uuid = uuid;
+        }
+        Ok(())
+    }
 
-            // Update parent's children list
-            if let Ok(node) = self.arena.get_mut(current_node_id) {
-                node.children_ids = child_ids;
+    /// `frameId` of the iframe whose document holds `node_id`, `None` in
+    /// the top document
+    fn frame_id_of(&self, node_id: NodeId) -> Result<Option<String>> {
+        self.arena.get(node_id)?;
+        let owner = std::iter::once(node_id)
+            .chain(self.arena.ancestor_ids(node_id))
+            .filter_map(|id| self.arena.get(id).ok())
+            .find(|node| node.node_type == NodeType::Document && node.parent_id.is_some())
+            .and_then(|document| document.parent_id);
+        Ok(match owner {
+            Some(owner) => self.arena.get(owner)?.frame_id.clone(),
+            None => None,
+        })
+    }
+
+    /// Next id for a node CDP sent without one
+    fn next_synthetic_id(&mut self) -> u32 {
+        let id = SYNTHETIC_ID_START - self.synthetic_ids;
+        self.synthetic_ids += 1;
+        id
+    }
+
+    /// Frame context for an iframe's `content_doc`, or `None` to skip it
+    ///
+    /// Skips cross-origin documents unless `cross_origin_iframes` is set,
+    /// and documents past `max_iframe_depth`/`max_iframes` unless
+    /// `strict_iframe_limits` turns those into errors.
+    fn enter_frame(
+        &self,
+        content_doc: &Value,
+        frame: &FrameContext,
+        iframes: &mut usize,
+    ) -> Result<Option<FrameContext>> {
+        let origin = content_doc["documentURL"]
+            .as_str()
+            .and_then(utils::url_origin);
+        // about:blank and srcdoc documents have no origin of their own
+        let cross_origin =
+            matches!((&frame.origin, &origin), (Some(outer), Some(inner)) if outer != inner);
+        if cross_origin && !self.config.cross_origin_iframes {
+            return Ok(None);
+        }
+
+        let depth = frame.depth + 1;
+        if depth > self.config.max_iframe_depth {
+            return match self.config.strict_iframe_limits {
+                true => Err(DomError::MaxIframeDepthExceeded {
+                    current: depth,
+                    max: self.config.max_iframe_depth,
+                }),
+                false => Ok(None),
+            };
+        }
+        if *iframes >= self.config.max_iframes {
+            return match self.config.strict_iframe_limits {
+                true => Err(DomError::MaxIframeCountExceeded {
+                    current: *iframes + 1,
+                    max: self.config.max_iframes,
+                }),
+                false => Ok(None),
+            };
+        }
+        *iframes += 1;
+
+        Ok(Some(FrameContext {
+            target_id: frame.target_id.clone(),
+            depth,
+            origin: origin.or_else(|| frame.origin.clone()),
+        }))
+    }
+
+    /// Tree depth of a node placed under `parent_id`
+    fn depth_under(&self, parent_id: Option<NodeId>) -> usize {
+        parent_id.map_or(0, |parent_id| {
+            self.arena.ancestor_ids(parent_id).count() + 1
+        })
+    }
+
+    /// Frame context for parsing new nodes under `parent_id`
+    ///
+    /// The arena doesn't keep document URLs, so the origin is unknown and
+    /// iframes in incremental updates aren't checked for cross-origin.
+    fn frame_context_of(&self, parent_id: Option<NodeId>) -> Result<(FrameContext, usize)> {
+        let iframes = self
+            .arena
+            .iter()
+            .filter(|node| node.content_document_id.is_some())
+            .count();
+        let Some(parent_id) = parent_id else {
+            let frame = FrameContext {
+                target_id: TargetId::from("default"),
+                depth: 0,
+                origin: None,
+            };
+            return Ok((frame, iframes));
+        };
+        let target_id = self.arena.get(parent_id)?.target_id.clone();
+        let depth = std::iter::once(parent_id)
+            .chain(self.arena.ancestor_ids(parent_id))
+            .filter(|&id| {
+                self.arena.get(id).is_ok_and(|node| {
+                    node.node_type == NodeType::Document && node.parent_id.is_some()
+                })
+            })
+            .count();
+        let frame = FrameContext {
+            target_id,
+            depth,
+            origin: None,
+        };
+        Ok((frame, iframes))
+    }
+
+    /// Replace the subtree of the node with `backend_node_id` by `cdp_subtree`
+    ///
+    /// `cdp_subtree` is a node in `DOM.getDocument`/`DOM.describeNode` form.
+    /// The new subtree takes the old one's place (child index, shadow root
+    /// or iframe document); the rest of the arena is untouched. Snapshot,
+    /// AX and visibility data of the new nodes need merging again.
+    pub fn apply_dom_update(
+        &mut self,
+        backend_node_id: u32,
+        cdp_subtree: &Value,
+    ) -> Result<NodeId> {
+        let node_id = self
+            .arena
+            .get_node_id_by_backend(backend_node_id)
+            .ok_or(DomError::BackendNodeNotFound(backend_node_id))?;
+        let parent_id = self.arena.get(node_id)?.parent_id;
+        let slot = self.slot_of(node_id)?;
+
+        // Parse before removing the old subtree, so a bad update leaves the
+        // tree as it was. The new nodes usually reuse the old backend IDs,
+        // so the old ones leave the index meanwhile.
+        let old_ids = self.arena.unindex_subtree(node_id)?;
+        let new_id = match self.parse_subtree(cdp_subtree, parent_id) {
+            Ok(new_id) => new_id,
+            Err(e) => {
+                self.arena.reindex(&old_ids);
+                return Err(e);
+            }
+        };
+        self.arena.remove_subtree(node_id)?;
+        self.put_in_slot(new_id, slot)?;
+        Ok(new_id)
+    }
+
+    /// Parse a CDP node under `parent_id`, without linking it in
+    ///
+    /// If parsing fails, the nodes parsed before the error are removed.
+    fn parse_subtree(&mut self, cdp_node: &Value, parent_id: Option<NodeId>) -> Result<NodeId> {
+        let (frame, mut iframes) = self.frame_context_of(parent_id)?;
+        let depth = self.depth_under(parent_id);
+        let mut warnings = Vec::new();
+        self.arena.checkpoint();
+        let parsed = self.parse_node(
+            cdp_node,
+            parent_id,
+            depth,
+            &frame,
+            &mut iframes,
+            &mut warnings,
+        );
+        let parsed = parsed.and_then(|node_id| {
+            self.assign_stable_uuids(node_id)?;
+            Ok(node_id)
+        });
+        match parsed {
+            Ok(_) => {
+                self.arena.commit();
+                log_warnings(&warnings);
+            }
+            Err(_) => {
+                self.arena.rollback();
             }
         }
+        parsed
+    }
 
-        // Parse content document (iframe)
-        if let Some(content_doc) = cdp_node.get("contentDocument") {
-            let doc_id = self.parse_node(content_doc, Some(current_node_id), target_id)?;
-            if let Ok(node) = self.arena.get_mut(current_node_id) {
-                node.content_document_id = Some(doc_id);
+    /// Apply a DOM domain event to the tree
+    ///
+    /// Handles `DOM.childNodeInserted` and `DOM.childNodeRemoved`, which
+    /// refer to nodes by CDP `nodeId`. Returns false for other methods, or
+    /// when the event's parent isn't in the tree (Chrome only reports
+    /// changes under nodes it sent us, so this means the tree is stale).
+    pub fn apply_cdp_event(&mut self, method: &str, params: &Value) -> Result<bool> {
+        match method {
+            "DOM.childNodeInserted" => {
+                let Some(parent_id) = self.find_by_cdp_id(&params["parentNodeId"]) else {
+                    return Ok(false);
+                };
+                let index = match params["previousNodeId"].as_u64() {
+                    Some(0) | None => 0,
+                    Some(previous) => {
+                        let Some(previous_id) = self.find_by_cdp_id(&previous.into()) else {
+                            return Ok(false);
+                        };
+                        self.arena
+                            .get(parent_id)?
+                            .children_ids
+                            .iter()
+                            .position(|&id| id == previous_id)
+                            .map_or(0, |index| index + 1)
+                    }
+                };
+                let child_id = self.parse_subtree(&params["node"], Some(parent_id))?;
+                self.put_in_slot(child_id, Slot::Child(parent_id, index))?;
+                Ok(true)
             }
+            "DOM.childNodeRemoved" => match self.find_by_cdp_id(&params["nodeId"]) {
+                Some(node_id) => {
+                    self.arena.remove_subtree(node_id)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            _ => Ok(false),
         }
+    }
 
-        // Parse shadow roots
-        if let Some(shadow_roots) = cdp_node["shadowRoots"].as_array() {
-            let mut shadow_ids = smallvec::SmallVec::new();
+    /// Live node with CDP `nodeId` `id`
+    fn find_by_cdp_id(&self, id: &Value) -> Option<NodeId> {
+        self.arena.get_node_id_by_cdp_id(id.as_u64()? as u32)
+    }
 
-            for shadow in shadow_roots {
-                let shadow_id = self.parse_node(shadow, Some(current_node_id), target_id)?;
-                shadow_ids.push(shadow_id);
+    /// Where `node_id` hangs in the tree
+    fn slot_of(&self, node_id: NodeId) -> Result<Slot> {
+        let Some(parent_id) = self.arena.get(node_id)?.parent_id else {
+            return Ok(if self.arena.root_id() == Some(node_id) {
+                Slot::Root
+            } else {
+                Slot::Detached
+            });
+        };
+        let parent = self.arena.get(parent_id)?;
+        if parent.content_document_id == Some(node_id) {
+            return Ok(Slot::ContentDocument(parent_id));
+        }
+        if let Some(index) = parent
+            .shadow_root_ids
+            .as_ref()
+            .and_then(|ids| ids.iter().position(|&id| id == node_id))
+        {
+            return Ok(Slot::ShadowRoot(parent_id, index));
+        }
+        let index = parent
+            .children_ids
+            .iter()
+            .position(|&id| id == node_id)
+            .unwrap_or(parent.children_ids.len());
+        Ok(Slot::Child(parent_id, index))
+    }
+
+    /// Link `node_id` into `slot` (indices past the end append)
+    fn put_in_slot(&mut self, node_id: NodeId, slot: Slot) -> Result<()> {
+        match slot {
+            Slot::Root => self.arena.set_root(node_id)?,
+            Slot::Detached => {}
+            Slot::Child(parent_id, index) => {
+                let children = &mut self.arena.get_mut(parent_id)?.children_ids;
+                children.insert(index.min(children.len()), node_id);
+            }
+            Slot::ShadowRoot(parent_id, index) => {
+                let shadow_root_ids = self
+                    .arena
+                    .get_mut(parent_id)?
+                    .shadow_root_ids
+                    .get_or_insert_with(Default::default);
+                shadow_root_ids.insert(index.min(shadow_root_ids.len()), node_id);
+            }
+            Slot::ContentDocument(parent_id) => {
+                self.arena.get_mut(parent_id)?.content_document_id = Some(node_id);
             }
+        }
+        Ok(())
+    }
 
-            if let Ok(node) = self.arena.get_mut(current_node_id) {
-                node.shadow_root_ids = Some(shadow_ids);
+    /// Merge adjacent sibling text nodes into one
+    ///
+    /// CDP splits text runs around entity references and after edits.
+    /// The first node of each run keeps the concatenated value; the rest
+    /// are unlinked and tombstoned. Returns the number of nodes merged away.
+    pub fn normalize_text_nodes(&mut self) -> Result<usize> {
+        self.merge_text_runs(|text, fragment| text.push_str(fragment))
+    }
+
+    /// Merge adjacent sibling text nodes, joining fragments with one space
+    ///
+    /// Like `normalize_text_nodes`, but whitespace where two fragments meet
+    /// collapses to a single space instead of being kept verbatim, so a
+    /// sentence split over several nodes serializes as one line. Returns
+    /// the number of nodes removed.
+    pub fn coalesce_text_nodes(&mut self) -> Result<usize> {
+        self.merge_text_runs(join_text)
+    }
+
+    /// Merge each run of adjacent text siblings into its first node
+    ///
+    /// `join` appends the next fragment to the run's text so far.
+    fn merge_text_runs(&mut self, join: fn(&mut String, &str)) -> Result<usize> {
+        let parent_ids: Vec<NodeId> = self.arena.node_ids().collect();
+        let mut merged = 0;
+
+        for parent_id in parent_ids {
+            // Text nodes merged away earlier in this pass
+            if self.arena.is_tombstoned(parent_id) {
+                continue;
+            }
+
+            let children = self.arena.get(parent_id)?.children_ids.clone();
+            if children.len() < 2 {
+                continue;
+            }
+
+            let mut kept = smallvec::SmallVec::new();
+            let mut run_head: Option<NodeId> = None;
+
+            for child_id in children {
+                if !self.arena.get(child_id)?.is_text() {
+                    run_head = None;
+                    kept.push(child_id);
+                    continue;
+                }
+
+                match run_head {
+                    Some(head_id) => {
+                        let value = std::mem::take(&mut self.arena.get_mut(child_id)?.node_value);
+                        join(&mut self.arena.get_mut(head_id)?.node_value, &value);
+                        self.arena.tombstone(child_id)?;
+                        merged += 1;
+                    }
+                    None => {
+                        run_head = Some(child_id);
+                        kept.push(child_id);
+                    }
+                }
             }
+
+            self.arena.get_mut(parent_id)?.children_ids = kept;
         }
 
-        Ok(current_node_id)
+        Ok(merged)
+    }
+
+    /// Radio/checkbox groups on the page (see `utils::find_input_groups`)
+    pub fn input_groups(&self) -> Vec<InputGroup> {
+        utils::find_input_groups(&self.arena)
     }
 
     /// Calculate visibility for all nodes
@@ -235,17 +1028,38 @@ impl DomService {
         }
 
         // Check bounds
-        let bounds = match &node.snapshot_node {
-            Some(snapshot) => snapshot.bounds.as_ref(),
-            None => return Ok(false),
+        let Some(mut bounds) = node.snapshot_node.as_ref().and_then(|s| s.bounds) else {
+            return Ok(false);
         };
 
-        if bounds.is_none() {
-            return Ok(false);
+        // Walk out through enclosing documents, innermost first. Snapshot
+        // bounds are relative to the node's own document: each IFRAME shifts
+        // them into its parent document, each HTML clips them to its viewport.
+        // Content documents are parented to their IFRAME, so this crosses frames.
+        for ancestor in self.arena.ancestors(node_id) {
+            if !ancestor.is_element() {
+                continue;
+            }
+            let Some(snapshot) = ancestor.snapshot_node.as_ref() else {
+                continue;
+            };
+
+            let name = ancestor.node_name.as_str();
+            if name.eq_ignore_ascii_case("iframe") || name.eq_ignore_ascii_case("frame") {
+                if let Some(frame_bounds) = snapshot.bounds {
+                    bounds = bounds.offset(frame_bounds.x, frame_bounds.y);
+                }
+            } else if name.eq_ignore_ascii_case("html") {
+                if let (Some(client), Some(scroll)) = (snapshot.client_rects, snapshot.scroll_rects)
+                {
+                    if !utils::check_frame_intersection(&bounds, &client, &scroll) {
+                        return Ok(false);
+                    }
+                    bounds = bounds.offset(-scroll.x, -scroll.y);
+                }
+            }
         }
 
-        // TODO: Implement full frame hierarchy visibility check
-        // For now, simplified version
         Ok(true)
     }
 
@@ -279,33 +1093,253 @@ impl DomService {
             role: ax_node["role"]["value"].as_str().map(String::from),
             name: ax_node["name"]["value"].as_str().map(String::from),
             description: ax_node["description"]["value"].as_str().map(String::from),
-            properties: None, // TODO: Parse properties
-            child_ids: None,  // TODO: Parse child IDs
+            properties: ax_node["properties"]
+                .as_array()
+                .map(|props| props.iter().filter_map(Self::parse_ax_property).collect()),
+            child_ids: ax_node["childIds"].as_array().map(|ids| {
+                ids.iter()
+                    .filter_map(|id| id.as_str().map(String::from))
+                    .collect()
+            }),
         })
     }
 
-    /// Merge snapshot data from DOMSnapshot.captureSnapshot
-    pub fn merge_snapshot(&mut self, _snapshot: &Value, _device_pixel_ratio: f64) -> Result<()> {
-        // TODO: Implement full snapshot merging
-        // This requires parsing the complex DOMSnapshot format
-        Ok(())
+    /// Parse one `{ name, value: { type, value } }` AX property
+    ///
+    /// Booleans and boolean tristates become `Bool`, so `"mixed"` stays a
+    /// string; numbers are kept as their string form.
+    fn parse_ax_property(property: &Value) -> Option<AXProperty> {
+        let name = AXPropertyName::from(property["name"].as_str()?);
+        let value = match &property["value"]["value"] {
+            Value::Bool(b) => AXPropertyValue::Bool(*b),
+            Value::String(s) if s == "true" => AXPropertyValue::Bool(true),
+            Value::String(s) if s == "false" => AXPropertyValue::Bool(false),
+            Value::String(s) => AXPropertyValue::String(s.clone()),
+            Value::Number(n) => AXPropertyValue::String(n.to_string()),
+            _ => AXPropertyValue::Null,
+        };
+        Some(AXProperty { name, value })
     }
 
-    /// Get serialized DOM state for LLM
-    pub fn serialize_for_llm(&self) -> Result<String> {
-        // TODO: Implement serialization
-        // This will use the serializer module
-        Ok(String::new())
-    }
-}
+    /// Merge snapshot data from DOMSnapshot.captureSnapshot
+    ///
+    /// The snapshot is columnar: per-document index arrays plus a shared
+    /// string table. Layout entries map back to DOM nodes via
+    /// `layout.nodeIndex` → `nodes.backendNodeId`. Raw layout coordinates are
+    /// device pixels; dividing by `device_pixel_ratio` gives CSS pixels.
+    ///
+    /// The request must use `REQUIRED_COMPUTED_STYLES` for `computedStyles`.
+    pub fn merge_snapshot(&mut self, snapshot: &Value, device_pixel_ratio: f64) -> Result<()> {
+        let documents = snapshot["documents"]
+            .as_array()
+            .ok_or_else(|| DomError::CdpError("Missing 'documents' in snapshot".to_string()))?;
+        let strings: Vec<&str> = snapshot["strings"]
+            .as_array()
+            .map(|s| s.iter().map(|v| v.as_str().unwrap_or("")).collect())
+            .unwrap_or_default();
+        let dpr = if device_pixel_ratio > 0.0 {
+            device_pixel_ratio
+        } else {
+            1.0
+        };
 
-impl Default for DomService {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+        for document in documents {
+            let backend_ids: Vec<u32> = document["nodes"]["backendNodeId"]
+                .as_array()
+                .map(|ids| ids.iter().map(|v| v.as_u64().unwrap_or(0) as u32).collect())
+                .unwrap_or_default();
 
-#[cfg(test)]
+            let mut snapshots: HashMap<usize, SnapshotNode> = HashMap::new();
+
+            // Clickability is rare boolean data: a list of node indices
+            for idx in Self::index_list(&document["nodes"]["isClickable"]["index"]) {
+                snapshots.entry(idx).or_default().is_clickable = Some(true);
+            }
+
+            let layout = &document["layout"];
+            let stacking: std::collections::HashSet<usize> =
+                Self::index_list(&layout["stackingContexts"]["index"]).collect();
+
+            for (layout_idx, node_idx) in Self::index_list(&layout["nodeIndex"]).enumerate() {
+                let entry = snapshots.entry(node_idx).or_default();
+
+                entry.bounds = Self::parse_rect(&layout["bounds"][layout_idx], dpr);
+                entry.client_rects = Self::parse_rect(&layout["clientRects"][layout_idx], dpr);
+                entry.scroll_rects = Self::parse_rect(&layout["scrollRects"][layout_idx], dpr);
+                entry.paint_order = layout["paintOrders"][layout_idx].as_i64().map(|p| p as i32);
+                entry.stacking_contexts = stacking.contains(&layout_idx).then_some(1);
+
+                if let Some(style_ids) = layout["styles"][layout_idx].as_array() {
+                    let styles: HashMap<String, String> = REQUIRED_COMPUTED_STYLES
+                        .iter()
+                        .zip(style_ids)
+                        .filter_map(|(name, id)| {
+                            let value = strings.get(id.as_u64()? as usize)?;
+                            Some((name.to_string(), value.to_string()))
+                        })
+                        .collect();
+                    entry.cursor_style = styles.get("cursor").cloned();
+                    entry.computed_styles = Some(styles);
+                }
+            }
+
+            for (node_idx, snapshot_node) in snapshots {
+                let Some(&backend_id) = backend_ids.get(node_idx) else {
+                    continue;
+                };
+                if let Some(node_id) = self.arena.get_node_id_by_backend(backend_id) {
+                    if let Ok(node) = self.arena.get_mut(node_id) {
+                        node.snapshot_node = Some(Box::new(snapshot_node));
+                    }
+                }
+            }
+        }
+
+        self.compute_absolute_positions()
+    }
+
+    /// Set each element's `absolute_position` from its snapshot bounds
+    ///
+    /// Snapshot bounds are relative to the node's own document. Walking down
+    /// from the root, each IFRAME shifts its content document by the frame's
+    /// own position, less that document's scroll offset, so the result is in
+    /// top-level page coordinates. Nodes without bounds get `None`.
+    /// `merge_snapshot` runs this already.
+    pub fn compute_absolute_positions(&mut self) -> Result<()> {
+        let Some(root_id) = self.arena.root_id() else {
+            return Ok(());
+        };
+
+        // (node, offset into top-level page coordinates, inside an iframe)
+        let mut stack = vec![(root_id, 0.0, 0.0, false)];
+        while let Some((node_id, dx, dy, in_frame)) = stack.pop() {
+            let node = self.arena.get_mut(node_id)?;
+            let bounds = node.snapshot_node.as_ref().and_then(|s| s.bounds);
+            node.absolute_position = bounds.map(|b| b.offset(dx, dy));
+
+            let name = node.node_name.as_str();
+            let (mut child_dx, mut child_dy) = (dx, dy);
+            // A frame document's content is drawn shifted up by its scroll;
+            // the top document's own scroll doesn't change page coordinates
+            if in_frame && name.eq_ignore_ascii_case("html") {
+                if let Some(scroll) = node.snapshot_node.as_ref().and_then(|s| s.scroll_rects) {
+                    child_dx -= scroll.x;
+                    child_dy -= scroll.y;
+                }
+            }
+
+            let node = self.arena.get(node_id)?;
+            if let Some(document_id) = node.content_document_id {
+                // A frame without layout has no visible content
+                let (frame_dx, frame_dy) = bounds.map_or((dx, dy), |b| (dx + b.x, dy + b.y));
+                stack.push((document_id, frame_dx, frame_dy, true));
+            }
+            stack.extend(
+                node.shadow_root_ids
+                    .iter()
+                    .flatten()
+                    .chain(&node.children_ids)
+                    .filter(|&&id| !self.arena.is_tombstoned(id))
+                    .map(|&id| (id, child_dx, child_dy, in_frame)),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Read a CDP index array as usizes
+    fn index_list(value: &Value) -> impl Iterator<Item = usize> + '_ {
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_u64().map(|i| i as usize))
+    }
+
+    /// Parse an `[x, y, width, height]` rect, scaled to CSS pixels
+    fn parse_rect(value: &Value, dpr: f64) -> Option<DomRect> {
+        let rect = value.as_array()?;
+        if rect.len() < 4 {
+            return None;
+        }
+        let n = |i: usize| rect[i].as_f64().unwrap_or(0.0) / dpr;
+        Some(DomRect::new(n(0), n(1), n(2), n(3)))
+    }
+
+    /// The parsed tree as real HTML, for debugging and diffing against the
+    /// page source
+    ///
+    /// Unlike `serialize_for_llm`, nothing is filtered out.
+    pub fn to_html(&self) -> String {
+        self.arena.to_html()
+    }
+
+    /// Get serialized DOM state for LLM
+    pub fn serialize_for_llm(&self) -> Result<String> {
+        self.serialize_for_llm_with(SerializerConfig {
+            paint_order_filtering: self.config.paint_order_filtering,
+            max_iframe_depth: self.config.max_iframe_depth,
+            exclude_aria_hidden: self.config.exclude_aria_hidden,
+            ..SerializerConfig::default()
+        })
+    }
+
+    /// Get serialized DOM state for LLM with a custom serializer config
+    ///
+    /// Visibility `calculate_visibility` hasn't stored yet is computed into
+    /// a side table, so the stored tree is left untouched.
+    pub fn serialize_for_llm_with(&self, config: SerializerConfig) -> Result<String> {
+        let mut visibility = AHashMap::new();
+        for node_id in self.arena.node_ids() {
+            if self.arena.get(node_id)?.is_visible.is_none() {
+                visibility.insert(node_id, self.is_node_visible(node_id)?);
+            }
+        }
+        DomSerializer::with_config(config)
+            .with_visibility(visibility)
+            .serialize(&self.arena)
+    }
+}
+
+impl Default for DomService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Best guess at the type of a node CDP sent without a valid `nodeType`
+fn node_type_from_name(node_name: &str) -> NodeType {
+    match node_name {
+        "#document" => NodeType::Document,
+        "#document-fragment" => NodeType::DocumentFragment,
+        "#text" => NodeType::Text,
+        "#comment" => NodeType::Comment,
+        "#cdata-section" => NodeType::CdataSection,
+        _ => NodeType::Element,
+    }
+}
+
+fn log_warnings(warnings: &[ParseWarning]) {
+    for warning in warnings {
+        tracing::warn!("Incomplete CDP node: {}", warning);
+    }
+}
+
+/// Append `fragment` to `text`, collapsing whitespace where they meet
+fn join_text(text: &mut String, fragment: &str) {
+    let trimmed = fragment.trim_start();
+    if trimmed.len() == fragment.len() && !text.ends_with(char::is_whitespace) {
+        text.push_str(fragment);
+        return;
+    }
+    text.truncate(text.trim_end().len());
+    if !text.is_empty() {
+        text.push(' ');
+    }
+    text.push_str(trimmed);
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -334,5 +1368,1227 @@ mod tests {
 
         assert_eq!(root_id, 0);
         assert_eq!(service.arena().len(), 2);
+
+        // Re-parsing the same page gives the same UUIDs
+        let uuid =
+            |service: &DomService| service.arena().get_by_backend_id(2).unwrap().uuid.clone();
+        let first = uuid(&service);
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert_eq!(uuid(&service), first);
+        assert_ne!(service.arena().get_by_backend_id(1).unwrap().uuid, first);
+
+        let mut random = DomService::with_config(DomServiceConfig {
+            random_node_uuids: true,
+            ..DomServiceConfig::default()
+        });
+        random.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert_ne!(uuid(&random), first);
+    }
+
+    #[test]
+    fn test_stable_uuids_do_not_collide() {
+        let iframe = |id: u32, frame_id: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "IFRAME",
+                "frameId": frame_id,
+                "contentDocument": {
+                    "nodeId": id + 1,
+                    // Both frame documents reuse one backend id
+                    "backendNodeId": 50,
+                    "nodeType": 9,
+                    "nodeName": "#document",
+                }
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [
+                    iframe(2, "F1"),
+                    iframe(4, "F2"),
+                    // Real id equal to the first synthetic one
+                    {"nodeId": 6, "backendNodeId": SYNTHETIC_ID_START, "nodeType": 1, "nodeName": "P"},
+                    {"nodeId": 7, "nodeType": 1, "nodeName": "P"},
+                ]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let uuids: Vec<String> = service.arena().iter().map(|n| n.uuid.clone()).collect();
+        assert_eq!(uuids.len(), 7);
+        let distinct: std::collections::HashSet<&String> = uuids.iter().collect();
+        assert_eq!(distinct.len(), uuids.len(), "{:?}", uuids);
+
+        // Still stable across re-parses
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let reparsed: Vec<String> = service.arena().iter().map(|n| n.uuid.clone()).collect();
+        assert_eq!(reparsed, uuids);
+    }
+
+    #[test]
+    fn test_parse_incomplete_nodes() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [
+                    // Pseudo-element without ids
+                    {"nodeType": 1, "nodeName": "::before"},
+                    {"nodeId": 3, "nodeName": "#text", "nodeValue": "hi"},
+                    {"nodeId": 4, "backendNodeId": 4, "nodeType": 42, "nodeName": "DIV"}
+                ]
+            }
+        });
+
+        let mut service = DomService::new();
+        let (root_id, warnings) = service.parse_cdp_dom_tree_with_warnings(&cdp_json).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                ParseWarning::MissingNodeId {
+                    assigned: SYNTHETIC_ID_START
+                },
+                ParseWarning::MissingBackendNodeId {
+                    node_id: SYNTHETIC_ID_START,
+                    assigned: SYNTHETIC_ID_START - 1
+                },
+                ParseWarning::MissingBackendNodeId {
+                    node_id: 3,
+                    assigned: SYNTHETIC_ID_START - 2
+                },
+                ParseWarning::InvalidNodeType {
+                    node_id: 3,
+                    value: None,
+                    assumed: NodeType::Text
+                },
+                ParseWarning::InvalidNodeType {
+                    node_id: 4,
+                    value: Some(42),
+                    assumed: NodeType::Element
+                },
+            ]
+        );
+        let arena = service.arena();
+        let types: Vec<_> = arena
+            .children(root_id)
+            .unwrap()
+            .iter()
+            .map(|node| node.node_type)
+            .collect();
+        assert_eq!(
+            types,
+            [NodeType::Element, NodeType::Text, NodeType::Element]
+        );
+        assert!(arena
+            .get_node_id_by_backend(SYNTHETIC_ID_START - 2)
+            .is_some());
+
+        // Synthetic ids restart with each full parse
+        let (_, again) = service.parse_cdp_dom_tree_with_warnings(&cdp_json).unwrap();
+        assert_eq!(again, warnings);
+
+        let mut strict = DomService::with_config(DomServiceConfig {
+            strict_parsing: true,
+            ..DomServiceConfig::default()
+        });
+        assert!(matches!(
+            strict.parse_cdp_dom_tree(&cdp_json),
+            Err(DomError::CdpError(message)) if message == "Missing nodeId"
+        ));
+    }
+
+    #[test]
+    fn test_failed_parse_keeps_previous_tree() {
+        let mut service = DomService::with_config(DomServiceConfig {
+            strict_parsing: true,
+            ..DomServiceConfig::default()
+        });
+        let good = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [
+                    {"nodeId": 2, "backendNodeId": 2, "nodeType": 1, "nodeName": "BODY"}
+                ]
+            }
+        });
+        let root_id = service.parse_cdp_dom_tree(&good).unwrap();
+
+        // Fails on the last node, after the others were parsed
+        let bad = serde_json::json!({
+            "root": {
+                "nodeId": 10,
+                "backendNodeId": 10,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [
+                    {"nodeId": 11, "backendNodeId": 11, "nodeType": 1, "nodeName": "DIV"},
+                    {"backendNodeId": 12, "nodeType": 1, "nodeName": "DIV"}
+                ]
+            }
+        });
+        let bad_flat = serde_json::json!({
+            "nodes": [
+                {"nodeId": 10, "backendNodeId": 10, "nodeType": 9, "nodeName": "#document"},
+                {"nodeId": 11, "parentId": 10, "backendNodeId": 11, "nodeType": 1, "nodeName": "DIV"},
+                {"parentId": 10, "backendNodeId": 12, "nodeType": 1, "nodeName": "DIV"}
+            ]
+        });
+        assert!(service.parse_cdp_dom_tree(&bad).is_err());
+        assert!(service.parse_cdp_flattened(&bad_flat).is_err());
+
+        let arena = service.arena();
+        assert_eq!(arena.root_id(), Some(root_id));
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get_by_backend_id(2).unwrap().node_name, "BODY");
+        assert!(arena.get_node_id_by_backend(11).is_none());
+    }
+
+    #[test]
+    fn test_parse_cdp_flattened() {
+        let nested = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 2,
+                    "nodeType": 1,
+                    "nodeName": "BODY",
+                    "children": [
+                        {"nodeId": 3, "backendNodeId": 3, "nodeType": 3, "nodeName": "#text", "nodeValue": "Hi"},
+                        {
+                            "nodeId": 4,
+                            "backendNodeId": 4,
+                            "nodeType": 1,
+                            "nodeName": "IFRAME",
+                            "contentDocument": {
+                                "nodeId": 6,
+                                "backendNodeId": 6,
+                                "nodeType": 9,
+                                "nodeName": "#document",
+                                "children": [{"nodeId": 7, "backendNodeId": 7, "nodeType": 1, "nodeName": "P"}]
+                            }
+                        },
+                        {
+                            "nodeId": 5,
+                            "backendNodeId": 5,
+                            "nodeType": 1,
+                            "nodeName": "MY-WIDGET",
+                            "attributes": ["id", "w"],
+                            "shadowRoots": [{
+                                "nodeId": 8,
+                                "backendNodeId": 8,
+                                "nodeType": 11,
+                                "nodeName": "#document-fragment",
+                                "shadowRootType": "open"
+                            }]
+                        }
+                    ]
+                }]
+            }
+        });
+        // Same tree, flattened; some nodes come before their parents
+        let flattened = serde_json::json!({
+            "nodes": [
+                {"nodeId": 1, "backendNodeId": 1, "nodeType": 9, "nodeName": "#document"},
+                {"nodeId": 2, "parentId": 1, "backendNodeId": 2, "nodeType": 1, "nodeName": "BODY"},
+                {"nodeId": 6, "parentId": 4, "backendNodeId": 6, "nodeType": 9, "nodeName": "#document"},
+                {"nodeId": 7, "parentId": 6, "backendNodeId": 7, "nodeType": 1, "nodeName": "P"},
+                {"nodeId": 3, "parentId": 2, "backendNodeId": 3, "nodeType": 3, "nodeName": "#text", "nodeValue": "Hi"},
+                {"nodeId": 4, "parentId": 2, "backendNodeId": 4, "nodeType": 1, "nodeName": "IFRAME"},
+                {"nodeId": 8, "parentId": 5, "backendNodeId": 8, "nodeType": 11, "nodeName": "#document-fragment", "shadowRootType": "open"},
+                {"nodeId": 5, "parentId": 2, "backendNodeId": 5, "nodeType": 1, "nodeName": "MY-WIDGET", "attributes": ["id", "w"]}
+            ]
+        });
+
+        let mut expected = DomService::with_config(DomServiceConfig {
+            cross_origin_iframes: true,
+            ..DomServiceConfig::default()
+        });
+        expected.parse_cdp_dom_tree(&nested).unwrap();
+        let mut service = DomService::new();
+        let root_id = service.parse_cdp_flattened(&flattened).unwrap();
+
+        assert_eq!(service.arena().root_id(), Some(root_id));
+        assert_eq!(service.arena().len(), 8);
+        assert_eq!(
+            service.serialize_for_llm().unwrap(),
+            expected.serialize_for_llm().unwrap()
+        );
+
+        let arena = service.arena();
+        let body = arena.get_by_backend_id(2).unwrap();
+        let children: Vec<u32> = body
+            .children_ids
+            .iter()
+            .map(|&id| arena.get(id).unwrap().backend_node_id)
+            .collect();
+        assert_eq!(children, [3, 4, 5]);
+        let iframe = arena.get_by_backend_id(4).unwrap();
+        let document = arena.get(iframe.content_document_id.unwrap()).unwrap();
+        assert_eq!(document.backend_node_id, 6);
+        assert_eq!(document.parent_id, arena.get_node_id_by_backend(4));
+        let widget = arena.get_by_backend_id(5).unwrap();
+        assert_eq!(
+            widget.shadow_root_ids.as_ref().map(|ids| ids.len()),
+            Some(1)
+        );
+
+        let missing = serde_json::json!({"root": {}});
+        assert!(service.parse_cdp_flattened(&missing).is_err());
+    }
+
+    #[test]
+    fn test_serialize_for_llm_matches_serializer() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "BUTTON",
+                "attributes": ["id", "go"],
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 2,
+                    "nodeType": 3,
+                    "nodeName": "#text",
+                    "nodeValue": "Go"
+                }]
+            }
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        for node_id in service.arena().node_ids().collect::<Vec<_>>() {
+            service.arena_mut().get_mut(node_id).unwrap().snapshot_node =
+                Some(Box::new(SnapshotNode {
+                    is_clickable: Some(true),
+                    bounds: Some(DomRect::new(0.0, 0.0, 80.0, 20.0)),
+                    ..SnapshotNode::default()
+                }));
+        }
+
+        // Visibility not computed yet - serialize_for_llm computes it on the side
+        let output = service.serialize_for_llm().unwrap();
+        assert!(output.contains("<BUTTON id=\"go\">"));
+        assert!(service.arena().iter().all(|n| n.is_visible.is_none()));
+
+        service.calculate_visibility().unwrap();
+        let expected = DomSerializer::new().serialize(service.arena()).unwrap();
+        assert_eq!(output, expected);
+
+        // Serializer errors keep their variant
+        let root = service.arena().root_id().unwrap();
+        service
+            .arena_mut()
+            .get_mut(root)
+            .unwrap()
+            .children_ids
+            .push(99);
+        let err = service.serialize_for_llm().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                DomError::NodeNotFound(99) | DomError::NodeNotFoundInContext { node_id: 99, .. }
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    /// `#document -> HTML -> BODY -> [DIV -> "old", P]`, nodeId == backendNodeId
+    fn small_page() -> DomService {
+        let element = |id: u32, name: &str, children: Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": name,
+                "children": children,
+            })
+        };
+        let text = serde_json::json!({
+            "nodeId": 5,
+            "backendNodeId": 5,
+            "nodeType": 3,
+            "nodeName": "#text",
+            "nodeValue": "old",
+        });
+        let body = element(
+            3,
+            "BODY",
+            serde_json::json!([
+                element(4, "DIV", serde_json::json!([text])),
+                element(6, "P", serde_json::json!([]))
+            ]),
+        );
+        let mut service = DomService::new();
+        service
+            .parse_cdp_dom_tree(&serde_json::json!({
+                "root": {
+                    "nodeId": 1,
+                    "backendNodeId": 1,
+                    "nodeType": 9,
+                    "nodeName": "#document",
+                    "children": [element(2, "HTML", serde_json::json!([body]))],
+                }
+            }))
+            .unwrap();
+        service
+    }
+
+    /// Tag names of BODY's children
+    fn body_children(service: &DomService) -> Vec<String> {
+        let body = service.arena().get_node_id_by_backend(3).unwrap();
+        service
+            .arena()
+            .children(body)
+            .unwrap()
+            .iter()
+            .map(|node| node.node_name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_dom_update_replaces_subtree_in_place() {
+        let mut service = small_page();
+        let new_id = service
+            .apply_dom_update(
+                4,
+                &serde_json::json!({
+                    "nodeId": 40,
+                    "backendNodeId": 40,
+                    "nodeType": 1,
+                    "nodeName": "SECTION",
+                    "children": [{ "nodeId": 41, "backendNodeId": 41, "nodeType": 1, "nodeName": "SPAN" }],
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(body_children(&service), vec!["SECTION", "P"]);
+        assert_eq!(service.arena().get_node_id_by_backend(40), Some(new_id));
+        assert!(service.arena().get_node_id_by_backend(5).is_none());
+        assert_eq!(service.arena().len(), 6);
+        assert!(matches!(
+            service.apply_dom_update(4, &Value::Null),
+            Err(DomError::BackendNodeNotFound(4))
+        ));
+    }
+
+    #[test]
+    fn test_apply_dom_update_failure_keeps_old_subtree() {
+        let mut service = small_page();
+        service.config.strict_parsing = true;
+        // Same backend IDs as the DIV's subtree, failing two levels down
+        let update = serde_json::json!({
+            "nodeId": 40,
+            "backendNodeId": 4,
+            "nodeType": 1,
+            "nodeName": "DIV",
+            "children": [{
+                "nodeId": 41,
+                "backendNodeId": 5,
+                "nodeType": 1,
+                "nodeName": "SPAN",
+                "children": [{ "backendNodeId": 42, "nodeType": 1, "nodeName": "B" }],
+            }],
+        });
+
+        assert!(matches!(
+            service.apply_dom_update(4, &update),
+            Err(DomError::CdpError(message)) if message == "Missing nodeId"
+        ));
+        assert_eq!(body_children(&service), vec!["DIV", "P"]);
+        assert_eq!(service.arena().len(), 6);
+        let text = service.arena().get_node_id_by_backend(5).unwrap();
+        assert_eq!(service.arena().get(text).unwrap().node_value, "old");
+        assert_eq!(service.arena().get_all_by_backend_id(4).len(), 1);
+    }
+
+    #[test]
+    fn test_apply_cdp_event() {
+        let mut service = small_page();
+        let inserted = |previous: u32, id: u32, name: &str| {
+            serde_json::json!({
+                "parentNodeId": 3,
+                "previousNodeId": previous,
+                "node": { "nodeId": id, "backendNodeId": id, "nodeType": 1, "nodeName": name },
+            })
+        };
+
+        assert!(service
+            .apply_cdp_event("DOM.childNodeInserted", &inserted(4, 7, "BUTTON"))
+            .unwrap());
+        assert!(service
+            .apply_cdp_event("DOM.childNodeInserted", &inserted(0, 8, "NAV"))
+            .unwrap());
+        assert_eq!(body_children(&service), vec!["NAV", "DIV", "BUTTON", "P"]);
+
+        let removed = serde_json::json!({ "parentNodeId": 3, "nodeId": 4 });
+        assert!(service
+            .apply_cdp_event("DOM.childNodeRemoved", &removed)
+            .unwrap());
+        assert_eq!(body_children(&service), vec!["NAV", "BUTTON", "P"]);
+        assert!(service.arena().get_node_id_by_backend(5).is_none());
+
+        // Unknown parent or method: nothing to apply
+        let orphan = serde_json::json!({ "parentNodeId": 99, "previousNodeId": 0, "node": {} });
+        assert!(!service
+            .apply_cdp_event("DOM.childNodeInserted", &orphan)
+            .unwrap());
+        assert!(!service
+            .apply_cdp_event("DOM.attributeModified", &removed)
+            .unwrap());
+    }
+
+    /// Outer page with a 400x300 iframe at `iframe_pos`, inner document scrolled
+    /// by `inner_scroll_y`, and a button at (10, 20) inside the iframe
+    fn iframe_page(iframe_pos: (f64, f64), inner_scroll_y: f64) -> DomService {
+        let element = |id: u32, tag: &str, children: Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": tag,
+                "attributes": [],
+                "children": children,
+            })
+        };
+        let mut iframe = element(3, "IFRAME", serde_json::json!([]));
+        iframe["contentDocument"] = serde_json::json!({
+            "nodeId": 4,
+            "backendNodeId": 4,
+            "nodeType": 9,
+            "nodeName": "#document",
+            "children": [element(5, "HTML", serde_json::json!([element(6, "BUTTON", serde_json::json!([]))]))],
+        });
+        let cdp_json = serde_json::json!({
+            "root": element(1, "HTML", serde_json::json!([element(2, "BODY", serde_json::json!([iframe]))]))
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let viewport = |width: f64, height: f64, scroll_y: f64| SnapshotNode {
+            bounds: Some(DomRect::new(0.0, 0.0, width, height)),
+            client_rects: Some(DomRect::new(0.0, 0.0, width, height)),
+            scroll_rects: Some(DomRect::new(0.0, scroll_y, width, 5000.0)),
+            ..SnapshotNode::default()
+        };
+        let snapshots = [
+            (1, viewport(1000.0, 800.0, 0.0)),
+            (
+                2,
+                SnapshotNode {
+                    bounds: Some(DomRect::new(0.0, 0.0, 1000.0, 800.0)),
+                    ..SnapshotNode::default()
+                },
+            ),
+            (
+                3,
+                SnapshotNode {
+                    bounds: Some(DomRect::new(iframe_pos.0, iframe_pos.1, 400.0, 300.0)),
+                    ..SnapshotNode::default()
+                },
+            ),
+            (5, viewport(400.0, 300.0, inner_scroll_y)),
+            (
+                6,
+                SnapshotNode {
+                    bounds: Some(DomRect::new(10.0, 20.0, 50.0, 20.0)),
+                    ..SnapshotNode::default()
+                },
+            ),
+        ];
+        for (backend_id, snapshot) in snapshots {
+            let id = service.arena().get_node_id_by_backend(backend_id).unwrap();
+            service.arena_mut().get_mut(id).unwrap().snapshot_node = Some(Box::new(snapshot));
+        }
+
+        service.calculate_visibility().unwrap();
+        service
+    }
+
+    fn button_visible(service: &DomService) -> Option<bool> {
+        service.arena().get_by_backend_id(6).unwrap().is_visible
+    }
+
+    #[test]
+    fn test_frame_visibility_in_view() {
+        let service = iframe_page((0.0, 100.0), 0.0);
+        assert_eq!(button_visible(&service), Some(true));
+    }
+
+    #[test]
+    fn test_frame_visibility_clipped_by_iframe_scroll() {
+        // Inner document scrolled 5000px past the button
+        let service = iframe_page((0.0, 100.0), 5000.0);
+        assert_eq!(button_visible(&service), Some(false));
+    }
+
+    #[test]
+    fn test_frame_visibility_clipped_by_outer_viewport() {
+        // Button is in view within the iframe, but the iframe is off to the right
+        let service = iframe_page((2000.0, 100.0), 0.0);
+        assert_eq!(button_visible(&service), Some(false));
+
+        // Below the fold but within the 1000px tolerance
+        let service = iframe_page((0.0, 1500.0), 0.0);
+        assert_eq!(button_visible(&service), Some(true));
+    }
+
+    #[test]
+    fn test_absolute_positions_cross_iframes() {
+        // Iframe at y=100, its document scrolled by 30
+        let mut service = iframe_page((50.0, 100.0), 30.0);
+        service.compute_absolute_positions().unwrap();
+
+        let position = |backend_id| {
+            service
+                .arena()
+                .get_by_backend_id(backend_id)
+                .unwrap()
+                .absolute_position
+        };
+        assert_eq!(position(3), Some(DomRect::new(50.0, 100.0, 400.0, 300.0)));
+        assert_eq!(position(6), Some(DomRect::new(60.0, 90.0, 50.0, 20.0)));
+        // The top document's scroll doesn't move page coordinates
+        assert_eq!(position(2), Some(DomRect::new(0.0, 0.0, 1000.0, 800.0)));
+        // The frame's #document has no layout box
+        assert_eq!(position(4), None);
+    }
+
+    /// `depth` iframes nested inside each other, the nth document loaded
+    /// from `urls[n]` (index 0 is the top document)
+    fn nested_iframes(depth: u32, urls: &[&str]) -> Value {
+        let mut doc = serde_json::json!({
+            "nodeId": 1000,
+            "backendNodeId": 1000,
+            "nodeType": 1,
+            "nodeName": "BUTTON",
+        });
+        for level in (0..=depth).rev() {
+            let id = level * 10;
+            let mut document = serde_json::json!({
+                "nodeId": id + 1,
+                "backendNodeId": id + 1,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [{
+                    "nodeId": id + 2,
+                    "backendNodeId": id + 2,
+                    "nodeType": 1,
+                    "nodeName": "HTML",
+                    "children": [doc],
+                }],
+            });
+            if let Some(url) = urls.get(level as usize) {
+                document["documentURL"] = serde_json::json!(url);
+            }
+            doc = document;
+            // Each iframe holds the next document
+            if level > 0 {
+                doc = serde_json::json!({
+                    "nodeId": id,
+                    "backendNodeId": id,
+                    "nodeType": 1,
+                    "nodeName": "IFRAME",
+                    "contentDocument": doc,
+                });
+            }
+        }
+        serde_json::json!({ "root": doc })
+    }
+
+    fn iframe_documents(service: &DomService) -> usize {
+        service
+            .arena()
+            .iter()
+            .filter(|node| node.node_type == NodeType::Document)
+            .count()
+    }
+
+    #[test]
+    fn test_iframe_depth_limit() {
+        let cdp_json = nested_iframes(4, &[]);
+
+        let mut service = DomService::with_config(DomServiceConfig {
+            max_iframe_depth: 2,
+            ..DomServiceConfig::default()
+        });
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        // Top document plus two levels; the third iframe is left empty
+        assert_eq!(iframe_documents(&service), 3);
+        let empty: Vec<_> = service
+            .arena()
+            .iter()
+            .filter(|node| node.node_name == "IFRAME" && node.content_document_id.is_none())
+            .filter(|node| node.children_ids.is_empty())
+            .collect();
+        assert_eq!(empty.len(), 1);
+        assert!(service.arena().get_node_id_by_backend(1000).is_none());
+
+        let mut strict = DomService::with_config(DomServiceConfig {
+            max_iframe_depth: 2,
+            strict_iframe_limits: true,
+            ..DomServiceConfig::default()
+        });
+        assert!(matches!(
+            strict.parse_cdp_dom_tree(&cdp_json),
+            Err(DomError::MaxIframeDepthExceeded { current: 3, max: 2 })
+        ));
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert!(service.arena().get_node_id_by_backend(1000).is_some());
+    }
+
+    #[test]
+    fn test_iframe_count_limit() {
+        let cdp_json = nested_iframes(3, &[]);
+
+        let mut service = DomService::with_config(DomServiceConfig {
+            max_iframes: 1,
+            ..DomServiceConfig::default()
+        });
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert_eq!(iframe_documents(&service), 2);
+
+        let mut strict = DomService::with_config(DomServiceConfig {
+            max_iframes: 1,
+            strict_iframe_limits: true,
+            ..DomServiceConfig::default()
+        });
+        assert!(matches!(
+            strict.parse_cdp_dom_tree(&cdp_json),
+            Err(DomError::MaxIframeCountExceeded { current: 2, max: 1 })
+        ));
+    }
+
+    /// A `DOM.getDocument` response as `DOM.getFlattenedDocument` would send it
+    fn flatten(tree: &Value) -> Value {
+        let mut nodes = Vec::new();
+        let mut stack = vec![(&tree["root"], None)];
+        while let Some((cdp_node, parent_id)) = stack.pop() {
+            let mut node = cdp_node.clone();
+            for key in ["children", "contentDocument", "shadowRoots"] {
+                node.as_object_mut().unwrap().remove(key);
+            }
+            if let Some(parent_id) = parent_id {
+                node["parentId"] = serde_json::json!(parent_id);
+            }
+            nodes.push(node);
+
+            let node_id = cdp_node["nodeId"].as_u64();
+            let nested = cdp_node["children"].as_array().into_iter().flatten();
+            let nested = nested
+                .chain(cdp_node.get("contentDocument"))
+                .chain(cdp_node["shadowRoots"].as_array().into_iter().flatten());
+            let nested: Vec<_> = nested.collect();
+            stack.extend(nested.into_iter().rev().map(|child| (child, node_id)));
+        }
+        serde_json::json!({ "nodes": nodes })
+    }
+
+    #[test]
+    fn test_flattened_iframe_limits() {
+        let cdp_json = nested_iframes(4, &[]);
+        let flattened = flatten(&cdp_json);
+
+        // Too deep: same documents as the nested parse
+        let config = DomServiceConfig {
+            max_iframe_depth: 2,
+            ..DomServiceConfig::default()
+        };
+        let mut nested = DomService::with_config(config.clone());
+        nested.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let mut service = DomService::with_config(config.clone());
+        service.parse_cdp_flattened(&flattened).unwrap();
+        assert_eq!(iframe_documents(&service), 3);
+        assert_eq!(service.arena().len(), nested.arena().len());
+        assert!(service.arena().get_node_id_by_backend(1000).is_none());
+        assert_eq!(
+            service.serialize_for_llm().unwrap(),
+            nested.serialize_for_llm().unwrap()
+        );
+
+        let mut strict = DomService::with_config(DomServiceConfig {
+            strict_iframe_limits: true,
+            ..config
+        });
+        assert!(matches!(
+            strict.parse_cdp_flattened(&flattened),
+            Err(DomError::MaxIframeDepthExceeded { current: 3, max: 2 })
+        ));
+
+        // Too many
+        let config = DomServiceConfig {
+            max_iframes: 1,
+            ..DomServiceConfig::default()
+        };
+        let mut service = DomService::with_config(config.clone());
+        service.parse_cdp_flattened(&flattened).unwrap();
+        assert_eq!(iframe_documents(&service), 2);
+        let mut strict = DomService::with_config(DomServiceConfig {
+            strict_iframe_limits: true,
+            ..config
+        });
+        assert!(matches!(
+            strict.parse_cdp_flattened(&flattened),
+            Err(DomError::MaxIframeCountExceeded { current: 2, max: 1 })
+        ));
+
+        // Within the limits, nothing is dropped
+        let mut service = DomService::new();
+        service.parse_cdp_flattened(&flattened).unwrap();
+        assert_eq!(iframe_documents(&service), 5);
+        assert!(service.arena().get_node_id_by_backend(1000).is_some());
+    }
+
+    #[test]
+    fn test_cross_origin_iframes_are_skipped() {
+        let urls = [
+            "https://example.com/",
+            "https://EXAMPLE.com/embed?x=1",
+            "about:blank",
+            "https://ads.example.net/frame",
+        ];
+        let cdp_json = nested_iframes(3, &urls);
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        // Same origin and about:blank are kept; the ad frame is not
+        assert_eq!(iframe_documents(&service), 3);
+        assert!(service.arena().get_node_id_by_backend(31).is_none());
+
+        let mut service = DomService::with_config(DomServiceConfig {
+            cross_origin_iframes: true,
+            ..DomServiceConfig::default()
+        });
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert_eq!(iframe_documents(&service), 4);
+    }
+
+    /// A chain of `depth` nested DIVs under the root
+    fn deep_tree(depth: u32) -> Value {
+        let mut node = serde_json::json!({
+            "nodeId": depth + 1,
+            "backendNodeId": depth + 1,
+            "nodeType": 1,
+            "nodeName": "DIV",
+        });
+        // Built bottom-up in a loop, moving each level in; json! would
+        // serialize a copy, recursing through the whole chain
+        for id in (1..=depth).rev() {
+            let mut parent = serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "DIV",
+            });
+            parent["children"] = Value::Array(vec![node]);
+            node = parent;
+        }
+        let mut tree = serde_json::json!({});
+        tree["root"] = node;
+        tree
+    }
+
+    /// Drop a `deep_tree` one level at a time; `Value`'s own drop recurses
+    fn drop_deep_tree(mut tree: Value) {
+        let mut node = tree["root"].take();
+        while let Some(child) = node.pointer_mut("/children/0").map(Value::take) {
+            node = child;
+        }
+    }
+
+    #[test]
+    fn test_deep_tree_is_rejected() {
+        let mut service = DomService::new();
+
+        let tree = deep_tree(50_000);
+        assert!(matches!(
+            service.parse_cdp_dom_tree(&tree),
+            Err(DomError::MaxTreeDepthExceeded {
+                max: MAX_PARSE_DEPTH
+            })
+        ));
+        drop_deep_tree(tree);
+
+        // The deepest tree allowed still parses on a test thread's stack
+        let tree = deep_tree(MAX_PARSE_DEPTH as u32 - 1);
+        service.parse_cdp_dom_tree(&tree).unwrap();
+        assert_eq!(service.arena().len(), MAX_PARSE_DEPTH);
+        drop_deep_tree(tree);
+    }
+
+    #[test]
+    fn test_merge_ax_tree_properties() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 7,
+                "nodeType": 1,
+                "nodeName": "INPUT",
+                "attributes": ["type", "checkbox"]
+            }
+        });
+        let ax_tree = serde_json::json!({
+            "nodes": [{
+                "nodeId": "42",
+                "backendDOMNodeId": 7,
+                "ignored": false,
+                "role": { "type": "role", "value": "checkbox" },
+                "properties": [
+                    { "name": "checked", "value": { "type": "tristate", "value": "mixed" } },
+                    { "name": "disabled", "value": { "type": "boolean", "value": true } },
+                    { "name": "level", "value": { "type": "integer", "value": 2 } },
+                    { "name": "focusable", "value": { "type": "booleanOrUndefined", "value": true } },
+                    { "name": "controls", "value": { "type": "idrefList", "relatedNodes": [] } }
+                ],
+                "childIds": ["43", "44"]
+            }]
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        service.merge_ax_tree(&ax_tree).unwrap();
+
+        let node = service.arena().get_by_backend_id(7).unwrap();
+        let ax = node.ax_node.as_ref().unwrap();
+        let props = ax.properties.as_ref().unwrap();
+        assert_eq!(props.len(), 5);
+        assert_eq!(props[0].name, AXPropertyName::Checked);
+        assert!(matches!(&props[0].value, AXPropertyValue::String(s) if s == "mixed"));
+        assert!(matches!(props[1].value, AXPropertyValue::Bool(true)));
+        assert!(matches!(&props[2].value, AXPropertyValue::String(s) if s == "2"));
+        assert_eq!(
+            props[3].name,
+            AXPropertyName::Other("focusable".to_string())
+        );
+        assert!(matches!(props[4].value, AXPropertyValue::Null));
+        assert_eq!(ax.child_ids.as_ref().unwrap().as_slice(), ["43", "44"]);
+
+        // AX disabled state now reaches the node helpers
+        assert!(node.is_disabled());
+    }
+
+    #[test]
+    fn test_aria_hidden_subtree_is_not_interactive() {
+        let element = |id: u32, name: &str, attributes: Value, children: Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": name,
+                "attributes": attributes,
+                "children": children,
+            })
+        };
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", serde_json::json!([]), serde_json::json!([
+                element(2, "DIV", serde_json::json!(["aria-hidden", "true"]), serde_json::json!([
+                    text(3, "Menu"),
+                    element(4, "BUTTON", serde_json::json!([]), serde_json::json!([text(5, "Close")])),
+                ])),
+                element(6, "BUTTON", serde_json::json!([]), serde_json::json!([text(7, "Open")])),
+            ]))
+        });
+
+        let serialize = |exclude_aria_hidden: bool| {
+            let mut service = DomService::with_config(DomServiceConfig {
+                exclude_aria_hidden,
+                ..DomServiceConfig::default()
+            });
+            service.parse_cdp_dom_tree(&cdp_json).unwrap();
+            for node_id in service.arena().node_ids().collect::<Vec<_>>() {
+                service.arena_mut().get_mut(node_id).unwrap().snapshot_node =
+                    Some(Box::new(SnapshotNode {
+                        bounds: Some(DomRect::new(0.0, 0.0, 80.0, 20.0)),
+                        ..SnapshotNode::default()
+                    }));
+            }
+            service.calculate_visibility().unwrap();
+            // Visibility is left alone either way
+            assert!(service.arena().iter().all(|n| n.is_visible == Some(true)));
+            service
+                .serialize_for_llm_with(SerializerConfig {
+                    index_interactive: true,
+                    exclude_aria_hidden: service.config.exclude_aria_hidden,
+                    ..SerializerConfig::default()
+                })
+                .unwrap()
+        };
+
+        // Hidden text still renders, but its button gets no index
+        let output = serialize(true);
+        assert!(output.contains("Menu"), "{}", output);
+        assert!(output.contains("Close"), "{}", output);
+        assert!(output.contains("    <BUTTON>"), "{}", output);
+        assert!(output.contains("[0]<BUTTON>"), "{}", output);
+        assert!(!output.contains("[1]"), "{}", output);
+
+        // Off by default
+        let output = serialize(false);
+        assert!(output.contains("[1]<BUTTON>"), "{}", output);
+    }
+
+    #[test]
+    fn test_input_groups_radio() {
+        let radio = |id: u32, value: &str, checked: bool| {
+            let mut attrs = vec!["type", "radio", "name", "size", "value", value];
+            if checked {
+                attrs.extend(["checked", ""]);
+            }
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": "INPUT",
+                "attributes": attrs,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "FORM",
+                "children": [radio(2, "s", false), radio(3, "m", true), radio(4, "l", false)]
+            }
+        });
+
+        let mut service = DomService::new();
+        let form_id = service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let groups = service.input_groups();
+        assert_eq!(groups.len(), 1);
+
+        let group = &groups[0];
+        assert_eq!(group.kind, InputGroupKind::Radio);
+        assert_eq!(group.name, "size");
+        assert_eq!(group.form_id, Some(form_id));
+        let values: Vec<_> = group.options.iter().map(|o| o.value.as_str()).collect();
+        assert_eq!(values, ["s", "m", "l"]);
+        let checked: Vec<_> = group.checked().map(|o| o.value.as_str()).collect();
+        assert_eq!(checked, ["m"]);
+    }
+
+    #[test]
+    fn test_merge_snapshot() {
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 10,
+                "nodeType": 9,
+                "nodeName": "#document",
+                "children": [{
+                    "nodeId": 2,
+                    "backendNodeId": 11,
+                    "nodeType": 1,
+                    "nodeName": "BUTTON",
+                }]
+            }
+        });
+        // styles index into strings, in REQUIRED_COMPUTED_STYLES order
+        let snapshot = serde_json::json!({
+            "strings": ["block", "visible", "1", "pointer"],
+            "documents": [{
+                "nodes": {
+                    "backendNodeId": [10, 11],
+                    "isClickable": { "index": [1] }
+                },
+                "layout": {
+                    "nodeIndex": [1],
+                    "bounds": [[20.0, 40.0, 200.0, 100.0]],
+                    "clientRects": [[]],
+                    "styles": [[0, 1, 2, 0, 0, 0, 3]],
+                    "paintOrders": [7],
+                    "stackingContexts": { "index": [] }
+                }
+            }]
+        });
+
+        let mut service = DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        service.merge_snapshot(&snapshot, 2.0).unwrap();
+
+        let button = service.arena().get_by_backend_id(11).unwrap();
+        let snap = button.snapshot_node.as_ref().expect("snapshot merged");
+        assert_eq!(snap.is_clickable, Some(true));
+        assert_eq!(snap.bounds, Some(DomRect::new(10.0, 20.0, 100.0, 50.0)));
+        assert_eq!(snap.client_rects, None);
+        assert_eq!(snap.paint_order, Some(7));
+        assert_eq!(snap.cursor_style.as_deref(), Some("pointer"));
+        let styles = snap.computed_styles.as_ref().unwrap();
+        assert_eq!(styles.get("display").map(String::as_str), Some("block"));
+        assert!(button.is_clickable());
+
+        let document = service.arena().get_by_backend_id(10).unwrap();
+        assert!(document.snapshot_node.is_none());
+    }
+
+    #[test]
+    fn test_load() {
+        let state = DomState {
+            document: serde_json::json!({
+                "root": {
+                    "nodeId": 1,
+                    "backendNodeId": 10,
+                    "nodeType": 9,
+                    "nodeName": "#document",
+                    "children": [{
+                        "nodeId": 2,
+                        "backendNodeId": 11,
+                        "nodeType": 1,
+                        "nodeName": "BUTTON",
+                    }]
+                }
+            }),
+            snapshot: serde_json::json!({
+                "strings": ["block", "visible", "1"],
+                "documents": [{
+                    "nodes": { "backendNodeId": [10, 11] },
+                    "layout": {
+                        "nodeIndex": [1],
+                        "bounds": [[20.0, 40.0, 200.0, 100.0]],
+                        "styles": [[0, 1, 2]]
+                    }
+                }]
+            }),
+            ax_tree: serde_json::json!({
+                "nodes": [{
+                    "nodeId": "5",
+                    "backendDOMNodeId": 11,
+                    "ignored": false,
+                    "role": { "type": "role", "value": "button" },
+                    "name": { "type": "computedString", "value": "Save" }
+                }]
+            }),
+        };
+
+        let mut service = DomService::new();
+        let root_id = service.load(&state, 1.0).unwrap();
+        assert_eq!(service.arena().root_id(), Some(root_id));
+
+        let button = service.arena().get_by_backend_id(11).unwrap();
+        assert_eq!(
+            button.ax_node.as_ref().unwrap().name.as_deref(),
+            Some("Save")
+        );
+        assert!(button.snapshot_node.is_some());
+        assert_eq!(button.is_visible, Some(true));
+
+        let missing = DomState {
+            ax_tree: Value::Null,
+            ..state
+        };
+        assert!(service.load(&missing, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_coalesce_text_nodes() {
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "P",
+                "children": [
+                    text(2, "Hello "),
+                    text(3, "\n  world"),
+                    text(4, "!"),
+                    {"nodeId": 5, "backendNodeId": 5, "nodeType": 1, "nodeName": "BR"},
+                    text(6, "Bye"),
+                ]
+            }
+        });
+
+        let mut service = DomService::with_config(DomServiceConfig {
+            coalesce_text_nodes: true,
+            ..DomServiceConfig::default()
+        });
+        let root_id = service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        let root = service.arena().get(root_id).unwrap();
+        assert_eq!(root.children_ids.len(), 3);
+        let text_node = service.arena().get(root.children_ids[0]).unwrap();
+        assert_eq!(text_node.node_value, "Hello world!");
+        assert!(service.arena().get_by_backend_id(3).is_err());
+        assert!(service.arena().get_by_backend_id(6).is_ok());
+        assert_eq!(service.coalesce_text_nodes().unwrap(), 0);
+
+        let mut joined = String::from("a");
+        join_text(&mut joined, "b");
+        join_text(&mut joined, "  ");
+        join_text(&mut joined, "c");
+        join_text(&mut joined, " d");
+        assert_eq!(joined, "ab c d");
+    }
+
+    #[test]
+    fn test_normalize_text_nodes() {
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": {
+                "nodeId": 1,
+                "backendNodeId": 1,
+                "nodeType": 1,
+                "nodeName": "P",
+                "children": [text(2, "Tom "), text(3, "&"), text(4, " Jerry")]
+            }
+        });
+
+        let mut service = DomService::new();
+        let root_id = service.parse_cdp_dom_tree(&cdp_json).unwrap();
+
+        assert_eq!(service.normalize_text_nodes().unwrap(), 2);
+
+        let root = service.arena().get(root_id).unwrap();
+        assert_eq!(root.children_ids.len(), 1);
+        let text_node = service.arena().get(root.children_ids[0]).unwrap();
+        assert_eq!(text_node.node_value, "Tom & Jerry");
+        assert_eq!(service.arena().len(), 2);
+        assert!(service.arena().get_by_backend_id(3).is_err());
     }
 }
@@ -100,6 +100,24 @@ impl DomRect {
             && self.y + self.height > other.y
     }
 
+    /// Check if `other` lies entirely within this rectangle
+    pub fn contains(&self, other: &DomRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// Check if `(x, y)` lies inside, counting the top and left edges only
+    pub fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Check for zero (or negative) width or height
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+
     /// Apply offset (for iframe coordinate transformation)
     pub fn offset(&self, dx: f64, dy: f64) -> Self {
         Self {
@@ -133,6 +151,32 @@ pub enum AXPropertyName {
     Other(String),
 }
 
+impl From<&str> for AXPropertyName {
+    /// Map a CDP property name (`"checked"`, `"hasPopup"`, ...)
+    fn from(name: &str) -> Self {
+        match name {
+            "checked" => Self::Checked,
+            "selected" => Self::Selected,
+            "expanded" => Self::Expanded,
+            "pressed" => Self::Pressed,
+            "disabled" => Self::Disabled,
+            "invalid" => Self::Invalid,
+            "valuemin" => Self::ValueMin,
+            "valuemax" => Self::ValueMax,
+            "valuenow" => Self::ValueNow,
+            "valuetext" => Self::ValueText,
+            "keyshortcuts" => Self::KeyShortcuts,
+            "hasPopup" | "haspopup" => Self::HasPopup,
+            "multiselectable" => Self::Multiselectable,
+            "required" => Self::Required,
+            "level" => Self::Level,
+            "busy" => Self::Busy,
+            "live" => Self::Live,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
 /// Accessibility property value
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AXProperty {
@@ -161,7 +205,7 @@ pub struct AXNode {
 }
 
 /// Snapshot data from DOMSnapshot.captureSnapshot
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SnapshotNode {
     pub is_clickable: Option<bool>,
     pub cursor_style: Option<String>,
@@ -222,18 +266,42 @@ pub struct DomNode {
     pub ax_node: Option<Box<AXNode>>,
     pub snapshot_node: Option<Box<SnapshotNode>>,
 
-    // UUID for tracking
+    /// Random, or stable across re-parses of the same page when parsed by a
+    /// `DomService` (see `DomNode::stable_uuid`)
     pub uuid: String,
 }
 
+/// Namespace for `DomNode::stable_uuid`
+const NODE_UUID_NAMESPACE: uuid::Uuid =
+    uuid::Uuid::from_u128(0x50301677_e50c_4865_a47b_b6ff2573c7e0);
+
 impl DomNode {
-    /// Create a new node with required fields
+    /// Create a new node with required fields and a random `uuid`
     pub fn new(
         node_id: NodeId,
         backend_node_id: u32,
         node_type: NodeType,
         node_name: String,
         target_id: TargetId,
+    ) -> Self {
+        Self::with_uuid(
+            node_id,
+            backend_node_id,
+            node_type,
+            node_name,
+            target_id,
+            uuid::Uuid::new_v4().to_string(),
+        )
+    }
+
+    /// Create a new node with a caller-supplied `uuid`
+    pub fn with_uuid(
+        node_id: NodeId,
+        backend_node_id: u32,
+        node_type: NodeType,
+        node_name: String,
+        target_id: TargetId,
+        uuid: String,
     ) -> Self {
         Self {
             node_id,
@@ -245,7 +313,6 @@ impl DomNode {
             parent_id: None,
             children_ids: SmallVec::new(),
             frame_id: None,
-            target_id,
             session_id: None,
             content_document_id: None,
             shadow_root_type: None,
@@ -255,10 +322,34 @@ impl DomNode {
             absolute_position: None,
             ax_node: None,
             snapshot_node: None,
-            uuid: uuid::Uuid::new_v4().to_string(),
+            uuid,
+            target_id,
         }
     }
 
+    /// Name-based (v5) UUID for a backend node within a target's frame
+    ///
+    /// `frame_id` is the frame whose document holds the node (`None` for the
+    /// top document), since frames can repeat backend ids. Synthetic ids
+    /// (`SYNTHETIC_ID_START`) get their own namespace, so they never share a
+    /// UUID with a real backend id of the same value.
+    pub fn stable_uuid(
+        target_id: &str,
+        frame_id: Option<&str>,
+        backend_node_id: u32,
+        synthetic: bool,
+    ) -> String {
+        let kind = if synthetic { "synthetic" } else { "backend" };
+        let name = format!(
+            "{}:{}:{}:{}",
+            target_id,
+            frame_id.unwrap_or_default(),
+            kind,
+            backend_node_id
+        );
+        uuid::Uuid::new_v5(&NODE_UUID_NAMESPACE, name.as_bytes()).to_string()
+    }
+
     /// Get tag name for element nodes
     pub fn tag_name(&self) -> Option<&str> {
         if self.node_type == NodeType::Element {
@@ -283,12 +374,100 @@ impl DomNode {
         self.attributes.get(name).map(|s| s.as_str())
     }
 
-    /// Check if element is clickable
+    /// Check if element is clickable (disabled elements never are)
     pub fn is_clickable(&self) -> bool {
+        !self.is_disabled()
+            && self
+                .snapshot_node
+                .as_ref()
+                .and_then(|s| s.is_clickable)
+                .unwrap_or(false)
+    }
+
+    /// Check if element accepts user interaction
+    ///
+    /// Snapshot clickability covers JS listeners and `cursor: pointer`; the
+    /// tag, role, focus and editing checks catch controls without snapshot
+    /// data. Disabled elements and hidden inputs never are.
+    pub fn is_interactive(&self) -> bool {
+        if !self.is_element() || self.is_disabled() {
+            return false;
+        }
+        if self.is_clickable() {
+            return true;
+        }
+
+        let tag = self.node_name.as_str();
+        if INTERACTIVE_TAGS.iter().any(|t| tag.eq_ignore_ascii_case(t)) {
+            return self.attr("type") != Some("hidden");
+        }
+        if tag.eq_ignore_ascii_case("a") && self.attributes.contains_key("href") {
+            return true;
+        }
+        if self.attr("role").is_some_and(|role| {
+            INTERACTIVE_ROLES
+                .iter()
+                .any(|r| role.trim().eq_ignore_ascii_case(r))
+        }) {
+            return true;
+        }
+        // tabindex="-1" is focusable from script only, not by the user
+        if self
+            .attr("tabindex")
+            .and_then(|index| index.trim().parse::<i32>().ok())
+            .is_some_and(|index| index >= 0)
+        {
+            return true;
+        }
+
+        self.attributes.contains_key("onclick")
+            || matches!(self.attr("contenteditable"), Some("" | "true"))
+    }
+
+    /// Computed CSS value from the merged snapshot
+    ///
+    /// Only properties in `REQUIRED_COMPUTED_STYLES` are captured.
+    pub fn computed_style(&self, prop: &str) -> Option<&str> {
         self.snapshot_node
+            .as_ref()?
+            .computed_styles
+            .as_ref()?
+            .get(prop)
+            .map(|s| s.as_str())
+    }
+
+    /// Computed `z-index`, `None` for `auto` or without a snapshot
+    pub fn z_index(&self) -> Option<i32> {
+        self.computed_style("z-index")?.parse().ok()
+    }
+
+    /// Check computed `position: fixed`
+    pub fn is_fixed_position(&self) -> bool {
+        self.computed_style("position") == Some("fixed")
+    }
+
+    /// Check `disabled`, `aria-disabled="true"`, and the AX Disabled property
+    pub fn is_disabled(&self) -> bool {
+        self.attributes.contains_key("disabled")
+            || self.attr("aria-disabled") == Some("true")
+            || self.ax_property_is_true(&AXPropertyName::Disabled)
+    }
+
+    /// Check `readonly` and `aria-readonly="true"`
+    pub fn is_readonly(&self) -> bool {
+        self.attributes.contains_key("readonly") || self.attr("aria-readonly") == Some("true")
+    }
+
+    /// Check if a merged AX boolean property is set
+    fn ax_property_is_true(&self, name: &AXPropertyName) -> bool {
+        self.ax_node
             .as_ref()
-            .and_then(|s| s.is_clickable)
-            .unwrap_or(false)
+            .and_then(|ax| ax.properties.as_ref())
+            .is_some_and(|props| {
+                props
+                    .iter()
+                    .any(|p| &p.name == name && matches!(p.value, AXPropertyValue::Bool(true)))
+            })
     }
 }
 
@@ -305,6 +484,59 @@ pub struct SimplifiedNode {
     pub is_compound_component: bool,
 }
 
+/// Kind of grouped form control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputGroupKind {
+    Radio,
+    Checkbox,
+}
+
+/// One option within an input group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputOption {
+    pub node_id: NodeId,
+    pub value: String,
+    pub checked: bool,
+}
+
+/// Radios/checkboxes sharing a `name` within the same form - one logical choice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputGroup {
+    pub kind: InputGroupKind,
+    pub name: String,
+    /// Enclosing `<form>`, if any
+    pub form_id: Option<NodeId>,
+    /// Options in document order
+    pub options: Vec<InputOption>,
+}
+
+impl InputGroup {
+    /// Options currently checked (at most one for radios)
+    pub fn checked(&self) -> impl Iterator<Item = &InputOption> {
+        self.options.iter().filter(|o| o.checked)
+    }
+}
+
+/// Computed styles requested from DOMSnapshot.captureSnapshot
+///
+/// The snapshot reports styles positionally, so the request's
+/// `computedStyles` must use exactly this list, in this order.
+pub const REQUIRED_COMPUTED_STYLES: &[&str] = &[
+    "display",
+    "visibility",
+    "opacity",
+    "overflow",
+    "overflow-x",
+    "overflow-y",
+    "cursor",
+    "pointer-events",
+    "position",
+    "background-color",
+    "z-index",
+    "clip",
+    "clip-path",
+];
+
 /// Default attributes to include in serialization
 pub const DEFAULT_INCLUDE_ATTRIBUTES: &[&str] = &[
     "title",
@@ -357,6 +589,31 @@ pub const DEFAULT_INCLUDE_ATTRIBUTES: &[&str] = &[
     "ax_name",
 ];
 
+/// Tags that are controls by themselves (`a` needs an `href`)
+const INTERACTIVE_TAGS: &[&str] = &[
+    "button", "input", "select", "textarea", "option", "details", "summary",
+];
+
+/// ARIA roles of widgets the user operates
+const INTERACTIVE_ROLES: &[&str] = &[
+    "button",
+    "link",
+    "checkbox",
+    "radio",
+    "menuitem",
+    "menuitemcheckbox",
+    "menuitemradio",
+    "option",
+    "switch",
+    "tab",
+    "textbox",
+    "combobox",
+    "searchbox",
+    "slider",
+    "spinbutton",
+    "treeitem",
+];
+
 /// Static attributes (for caching/optimization)
 pub const STATIC_ATTRIBUTES: &[&str] = &[
     "class",
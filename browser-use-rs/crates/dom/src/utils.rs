@@ -2,40 +2,185 @@
 
 use crate::arena::DomArena;
 use crate::error::Result;
-use crate::types::{DomNode, DomRect, NodeType};
+use crate::types::{DomNode, DomRect, InputGroup, InputGroupKind, InputOption, NodeId, NodeType};
+use std::collections::HashMap;
 
 /// Cap text length to avoid token explosion
+///
+/// `max_len` counts characters, not bytes, so CJK and emoji text get the
+/// same budget as ASCII and the cut never splits a UTF-8 sequence.
 pub fn cap_text_length(text: &str, max_len: usize) -> String {
-    if text.len() <= max_len {
-        text.to_string()
-    } else {
-        format!("{}...", &text[..max_len])
+    match text.char_indices().nth(max_len) {
+        Some((byte_idx, _)) => format!("{}...", &text[..byte_idx]),
+        None => text.to_string(),
     }
 }
 
+/// Collapse whitespace runs (newlines included) to one space, drop other
+/// control characters, and trim
+pub fn normalize_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+        } else if !c.is_control() {
+            if pending_space && !out.is_empty() {
+                out.push(' ');
+            }
+            pending_space = false;
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Escape `&`, `<` and `>` so text can't pass for markup
+pub fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape for a double-quoted attribute value
+pub fn escape_attribute(value: &str) -> String {
+    escape_text(value).replace('"', "&quot;")
+}
+
+/// `scheme://host[:port]` of a hierarchical URL, `None` for `about:`,
+/// `data:` and other URLs without an authority
+pub fn url_origin(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // Credentials aren't part of the origin
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    if scheme.is_empty() || host.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}://{}",
+        scheme.to_ascii_lowercase(),
+        host.to_ascii_lowercase()
+    ))
+}
+
 /// Check if element is visible according to CSS
+///
+/// Besides `display`/`visibility`/`opacity`, catches boxless
+/// `display: contents`, fully clipped elements (`clip: rect(0 0 0 0)`,
+/// `clip-path: inset(100%)`), and zero-sized boxes that show nothing: leaves,
+/// or boxes clipping their overflow. Content overflowing a zero-sized box
+/// is still painted, so such wrappers stay visible.
 pub fn is_element_visible_by_css(node: &DomNode) -> bool {
-    if let Some(snapshot) = &node.snapshot_node {
-        if let Some(styles) = &snapshot.computed_styles {
-            let display = styles.get("display").map(|s| s.as_str()).unwrap_or("");
-            let visibility = styles.get("visibility").map(|s| s.as_str()).unwrap_or("");
-            let opacity = styles.get("opacity").map(|s| s.as_str()).unwrap_or("1");
+    let Some(snapshot) = &node.snapshot_node else {
+        return true;
+    };
+    let style = |name: &str| {
+        snapshot
+            .computed_styles
+            .as_ref()
+            .and_then(|styles| styles.get(name))
+            .map_or("", |s| s.as_str())
+    };
+
+    if snapshot.computed_styles.is_some() {
+        if matches!(style("display"), "none" | "contents") || style("visibility") == "hidden" {
+            return false;
+        }
 
-            if display == "none" || visibility == "hidden" {
+        if let Ok(opacity_val) = style("opacity").parse::<f64>() {
+            if opacity_val <= 0.0 {
                 return false;
             }
+        }
 
-            if let Ok(opacity_val) = opacity.parse::<f64>() {
-                if opacity_val <= 0.0 {
-                    return false;
-                }
-            }
+        if is_clip_rect_empty(style("clip")) || is_clip_path_empty(style("clip-path")) {
+            return false;
+        }
+    }
+
+    if let Some(bounds) = &snapshot.bounds {
+        let is_leaf = node.children_ids.is_empty()
+            && node
+                .shadow_root_ids
+                .as_ref()
+                .is_none_or(|ids| ids.is_empty())
+            && node.content_document_id.is_none();
+        // `overflow-x`/`-y` are the computed longhands of `overflow`
+        let hides_overflow = |axis: &str| is_leaf || matches!(style(axis), "hidden" | "clip");
+        if (bounds.width <= 0.0 && hides_overflow("overflow-x"))
+            || (bounds.height <= 0.0 && hides_overflow("overflow-y"))
+        {
+            return false;
         }
     }
 
     true
 }
 
+/// `clip: rect(top, right, bottom, left)` that leaves no area
+fn is_clip_rect_empty(clip: &str) -> bool {
+    let Some(args) = clip
+        .trim()
+        .strip_prefix("rect(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return false;
+    };
+    let edges: Vec<Option<f64>> = args
+        .split([',', ' '])
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches("px").parse().ok())
+        .collect();
+    match edges[..] {
+        [Some(top), Some(right), Some(bottom), Some(left)] => bottom <= top || right <= left,
+        _ => false,
+    }
+}
+
+/// `clip-path: inset(...)` whose insets meet across either axis
+fn is_clip_path_empty(clip_path: &str) -> bool {
+    let Some(args) = clip_path
+        .trim()
+        .strip_prefix("inset(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return false;
+    };
+    // Only percentages are comparable without the box size; `round` starts the radii
+    let insets: Vec<Option<f64>> = args
+        .split_whitespace()
+        .take_while(|s| *s != "round")
+        .map(|s| s.strip_suffix('%').and_then(|n| n.parse().ok()))
+        .collect();
+    let [top, right, bottom, left] = match insets[..] {
+        [Some(all)] => [all; 4],
+        [Some(v), Some(h)] => [v, h, v, h],
+        [Some(t), Some(h), Some(b)] => [t, h, b, h],
+        [Some(t), Some(r), Some(b), Some(l)] => [t, r, b, l],
+        _ => return false,
+    };
+    top + bottom >= 100.0 || left + right >= 100.0
+}
+
+/// Check if element accepts user interaction; see `DomNode::is_interactive`
+pub fn is_interactive(node: &DomNode) -> bool {
+    node.is_interactive()
+}
+
+/// Check if element is hidden from assistive tech (`aria-hidden="true"` or `inert`)
+pub fn is_aria_hidden_or_inert(node: &DomNode) -> bool {
+    node.is_element()
+        && (node.attr("aria-hidden") == Some("true") || node.attributes.contains_key("inert"))
+}
+
 /// Check if element intersects with viewport/frame
 pub fn check_frame_intersection(
     element_bounds: &DomRect,
@@ -52,14 +197,19 @@ pub fn check_frame_intersection(
     let adjusted_x = element_bounds.x - frame_scroll_rect.x;
     let adjusted_y = element_bounds.y - frame_scroll_rect.y;
 
-    // Check intersection (with 1000px tolerance for below-fold content)
+    // Check intersection (with 1000px tolerance for below-fold content);
+    // a zero-width box at the left edge still counts, its content overflows
     adjusted_x < viewport_right
-        && adjusted_x + element_bounds.width > viewport_left
+        && (adjusted_x + element_bounds.width > viewport_left
+            || (element_bounds.width <= 0.0 && adjusted_x >= viewport_left))
         && adjusted_y < viewport_bottom + 1000.0
         && adjusted_y + element_bounds.height > viewport_top - 1000.0
 }
 
 /// Get all text content from node and its children
+///
+/// Text nodes are joined verbatim, script and style included; see
+/// `get_text_content_opts` for readable text.
 pub fn get_text_content(arena: &DomArena, node_id: u32) -> Result<String> {
     let mut text = String::new();
 
@@ -73,6 +223,203 @@ pub fn get_text_content(arena: &DomArena, node_id: u32) -> Result<String> {
     Ok(text.trim().to_string())
 }
 
+/// Elements whose text isn't page content
+const NON_CONTENT_TAGS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// Elements rendered on their own line
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "option",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Check if `tag` is rendered on its own line (any case)
+pub(crate) fn is_block_tag(tag: &str) -> bool {
+    BLOCK_TAGS
+        .iter()
+        .any(|block| block.eq_ignore_ascii_case(tag))
+}
+
+/// How `get_text_content_opts` joins text
+#[derive(Debug, Clone)]
+pub struct TextOptions {
+    /// Separate block-level elements with a newline instead of a space
+    pub newline_between_blocks: bool,
+    /// Leave out text of `<script>`, `<style>`, `<noscript>` and `<template>`
+    pub skip_non_content: bool,
+    /// Cap the result, in characters as with `cap_text_length`
+    pub max_len: Option<usize>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            newline_between_blocks: true,
+            skip_non_content: true,
+            max_len: None,
+        }
+    }
+}
+
+/// Readable text of a node and its children
+///
+/// Each text node is whitespace-normalized and empty ones dropped. Runs are
+/// joined with a space, or a newline where a block element starts or ends,
+/// so `<p>Hello</p><p>World</p>` doesn't come out as "HelloWorld".
+pub fn get_text_content_opts(
+    arena: &DomArena,
+    node_id: NodeId,
+    options: &TextOptions,
+) -> Result<String> {
+    let mut text = String::new();
+    let mut chars = 0;
+    let mut block_break = false;
+    // (node, leaving): block elements are visited again on the way out
+    let mut stack = vec![(node_id, false)];
+
+    while let Some((id, leaving)) = stack.pop() {
+        if leaving {
+            block_break = true;
+            continue;
+        }
+        let node = arena.get(id)?;
+        if node.node_type == NodeType::Text {
+            let run = normalize_whitespace(&node.node_value);
+            if run.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push(if block_break && options.newline_between_blocks {
+                    '\n'
+                } else {
+                    ' '
+                });
+                chars += 1;
+            }
+            block_break = false;
+            chars += run.chars().count();
+            text.push_str(&run);
+            if options.max_len.is_some_and(|max_len| chars > max_len) {
+                break;
+            }
+            continue;
+        }
+
+        if node.is_element() {
+            let tag = node.node_name.to_ascii_lowercase();
+            if options.skip_non_content && NON_CONTENT_TAGS.contains(&tag.as_str()) {
+                continue;
+            }
+            if BLOCK_TAGS.contains(&tag.as_str()) {
+                block_break = true;
+                stack.push((id, true));
+            }
+        }
+        stack.extend(node.children_ids.iter().rev().map(|&child| (child, false)));
+    }
+
+    Ok(match options.max_len {
+        Some(max_len) => cap_text_length(&text, max_len),
+        None => text,
+    })
+}
+
+/// Group radios/checkboxes by `name` within their enclosing form
+///
+/// Only groups with two or more options are returned - a lone checkbox
+/// is not a choice. Groups and options are in document order.
+pub fn find_input_groups(arena: &DomArena) -> Vec<InputGroup> {
+    let mut groups: Vec<InputGroup> = Vec::new();
+    let mut index: HashMap<(Option<NodeId>, InputGroupKind, String), usize> = HashMap::new();
+
+    for node_id in arena.node_ids() {
+        let Ok(node) = arena.get(node_id) else {
+            continue;
+        };
+        if !node.is_element() || !node.node_name.eq_ignore_ascii_case("input") {
+            continue;
+        }
+
+        let kind = match node.attr("type").map(|t| t.to_ascii_lowercase()).as_deref() {
+            Some("radio") => InputGroupKind::Radio,
+            Some("checkbox") => InputGroupKind::Checkbox,
+            _ => continue,
+        };
+        let name = match node.attr("name") {
+            Some(name) if !name.is_empty() => name,
+            _ => continue,
+        };
+
+        let form_id = enclosing_form(arena, node);
+        let slot = *index
+            .entry((form_id, kind, name.to_string()))
+            .or_insert_with(|| {
+                groups.push(InputGroup {
+                    kind,
+                    name: name.to_string(),
+                    form_id,
+                    options: Vec::new(),
+                });
+                groups.len() - 1
+            });
+
+        groups[slot].options.push(InputOption {
+            node_id,
+            value: node.attr("value").unwrap_or("on").to_string(),
+            checked: node.attributes.contains_key("checked")
+                || node.attr("aria-checked") == Some("true"),
+        });
+    }
+
+    groups.retain(|g| g.options.len() > 1);
+    groups
+}
+
+/// Find the nearest `<form>` ancestor
+fn enclosing_form(arena: &DomArena, node: &DomNode) -> Option<NodeId> {
+    arena.closest(node.parent_id?, |ancestor| {
+        ancestor.is_element() && ancestor.node_name.eq_ignore_ascii_case("form")
+    })
+}
+
+const NEXT_PATTERNS: &[&str] = &["next", ">", "»", "→", "siguiente", "suivant", "weiter"];
+const PREV_PATTERNS: &[&str] = &["prev", "previous", "<", "«", "←", "anterior"];
+const FIRST_PATTERNS: &[&str] = &["first", "⇤", "primera", "première", "erste"];
+const LAST_PATTERNS: &[&str] = &["last", "⇥", "última", "dernier", "letzte"];
+
 /// Detect if button is pagination button based on text/attributes
 pub fn is_pagination_button(node: &DomNode) -> Option<PaginationType> {
     if !node.is_clickable() {
@@ -96,11 +443,6 @@ pub fn is_pagination_button(node: &DomNode) -> Option<PaginationType> {
     }
 
     // Pattern matching
-    const NEXT_PATTERNS: &[&str] = &["next", ">", "»", "→", "siguiente", "suivant", "weiter"];
-    const PREV_PATTERNS: &[&str] = &["prev", "previous", "<", "«", "←", "anterior"];
-    const FIRST_PATTERNS: &[&str] = &["first", "⇤", "primera", "première", "erste"];
-    const LAST_PATTERNS: &[&str] = &["last", "⇥", "última", "dernier", "letzte"];
-
     for pattern in NEXT_PATTERNS {
         if all_text.contains(pattern) {
             return Some(PaginationType::Next);
@@ -136,7 +478,7 @@ pub fn is_pagination_button(node: &DomNode) -> Option<PaginationType> {
     None
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PaginationType {
     Next,
     Previous,
@@ -145,14 +487,443 @@ pub enum PaginationType {
     PageNumber,
 }
 
+/// Most likely pagination controls of a page, from `find_pagination_controls`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaginationControls {
+    pub next: Option<NodeId>,
+    pub previous: Option<NodeId>,
+    pub first: Option<NodeId>,
+    pub last: Option<NodeId>,
+    /// Numbered page links, in document order
+    pub page_numbers: Vec<NodeId>,
+}
+
+/// Find the best Next/Previous/First/Last controls on the page
+///
+/// Candidates are enabled interactive elements, scored by how strongly
+/// their text, `aria-label`, `title`, `rel` or class names match, whether
+/// they are visible and clickable, and how close they sit to numbered page
+/// links. When a page repeats its pager (header and footer), equal scores
+/// go to the one later in the document.
+pub fn find_pagination_controls(arena: &DomArena) -> PaginationControls {
+    let order: Vec<NodeId> = match arena
+        .root_id()
+        .and_then(|root| arena.descendants_iter(root).ok())
+    {
+        Some(descendants) => arena.root_id().into_iter().chain(descendants).collect(),
+        None => arena.node_ids().collect(),
+    };
+    let text_options = TextOptions {
+        max_len: Some(40),
+        ..TextOptions::default()
+    };
+
+    let mut controls = PaginationControls::default();
+    let mut candidates = Vec::new();
+    for &id in &order {
+        let Ok(node) = arena.get(id) else {
+            continue;
+        };
+        if !node.is_interactive()
+            || node.is_disabled()
+            || node
+                .attr("class")
+                .is_some_and(|class| class.contains("disabled"))
+        {
+            continue;
+        }
+        let text = get_text_content_opts(arena, id, &text_options)
+            .unwrap_or_default()
+            .to_lowercase();
+        if !text.is_empty() && text.len() <= 3 && text.chars().all(|c| c.is_ascii_digit()) {
+            controls.page_numbers.push(id);
+        } else if let Some((kind, strength)) = pagination_match(node, &text) {
+            candidates.push((id, kind, strength));
+        }
+    }
+
+    // Containers of page numbers, with how many levels up they are
+    let mut pager_levels: HashMap<NodeId, u32> = HashMap::new();
+    for &id in &controls.page_numbers {
+        for (level, ancestor) in (1..=PAGER_LEVELS).zip(arena.ancestor_ids(id)) {
+            let entry = pager_levels.entry(ancestor).or_insert(level);
+            *entry = (*entry).min(level);
+        }
+    }
+
+    let mut best: HashMap<PaginationType, (i32, NodeId)> = HashMap::new();
+    for (id, kind, strength) in candidates {
+        let Ok(node) = arena.get(id) else {
+            continue;
+        };
+        let mut score = strength as i32 * 3;
+        score += match node.is_visible {
+            Some(true) => 2,
+            Some(false) => -4,
+            None => 0,
+        };
+        if node.is_clickable() {
+            score += 1;
+        }
+        let nearest_pager = (1..=PAGER_LEVELS)
+            .zip(arena.ancestor_ids(id))
+            .filter_map(|(level, ancestor)| pager_levels.get(&ancestor).map(|&up| level + up))
+            .min();
+        if let Some(distance) = nearest_pager {
+            score += (2 * PAGER_LEVELS + 1 - distance) as i32;
+        }
+        // Later in the document wins ties
+        if best.get(&kind).is_none_or(|&(top, _)| score >= top) {
+            best.insert(kind, (score, id));
+        }
+    }
+
+    let pick = |kind| best.get(&kind).map(|&(_, id)| id);
+    controls.next = pick(PaginationType::Next);
+    controls.previous = pick(PaginationType::Previous);
+    controls.first = pick(PaginationType::First);
+    controls.last = pick(PaginationType::Last);
+    controls
+}
+
+/// How far up to look for a shared container with page numbers
+const PAGER_LEVELS: u32 = 3;
+
+/// Classify a candidate and rate the match, 1 (weak) to 4 (`rel`)
+///
+/// A whole word of the text or `aria-label` rates 3, an arrow in them or
+/// any match in `title` 2, a word inside a class name or a bare substring 1.
+fn pagination_match(node: &DomNode, text: &str) -> Option<(PaginationType, u32)> {
+    let rel = node.attr("rel").unwrap_or("").to_lowercase();
+    if rel.split_whitespace().any(|rel| rel == "next") {
+        return Some((PaginationType::Next, 4));
+    }
+    if rel.split_whitespace().any(|rel| rel == "prev") {
+        return Some((PaginationType::Previous, 4));
+    }
+
+    let label = node.attr("aria-label").unwrap_or("").to_lowercase();
+    let title = node.attr("title").unwrap_or("").to_lowercase();
+    let class = node.attr("class").unwrap_or("").to_lowercase();
+    let words = |s: &str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (text_words, class_words) = (words(&format!("{} {}", text, label)), words(&class));
+
+    let rate = |pattern: &str| -> u32 {
+        let is_word = pattern.chars().all(char::is_alphabetic);
+        if is_word && text_words.iter().any(|word| word == pattern) {
+            3
+        } else if (!is_word && (text.contains(pattern) || label.contains(pattern)))
+            || title.contains(pattern)
+        {
+            2
+        } else if is_word
+            && (class_words.iter().any(|word| word == pattern)
+                || text.contains(pattern)
+                || label.contains(pattern))
+        {
+            1
+        } else {
+            0
+        }
+    };
+
+    [
+        (PaginationType::Next, NEXT_PATTERNS),
+        (PaginationType::Previous, PREV_PATTERNS),
+        (PaginationType::First, FIRST_PATTERNS),
+        (PaginationType::Last, LAST_PATTERNS),
+    ]
+    .into_iter()
+    .filter_map(|(kind, patterns)| {
+        let strength = patterns.iter().map(|pattern| rate(pattern)).max()?;
+        (strength > 0).then_some((kind, strength))
+    })
+    // First of the strongest, so Next beats Previous on a tie like
+    // is_pagination_button
+    .fold(
+        None,
+        |best: Option<(PaginationType, u32)>, (kind, strength)| match best {
+            Some((_, top)) if top >= strength => best,
+            _ => Some((kind, strength)),
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_url_origin() {
+        assert_eq!(
+            url_origin("HTTPS://user:pw@Example.com:8443/a?b#c").as_deref(),
+            Some("https://example.com:8443")
+        );
+        assert_eq!(
+            url_origin("http://example.com").as_deref(),
+            Some("http://example.com")
+        );
+        assert_eq!(url_origin("about:blank"), None);
+        assert_eq!(url_origin("data:text/html,<p>hi</p>"), None);
+    }
+
+    #[test]
+    fn test_is_interactive() {
+        let element = |tag: &str, attrs: &[(&str, &str)]| {
+            let mut node = DomNode::new(0, 1, NodeType::Element, tag.to_string(), String::new());
+            for (k, v) in attrs {
+                node.attributes.insert(k.to_string(), v.to_string());
+            }
+            node
+        };
+
+        assert!(is_interactive(&element("INPUT", &[])));
+        assert!(is_interactive(&element("A", &[("href", "/next")])));
+        assert!(is_interactive(&element("DIV", &[("role", "tab")])));
+        assert!(is_interactive(&element(
+            "DIV",
+            &[("contenteditable", "true")]
+        )));
+        assert!(!is_interactive(&element("A", &[])));
+        assert!(!is_interactive(&element("DIV", &[])));
+        assert!(!is_interactive(&element("INPUT", &[("type", "hidden")])));
+        assert!(!is_interactive(&element("BUTTON", &[("disabled", "")])));
+
+        // Focusable or scripted elements without snapshot data
+        assert!(is_interactive(&element("DIV", &[("tabindex", "0")])));
+        assert!(is_interactive(&element("SPAN", &[("onclick", "go()")])));
+        assert!(!is_interactive(&element("DIV", &[("tabindex", "-1")])));
+        assert!(!is_interactive(&element("DIV", &[("tabindex", "x")])));
+        assert!(is_interactive(&element("DIV", &[("role", "Button")])));
+    }
+
+    #[test]
+    fn test_get_text_content_opts() {
+        let element = |id: u32, tag: &str, children: serde_json::Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": tag,
+                "children": children,
+            })
+        };
+        let text = |id: u32, value: &str| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 3,
+                "nodeName": "#text",
+                "nodeValue": value,
+            })
+        };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", serde_json::json!([
+                element(2, "H1", serde_json::json!([text(3, "Title")])),
+                element(4, "P", serde_json::json!([
+                    element(5, "SPAN", serde_json::json!([text(6, "Hello")])),
+                    element(7, "SPAN", serde_json::json!([text(8, "World")])),
+                ])),
+                element(9, "SCRIPT", serde_json::json!([text(10, "track()")])),
+                text(11, "\n    "),
+                element(12, "P", serde_json::json!([text(13, "  Bye\n  now ")])),
+            ]))
+        });
+        let mut service = crate::service::DomService::new();
+        let root = service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        let arena = service.arena();
+
+        assert_eq!(
+            get_text_content(arena, root).unwrap(),
+            "TitleHelloWorldtrack()\n      Bye\n  now"
+        );
+        assert_eq!(
+            get_text_content_opts(arena, root, &TextOptions::default()).unwrap(),
+            "Title\nHello World\nBye now"
+        );
+
+        let options = TextOptions {
+            newline_between_blocks: false,
+            skip_non_content: false,
+            max_len: Some(20),
+        };
+        assert_eq!(
+            get_text_content_opts(arena, root, &options).unwrap(),
+            "Title Hello World tr..."
+        );
+    }
+
+    #[test]
+    fn test_find_pagination_controls() {
+        let element = |id: u32, tag: &str, attributes: &[&str], children: serde_json::Value| {
+            serde_json::json!({
+                "nodeId": id,
+                "backendNodeId": id,
+                "nodeType": 1,
+                "nodeName": tag,
+                "attributes": attributes,
+                "children": children,
+            })
+        };
+        let link = |id: u32, attributes: &[&str], label: &str| {
+            let mut attrs = vec!["href", "#"];
+            attrs.extend_from_slice(attributes);
+            element(
+                id,
+                "A",
+                &attrs,
+                serde_json::json!([{
+                    "nodeId": id + 1,
+                    "backendNodeId": id + 1,
+                    "nodeType": 3,
+                    "nodeName": "#text",
+                    "nodeValue": label,
+                }]),
+            )
+        };
+        // Header pager hidden on this viewport, footer pager visible; an
+        // unrelated "Next article" link far from any page numbers
+        let pager = |base: u32| {
+            element(
+                base,
+                "NAV",
+                &[],
+                serde_json::json!([
+                    link(base + 10, &[], "« First"),
+                    link(base + 20, &[], "‹ Previous"),
+                    link(base + 30, &[], "1"),
+                    link(base + 40, &[], "2"),
+                    link(base + 50, &["aria-label", "Next page"], "›"),
+                    link(base + 60, &["class", "pager-last"], "Last »"),
+                ]),
+            )
+        };
+        let cdp_json = serde_json::json!({
+            "root": element(1, "BODY", &[], serde_json::json!([
+                pager(100),
+                element(2, "ARTICLE", &[], serde_json::json!([link(10, &[], "Next article")])),
+                pager(200),
+            ]))
+        });
+        let mut service = crate::service::DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        for backend_id in [150, 160, 110, 120] {
+            let id = service.arena().get_node_id_by_backend(backend_id).unwrap();
+            service.arena_mut().get_mut(id).unwrap().is_visible = Some(false);
+        }
+        let arena = service.arena();
+        let id = |backend_id| arena.get_node_id_by_backend(backend_id);
+
+        let controls = find_pagination_controls(arena);
+        assert_eq!(controls.next, id(250));
+        assert_eq!(controls.previous, id(220));
+        assert_eq!(controls.first, id(210));
+        // The word outranks the arrow
+        assert_eq!(controls.last, id(260));
+        assert_eq!(
+            controls.page_numbers,
+            [130, 140, 230, 240].map(|b| id(b).unwrap())
+        );
+
+        // With equal scores the later pager wins
+        let mut service = crate::service::DomService::new();
+        service.parse_cdp_dom_tree(&cdp_json).unwrap();
+        assert_eq!(find_pagination_controls(service.arena()).next, id(250));
+    }
+
     #[test]
     fn test_cap_text_length() {
         assert_eq!(cap_text_length("hello", 10), "hello");
         assert_eq!(cap_text_length("hello world", 5), "hello...");
+        assert_eq!(cap_text_length("hello", 5), "hello");
+    }
+
+    #[test]
+    fn test_normalize_and_escape() {
+        assert_eq!(normalize_whitespace("  a\n\n b\tc\u{7}d  "), "a b cd");
+        assert_eq!(normalize_whitespace("\r\n"), "");
+        assert_eq!(
+            escape_text("a < b && c > d"),
+            "a &lt; b &amp;&amp; c &gt; d"
+        );
+        assert_eq!(
+            escape_attribute(r#"say "hi" & <go>"#),
+            "say &quot;hi&quot; &amp; &lt;go&gt;"
+        );
+    }
+
+    #[test]
+    fn test_cap_text_length_multibyte() {
+        // Byte 1 and 2 fall inside the 4-byte emoji
+        assert_eq!(cap_text_length("😀😀😀", 1), "😀...");
+        assert_eq!(cap_text_length("😀😀😀", 3), "😀😀😀");
+        assert_eq!(cap_text_length("你好世界", 2), "你好...");
+        assert_eq!(cap_text_length("héllo", 2), "hé...");
+        assert_eq!(cap_text_length("你好", 0), "...");
+    }
+
+    #[test]
+    fn test_css_visibility_and_styles() {
+        use crate::types::SnapshotNode;
+
+        let styled = |styles: &[(&str, &str)], size: (f64, f64)| {
+            let mut node = DomNode::new(0, 1, NodeType::Element, "DIV".to_string(), String::new());
+            node.snapshot_node = Some(Box::new(SnapshotNode {
+                bounds: Some(DomRect::new(0.0, 0.0, size.0, size.1)),
+                computed_styles: Some(
+                    styles
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            }));
+            node
+        };
+        let visible =
+            |styles: &[(&str, &str)]| is_element_visible_by_css(&styled(styles, (10.0, 10.0)));
+
+        assert!(visible(&[
+            ("display", "block"),
+            ("clip", "auto"),
+            ("clip-path", "none")
+        ]));
+        assert!(!visible(&[("display", "contents")]));
+        assert!(!visible(&[("opacity", "0")]));
+        assert!(!is_element_visible_by_css(&styled(&[], (0.0, 10.0))));
+        // A zero-sized wrapper shows its overflowing content, unless clipped
+        let wrapper = |styles: &[(&str, &str)], size: (f64, f64)| {
+            let mut node = styled(styles, size);
+            node.children_ids.push(1);
+            is_element_visible_by_css(&node)
+        };
+        assert!(wrapper(&[("overflow-y", "visible")], (10.0, 0.0)));
+        assert!(wrapper(&[("overflow-y", "hidden")], (0.0, 10.0)));
+        assert!(!wrapper(&[("overflow-y", "hidden")], (10.0, 0.0)));
+        assert!(!wrapper(&[("overflow-x", "clip")], (0.0, 10.0)));
+        assert!(!visible(&[("clip", "rect(0px, 0px, 0px, 0px)")]));
+        assert!(!visible(&[("clip", "rect(1px 1px 1px 1px)")]));
+        assert!(visible(&[("clip", "rect(0px, 10px, 10px, 0px)")]));
+        assert!(!visible(&[("clip-path", "inset(100%)")]));
+        assert!(!visible(&[("clip-path", "inset(50% 0%)")]));
+        assert!(visible(&[("clip-path", "inset(10% 20% round 4px)")]));
+        assert!(visible(&[("clip-path", "inset(10px)")]));
+
+        let node = styled(&[("position", "fixed"), ("z-index", "10")], (10.0, 10.0));
+        assert_eq!(node.computed_style("position"), Some("fixed"));
+        assert!(node.is_fixed_position());
+        assert_eq!(node.z_index(), Some(10));
+        assert_eq!(styled(&[("z-index", "auto")], (1.0, 1.0)).z_index(), None);
+        assert_eq!(
+            DomNode::new(0, 1, NodeType::Text, "#text".into(), String::new())
+                .computed_style("color"),
+            None
+        );
     }
 
     #[test]
@@ -162,5 +933,10 @@ mod tests {
         let scroll = DomRect::new(0.0, 0.0, 0.0, 0.0);
 
         assert!(check_frame_intersection(&element, &client, &scroll));
+        // Zero-width wrapper at the left edge, and one scrolled out left
+        let wrapper = DomRect::new(0.0, 10.0, 0.0, 100.0);
+        assert!(check_frame_intersection(&wrapper, &client, &scroll));
+        let scrolled = DomRect::new(50.0, 0.0, 0.0, 0.0);
+        assert!(!check_frame_intersection(&wrapper, &client, &scrolled));
     }
 }